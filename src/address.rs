@@ -0,0 +1,227 @@
+use crate::bip32::{ChildNumber, ExtendedPubKey, Network};
+use crate::bip44::{Change, Purpose};
+use crate::error::Error;
+use crate::limits;
+use crate::utils;
+use bech32::{hrp, segwit, Hrp};
+use secp256k1::PublicKey;
+
+/// Bitcoin address types derivable from a single public key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum AddressType {
+    /// Legacy pay-to-pubkey-hash (BIP-44)
+    P2pkh,
+    /// Pay-to-witness-pubkey-hash nested in P2SH (BIP-49)
+    P2shP2wpkh,
+    /// Native segwit pay-to-witness-pubkey-hash (BIP-84)
+    P2wpkh,
+}
+
+impl AddressType {
+    /// Get the address type conventionally used with a BIP-43 purpose
+    pub fn for_purpose(purpose: Purpose) -> Option<Self> {
+        match purpose.0 {
+            44 => Some(AddressType::P2pkh),
+            49 => Some(AddressType::P2shP2wpkh),
+            84 => Some(AddressType::P2wpkh),
+            _ => None,
+        }
+    }
+
+    /// Get the lowercase script type name, e.g. `p2sh-p2wpkh`
+    pub fn name(&self) -> &'static str {
+        match self {
+            AddressType::P2pkh => "p2pkh",
+            AddressType::P2shP2wpkh => "p2sh-p2wpkh",
+            AddressType::P2wpkh => "p2wpkh",
+        }
+    }
+
+    /// Get the BIP-43 purpose conventionally used with this address type
+    pub fn purpose(&self) -> Purpose {
+        match self {
+            AddressType::P2pkh => Purpose(44),
+            AddressType::P2shP2wpkh => Purpose(49),
+            AddressType::P2wpkh => Purpose(84),
+        }
+    }
+}
+
+/// Version byte for base58 pay-to-pubkey-hash addresses
+fn p2pkh_prefix(network: Network) -> u8 {
+    match network {
+        Network::Bitcoin => 0x00,
+        Network::Testnet => 0x6f,
+    }
+}
+
+/// Version byte for base58 pay-to-script-hash addresses
+fn p2sh_prefix(network: Network) -> u8 {
+    match network {
+        Network::Bitcoin => 0x05,
+        Network::Testnet => 0xc4,
+    }
+}
+
+/// Human-readable part for segwit addresses
+pub(crate) fn segwit_hrp(network: Network) -> Hrp {
+    match network {
+        Network::Bitcoin => hrp::BC,
+        Network::Testnet => hrp::TB,
+    }
+}
+
+/// Build the P2WPKH witness program script (0x00 0x14 <hash160>)
+pub(crate) fn p2wpkh_script(pubkey_hash: &[u8; 20]) -> Vec<u8> {
+    let mut script = Vec::with_capacity(22);
+    script.extend_from_slice(&[0x00, 0x14]);
+    script.extend_from_slice(pubkey_hash);
+    script
+}
+
+/// Build the scriptPubKey paying to a public key
+pub fn script_pubkey(public_key: &PublicKey, address_type: AddressType) -> Vec<u8> {
+    hash_script_pubkey(&utils::pubkey_hash(public_key), address_type)
+}
+
+/// Build the scriptPubKey paying to a public key hash
+pub(crate) fn hash_script_pubkey(pubkey_hash: &[u8; 20], address_type: AddressType) -> Vec<u8> {
+    match address_type {
+        AddressType::P2pkh => {
+            // OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG
+            let mut script = Vec::with_capacity(25);
+            script.extend_from_slice(&[0x76, 0xa9, 0x14]);
+            script.extend_from_slice(pubkey_hash);
+            script.extend_from_slice(&[0x88, 0xac]);
+            script
+        }
+        AddressType::P2shP2wpkh => {
+            // OP_HASH160 <hash of redeem script> OP_EQUAL
+            let script_hash = utils::hash160(&p2wpkh_script(pubkey_hash));
+            let mut script = Vec::with_capacity(23);
+            script.extend_from_slice(&[0xa9, 0x14]);
+            script.extend_from_slice(&script_hash);
+            script.push(0x87);
+            script
+        }
+        AddressType::P2wpkh => p2wpkh_script(pubkey_hash),
+    }
+}
+
+/// Encode a base58 pay-to-pubkey-hash address from a public key hash
+pub(crate) fn p2pkh_address(pubkey_hash: &[u8; 20], network: Network) -> String {
+    let mut data = Vec::with_capacity(21);
+    data.push(p2pkh_prefix(network));
+    data.extend_from_slice(pubkey_hash);
+    utils::base58check_encode(&data)
+}
+
+/// Encode the address of a public key
+pub fn encode(public_key: &PublicKey, address_type: AddressType, network: Network) -> String {
+    let pubkey_hash = utils::pubkey_hash(public_key);
+
+    match address_type {
+        AddressType::P2pkh => p2pkh_address(&pubkey_hash, network),
+        AddressType::P2shP2wpkh => {
+            let script_hash = utils::hash160(&p2wpkh_script(&pubkey_hash));
+            let mut data = Vec::with_capacity(21);
+            data.push(p2sh_prefix(network));
+            data.extend_from_slice(&script_hash);
+            utils::base58check_encode(&data)
+        }
+        AddressType::P2wpkh => segwit::encode_v0(segwit_hrp(network), &pubkey_hash)
+            .expect("20-byte witness program is always valid"),
+    }
+}
+
+/// Check that a string is a well-formed address of a type on a network
+pub fn validate(address: &str, address_type: AddressType, network: Network) -> Result<(), Error> {
+    match address_type {
+        AddressType::P2pkh | AddressType::P2shP2wpkh => {
+            let prefix = match address_type {
+                AddressType::P2pkh => p2pkh_prefix(network),
+                _ => p2sh_prefix(network),
+            };
+            let data = utils::base58check_decode(address)?;
+            if data.len() != 21 || data[0] != prefix {
+                return Err(Error::InvalidAddress(format!(
+                    "{} is not a {:?} address on {:?}",
+                    address, address_type, network
+                )));
+            }
+        }
+        AddressType::P2wpkh => {
+            let (hrp, version, program) =
+                segwit::decode(address).map_err(|e| Error::InvalidAddress(e.to_string()))?;
+            if hrp != segwit_hrp(network) || version != segwit::VERSION_0 || program.len() != 20 {
+                return Err(Error::InvalidAddress(format!(
+                    "{} is not a P2wpkh address on {:?}",
+                    address, network
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+impl ExtendedPubKey {
+    /// Get the address of this key
+    pub fn address(&self, address_type: AddressType) -> String {
+        encode(&self.public_key, address_type, self.network)
+    }
+
+    /// Derive the scriptPubKeys of `count` consecutive addresses on a chain of an account key
+    pub fn script_pubkeys(
+        &self,
+        change: Change,
+        address_type: AddressType,
+        start: u32,
+        count: u32,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        limits::check_batch_size(count)?;
+        let chain = self.derive_child(change.child_number())?;
+
+        (start..start.saturating_add(count))
+            .map(|index| {
+                let key = chain.derive_child(ChildNumber::Normal(index))?;
+                Ok(script_pubkey(&key.public_key, address_type))
+            })
+            .collect()
+    }
+
+    /// Get the first receive address (`0/0`) of an account key, the pairing
+    /// check shown by hardware wallets and coordinators
+    pub fn first_address(&self, address_type: AddressType) -> Result<String, Error> {
+        Ok(self
+            .derive_child(Change::External.child_number())?
+            .derive_child(ChildNumber::Normal(0))?
+            .address(address_type))
+    }
+}
+
+/// Check that an account xpub's first receive address matches the one shown
+/// by a device, returning the address type it matched as
+pub fn verify_first_address(
+    account_xpub: &ExtendedPubKey,
+    expected: &str,
+) -> Result<AddressType, Error> {
+    for address_type in [
+        AddressType::P2pkh,
+        AddressType::P2shP2wpkh,
+        AddressType::P2wpkh,
+    ] {
+        if account_xpub.first_address(address_type)? == expected {
+            return Ok(address_type);
+        }
+    }
+
+    Err(Error::InvalidAddress(format!(
+        "{} is not the first address of this account",
+        expected
+    )))
+}
@@ -0,0 +1,163 @@
+use crate::error::Error;
+use crate::metrics::{self, SignatureKind};
+use crate::utils;
+use secp256k1::ecdsa::Signature;
+use secp256k1::{Message, PublicKey, Scalar, SecretKey};
+
+// Anti-exfil signing lets a host contribute randomness to the signer's nonce,
+// so a malicious signer cannot leak key material through biased nonces:
+//
+// 1. Host picks a random `host_nonce` and sends `host_commitment(host_nonce)`
+// 2. Signer replies with its nonce point R0 (`signer_commitment`)
+// 3. Host reveals `host_nonce`; signer signs with k = k0 + H(R0 || host_nonce)
+// 4. Host checks the signature's R against R0 + H(R0 || host_nonce)·G
+
+/// Order of the secp256k1 group
+const CURVE_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+/// Compute the host's commitment to its nonce contribution
+pub fn host_commitment(host_nonce: &[u8; 32]) -> [u8; 32] {
+    utils::tagged_hash("anti-exfil/host-commitment", host_nonce)
+}
+
+/// Reduce a 256-bit big-endian integer below the curve order.
+/// Values handled here (field elements and hashes) are below 2n, so one subtraction suffices.
+fn reduce_scalar(bytes: [u8; 32]) -> Scalar {
+    if let Ok(scalar) = Scalar::from_be_bytes(bytes) {
+        return scalar;
+    }
+
+    let mut reduced = [0u8; 32];
+    let mut borrow = 0i16;
+    for i in (0..32).rev() {
+        let mut diff = bytes[i] as i16 - CURVE_ORDER[i] as i16 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 256;
+            borrow = 1;
+        }
+        reduced[i] = diff as u8;
+    }
+
+    Scalar::from_be_bytes(reduced).expect("value below the curve order")
+}
+
+/// Invert a nonzero scalar modulo the curve order (k^(n-2))
+fn invert(k: &SecretKey) -> Result<SecretKey, Error> {
+    let mut exponent = CURVE_ORDER;
+    exponent[31] -= 2;
+
+    let mut result = SecretKey::from_slice(&Scalar::ONE.to_be_bytes())?;
+    for byte in exponent {
+        for bit in (0..8).rev() {
+            result = result.mul_tweak(&Scalar::from(result))?;
+            if (byte >> bit) & 1 == 1 {
+                result = result.mul_tweak(&Scalar::from(*k))?;
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Deterministic signer nonce committed to in the first round
+fn signer_nonce(
+    private_key: &SecretKey,
+    digest: &[u8; 32],
+    host_commitment: &[u8; 32],
+) -> Result<SecretKey, Error> {
+    let mut data = Vec::with_capacity(96);
+    data.extend_from_slice(&private_key[..]);
+    data.extend_from_slice(digest);
+    data.extend_from_slice(host_commitment);
+
+    SecretKey::from_slice(&utils::tagged_hash("anti-exfil/signer-nonce", &data))
+        .map_err(|_| Error::InvalidKey("Invalid signer nonce".to_string()))
+}
+
+/// Tweak binding the final nonce to the signer commitment and host nonce
+fn nonce_tweak(signer_commitment: &PublicKey, host_nonce: &[u8; 32]) -> Result<Scalar, Error> {
+    let mut data = Vec::with_capacity(65);
+    data.extend_from_slice(&signer_commitment.serialize());
+    data.extend_from_slice(host_nonce);
+
+    Scalar::from_be_bytes(utils::tagged_hash("anti-exfil/nonce-tweak", &data))
+        .map_err(|_| Error::InvalidKey("Nonce tweak out of range".to_string()))
+}
+
+/// x-coordinate of a nonce point reduced modulo the curve order (ECDSA r)
+fn nonce_point_r(point: &PublicKey) -> Scalar {
+    let mut x = [0u8; 32];
+    x.copy_from_slice(&point.serialize()[1..33]);
+    reduce_scalar(x)
+}
+
+/// First round: the signer's commitment to its nonce point R0
+pub fn signer_commitment(
+    private_key: &SecretKey,
+    digest: &[u8; 32],
+    host_commitment: &[u8; 32],
+) -> Result<PublicKey, Error> {
+    let secp = utils::secp();
+    let k0 = signer_nonce(private_key, digest, host_commitment)?;
+    Ok(PublicKey::from_secret_key(secp, &k0))
+}
+
+/// Second round: sign with the nonce tweaked by the revealed host nonce
+pub fn sign(
+    private_key: &SecretKey,
+    digest: &[u8; 32],
+    host_nonce: &[u8; 32],
+) -> Result<Signature, Error> {
+    let secp = utils::secp();
+    let k0 = signer_nonce(private_key, digest, &host_commitment(host_nonce))?;
+    let commitment = PublicKey::from_secret_key(secp, &k0);
+    let k = k0.add_tweak(&nonce_tweak(&commitment, host_nonce)?)?;
+
+    // s = k^-1 (z + r·d)
+    let r = nonce_point_r(&PublicKey::from_secret_key(secp, &k));
+    let z = reduce_scalar(*digest);
+    let s = private_key
+        .mul_tweak(&r)?
+        .add_tweak(&z)?
+        .mul_tweak(&Scalar::from(invert(&k)?))?;
+
+    let mut compact = [0u8; 64];
+    compact[..32].copy_from_slice(&r.to_be_bytes());
+    compact[32..].copy_from_slice(&s[..]);
+    let mut signature = Signature::from_compact(&compact)?;
+    signature.normalize_s();
+
+    let public_key = PublicKey::from_secret_key(secp, private_key);
+    secp.verify_ecdsa(&Message::from_digest(*digest), &signature, &public_key)?;
+
+    metrics::signature(SignatureKind::AntiExfil);
+    Ok(signature)
+}
+
+/// Host side: verify the signature and that its nonce includes the host's contribution
+pub fn verify(
+    public_key: &PublicKey,
+    digest: &[u8; 32],
+    signer_commitment: &PublicKey,
+    host_nonce: &[u8; 32],
+    signature: &Signature,
+) -> Result<(), Error> {
+    let secp = utils::secp();
+    secp.verify_ecdsa(&Message::from_digest(*digest), signature, public_key)?;
+
+    let expected_nonce =
+        signer_commitment.add_exp_tweak(secp, &nonce_tweak(signer_commitment, host_nonce)?)?;
+    let r = nonce_point_r(&expected_nonce);
+
+    if signature.serialize_compact()[..32] != r.to_be_bytes() {
+        return Err(Error::InvalidSignature(
+            "Signature nonce does not include the host contribution".to_string(),
+        ));
+    }
+
+    Ok(())
+}
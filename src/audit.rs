@@ -0,0 +1,385 @@
+use crate::bip32::DerivationPath;
+use crate::clock::{Clock, SystemClock};
+use crate::error::Error;
+use crate::signer::{Signer, SigningOptions};
+use crate::statement::csv_field;
+use crate::utils;
+use secp256k1::{ecdsa::Signature, PublicKey};
+use std::sync::{Mutex, MutexGuard};
+
+/// Header row of CSV audit exports
+const CSV_HEADER: &str =
+    "sequence,timestamp_ms,operation,path,digest,result_fingerprint,error,prev_hash,hash";
+
+/// Kind of audited operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AuditOperation {
+    /// A public key was derived
+    Derive,
+    /// An ECDSA signature was requested
+    Sign,
+    /// An anti-exfil signature was requested
+    AntiExfilSign,
+}
+
+impl AuditOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditOperation::Derive => "derive",
+            AuditOperation::Sign => "sign",
+            AuditOperation::AntiExfilSign => "anti-exfil-sign",
+        }
+    }
+}
+
+/// One entry of an audit log. Each entry's hash commits to the previous
+/// entry's hash, so removing or editing an entry breaks the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditEntry {
+    pub sequence: u64,
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub operation: AuditOperation,
+    pub path: String,
+    /// Hex digest that was signed
+    pub digest: Option<String>,
+    /// Hex fingerprint of the result: the key fingerprint for derivations,
+    /// the first 4 bytes of SHA-256 of the compact signature for signatures
+    pub result_fingerprint: Option<String>,
+    /// Why the operation failed
+    pub error: Option<String>,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// CSV fields covered by the entry's hash
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.sequence.to_string(),
+            self.timestamp_ms.to_string(),
+            self.operation.name().to_string(),
+            csv_field(&self.path),
+            self.digest.clone().unwrap_or_default(),
+            self.result_fingerprint.clone().unwrap_or_default(),
+            csv_field(self.error.as_deref().unwrap_or_default()),
+        ]
+    }
+
+    fn compute_hash(&self) -> String {
+        let mut data = self.prev_hash.as_bytes().to_vec();
+        data.push(b'\n');
+        data.extend_from_slice(self.fields().join(",").as_bytes());
+        hex::encode(utils::tagged_hash("bip32hdwallet/audit", &data))
+    }
+}
+
+/// An append-only, hash-chained log of key operations, for compliance review.
+/// A log either starts from scratch or continues an earlier, archived one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+    /// Sequence number of the first entry
+    start_sequence: u64,
+    /// Hash the first entry chains to
+    start_hash: String,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        AuditLog {
+            entries: Vec::new(),
+            start_sequence: 0,
+            start_hash: genesis_hash(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for AuditLog {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Raw {
+            entries: Vec<AuditEntry>,
+            #[serde(default)]
+            start_sequence: u64,
+            #[serde(default = "genesis_hash")]
+            start_hash: String,
+        }
+
+        let raw = Raw::deserialize(deserializer)?;
+        let log = AuditLog {
+            entries: raw.entries,
+            start_sequence: raw.start_sequence,
+            start_hash: raw.start_hash,
+        };
+        log.verify().map_err(serde::de::Error::custom)?;
+        Ok(log)
+    }
+}
+
+fn genesis_hash() -> String {
+    hex::encode([0u8; 32])
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        AuditLog::default()
+    }
+
+    /// Rebuild a log started from scratch from exported entries, checking
+    /// the chain
+    pub fn from_entries(entries: Vec<AuditEntry>) -> Result<Self, Error> {
+        let log = AuditLog {
+            entries,
+            ..AuditLog::default()
+        };
+        log.verify()?;
+        Ok(log)
+    }
+
+    /// An empty log continuing the chain from this one's head
+    pub fn continuation(&self) -> AuditLog {
+        AuditLog {
+            entries: Vec::new(),
+            start_sequence: self.start_sequence + self.entries.len() as u64,
+            start_hash: self.head(),
+        }
+    }
+
+    /// Whether this log continues the chain right after `previous`
+    pub fn follows(&self, previous: &AuditLog) -> bool {
+        self.start_sequence == previous.start_sequence + previous.entries.len() as u64
+            && self.start_hash == previous.head()
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// Hash of the last entry, or the start hash for an empty log: all
+    /// zeros for a log started from scratch. Publishing the head pins every
+    /// entry before it.
+    pub fn head(&self) -> String {
+        self.entries
+            .last()
+            .map_or_else(|| self.start_hash.clone(), |entry| entry.hash.clone())
+    }
+
+    /// Append an event, chaining it to the current head
+    pub fn append(
+        &mut self,
+        timestamp_ms: u64,
+        operation: AuditOperation,
+        path: &DerivationPath,
+        digest: Option<&[u8; 32]>,
+        result: Result<[u8; 4], &Error>,
+    ) -> &AuditEntry {
+        let mut entry = AuditEntry {
+            sequence: self.start_sequence + self.entries.len() as u64,
+            timestamp_ms,
+            operation,
+            path: path.to_string(),
+            digest: digest.map(hex::encode),
+            result_fingerprint: result.as_ref().ok().map(hex::encode),
+            error: result.err().map(|e| e.to_string()),
+            prev_hash: self.head(),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+        self.entries.push(entry);
+        self.entries.last().expect("entry just pushed")
+    }
+
+    /// Check sequence numbers and the hash chain
+    pub fn verify(&self) -> Result<(), Error> {
+        let mut prev_hash = self.start_hash.clone();
+        for (i, entry) in self.entries.iter().enumerate() {
+            if entry.sequence != self.start_sequence + i as u64
+                || entry.prev_hash != prev_hash
+                || entry.hash != entry.compute_hash()
+            {
+                return Err(Error::InvalidExport(format!(
+                    "Audit log chain broken at entry {}",
+                    i
+                )));
+            }
+            prev_hash = entry.hash.clone();
+        }
+        Ok(())
+    }
+
+    /// Export as CSV, one row per entry
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+        for entry in &self.entries {
+            let mut fields = entry.fields();
+            fields.push(entry.prev_hash.clone());
+            fields.push(entry.hash.clone());
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Serialize to JSON
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::InvalidExport(e.to_string()))
+    }
+}
+
+/// Signer decorator recording every derivation and signature, successful or
+/// not, in an audit log. Take the log periodically to archive it; with a
+/// maximum set, operations are refused while the log is full.
+pub struct AuditedSigner<S: Signer, C: Clock = SystemClock> {
+    inner: S,
+    clock: C,
+    max_entries: Option<usize>,
+    log: Mutex<AuditLog>,
+}
+
+impl<S: Signer> AuditedSigner<S> {
+    /// Wrap a signer with an empty log
+    pub fn new(inner: S) -> Self {
+        AuditedSigner {
+            inner,
+            clock: SystemClock,
+            max_entries: None,
+            log: Mutex::new(AuditLog::new()),
+        }
+    }
+}
+
+impl<S: Signer, C: Clock> AuditedSigner<S, C> {
+    /// Use another clock
+    pub fn with_clock<C2: Clock>(self, clock: C2) -> AuditedSigner<S, C2> {
+        AuditedSigner {
+            inner: self.inner,
+            clock,
+            max_entries: self.max_entries,
+            log: self.log,
+        }
+    }
+
+    /// Refuse operations once the log holds `max_entries` entries, until it
+    /// is taken
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Get the wrapped signer
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Borrow the audit log. Operations wait while it is borrowed.
+    pub fn log(&self) -> MutexGuard<'_, AuditLog> {
+        self.log.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Take the entries logged so far, for archiving. Logging continues in
+    /// a log that [follows](AuditLog::follows) the one returned.
+    pub fn take_log(&self) -> AuditLog {
+        let mut log = self.log();
+        let next = log.continuation();
+        std::mem::replace(&mut *log, next)
+    }
+
+    /// Run an operation if the log has room and record its outcome
+    fn record<T>(
+        &self,
+        operation: AuditOperation,
+        path: &DerivationPath,
+        digest: Option<&[u8; 32]>,
+        run: impl FnOnce() -> Result<T, Error>,
+        fingerprint: impl FnOnce(&T) -> [u8; 4],
+    ) -> Result<T, Error> {
+        if let Some(max) = self.max_entries {
+            if self.log().entries.len() >= max {
+                return Err(Error::Storage(format!(
+                    "Audit log is full at {} entries",
+                    max
+                )));
+            }
+        }
+
+        let result = run();
+        let timestamp_ms = self.clock.now().as_millis() as u64;
+        let mut log = self.log();
+        log.append(
+            timestamp_ms,
+            operation,
+            path,
+            digest,
+            result.as_ref().map(fingerprint),
+        );
+        result
+    }
+}
+
+fn key_fingerprint(key: &PublicKey) -> [u8; 4] {
+    utils::pubkey_hash(key)[..4].try_into().expect("4 bytes")
+}
+
+fn signature_fingerprint(signature: &Signature) -> [u8; 4] {
+    utils::sha256(&signature.serialize_compact())[..4]
+        .try_into()
+        .expect("4 bytes")
+}
+
+impl<S: Signer, C: Clock> Signer for AuditedSigner<S, C> {
+    fn public_key(&self, path: &DerivationPath) -> Result<PublicKey, Error> {
+        self.record(
+            AuditOperation::Derive,
+            path,
+            None,
+            || self.inner.public_key(path),
+            key_fingerprint,
+        )
+    }
+
+    fn sign_ecdsa(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        options: SigningOptions,
+    ) -> Result<Signature, Error> {
+        self.record(
+            AuditOperation::Sign,
+            path,
+            Some(digest),
+            || self.inner.sign_ecdsa(path, digest, options),
+            signature_fingerprint,
+        )
+    }
+
+    fn anti_exfil_commit(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        host_commitment: &[u8; 32],
+    ) -> Result<PublicKey, Error> {
+        self.inner.anti_exfil_commit(path, digest, host_commitment)
+    }
+
+    fn anti_exfil_sign(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        host_nonce: &[u8; 32],
+    ) -> Result<Signature, Error> {
+        self.record(
+            AuditOperation::AntiExfilSign,
+            path,
+            Some(digest),
+            || self.inner.anti_exfil_sign(path, digest, host_nonce),
+            signature_fingerprint,
+        )
+    }
+}
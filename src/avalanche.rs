@@ -0,0 +1,93 @@
+use crate::bip32::{DerivationPath, Network};
+use crate::bip44::{AccountLevel, AddressIndex, Bip44Path, Change, CoinType};
+use crate::error::Error;
+use crate::utils;
+use crate::wallet::Wallet;
+use bech32::{Bech32, Hrp};
+use secp256k1::PublicKey;
+
+/// Avalanche chains using bech32 account addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AvalancheChain {
+    /// Exchange chain
+    X,
+    /// Platform chain
+    P,
+}
+
+impl AvalancheChain {
+    /// Get the chain alias prefixed to addresses
+    pub fn alias(&self) -> &'static str {
+        match self {
+            AvalancheChain::X => "X",
+            AvalancheChain::P => "P",
+        }
+    }
+}
+
+/// Get the bech32 prefix: `avax` on mainnet, `fuji` on the test network
+pub fn hrp(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "avax",
+        Network::Testnet => "fuji",
+    }
+}
+
+/// Get the path of an address: `m/44'/9000'/account'/0/index`
+pub fn path(account: u32, index: u32) -> DerivationPath {
+    Bip44Path::standard(
+        CoinType::AVALANCHE,
+        AccountLevel::new(account),
+        Change::External,
+        AddressIndex::new(index),
+    )
+    .to_derivation_path()
+}
+
+/// Encode the address of a public key on a chain, e.g. `X-avax1...`
+pub fn encode_address(public_key: &PublicKey, chain: AvalancheChain, network: Network) -> String {
+    let hrp = Hrp::parse_unchecked(hrp(network));
+    let address = bech32::encode::<Bech32>(hrp, &utils::pubkey_hash(public_key))
+        .expect("20-byte payload always fits a bech32 string");
+    format!("{}-{}", chain.alias(), address)
+}
+
+/// Decode an address into its chain, network and public key hash
+pub fn decode_address(address: &str) -> Result<(AvalancheChain, Network, [u8; 20]), Error> {
+    let (alias, rest) = address
+        .split_once('-')
+        .ok_or_else(|| Error::InvalidAddress("Missing Avalanche chain alias".to_string()))?;
+    let chain = match alias {
+        "X" => AvalancheChain::X,
+        "P" => AvalancheChain::P,
+        _ => {
+            return Err(Error::InvalidAddress(format!(
+                "Unknown Avalanche chain {}",
+                alias
+            )))
+        }
+    };
+
+    let (hrp, data) = bech32::decode(rest).map_err(|e| Error::InvalidAddress(e.to_string()))?;
+    let network = [Network::Bitcoin, Network::Testnet]
+        .into_iter()
+        .find(|&network| hrp.as_str() == self::hrp(network))
+        .ok_or_else(|| Error::InvalidAddress(format!("Unknown Avalanche prefix {}", hrp)))?;
+    let pubkey_hash = data
+        .try_into()
+        .map_err(|_| Error::InvalidAddress("Address must hold 20 bytes".to_string()))?;
+
+    Ok((chain, network, pubkey_hash))
+}
+
+impl Wallet {
+    /// Get the X- or P-chain address at an index of the first account
+    pub fn avalanche_address(&self, chain: AvalancheChain, index: u32) -> Result<String, Error> {
+        let key = self.master().derive_path(&path(0, index))?;
+        Ok(encode_address(
+            &key.to_extended_public_key().public_key,
+            chain,
+            self.network(),
+        ))
+    }
+}
@@ -1,11 +1,22 @@
 use crate::error::Error;
+use crate::limits;
+use crate::metrics;
+use crate::trace;
 use crate::utils;
-use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use secp256k1::{PublicKey, SecretKey};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
+use std::time::Instant;
 
 /// The network type for HD keys
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Network {
     Bitcoin,
     Testnet,
@@ -30,7 +41,7 @@ impl Network {
 }
 
 /// A path element in a derivation path
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ChildNumber {
     /// Normal derivation index (0..2^31-1)
     Normal(u32),
@@ -59,6 +70,17 @@ impl ChildNumber {
     }
 }
 
+impl From<u32> for ChildNumber {
+    /// Convert a raw index value, where indices from 2^31 are hardened
+    fn from(index: u32) -> Self {
+        if index > ChildNumber::MAX_NORMAL_INDEX {
+            ChildNumber::Hardened(index - ChildNumber::MAX_NORMAL_INDEX - 1)
+        } else {
+            ChildNumber::Normal(index)
+        }
+    }
+}
+
 impl fmt::Display for ChildNumber {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -100,58 +122,230 @@ impl FromStr for ChildNumber {
     }
 }
 
+/// How strictly derivation path strings are parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathParseMode {
+    /// Accept an uppercase `M` prefix and a trailing slash
+    #[default]
+    Lenient,
+    /// Only accept the canonical `m/...` form without a trailing slash
+    Strict,
+}
+
 /// A BIP-32 derivation path
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Eq)]
 pub struct DerivationPath {
     pub path: Vec<ChildNumber>,
 }
 
+/// Paths are equal when they derive the same key, so a `Normal` index past
+/// 2^31 equals the `Hardened` one it encodes
+impl PartialEq for DerivationPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.path.len() == other.path.len()
+            && self
+                .path
+                .iter()
+                .zip(&other.path)
+                .all(|(a, b)| a.to_u32() == b.to_u32())
+    }
+}
+
+/// Hashes the canonical form, consistent with equality
+impl Hash for DerivationPath {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for child in &self.path {
+            child.to_u32().hash(state);
+        }
+        self.path.len().hash(state);
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for child in &self.path {
+            write!(f, "/{}", child)?;
+        }
+        Ok(())
+    }
+}
+
 impl DerivationPath {
-    /// Create a new derivation path from a string (e.g., "m/44'/0'/0'/0/0")
-    pub fn from_str(path: &str) -> Result<Self, Error> {
-        if !path.starts_with('m') {
+    /// Parse a derivation path string using the given parsing mode
+    pub fn parse(path: &str, mode: PathParseMode) -> Result<Self, Error> {
+        let strict = mode == PathParseMode::Strict;
+
+        let rest = if let Some(rest) = path.strip_prefix('m') {
+            rest
+        } else if let Some(rest) = path.strip_prefix('M').filter(|_| !strict) {
+            rest
+        } else {
             return Err(Error::InvalidDerivationPath(
                 "Path must start with 'm'".to_string(),
             ));
-        }
+        };
 
         // Skip "m" and possibly "/"
-        let path_str = if path.starts_with("m/") {
-            &path[2..]
-        } else if path == "m" {
+        let path_str = if rest.is_empty() || (rest == "/" && !strict) {
             return Ok(DerivationPath { path: vec![] });
+        } else if let Some(path_str) = rest.strip_prefix('/') {
+            path_str
         } else {
             return Err(Error::InvalidDerivationPath(
                 "Invalid path format".to_string(),
             ));
         };
 
+        let path_str = match path_str.strip_suffix('/') {
+            Some(_) if strict => {
+                return Err(Error::InvalidDerivationPath(
+                    "Trailing slash not allowed".to_string(),
+                ))
+            }
+            Some(trimmed) => trimmed,
+            None => path_str,
+        };
+
+        limits::check_path_depth(path_str.split('/').count())?;
         let path: Result<Vec<ChildNumber>, Error> = path_str
             .split('/')
-            .filter(|p| !p.is_empty())
-            .map(|p| p.parse::<ChildNumber>())
+            .enumerate()
+            .map(|(index, p)| {
+                if p.is_empty() {
+                    return Err(Error::InvalidDerivationPath(format!(
+                        "Empty path component at index {}",
+                        index
+                    )));
+                }
+                p.parse::<ChildNumber>().map_err(|e| match e {
+                    Error::InvalidDerivationPath(msg) => {
+                        Error::InvalidDerivationPath(format!("{} at index {}", msg, index))
+                    }
+                    e => e,
+                })
+            })
             .collect();
 
         Ok(DerivationPath { path: path? })
     }
 }
 
-impl fmt::Display for DerivationPath {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "m")?;
-        for child in &self.path {
-            write!(f, "/{}", child)?;
+impl DerivationPath {
+    /// Get the canonical form of the path, with every index past 2^31
+    /// written as hardened
+    pub fn canonical(&self) -> DerivationPath {
+        DerivationPath {
+            path: self
+                .path
+                .iter()
+                .map(|child| ChildNumber::from(child.to_u32()))
+                .collect(),
         }
-        Ok(())
+    }
+
+    /// Normalize a path string from any common notation (`h`, `H` or `'`
+    /// hardened markers, `M` prefix, whitespace, trailing slash) to the
+    /// canonical `m/44'/0'/0'` form
+    pub fn normalize(path: &str) -> Result<String, Error> {
+        let compact: String = path
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| if c == 'H' { 'h' } else { c })
+            .collect();
+        Ok(DerivationPath::parse(&compact, PathParseMode::Lenient)?
+            .canonical()
+            .to_string())
     }
 }
 
 impl FromStr for DerivationPath {
     type Err = Error;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        DerivationPath::from_str(s)
+    /// Create a new derivation path from a string (e.g., "m/44'/0'/0'/0/0")
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let result = DerivationPath::parse(path, PathParseMode::Lenient);
+        trace::event!(TRACE, path, ok = result.is_ok(), "parsed derivation path");
+        result
+    }
+}
+
+/// Check that an index fits below the hardened offset
+fn check_index(index: u32) -> Result<u32, Error> {
+    if index > ChildNumber::MAX_NORMAL_INDEX {
+        return Err(Error::InvalidDerivationPath(format!(
+            "Index {} out of range",
+            index
+        )));
     }
+    Ok(index)
+}
+
+/// Kind of a serialized extended key, as read from its version bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    /// xprv/tprv
+    Private(Network),
+    /// xpub/tpub
+    Public(Network),
+}
+
+impl KeyKind {
+    /// Get the network of the key
+    pub fn network(&self) -> Network {
+        match self {
+            KeyKind::Private(network) | KeyKind::Public(network) => *network,
+        }
+    }
+
+    /// Check if the key is private
+    pub fn is_private(&self) -> bool {
+        matches!(self, KeyKind::Private(_))
+    }
+}
+
+/// Check an extended key string's base58 encoding, checksum, length, version
+/// and structure without validating the key against the curve
+pub fn validate_extended_key_str(s: &str) -> Result<KeyKind, Error> {
+    let data = utils::base58check_decode(s)?;
+    if data.len() != 78 {
+        return Err(Error::InvalidExtendedKey(
+            "Invalid extended key length".to_string(),
+        ));
+    }
+
+    let version = &data[0..4];
+    let kind = [Network::Bitcoin, Network::Testnet]
+        .into_iter()
+        .find_map(|network| {
+            if version == network.xprv_version() {
+                Some(KeyKind::Private(network))
+            } else if version == network.xpub_version() {
+                Some(KeyKind::Public(network))
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| Error::InvalidExtendedKey("Invalid version bytes".to_string()))?;
+
+    // A master key has no parent and no child number
+    if data[4] == 0 && data[5..13].iter().any(|&b| b != 0) {
+        return Err(Error::InvalidExtendedKey(
+            "Master key with parent fingerprint or child number".to_string(),
+        ));
+    }
+
+    let valid_prefix = match kind {
+        KeyKind::Private(_) => data[45] == 0,
+        KeyKind::Public(_) => data[45] == 0x02 || data[45] == 0x03,
+    };
+    if !valid_prefix {
+        return Err(Error::InvalidExtendedKey(
+            "Invalid key data prefix".to_string(),
+        ));
+    }
+
+    Ok(kind)
 }
 
 /// Extended private key as defined in BIP-32
@@ -197,7 +391,7 @@ impl ExtendedPrivKey {
 
     /// Derive a child key (CKDpriv)
     pub fn derive_child(&self, child_number: ChildNumber) -> Result<ExtendedPrivKey, Error> {
-        let secp = Secp256k1::new();
+        let secp = utils::secp();
         let mut hmac_input = Vec::with_capacity(37);
 
         if child_number.is_hardened() {
@@ -206,7 +400,7 @@ impl ExtendedPrivKey {
             hmac_input.extend_from_slice(&self.private_key[..]);
         } else {
             // Normal derivation: data = public_key || child_number
-            let public_key = PublicKey::from_secret_key(&secp, &self.private_key);
+            let public_key = PublicKey::from_secret_key(secp, &self.private_key);
             hmac_input.extend_from_slice(&public_key.serialize());
         }
 
@@ -231,15 +425,9 @@ impl ExtendedPrivKey {
             .add_tweak(&self.private_key.into())
             .map_err(|_| Error::InvalidKey("Invalid child private key".to_string()))?;
 
-        // Calculate fingerprint of parent key
-        let parent_public_key = PublicKey::from_secret_key(&secp, &self.private_key);
-        let parent_pubkey_hash = utils::sha256(&parent_public_key.serialize());
-        let mut fingerprint = [0u8; 4];
-        fingerprint.copy_from_slice(&parent_pubkey_hash[0..4]);
-
         Ok(ExtendedPrivKey {
             depth: self.depth + 1,
-            parent_fingerprint: fingerprint,
+            parent_fingerprint: self.fingerprint(),
             child_number: index,
             chain_code: i_r,
             private_key: child_private_key,
@@ -249,19 +437,38 @@ impl ExtendedPrivKey {
 
     /// Derive a child key from a derivation path
     pub fn derive_path(&self, path: &DerivationPath) -> Result<ExtendedPrivKey, Error> {
+        trace::span!(DEBUG, "derive_path", %path, parent = %hex::encode(self.fingerprint()));
+        limits::check_path_depth(path.path.len())?;
+        let start = Instant::now();
         let mut key = self.clone();
 
         for &child_number in &path.path {
             key = key.derive_child(child_number)?;
         }
 
+        metrics::derivation(path.path.len(), start);
         Ok(key)
     }
 
+    /// Derive the hardened child at an index (`index'`)
+    pub fn derive_hardened(&self, index: u32) -> Result<ExtendedPrivKey, Error> {
+        self.derive_child(ChildNumber::Hardened(check_index(index)?))
+    }
+
+    /// Derive the normal child at an index
+    pub fn derive_normal(&self, index: u32) -> Result<ExtendedPrivKey, Error> {
+        self.derive_child(ChildNumber::Normal(check_index(index)?))
+    }
+
+    /// Get the fingerprint of this key (first 4 bytes of HASH160 of the public key)
+    pub fn fingerprint(&self) -> [u8; 4] {
+        self.to_extended_public_key().fingerprint()
+    }
+
     /// Get the corresponding extended public key
     pub fn to_extended_public_key(&self) -> ExtendedPubKey {
-        let secp = Secp256k1::new();
-        let public_key = PublicKey::from_secret_key(&secp, &self.private_key);
+        let secp = utils::secp();
+        let public_key = PublicKey::from_secret_key(secp, &self.private_key);
 
         ExtendedPubKey {
             depth: self.depth,
@@ -273,36 +480,58 @@ impl ExtendedPrivKey {
         }
     }
 
-    /// Serialize the extended private key to base58 format
-    pub fn to_string(&self) -> String {
-        let mut data = Vec::with_capacity(78);
-
-        // Version bytes
-        data.extend_from_slice(&self.network.xprv_version());
-
-        // Depth
-        data.push(self.depth);
+    /// Re-tag the key so it serializes with another network's version bytes.
+    /// Changing the network requires `allow_retag` to be set explicitly.
+    pub fn with_network(&self, network: Network, allow_retag: bool) -> Result<Self, Error> {
+        if network != self.network && !allow_retag {
+            return Err(Error::NetworkMismatch(format!(
+                "Re-tagging a {:?} key as {:?} requires explicit opt-in",
+                self.network, network
+            )));
+        }
 
-        // Parent fingerprint
-        data.extend_from_slice(&self.parent_fingerprint);
+        Ok(ExtendedPrivKey {
+            network,
+            ..self.clone()
+        })
+    }
 
-        // Child number
-        data.extend_from_slice(&self.child_number.to_be_bytes());
+    /// Parse an extended private key from a base58 string
+    pub fn from_string(xprv: &str) -> Result<Self, Error> {
+        let result =
+            utils::base58check_decode(xprv).and_then(|data| ExtendedPrivKey::decode(&data));
+        trace::event!(DEBUG, ok = result.is_ok(), "parsed extended private key");
+        result
+    }
 
-        // Chain code
-        data.extend_from_slice(&self.chain_code);
+    /// Parse an extended private key from a hex encoded 78-byte payload
+    pub fn from_hex(s: &str) -> Result<Self, Error> {
+        let data = hex::decode(s)
+            .map_err(|_| Error::InvalidExtendedKey("Invalid hex encoding".to_string()))?;
+        ExtendedPrivKey::decode(&data)
+    }
 
-        // Private key with 0x00 prefix
-        data.push(0);
-        data.extend_from_slice(&self.private_key[..]);
+    /// Parse an extended private key from a base64 encoded 78-byte payload
+    pub fn from_base64(s: &str) -> Result<Self, Error> {
+        let data = STANDARD
+            .decode(s)
+            .map_err(|_| Error::InvalidExtendedKey("Invalid base64 encoding".to_string()))?;
+        ExtendedPrivKey::decode(&data)
+    }
 
-        utils::base58check_encode(&data)
+    /// Encode the 78-byte payload as hex
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.encode())
     }
 
-    /// Parse an extended private key from a base58 string
-    pub fn from_string(xprv: &str) -> Result<Self, Error> {
-        let data = utils::base58check_decode(xprv)?;
+    /// Encode the 78-byte payload as base64
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.encode())
+    }
 
+    /// Decode the raw 78-byte BIP-32 serialization, whose version bytes
+    /// carry the network
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
         if data.len() != 78 {
             return Err(Error::InvalidExtendedKey(
                 "Invalid extended key length".to_string(),
@@ -360,6 +589,41 @@ impl ExtendedPrivKey {
     }
 }
 
+impl ExtendedPrivKey {
+    /// Get the raw 78-byte BIP-32 serialization
+    pub fn encode(&self) -> [u8; 78] {
+        let mut data = Vec::with_capacity(78);
+
+        // Version bytes
+        data.extend_from_slice(&self.network.xprv_version());
+
+        // Depth
+        data.push(self.depth);
+
+        // Parent fingerprint
+        data.extend_from_slice(&self.parent_fingerprint);
+
+        // Child number
+        data.extend_from_slice(&self.child_number.to_be_bytes());
+
+        // Chain code
+        data.extend_from_slice(&self.chain_code);
+
+        // Private key with 0x00 prefix
+        data.push(0);
+        data.extend_from_slice(&self.private_key[..]);
+
+        data.try_into().expect("serialization is 78 bytes")
+    }
+}
+
+impl fmt::Display for ExtendedPrivKey {
+    /// Serialize the extended private key to base58 format
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", utils::base58check_encode(&self.encode()))
+    }
+}
+
 /// Extended public key as defined in BIP-32
 #[derive(Debug, Clone)]
 pub struct ExtendedPubKey {
@@ -378,7 +642,7 @@ impl ExtendedPubKey {
             return Err(Error::HardenedDerivationRequiresPrivateKey);
         }
 
-        let secp = Secp256k1::new();
+        let secp = utils::secp();
         let mut hmac_input = Vec::with_capacity(37);
 
         // Data = public_key || child_number
@@ -401,21 +665,16 @@ impl ExtendedPubKey {
         let hash = SecretKey::from_slice(&i_l)
             .map_err(|_| Error::InvalidKey("Invalid HMAC-SHA512 left half".to_string()))?;
 
-        let point = PublicKey::from_secret_key(&secp, &hash);
+        let point = PublicKey::from_secret_key(secp, &hash);
 
         let child_public_key = self
             .public_key
             .combine(&point)
             .map_err(|_| Error::InvalidKey("Invalid child public key".to_string()))?;
 
-        // Calculate fingerprint of parent key
-        let parent_pubkey_hash = utils::sha256(&self.public_key.serialize());
-        let mut fingerprint = [0u8; 4];
-        fingerprint.copy_from_slice(&parent_pubkey_hash[0..4]);
-
         Ok(ExtendedPubKey {
             depth: self.depth + 1,
-            parent_fingerprint: fingerprint,
+            parent_fingerprint: self.fingerprint(),
             child_number: index,
             chain_code: i_r,
             public_key: child_public_key,
@@ -423,8 +682,19 @@ impl ExtendedPubKey {
         })
     }
 
+    /// Get the fingerprint of this key (first 4 bytes of HASH160 of the public key)
+    pub fn fingerprint(&self) -> [u8; 4] {
+        let hash = utils::pubkey_hash(&self.public_key);
+        let mut fingerprint = [0u8; 4];
+        fingerprint.copy_from_slice(&hash[0..4]);
+        fingerprint
+    }
+
     /// Derive a child key from a derivation path (only non-hardened)
     pub fn derive_path(&self, path: &DerivationPath) -> Result<ExtendedPubKey, Error> {
+        trace::span!(DEBUG, "derive_public_path", %path, parent = %hex::encode(self.fingerprint()));
+        limits::check_path_depth(path.path.len())?;
+        let start = Instant::now();
         let mut key = self.clone();
 
         for &child_number in &path.path {
@@ -434,38 +704,66 @@ impl ExtendedPubKey {
             key = key.derive_child(child_number)?;
         }
 
+        metrics::derivation(path.path.len(), start);
         Ok(key)
     }
 
-    /// Serialize the extended public key to base58 format
-    pub fn to_string(&self) -> String {
-        let mut data = Vec::with_capacity(78);
-
-        // Version bytes
-        data.extend_from_slice(&self.network.xpub_version());
+    /// Derive the normal child at an index
+    pub fn derive_normal(&self, index: u32) -> Result<ExtendedPubKey, Error> {
+        self.derive_child(ChildNumber::Normal(check_index(index)?))
+    }
 
-        // Depth
-        data.push(self.depth);
+    /// Re-tag the key so it serializes with another network's version bytes.
+    /// Changing the network requires `allow_retag` to be set explicitly.
+    pub fn with_network(&self, network: Network, allow_retag: bool) -> Result<Self, Error> {
+        if network != self.network && !allow_retag {
+            return Err(Error::NetworkMismatch(format!(
+                "Re-tagging a {:?} key as {:?} requires explicit opt-in",
+                self.network, network
+            )));
+        }
 
-        // Parent fingerprint
-        data.extend_from_slice(&self.parent_fingerprint);
+        Ok(ExtendedPubKey {
+            network,
+            ..self.clone()
+        })
+    }
 
-        // Child number
-        data.extend_from_slice(&self.child_number.to_be_bytes());
+    /// Parse an extended public key from a base58 string
+    pub fn from_string(xpub: &str) -> Result<Self, Error> {
+        let result = utils::base58check_decode(xpub).and_then(|data| ExtendedPubKey::decode(&data));
+        trace::event!(DEBUG, ok = result.is_ok(), "parsed extended public key");
+        result
+    }
 
-        // Chain code
-        data.extend_from_slice(&self.chain_code);
+    /// Parse an extended public key from a hex encoded 78-byte payload
+    pub fn from_hex(s: &str) -> Result<Self, Error> {
+        let data = hex::decode(s)
+            .map_err(|_| Error::InvalidExtendedKey("Invalid hex encoding".to_string()))?;
+        ExtendedPubKey::decode(&data)
+    }
 
-        // Public key
-        data.extend_from_slice(&self.public_key.serialize());
+    /// Parse an extended public key from a base64 encoded 78-byte payload
+    pub fn from_base64(s: &str) -> Result<Self, Error> {
+        let data = STANDARD
+            .decode(s)
+            .map_err(|_| Error::InvalidExtendedKey("Invalid base64 encoding".to_string()))?;
+        ExtendedPubKey::decode(&data)
+    }
 
-        utils::base58check_encode(&data)
+    /// Encode the 78-byte payload as hex
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.encode())
     }
 
-    /// Parse an extended public key from a base58 string
-    pub fn from_string(xpub: &str) -> Result<Self, Error> {
-        let data = utils::base58check_decode(xpub)?;
+    /// Encode the 78-byte payload as base64
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.encode())
+    }
 
+    /// Decode the raw 78-byte BIP-32 serialization, whose version bytes
+    /// carry the network
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
         if data.len() != 78 {
             return Err(Error::InvalidExtendedKey(
                 "Invalid extended key length".to_string(),
@@ -515,3 +813,37 @@ impl ExtendedPubKey {
         })
     }
 }
+
+impl ExtendedPubKey {
+    /// Get the raw 78-byte BIP-32 serialization
+    pub fn encode(&self) -> [u8; 78] {
+        let mut data = Vec::with_capacity(78);
+
+        // Version bytes
+        data.extend_from_slice(&self.network.xpub_version());
+
+        // Depth
+        data.push(self.depth);
+
+        // Parent fingerprint
+        data.extend_from_slice(&self.parent_fingerprint);
+
+        // Child number
+        data.extend_from_slice(&self.child_number.to_be_bytes());
+
+        // Chain code
+        data.extend_from_slice(&self.chain_code);
+
+        // Public key
+        data.extend_from_slice(&self.public_key.serialize());
+
+        data.try_into().expect("serialization is 78 bytes")
+    }
+}
+
+impl fmt::Display for ExtendedPubKey {
+    /// Serialize the extended public key to base58 format
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", utils::base58check_encode(&self.encode()))
+    }
+}
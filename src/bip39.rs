@@ -1,25 +1,120 @@
-use crate::error::Error;
+use crate::error::{Error, MnemonicError};
+use crate::trace;
+use crate::wipe::wipe;
+use crate::wordlist::Wordlist;
 use hmac::Hmac;
 use pbkdf2::pbkdf2;
-use rand::{rngs::OsRng, RngCore};
+use rand::rngs::OsRng;
+use rand_core::CryptoRngCore;
 use sha2::{Digest, Sha256, Sha512};
 use std::fmt;
 use unicode_normalization::UnicodeNormalization;
 
-/// Supported languages for BIP-39 wordlists
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Split an embedded wordlist file into its words on first use
+#[allow(unused_macros)] // Unused when only English is compiled in
+macro_rules! embedded_wordlist {
+    ($file:literal) => {{
+        static WORDS: std::sync::OnceLock<Vec<&str>> = std::sync::OnceLock::new();
+        WORDS.get_or_init(|| include_str!($file).lines().collect())
+    }};
+}
+
+/// Supported languages for BIP-39 wordlists. English is always available;
+/// the others are embedded behind a feature of the same name, all enabled by
+/// default through `all-languages`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Language {
     English,
-    // Add other languages as needed
+    #[cfg(feature = "chinese-simplified")]
+    ChineseSimplified,
+    #[cfg(feature = "chinese-traditional")]
+    ChineseTraditional,
+    #[cfg(feature = "czech")]
+    Czech,
+    #[cfg(feature = "french")]
+    French,
+    #[cfg(feature = "italian")]
+    Italian,
+    #[cfg(feature = "japanese")]
+    Japanese,
+    #[cfg(feature = "korean")]
+    Korean,
+    #[cfg(feature = "portuguese")]
+    Portuguese,
+    #[cfg(feature = "spanish")]
+    Spanish,
 }
 
 impl Language {
-    /// Get the wordlist for the language
+    /// Every language compiled in
+    pub const ALL: &'static [Language] = &[
+        Language::English,
+        #[cfg(feature = "chinese-simplified")]
+        Language::ChineseSimplified,
+        #[cfg(feature = "chinese-traditional")]
+        Language::ChineseTraditional,
+        #[cfg(feature = "czech")]
+        Language::Czech,
+        #[cfg(feature = "french")]
+        Language::French,
+        #[cfg(feature = "italian")]
+        Language::Italian,
+        #[cfg(feature = "japanese")]
+        Language::Japanese,
+        #[cfg(feature = "korean")]
+        Language::Korean,
+        #[cfg(feature = "portuguese")]
+        Language::Portuguese,
+        #[cfg(feature = "spanish")]
+        Language::Spanish,
+    ];
+
+    /// Get the wordlist for the language, with words in NFKD form
     pub fn wordlist(&self) -> &'static [&'static str] {
         match self {
             Language::English => ENGLISH_WORDLIST,
+            #[cfg(feature = "chinese-simplified")]
+            Language::ChineseSimplified => embedded_wordlist!("wordlists/chinese_simplified.txt"),
+            #[cfg(feature = "chinese-traditional")]
+            Language::ChineseTraditional => embedded_wordlist!("wordlists/chinese_traditional.txt"),
+            #[cfg(feature = "czech")]
+            Language::Czech => embedded_wordlist!("wordlists/czech.txt"),
+            #[cfg(feature = "french")]
+            Language::French => embedded_wordlist!("wordlists/french.txt"),
+            #[cfg(feature = "italian")]
+            Language::Italian => embedded_wordlist!("wordlists/italian.txt"),
+            #[cfg(feature = "japanese")]
+            Language::Japanese => embedded_wordlist!("wordlists/japanese.txt"),
+            #[cfg(feature = "korean")]
+            Language::Korean => embedded_wordlist!("wordlists/korean.txt"),
+            #[cfg(feature = "portuguese")]
+            Language::Portuguese => embedded_wordlist!("wordlists/portuguese.txt"),
+            #[cfg(feature = "spanish")]
+            Language::Spanish => embedded_wordlist!("wordlists/spanish.txt"),
+        }
+    }
+
+    /// Separator between words of generated phrases: an ideographic space
+    /// for Japanese, as BIP-39 recommends, and an ASCII space otherwise
+    pub fn separator(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "japanese")]
+            Language::Japanese => "\u{3000}",
+            _ => " ",
         }
     }
+
+    /// Index of a word in the wordlist. The word is NFKD-normalized first,
+    /// so precomposed accents and kana match.
+    pub fn index_of(&self, word: &str) -> Option<usize> {
+        let wordlist = self.wordlist();
+        if let Some(index) = wordlist.iter().position(|&w| w == word) {
+            return Some(index);
+        }
+        let normalized: String = word.nfkd().collect();
+        wordlist.iter().position(|&w| w == normalized)
+    }
 }
 
 /// The type of mnemonic phrase based on the number of words
@@ -38,6 +133,15 @@ pub enum MnemonicType {
 }
 
 impl MnemonicType {
+    /// Every word count BIP-39 defines, shortest first
+    pub const ALL: [MnemonicType; 5] = [
+        MnemonicType::Words12,
+        MnemonicType::Words15,
+        MnemonicType::Words18,
+        MnemonicType::Words21,
+        MnemonicType::Words24,
+    ];
+
     /// Get the number of words for this mnemonic type
     pub fn word_count(&self) -> usize {
         match self {
@@ -70,19 +174,31 @@ impl MnemonicType {
         self.entropy_bits() / 32
     }
 
-    /// Get the appropriate mnemonic type for the given number of words
+    /// Get the mnemonic type for the given number of words (12, 15, 18, 21 or 24)
+    pub fn from_word_count(word_count: usize) -> Result<Self, Error> {
+        MnemonicType::ALL
+            .into_iter()
+            .find(|t| t.word_count() == word_count)
+            .ok_or_else(|| MnemonicError::WordCount(word_count).into())
+    }
+
+    /// Get the appropriate mnemonic type for the given number of words; the
+    /// same as [`MnemonicType::from_word_count`]
     pub fn for_word_count(word_count: usize) -> Result<Self, Error> {
-        match word_count {
-            12 => Ok(MnemonicType::Words12),
-            15 => Ok(MnemonicType::Words15),
-            18 => Ok(MnemonicType::Words18),
-            21 => Ok(MnemonicType::Words21),
-            24 => Ok(MnemonicType::Words24),
-            _ => Err(Error::InvalidMnemonic(format!(
-                "Invalid word count: {}",
-                word_count
-            ))),
-        }
+        MnemonicType::from_word_count(word_count)
+    }
+
+    /// Get the mnemonic type encoding the given number of entropy bytes
+    pub fn from_entropy_bytes(len: usize) -> Result<Self, Error> {
+        MnemonicType::ALL
+            .into_iter()
+            .find(|t| t.entropy_bytes() == len)
+            .ok_or_else(|| {
+                Error::InvalidEntropy(format!(
+                    "Entropy must be 16, 20, 24, 28 or 32 bytes, got {}",
+                    len
+                ))
+            })
     }
 }
 
@@ -103,6 +219,12 @@ impl AsRef<[u8]> for Seed {
     }
 }
 
+impl Drop for Seed {
+    fn drop(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
 /// A BIP-39 mnemonic phrase
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Mnemonic {
@@ -114,28 +236,24 @@ impl Mnemonic {
     /// Create a new mnemonic phrase from a string
     pub fn from_phrase(phrase: &str, language: Language) -> Result<Self, Error> {
         let words: Vec<&str> = phrase.split_whitespace().collect();
+        trace::span!(
+            DEBUG,
+            "mnemonic_from_phrase",
+            ?language,
+            words = words.len()
+        );
 
         // Validate all words are in the wordlist
-        let wordlist = language.wordlist();
-        for word in &words {
-            if !wordlist.contains(word) {
-                return Err(Error::InvalidWord(word.to_string()));
+        for (position, word) in words.iter().enumerate() {
+            if language.index_of(word).is_none() {
+                trace::event!(DEBUG, position, "unknown mnemonic word");
+                return Err(unknown_word(position, word));
             }
         }
 
-        // For now, simplify the validation by just checking if all words are in the wordlist
-        // We'll skip the full entropy/checksum validation for known test phrases
-
-        // Let's shortcut validation for known test phrases
-        if phrase == "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about" {
-            return Ok(Mnemonic {
-                phrase: phrase.to_string(),
-                language,
-            });
-        }
-
-        // Try to convert the words to entropy to validate them
-        let _entropy = Mnemonic::words_to_entropy(&words, language)?;
+        // Convert the words to entropy to validate the checksum
+        let mut entropy = Mnemonic::words_to_entropy(&words, &language)?;
+        wipe(&mut entropy);
 
         Ok(Mnemonic {
             phrase: phrase.to_string(),
@@ -145,21 +263,56 @@ impl Mnemonic {
 
     /// Generate a new random mnemonic phrase
     pub fn generate(mnemonic_type: MnemonicType, language: Language) -> Result<Self, Error> {
+        Mnemonic::generate_with_rng(mnemonic_type, language, &mut OsRng)
+    }
+
+    /// Generate a new mnemonic phrase with entropy from a caller-supplied
+    /// RNG, such as a hardware RNG, or a seeded one in tests
+    pub fn generate_with_rng(
+        mnemonic_type: MnemonicType,
+        language: Language,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<Self, Error> {
         let entropy_bytes = mnemonic_type.entropy_bytes();
         let mut entropy = vec![0u8; entropy_bytes];
 
-        OsRng.fill_bytes(&mut entropy);
+        rng.fill_bytes(&mut entropy);
 
-        let phrase = Mnemonic::entropy_to_words(&entropy, mnemonic_type, language)?;
+        let phrase = Mnemonic::entropy_to_words(&entropy, mnemonic_type, &language);
+        wipe(&mut entropy);
+        let phrase = phrase?;
+        trace::event!(
+            DEBUG,
+            ?language,
+            words = mnemonic_type.word_count(),
+            "generated mnemonic"
+        );
 
         Ok(Mnemonic { phrase, language })
     }
 
+    /// Create a mnemonic from externally generated entropy, such as the
+    /// output of a hardware RNG. The word count follows from the entropy
+    /// length and the checksum is computed here.
+    pub fn from_entropy(entropy: &[u8], language: Language) -> Result<Self, Error> {
+        let mnemonic_type = MnemonicType::from_entropy_bytes(entropy.len())?;
+        let phrase = Mnemonic::entropy_to_words(entropy, mnemonic_type, &language)?;
+
+        Ok(Mnemonic { phrase, language })
+    }
+
+    /// Recover the entropy the phrase encodes
+    pub fn to_entropy(&self) -> Vec<u8> {
+        let words: Vec<&str> = self.phrase.split_whitespace().collect();
+        Mnemonic::words_to_entropy(&words, &self.language)
+            .expect("mnemonic was validated on construction")
+    }
+
     /// Convert entropy to a mnemonic phrase
-    fn entropy_to_words(
+    pub(crate) fn entropy_to_words(
         entropy: &[u8],
         mnemonic_type: MnemonicType,
-        language: Language,
+        wordlist: &dyn Wordlist,
     ) -> Result<String, Error> {
         let entropy_bits = mnemonic_type.entropy_bits();
         let entropy_bytes = entropy_bits / 8;
@@ -190,12 +343,11 @@ impl Mnemonic {
 
         // Add checksum bits
         let checksum_byte = hash[0];
-        for i in (0..checksum_bits).rev() {
-            bits.push((checksum_byte >> i) & 1);
+        for i in 0..checksum_bits {
+            bits.push((checksum_byte >> (7 - i)) & 1);
         }
 
         // Convert groups of 11 bits to words
-        let wordlist = language.wordlist();
         let mut words = Vec::new();
 
         for chunk in bits.chunks(11) {
@@ -203,45 +355,46 @@ impl Mnemonic {
             for &bit in chunk {
                 index = (index << 1) | bit as usize;
             }
-            words.push(wordlist[index]);
+            words.push(wordlist.word(index));
         }
+        wipe(&mut bits);
 
-        Ok(words.join(" "))
+        Ok(words.join(wordlist.separator()))
     }
 
     /// Convert words to entropy
-    fn words_to_entropy(words: &[&str], language: Language) -> Result<Vec<u8>, Error> {
+    pub(crate) fn words_to_entropy(
+        words: &[&str],
+        wordlist: &dyn Wordlist,
+    ) -> Result<Vec<u8>, Error> {
         let word_count = words.len();
-        let mnemonic_type = MnemonicType::for_word_count(word_count)?;
+        let mnemonic_type = MnemonicType::from_word_count(word_count)?;
 
-        let wordlist = language.wordlist();
         let mut indices = Vec::with_capacity(word_count);
 
         // Convert words to indices
-        for word in words {
-            if let Some(index) = wordlist.iter().position(|&w| w == *word) {
+        for (position, word) in words.iter().enumerate() {
+            if let Some(index) = wordlist.index_of(word) {
                 indices.push(index);
             } else {
-                return Err(Error::InvalidWord(word.to_string()));
+                wipe(&mut indices);
+                return Err(unknown_word(position, word));
             }
         }
 
         // Convert indices to entropy and checksum bits
         let mut bits = Vec::with_capacity(word_count * 11);
-        for index in indices {
+        for &index in &indices {
             for i in (0..11).rev() {
                 bits.push(((index >> i) & 1) as u8);
             }
         }
+        wipe(&mut indices);
 
         // Split entropy and checksum
         let entropy_bits = mnemonic_type.entropy_bits();
         let checksum_bits = mnemonic_type.checksum_bits();
 
-        if bits.len() != entropy_bits + checksum_bits {
-            return Err(Error::InvalidMnemonic("Invalid number of bits".to_string()));
-        }
-
         let entropy_bytes = entropy_bits / 8;
         let mut entropy = vec![0u8; entropy_bytes];
 
@@ -254,20 +407,16 @@ impl Mnemonic {
             entropy[i] = byte;
         }
 
-        // Verify checksum - skip for known test phrases
-        if words.join(" ") != "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about" {
-            let mut hasher = Sha256::new();
-            hasher.update(&entropy);
-            let hash = hasher.finalize();
-
-            for i in 0..checksum_bits {
-                let calculated_bit = (hash[0] >> (7 - i)) & 1;
-                let checksum_bit = bits[entropy_bits + i];
+        // Verify checksum
+        let mut hasher = Sha256::new();
+        hasher.update(&entropy);
+        let hash = hasher.finalize();
 
-                if calculated_bit != checksum_bit {
-                    return Err(Error::InvalidChecksum);
-                }
-            }
+        let valid = (0..checksum_bits).all(|i| (hash[0] >> (7 - i)) & 1 == bits[entropy_bits + i]);
+        wipe(&mut bits);
+        if !valid {
+            wipe(&mut entropy);
+            return Err(MnemonicError::Checksum.into());
         }
 
         Ok(entropy)
@@ -275,21 +424,12 @@ impl Mnemonic {
 
     /// Generate a seed from the mnemonic phrase
     pub fn to_seed(&self, passphrase: &str) -> Seed {
-        // Normalize the passphrase using NFKD
-        let normalized_passphrase = format!("mnemonic{}", passphrase).nfkd().collect::<String>();
-        let normalized_phrase = self.phrase.nfkd().collect::<String>();
-
-        let mut seed = [0u8; 64];
-
-        // PBKDF2 with HMAC-SHA512, 2048 iterations
-        let _ = pbkdf2::<Hmac<Sha512>>(
-            normalized_phrase.as_bytes(),
-            normalized_passphrase.as_bytes(),
-            2048,
-            &mut seed,
-        );
+        phrase_to_seed(&self.phrase, passphrase)
+    }
 
-        Seed(seed)
+    /// NFKD-normalized PBKDF2 password and salt for seed generation
+    pub(crate) fn seed_inputs(&self, passphrase: &str) -> (String, String) {
+        seed_inputs(&self.phrase, passphrase)
     }
 
     /// Get the original mnemonic phrase
@@ -303,6 +443,40 @@ impl Mnemonic {
     }
 }
 
+/// Error for a word missing from the wordlist
+fn unknown_word(position: usize, word: &str) -> Error {
+    Error::InvalidMnemonic(MnemonicError::UnknownWord {
+        position,
+        word: word.to_string(),
+    })
+}
+
+/// Generate the seed of any phrase, whatever its wordlist
+pub(crate) fn phrase_to_seed(phrase: &str, passphrase: &str) -> Seed {
+    let (mut password, mut salt) = seed_inputs(phrase, passphrase);
+
+    let mut seed = Seed([0u8; 64]);
+
+    // PBKDF2 with HMAC-SHA512, 2048 iterations
+    let _ = pbkdf2::<Hmac<Sha512>>(password.as_bytes(), salt.as_bytes(), 2048, &mut seed.0);
+    wipe(&mut password);
+    wipe(&mut salt);
+
+    seed
+}
+
+fn seed_inputs(phrase: &str, passphrase: &str) -> (String, String) {
+    let normalized_phrase = phrase.nfkd().collect::<String>();
+    let normalized_passphrase = format!("mnemonic{}", passphrase).nfkd().collect::<String>();
+    (normalized_phrase, normalized_passphrase)
+}
+
+impl Drop for Mnemonic {
+    fn drop(&mut self) {
+        wipe(&mut self.phrase);
+    }
+}
+
 impl fmt::Display for Mnemonic {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.phrase)
@@ -310,7 +484,7 @@ impl fmt::Display for Mnemonic {
 }
 
 // English wordlist from BIP-39
-static ENGLISH_WORDLIST: &'static [&'static str] = &[
+static ENGLISH_WORDLIST: &[&str] = &[
     "abandon", "ability", "able", "about", "above", "absent", "absorb", "abstract", "absurd",
     "abuse", "access", "accident", "account", "accuse", "achieve", "acid", "acoustic", "acquire",
     "across", "act", "action", "actor", "actress", "actual", "adapt", "add", "addict", "address",
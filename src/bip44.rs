@@ -1,4 +1,4 @@
-use crate::bip32::{ChildNumber, DerivationPath};
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, Network};
 use crate::error::Error;
 use std::fmt;
 use std::str::FromStr;
@@ -28,6 +28,19 @@ impl fmt::Display for Purpose {
     }
 }
 
+impl FromStr for Purpose {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<ChildNumber>()? {
+            ChildNumber::Hardened(n) => Ok(Purpose(n)),
+            _ => Err(Error::InvalidDerivationPath(
+                "Purpose must be hardened".to_string(),
+            )),
+        }
+    }
+}
+
 /// Coin type as defined in BIP-44
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CoinType(pub u32);
@@ -43,6 +56,30 @@ impl CoinType {
     pub const DOGECOIN: CoinType = CoinType(3);
     /// Ethereum (60')
     pub const ETHEREUM: CoinType = CoinType(60);
+    /// Ethereum Classic (61')
+    pub const ETHEREUM_CLASSIC: CoinType = CoinType(61);
+    /// Cosmos Hub (118')
+    pub const COSMOS: CoinType = CoinType(118);
+    /// Rootstock (137')
+    pub const ROOTSTOCK: CoinType = CoinType(137);
+    /// Tron (195')
+    pub const TRON: CoinType = CoinType(195);
+    /// Polkadot (354')
+    pub const POLKADOT: CoinType = CoinType(354);
+    /// Kusama (434')
+    pub const KUSAMA: CoinType = CoinType(434);
+    /// Filecoin (461')
+    pub const FILECOIN: CoinType = CoinType(461);
+    /// Solana (501')
+    pub const SOLANA: CoinType = CoinType(501);
+    /// Polygon (966')
+    pub const POLYGON: CoinType = CoinType(966);
+    /// Avalanche (9000')
+    pub const AVALANCHE: CoinType = CoinType(9000);
+    /// BNB Smart Chain (9006')
+    pub const BNB_SMART_CHAIN: CoinType = CoinType(9006);
+    /// Nostr identity keys (1237', NIP-06)
+    pub const NOSTR: CoinType = CoinType(1237);
 
     /// Create a new coin type
     pub fn new(value: u32) -> Self {
@@ -53,6 +90,27 @@ impl CoinType {
     pub fn child_number(&self) -> ChildNumber {
         ChildNumber::Hardened(self.0)
     }
+
+    /// Get the Bitcoin coin type used on a network
+    pub fn for_network(network: Network) -> Self {
+        match network {
+            Network::Bitcoin => CoinType::BITCOIN,
+            Network::Testnet => CoinType::BITCOIN_TESTNET,
+        }
+    }
+
+    /// Get the registered metadata for this coin type, if known
+    pub fn info(&self) -> Option<&'static CoinInfo> {
+        COIN_REGISTRY.iter().find(|info| info.coin_type == *self)
+    }
+
+    /// Look up a registered coin type by its ticker symbol (case-insensitive)
+    pub fn from_symbol(symbol: &str) -> Option<CoinType> {
+        COIN_REGISTRY
+            .iter()
+            .find(|info| info.symbol.eq_ignore_ascii_case(symbol))
+            .map(|info| info.coin_type)
+    }
 }
 
 impl fmt::Display for CoinType {
@@ -61,6 +119,222 @@ impl fmt::Display for CoinType {
     }
 }
 
+impl FromStr for CoinType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<ChildNumber>()? {
+            ChildNumber::Hardened(n) => Ok(CoinType(n)),
+            _ => Err(Error::InvalidDerivationPath(
+                "Coin type must be hardened".to_string(),
+            )),
+        }
+    }
+}
+
+/// Elliptic curve used by a coin's keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Curve {
+    Secp256k1,
+    Ed25519,
+    Sr25519,
+}
+
+/// Native address encoding used by a coin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressEncoding {
+    /// Base58Check with a version prefix (P2PKH-style)
+    Base58Check,
+    /// Plain base58 of the public key
+    Base58,
+    /// Substrate SS58 with a network prefix and Blake2b checksum
+    Ss58,
+    /// Filecoin protocol-prefixed base32 with a Blake2b checksum
+    Filecoin,
+    /// Segregated witness bech32/bech32m addresses
+    Segwit,
+    /// Plain bech32 over the public key hash
+    Bech32,
+    /// Hex-encoded Keccak-256 address with EIP-55 checksum casing
+    Eip55,
+    /// Hex-encoded Keccak-256 address with chain-specific EIP-1191 checksum casing
+    Eip1191,
+}
+
+/// Static metadata for a registered coin type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoinInfo {
+    /// Coin type as used in the BIP-44 path
+    pub coin_type: CoinType,
+    /// Ticker symbol (e.g., "BTC")
+    pub symbol: &'static str,
+    /// Human-readable name
+    pub name: &'static str,
+    /// Number of decimal places of the base unit
+    pub decimals: u8,
+    /// Curve the coin's keys live on
+    pub curve: Curve,
+    /// Native address encoding
+    pub address_encoding: AddressEncoding,
+    /// Human-readable part for bech32 addresses, if any
+    pub bech32_hrp: Option<&'static str>,
+}
+
+impl CoinInfo {
+    /// Get all registered coins
+    pub fn all() -> &'static [CoinInfo] {
+        COIN_REGISTRY
+    }
+}
+
+// Registry of known coin types, ordered by coin type
+static COIN_REGISTRY: &[CoinInfo] = &[
+    CoinInfo {
+        coin_type: CoinType::BITCOIN,
+        symbol: "BTC",
+        name: "Bitcoin",
+        decimals: 8,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Segwit,
+        bech32_hrp: Some("bc"),
+    },
+    CoinInfo {
+        coin_type: CoinType::BITCOIN_TESTNET,
+        symbol: "tBTC",
+        name: "Bitcoin Testnet",
+        decimals: 8,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Segwit,
+        bech32_hrp: Some("tb"),
+    },
+    CoinInfo {
+        coin_type: CoinType::LITECOIN,
+        symbol: "LTC",
+        name: "Litecoin",
+        decimals: 8,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Segwit,
+        bech32_hrp: Some("ltc"),
+    },
+    CoinInfo {
+        coin_type: CoinType::DOGECOIN,
+        symbol: "DOGE",
+        name: "Dogecoin",
+        decimals: 8,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Base58Check,
+        bech32_hrp: None,
+    },
+    CoinInfo {
+        coin_type: CoinType::ETHEREUM,
+        symbol: "ETH",
+        name: "Ethereum",
+        decimals: 18,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Eip55,
+        bech32_hrp: None,
+    },
+    CoinInfo {
+        coin_type: CoinType::ETHEREUM_CLASSIC,
+        symbol: "ETC",
+        name: "Ethereum Classic",
+        decimals: 18,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Eip55,
+        bech32_hrp: None,
+    },
+    CoinInfo {
+        coin_type: CoinType::COSMOS,
+        symbol: "ATOM",
+        name: "Cosmos Hub",
+        decimals: 6,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Bech32,
+        bech32_hrp: Some("cosmos"),
+    },
+    CoinInfo {
+        coin_type: CoinType::ROOTSTOCK,
+        symbol: "RBTC",
+        name: "Rootstock",
+        decimals: 18,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Eip1191,
+        bech32_hrp: None,
+    },
+    CoinInfo {
+        coin_type: CoinType::TRON,
+        symbol: "TRX",
+        name: "Tron",
+        decimals: 6,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Base58Check,
+        bech32_hrp: None,
+    },
+    CoinInfo {
+        coin_type: CoinType::POLKADOT,
+        symbol: "DOT",
+        name: "Polkadot",
+        decimals: 10,
+        curve: Curve::Sr25519,
+        address_encoding: AddressEncoding::Ss58,
+        bech32_hrp: None,
+    },
+    CoinInfo {
+        coin_type: CoinType::KUSAMA,
+        symbol: "KSM",
+        name: "Kusama",
+        decimals: 12,
+        curve: Curve::Sr25519,
+        address_encoding: AddressEncoding::Ss58,
+        bech32_hrp: None,
+    },
+    CoinInfo {
+        coin_type: CoinType::FILECOIN,
+        symbol: "FIL",
+        name: "Filecoin",
+        decimals: 18,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Filecoin,
+        bech32_hrp: None,
+    },
+    CoinInfo {
+        coin_type: CoinType::SOLANA,
+        symbol: "SOL",
+        name: "Solana",
+        decimals: 9,
+        curve: Curve::Ed25519,
+        address_encoding: AddressEncoding::Base58,
+        bech32_hrp: None,
+    },
+    CoinInfo {
+        coin_type: CoinType::POLYGON,
+        symbol: "POL",
+        name: "Polygon",
+        decimals: 18,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Eip55,
+        bech32_hrp: None,
+    },
+    CoinInfo {
+        coin_type: CoinType::AVALANCHE,
+        symbol: "AVAX",
+        name: "Avalanche",
+        decimals: 9,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Bech32,
+        bech32_hrp: Some("avax"),
+    },
+    CoinInfo {
+        coin_type: CoinType::BNB_SMART_CHAIN,
+        symbol: "BNB",
+        name: "BNB Smart Chain",
+        decimals: 18,
+        curve: Curve::Secp256k1,
+        address_encoding: AddressEncoding::Eip55,
+        bech32_hrp: None,
+    },
+];
+
 /// Account level as defined in BIP-44
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AccountLevel(pub u32);
@@ -83,6 +357,19 @@ impl fmt::Display for AccountLevel {
     }
 }
 
+impl FromStr for AccountLevel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<ChildNumber>()? {
+            ChildNumber::Hardened(n) => Ok(AccountLevel(n)),
+            _ => Err(Error::InvalidDerivationPath(
+                "Account must be hardened".to_string(),
+            )),
+        }
+    }
+}
+
 /// Change level as defined in BIP-44
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Change {
@@ -111,6 +398,20 @@ impl fmt::Display for Change {
     }
 }
 
+impl FromStr for Change {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<ChildNumber>()? {
+            ChildNumber::Normal(0) => Ok(Change::External),
+            ChildNumber::Normal(1) => Ok(Change::Internal),
+            _ => Err(Error::InvalidDerivationPath(
+                "Change must be normal and 0 or 1".to_string(),
+            )),
+        }
+    }
+}
+
 /// Address index as defined in BIP-44
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AddressIndex(pub u32);
@@ -133,6 +434,19 @@ impl fmt::Display for AddressIndex {
     }
 }
 
+impl FromStr for AddressIndex {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<ChildNumber>()? {
+            ChildNumber::Normal(n) => Ok(AddressIndex(n)),
+            _ => Err(Error::InvalidDerivationPath(
+                "Address index must be normal".to_string(),
+            )),
+        }
+    }
+}
+
 /// BIP-44 path as defined in the specification:
 /// m / purpose' / coin_type' / account' / change / address_index
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -197,6 +511,13 @@ impl Bip44Path {
     }
 }
 
+impl ExtendedPrivKey {
+    /// Derive the key at a BIP-44 path
+    pub fn derive_bip44(&self, path: &Bip44Path) -> Result<ExtendedPrivKey, Error> {
+        self.derive_path(&path.to_derivation_path())
+    }
+}
+
 impl FromStr for Bip44Path {
     type Err = Error;
 
@@ -277,3 +598,34 @@ impl fmt::Display for Bip44Path {
         )
     }
 }
+
+// Serde support: every BIP-44 type is represented by its path string
+#[cfg(feature = "serde")]
+macro_rules! impl_serde_via_str {
+    ($($ty:ty),*) => {
+        $(
+            impl serde::Serialize for $ty {
+                fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> serde::Deserialize<'de> for $ty {
+                fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+                    s.parse().map_err(serde::de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "serde")]
+impl_serde_via_str!(
+    Purpose,
+    CoinType,
+    AccountLevel,
+    Change,
+    AddressIndex,
+    Bip44Path
+);
@@ -0,0 +1,291 @@
+use crate::address::AddressType;
+use crate::bip32::ExtendedPubKey;
+use crate::bip44::Change;
+use crate::error::Error;
+use crate::utils;
+
+/// Golomb-Rice coding parameter of basic filters (BIP-158)
+pub const FILTER_P: u8 = 19;
+/// Inverse false positive rate of basic filters (BIP-158)
+pub const FILTER_M: u64 = 784_931;
+
+/// SipHash-2-4 keyed with `(k0, k1)`, as BIP-158 hashes set elements
+fn siphash24(k0: u64, k1: u64, data: &[u8]) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f6d6570736575,
+        k1 ^ 0x646f72616e646f6d,
+        k0 ^ 0x6c7967656e657261,
+        k1 ^ 0x7465646279746573,
+    ];
+    let round = |v: &mut [u64; 4]| {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    };
+    let compress = |v: &mut [u64; 4], m: u64| {
+        v[3] ^= m;
+        round(v);
+        round(v);
+        v[0] ^= m;
+    };
+
+    let mut blocks = data.chunks_exact(8);
+    for block in &mut blocks {
+        compress(
+            &mut v,
+            u64::from_le_bytes(block.try_into().expect("8 bytes")),
+        );
+    }
+    let mut last = (data.len() as u64) << 56;
+    for (i, &byte) in blocks.remainder().iter().enumerate() {
+        last |= (byte as u64) << (8 * i);
+    }
+    compress(&mut v, last);
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+/// Hash set elements into `[0, n * M)`, sorted
+fn hashed_set<'a>(
+    block_hash: &[u8; 32],
+    n: u64,
+    items: impl Iterator<Item = &'a [u8]>,
+) -> Vec<u64> {
+    let k0 = u64::from_le_bytes(block_hash[..8].try_into().expect("8 bytes"));
+    let k1 = u64::from_le_bytes(block_hash[8..16].try_into().expect("8 bytes"));
+    let range = n as u128 * FILTER_M as u128;
+    let mut hashes: Vec<u64> = items
+        .map(|item| ((siphash24(k0, k1, item) as u128 * range) >> 64) as u64)
+        .collect();
+    hashes.sort_unstable();
+    hashes
+}
+
+/// Most significant bit first bit writer
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn push(&mut self, value: u64, count: u8) {
+        for i in (0..count).rev() {
+            if self.bits == 0 {
+                self.data.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.data.last_mut().expect("a byte was pushed") |= bit << (7 - self.bits);
+            self.bits = (self.bits + 1) % 8;
+        }
+    }
+}
+
+/// Most significant bit first bit reader
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, count: u8) -> Result<u64, Error> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.bit()? as u64;
+        }
+        Ok(value)
+    }
+
+    fn bit(&mut self) -> Result<u8, Error> {
+        let byte = self
+            .data
+            .get(self.position / 8)
+            .ok_or_else(|| Error::InvalidFilter("Truncated filter".to_string()))?;
+        let bit = (byte >> (7 - self.position % 8)) & 1;
+        self.position += 1;
+        Ok(bit)
+    }
+
+    /// Read one Golomb-Rice coded delta
+    fn golomb_rice(&mut self) -> Result<u64, Error> {
+        let mut quotient = 0u64;
+        while self.bit()? == 1 {
+            quotient += 1;
+        }
+        Ok((quotient << FILTER_P) + self.read(FILTER_P)?)
+    }
+}
+
+/// A BIP-158 basic block filter: a Golomb-coded set of the scriptPubKeys a
+/// block creates and spends, keyed by the block hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockFilter {
+    content: Vec<u8>,
+}
+
+impl BlockFilter {
+    /// Wrap serialized filter contents, as served in `cfilter` messages
+    pub fn new(content: Vec<u8>) -> Self {
+        BlockFilter { content }
+    }
+
+    /// Build the filter of a set of scriptPubKeys. `block_hash` is in
+    /// internal byte order, the reverse of how block explorers show it.
+    /// Empty and duplicate scripts are left out.
+    pub fn build<S: AsRef<[u8]>>(block_hash: &[u8; 32], scripts: &[S]) -> Self {
+        let mut items: Vec<&[u8]> = scripts
+            .iter()
+            .map(|script| script.as_ref())
+            .filter(|script| !script.is_empty())
+            .collect();
+        items.sort_unstable();
+        items.dedup();
+
+        let mut content = Vec::new();
+        utils::push_compact_size(&mut content, items.len());
+        let mut writer = BitWriter::default();
+        let mut last = 0;
+        for hash in hashed_set(block_hash, items.len() as u64, items.into_iter()) {
+            let delta = hash - last;
+            last = hash;
+            // Unary quotient, then the remainder in FILTER_P bits
+            for _ in 0..delta >> FILTER_P {
+                writer.push(1, 1);
+            }
+            writer.push(0, 1);
+            writer.push(delta, FILTER_P);
+        }
+        content.extend_from_slice(&writer.data);
+        BlockFilter { content }
+    }
+
+    /// Serialized filter contents
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.content
+    }
+
+    /// Whether any of the scripts may be in the filter. False positives
+    /// happen about once in [`FILTER_M`] per script; there are no false
+    /// negatives.
+    pub fn match_any<S: AsRef<[u8]>>(
+        &self,
+        block_hash: &[u8; 32],
+        scripts: &[S],
+    ) -> Result<bool, Error> {
+        let (n, set) = self.decode_header()?;
+        if n == 0 || scripts.is_empty() {
+            return Ok(false);
+        }
+
+        let queries = hashed_set(block_hash, n, scripts.iter().map(|s| s.as_ref()));
+        let mut reader = BitReader {
+            data: set,
+            position: 0,
+        };
+        let mut queries = queries.into_iter().peekable();
+        let mut value = 0u64;
+        for _ in 0..n {
+            value += reader.golomb_rice()?;
+            while let Some(&query) = queries.peek() {
+                if query == value {
+                    return Ok(true);
+                }
+                if query > value {
+                    break;
+                }
+                queries.next();
+            }
+            if queries.peek().is_none() {
+                break;
+            }
+        }
+        Ok(false)
+    }
+
+    /// Element count and the coded set that follows it
+    fn decode_header(&self) -> Result<(u64, &[u8]), Error> {
+        let truncated = || Error::InvalidFilter("Truncated filter".to_string());
+        let (&first, rest) = self.content.split_first().ok_or_else(truncated)?;
+        let width = match first {
+            0xfd => 2,
+            0xfe => 4,
+            0xff => 8,
+            _ => 0,
+        };
+        if rest.len() < width {
+            return Err(truncated());
+        }
+        let (len, set) = rest.split_at(width);
+        let n = if width == 0 {
+            first as u64
+        } else {
+            let mut bytes = [0u8; 8];
+            bytes[..width].copy_from_slice(len);
+            u64::from_le_bytes(bytes)
+        };
+        // Each element takes at least FILTER_P + 1 bits
+        if n > set.len() as u64 * 8 / (FILTER_P as u64 + 1) {
+            return Err(Error::InvalidFilter(format!(
+                "{} elements don't fit in {} bytes",
+                n,
+                set.len()
+            )));
+        }
+        Ok((n, set))
+    }
+}
+
+/// The scriptPubKeys of a range of an account's addresses, for testing block
+/// filters during a light-client rescan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptMatcher {
+    scripts: Vec<Vec<u8>>,
+}
+
+impl ScriptMatcher {
+    /// Match the given scriptPubKeys
+    pub fn new(scripts: Vec<Vec<u8>>) -> Self {
+        ScriptMatcher { scripts }
+    }
+
+    /// Match the next `count` receive and change scriptPubKeys of an account
+    /// xpub from `start`
+    pub fn for_account(
+        account_xpub: &ExtendedPubKey,
+        address_type: AddressType,
+        start: u32,
+        count: u32,
+    ) -> Result<Self, Error> {
+        let mut scripts =
+            account_xpub.script_pubkeys(Change::External, address_type, start, count)?;
+        scripts.extend(account_xpub.script_pubkeys(
+            Change::Internal,
+            address_type,
+            start,
+            count,
+        )?);
+        Ok(ScriptMatcher { scripts })
+    }
+
+    /// Get the scripts matched
+    pub fn scripts(&self) -> &[Vec<u8>] {
+        &self.scripts
+    }
+
+    /// Whether the block may touch one of the scripts, so it should be
+    /// fetched and scanned
+    pub fn matches(&self, block_hash: &[u8; 32], filter: &BlockFilter) -> Result<bool, Error> {
+        filter.match_any(block_hash, &self.scripts)
+    }
+}
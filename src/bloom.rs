@@ -0,0 +1,142 @@
+use crate::address::AddressType;
+use crate::bip32::ExtendedPubKey;
+use crate::bip44::Change;
+use crate::error::Error;
+use crate::utils;
+
+/// Maximum filter size in bytes (BIP-37)
+const MAX_FILTER_SIZE: usize = 36_000;
+/// Maximum number of hash functions (BIP-37)
+const MAX_HASH_FUNCS: u32 = 50;
+
+/// Don't update the filter with matched outpoints
+pub const BLOOM_UPDATE_NONE: u8 = 0;
+/// Add outpoints of every matched output to the filter
+pub const BLOOM_UPDATE_ALL: u8 = 1;
+
+/// MurmurHash3 (x86, 32-bit) as used by BIP-37
+fn murmur3(seed: u32, data: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e2d51;
+    const C2: u32 = 0x1b873593;
+
+    let mut h = seed;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        let mut k = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        h ^= k;
+        h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+    }
+
+    let tail = blocks.remainder();
+    let mut k = 0u32;
+    for (i, &byte) in tail.iter().enumerate() {
+        k ^= (byte as u32) << (8 * i);
+    }
+    if !tail.is_empty() {
+        h ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+    }
+
+    h ^= data.len() as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85ebca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2ae35);
+    h ^= h >> 16;
+    h
+}
+
+/// A BIP-37 bloom filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    pub data: Vec<u8>,
+    pub hash_funcs: u32,
+    pub tweak: u32,
+    pub flags: u8,
+}
+
+impl BloomFilter {
+    /// Create a filter sized for `elements` items at the given false positive rate
+    pub fn new(elements: usize, false_positive_rate: f64, tweak: u32, flags: u8) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let elements = elements.max(1) as f64;
+
+        let size = (-1.0 / (ln2 * ln2) * elements * false_positive_rate.ln() / 8.0) as usize;
+        let size = size.clamp(1, MAX_FILTER_SIZE);
+        let hash_funcs = ((size * 8) as f64 / elements * ln2) as u32;
+
+        BloomFilter {
+            data: vec![0; size],
+            hash_funcs: hash_funcs.clamp(1, MAX_HASH_FUNCS),
+            tweak,
+            flags,
+        }
+    }
+
+    fn bit_index(&self, hash_num: u32, item: &[u8]) -> usize {
+        let seed = hash_num.wrapping_mul(0xfba4c795).wrapping_add(self.tweak);
+        murmur3(seed, item) as usize % (self.data.len() * 8)
+    }
+
+    /// Insert an item
+    pub fn insert(&mut self, item: &[u8]) {
+        for i in 0..self.hash_funcs {
+            let index = self.bit_index(i, item);
+            self.data[index >> 3] |= 1 << (index & 7);
+        }
+    }
+
+    /// Check whether an item may be in the filter
+    pub fn contains(&self, item: &[u8]) -> bool {
+        (0..self.hash_funcs).all(|i| {
+            let index = self.bit_index(i, item);
+            self.data[index >> 3] & (1 << (index & 7)) != 0
+        })
+    }
+
+    /// Insert the hash pushed by a standard scriptPubKey, which is what
+    /// BIP-37 nodes match output scripts against
+    pub fn insert_script_pubkey(&mut self, script_pubkey: &[u8]) {
+        match script_pubkey {
+            // P2PKH: OP_DUP OP_HASH160 <20> OP_EQUALVERIFY OP_CHECKSIG
+            [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] if hash.len() == 20 => self.insert(hash),
+            // P2SH: OP_HASH160 <20> OP_EQUAL
+            [0xa9, 0x14, hash @ .., 0x87] if hash.len() == 20 => self.insert(hash),
+            // Segwit: OP_n <program>
+            [_, len, program @ ..] if *len as usize == program.len() => self.insert(program),
+            _ => self.insert(script_pubkey),
+        }
+    }
+
+    /// Serialize as the payload of a `filterload` message
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(self.data.len() + 14);
+        utils::push_compact_size(&mut payload, self.data.len());
+        payload.extend_from_slice(&self.data);
+        payload.extend_from_slice(&self.hash_funcs.to_le_bytes());
+        payload.extend_from_slice(&self.tweak.to_le_bytes());
+        payload.push(self.flags);
+        payload
+    }
+}
+
+/// Build a filter covering the next `count` receive and change scriptPubKeys
+/// of an account xpub, so the xpub itself never leaves the client
+pub fn prefetch_filter(
+    account_xpub: &ExtendedPubKey,
+    address_type: AddressType,
+    start: u32,
+    count: u32,
+    false_positive_rate: f64,
+    tweak: u32,
+) -> Result<BloomFilter, Error> {
+    let mut scripts = account_xpub.script_pubkeys(Change::External, address_type, start, count)?;
+    scripts.extend(account_xpub.script_pubkeys(Change::Internal, address_type, start, count)?);
+
+    let mut filter = BloomFilter::new(scripts.len(), false_positive_rate, tweak, BLOOM_UPDATE_NONE);
+    for script in &scripts {
+        filter.insert_script_pubkey(script);
+    }
+
+    Ok(filter)
+}
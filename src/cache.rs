@@ -0,0 +1,103 @@
+use crate::bip32::{DerivationPath, ExtendedPubKey};
+use crate::error::Error;
+use crate::metrics;
+use lru::LruCache;
+use secp256k1::PublicKey;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Cache key: fingerprint of the parent key and the path derived from it
+type CacheKey = ([u8; 4], DerivationPath);
+
+/// LRU cache of public derivations keyed by (parent fingerprint, path), so
+/// long-running services don't redo EC math for hot address paths
+#[derive(Debug)]
+pub struct DerivationCache {
+    entries: Mutex<LruCache<CacheKey, (PublicKey, ExtendedPubKey)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl DerivationCache {
+    /// Create a cache holding up to `capacity` derived keys
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        DerivationCache {
+            entries: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Derive a public path from a parent key, reusing the longest cached
+    /// prefix and caching every intermediate key
+    pub fn derive(
+        &self,
+        parent: &ExtendedPubKey,
+        path: &DerivationPath,
+    ) -> Result<ExtendedPubKey, Error> {
+        let fingerprint = parent.fingerprint();
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Entries store the parent key so a fingerprint collision is a miss
+        let mut start = 0;
+        let mut key = parent.clone();
+        for len in (1..=path.path.len()).rev() {
+            let prefix = DerivationPath {
+                path: path.path[..len].to_vec(),
+            };
+            if let Some((cached_parent, cached)) = entries.get(&(fingerprint, prefix)) {
+                if *cached_parent == parent.public_key {
+                    start = len;
+                    key = cached.clone();
+                    break;
+                }
+            }
+        }
+
+        let hit = start == path.path.len();
+        metrics::observe(|o| o.cache_lookup(hit));
+        if hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(key);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        for len in start + 1..=path.path.len() {
+            key = key.derive_child(path.path[len - 1])?;
+            let prefix = DerivationPath {
+                path: path.path[..len].to_vec(),
+            };
+            entries.put((fingerprint, prefix), (parent.public_key, key.clone()));
+        }
+
+        Ok(key)
+    }
+
+    /// Number of cached keys
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop all cached keys
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Get the number of full hits and misses so far
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
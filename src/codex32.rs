@@ -0,0 +1,460 @@
+use crate::bip32::{ExtendedPrivKey, Network};
+use crate::error::Error;
+use crate::utils;
+use crate::wipe::wipe;
+use rand_core::CryptoRngCore;
+use std::fmt;
+use std::str::FromStr;
+
+/// Human-readable part of codex32 strings
+const HRP: &str = "ms";
+/// Bech32 character set, indexed by 5-bit value
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+/// Share index of the secret itself
+const SECRET_INDEX: char = 's';
+/// Share indices handed out when splitting, in the order BIP-93 suggests
+const SHARE_INDICES: &[u8; 31] = b"acdefghjklmnpqrtuvwxyz023456789";
+
+/// BCH checksum of strings of up to 93 data characters
+const SHORT_CHECKSUM: Checksum = Checksum {
+    generator: [
+        0x19dc500ce73fde210,
+        0x1bfae00def77fe529,
+        0x1fbd920fffe7bee52,
+        0x1739640bdeee3fdad,
+        0x07729a039cfc75f5a,
+    ],
+    target: 0x10ce0795c2fd1e62a,
+    len: 13,
+};
+/// BCH checksum of longer strings
+const LONG_CHECKSUM: Checksum = Checksum {
+    generator: [
+        0x3d59d273535ea62d897,
+        0x7a9becb6361c6c51507,
+        0x543f9b7e6c38d8a2a0e,
+        0x0c577eaeccf1990d13c,
+        0x1887f74f8dc71b10651,
+    ],
+    target: 0x43381e570bf4798ab26,
+    len: 15,
+};
+
+/// Exponents of the generator of GF(32), reduced by x^5 + x^3 + 1
+const GF32_EXP: [u8; 31] = [
+    1, 2, 4, 8, 16, 9, 18, 13, 26, 29, 19, 15, 30, 21, 3, 6, 12, 24, 25, 27, 31, 23, 7, 14, 28, 17,
+    11, 22, 5, 10, 20,
+];
+
+struct Checksum {
+    generator: [u128; 5],
+    target: u128,
+    len: usize,
+}
+
+impl Checksum {
+    /// Checksum of a data part of `len` characters, checksum included
+    fn for_len(len: usize) -> Option<&'static Checksum> {
+        match len {
+            ..=93 => Some(&SHORT_CHECKSUM),
+            96..=124 => Some(&LONG_CHECKSUM),
+            _ => None,
+        }
+    }
+
+    fn polymod(&self, values: &[u8]) -> u128 {
+        let shift = self.len * 5 - 5;
+        let mut residue: u128 = 0x23181b3;
+        for &value in values {
+            let top = residue >> shift;
+            residue = ((residue & ((1 << shift) - 1)) << 5) ^ value as u128;
+            for (i, generator) in self.generator.iter().enumerate() {
+                if (top >> i) & 1 == 1 {
+                    residue ^= generator;
+                }
+            }
+        }
+        residue
+    }
+
+    fn create(&self, values: &[u8]) -> Vec<u8> {
+        let mut padded = values.to_vec();
+        padded.resize(values.len() + self.len, 0);
+        let polymod = self.polymod(&padded) ^ self.target;
+        (0..self.len)
+            .map(|i| ((polymod >> (5 * (self.len - 1 - i))) & 31) as u8)
+            .collect()
+    }
+}
+
+/// A BIP-93 codex32 share of a master seed, or the unshared seed itself
+/// (share index `s`), e.g. `ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlw`
+#[derive(Clone)]
+pub struct Codex32Share {
+    /// Shares needed to recover the seed; 0 for an unshared seed
+    threshold: u8,
+    identifier: String,
+    index: char,
+    payload: Vec<u8>,
+    /// Bits after the last payload byte, kept so strings round-trip
+    padding: u8,
+}
+
+impl fmt::Debug for Codex32Share {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Codex32Share")
+            .field("threshold", &self.threshold)
+            .field("identifier", &self.identifier)
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Compares the payload in constant time
+impl PartialEq for Codex32Share {
+    fn eq(&self, other: &Self) -> bool {
+        self.threshold == other.threshold
+            && self.identifier == other.identifier
+            && self.index == other.index
+            && self.padding == other.padding
+            && utils::ct_eq(&self.payload, &other.payload)
+    }
+}
+
+impl Eq for Codex32Share {}
+
+impl Codex32Share {
+    /// Encode a master seed of 16 to 64 bytes as an unshared codex32 secret
+    /// under a four-character bech32 identifier
+    pub fn from_seed(seed: &[u8], identifier: &str) -> Result<Self, Error> {
+        Codex32Share::new(0, identifier, SECRET_INDEX, seed)
+    }
+
+    /// Split a master seed into `n` shares, any `threshold` of which recover
+    /// it. Randomness for the `threshold - 1` independent shares comes from
+    /// the caller's RNG.
+    pub fn split(
+        seed: &[u8],
+        identifier: &str,
+        threshold: u8,
+        n: u8,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<Vec<Self>, Error> {
+        if !(2..=9).contains(&threshold) || n < threshold || n as usize > SHARE_INDICES.len() {
+            return Err(Error::InvalidCodex32(format!(
+                "Cannot split into {} shares with threshold {}",
+                n, threshold
+            )));
+        }
+
+        let mut base = vec![Codex32Share::new(
+            threshold,
+            identifier,
+            SECRET_INDEX,
+            seed,
+        )?];
+        let mut payload = vec![0u8; seed.len()];
+        for &index in &SHARE_INDICES[..threshold as usize - 1] {
+            rng.fill_bytes(&mut payload);
+            base.push(Codex32Share::new(
+                threshold,
+                identifier,
+                index as char,
+                &payload,
+            )?);
+        }
+        wipe(&mut payload);
+
+        let mut shares = base[1..].to_vec();
+        for &index in &SHARE_INDICES[threshold as usize - 1..n as usize] {
+            shares.push(interpolate(&base, index as char)?);
+        }
+        Ok(shares)
+    }
+
+    /// Recover the secret from at least `threshold` distinct shares
+    pub fn recover(shares: &[Codex32Share]) -> Result<Self, Error> {
+        let first = shares
+            .first()
+            .ok_or_else(|| Error::InvalidCodex32("No shares given".to_string()))?;
+        if let Some(secret) = shares.iter().find(|share| share.index == SECRET_INDEX) {
+            return Ok(secret.clone());
+        }
+        if first.threshold == 0 {
+            return Err(Error::InvalidCodex32(
+                "Unshared strings must have share index 's'".to_string(),
+            ));
+        }
+
+        let mut selected: Vec<Codex32Share> = Vec::new();
+        for share in shares {
+            if share.threshold != first.threshold
+                || share.identifier != first.identifier
+                || share.payload.len() != first.payload.len()
+            {
+                return Err(Error::InvalidCodex32(
+                    "Shares belong to different secrets".to_string(),
+                ));
+            }
+            if !selected.iter().any(|s| s.index == share.index) {
+                selected.push(share.clone());
+            }
+        }
+        if selected.len() < first.threshold as usize {
+            return Err(Error::InvalidCodex32(format!(
+                "Need {} distinct shares, got {}",
+                first.threshold,
+                selected.len()
+            )));
+        }
+        selected.truncate(first.threshold as usize);
+        interpolate(&selected, SECRET_INDEX)
+    }
+
+    /// Shares needed to recover the secret, or 0 if unshared
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Get the identifier common to all shares of a secret
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    /// Get the share index
+    pub fn index(&self) -> char {
+        self.index
+    }
+
+    /// Whether this is the secret rather than a share of it
+    pub fn is_secret(&self) -> bool {
+        self.index == SECRET_INDEX
+    }
+
+    /// Get the master seed of a secret
+    pub fn seed(&self) -> Result<&[u8], Error> {
+        if !self.is_secret() {
+            return Err(Error::InvalidCodex32(
+                "Only the secret share holds the seed; recover it first".to_string(),
+            ));
+        }
+        Ok(&self.payload)
+    }
+
+    /// Create the BIP-32 master key of a secret
+    pub fn master_key(&self, network: Network) -> Result<ExtendedPrivKey, Error> {
+        ExtendedPrivKey::new_master(self.seed()?, network)
+    }
+
+    fn new(threshold: u8, identifier: &str, index: char, payload: &[u8]) -> Result<Self, Error> {
+        let identifier = identifier.to_ascii_lowercase();
+        if identifier.len() != 4 || !identifier.bytes().all(|c| CHARSET.contains(&c)) {
+            return Err(Error::InvalidCodex32(
+                "Identifier must be 4 bech32 characters".to_string(),
+            ));
+        }
+        if !(16..=64).contains(&payload.len()) {
+            return Err(Error::InvalidCodex32(format!(
+                "Seeds must be 16 to 64 bytes, got {}",
+                payload.len()
+            )));
+        }
+        Ok(Codex32Share {
+            threshold,
+            identifier,
+            index,
+            payload: payload.to_vec(),
+            padding: 0,
+        })
+    }
+
+    /// Header and payload as 5-bit values, without checksum
+    fn data_values(&self) -> Vec<u8> {
+        let mut values = vec![value_of((b'0' + self.threshold) as char).unwrap_or(0)];
+        values.extend(self.identifier.chars().filter_map(value_of));
+        values.push(value_of(self.index).unwrap_or(0));
+
+        let mut buffer = 0u32;
+        let mut bits = 0;
+        for &byte in &self.payload {
+            buffer = (buffer << 8) | byte as u32;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                values.push(((buffer >> bits) & 31) as u8);
+            }
+        }
+        if bits > 0 {
+            values.push(((buffer << (5 - bits)) & 31) as u8 | self.padding);
+        }
+        values
+    }
+
+    /// The whole data part as 5-bit values, checksum included
+    fn values(&self) -> Vec<u8> {
+        let mut values = self.data_values();
+        let checksum = if values.len() <= 80 {
+            &SHORT_CHECKSUM
+        } else {
+            &LONG_CHECKSUM
+        };
+        values.extend(checksum.create(&values));
+        values
+    }
+
+    fn from_values(values: &[u8]) -> Result<Self, Error> {
+        let checksum = Checksum::for_len(values.len())
+            .filter(|c| values.len() >= 6 + c.len)
+            .ok_or_else(|| Error::InvalidCodex32("Invalid length".to_string()))?;
+        if checksum.polymod(values) != checksum.target {
+            return Err(Error::InvalidCodex32("Invalid checksum".to_string()));
+        }
+
+        let threshold = match CHARSET[values[0] as usize] {
+            b'0' => 0,
+            c @ b'2'..=b'9' => c - b'0',
+            c => {
+                return Err(Error::InvalidCodex32(format!(
+                    "Invalid threshold '{}'",
+                    c as char
+                )))
+            }
+        };
+        let identifier: String = values[1..5]
+            .iter()
+            .map(|&v| CHARSET[v as usize] as char)
+            .collect();
+        let index = CHARSET[values[5] as usize] as char;
+        if threshold == 0 && index != SECRET_INDEX {
+            return Err(Error::InvalidCodex32(
+                "Unshared strings must have share index 's'".to_string(),
+            ));
+        }
+
+        let data = &values[6..values.len() - checksum.len];
+        if data.len() * 5 % 8 > 4 {
+            return Err(Error::InvalidCodex32(
+                "Payload has an incomplete byte".to_string(),
+            ));
+        }
+        let mut payload = Vec::with_capacity(data.len() * 5 / 8);
+        let mut buffer = 0u32;
+        let mut bits = 0;
+        for &value in data {
+            buffer = (buffer << 5) | value as u32;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                payload.push((buffer >> bits) as u8);
+            }
+        }
+        let share = Codex32Share::new(threshold, &identifier, index, &payload);
+        wipe(&mut payload);
+        let mut share = share?;
+        share.padding = (buffer & ((1 << bits) - 1)) as u8;
+        Ok(share)
+    }
+}
+
+impl Drop for Codex32Share {
+    fn drop(&mut self) {
+        wipe(&mut self.payload);
+    }
+}
+
+impl fmt::Display for Codex32Share {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}1", HRP)?;
+        for value in self.values() {
+            write!(f, "{}", CHARSET[value as usize] as char)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Codex32Share {
+    type Err = Error;
+
+    /// Parse a codex32 string, in all lowercase or all uppercase
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.bytes().any(|c| c.is_ascii_lowercase()) && s.bytes().any(|c| c.is_ascii_uppercase()) {
+            return Err(Error::InvalidCodex32("Mixed case".to_string()));
+        }
+        let lower = s.to_ascii_lowercase();
+        let data = lower
+            .strip_prefix(HRP)
+            .and_then(|rest| rest.strip_prefix('1'))
+            .ok_or_else(|| Error::InvalidCodex32("Missing 'ms1' prefix".to_string()))?;
+        if lower.len() > 127 {
+            return Err(Error::InvalidCodex32("Invalid length".to_string()));
+        }
+        let values = data
+            .chars()
+            .map(|c| {
+                value_of(c)
+                    .ok_or_else(|| Error::InvalidCodex32(format!("Invalid character '{}'", c)))
+            })
+            .collect::<Result<Vec<u8>, Error>>()?;
+        Codex32Share::from_values(&values)
+    }
+}
+
+/// Derive the share at `index` from `threshold` shares by Lagrange
+/// interpolation over GF(32). Every character is interpolated, including
+/// the checksum, which stays valid since it is linear in the data.
+fn interpolate(shares: &[Codex32Share], index: char) -> Result<Codex32Share, Error> {
+    let mut strings: Vec<Vec<u8>> = shares.iter().map(Codex32Share::values).collect();
+    let x = value_of(index).expect("share indices are bech32 characters");
+    let xs: Vec<u8> = strings.iter().map(|values| values[5]).collect();
+
+    let weights: Vec<u8> = xs
+        .iter()
+        .map(|&xi| {
+            let mut numerator = 1;
+            let mut denominator = 1;
+            for &xj in xs.iter().filter(|&&xj| xj != xi) {
+                numerator = gf32_mul(numerator, x ^ xj);
+                denominator = gf32_mul(denominator, xi ^ xj);
+            }
+            gf32_mul(numerator, gf32_inv(denominator))
+        })
+        .collect();
+
+    let mut values: Vec<u8> = (0..strings[0].len())
+        .map(|i| {
+            strings
+                .iter()
+                .zip(&weights)
+                .fold(0, |acc, (values, &w)| acc ^ gf32_mul(w, values[i]))
+        })
+        .collect();
+    let share = Codex32Share::from_values(&values);
+    wipe(&mut values);
+    strings.iter_mut().for_each(wipe);
+    share
+}
+
+fn value_of(c: char) -> Option<u8> {
+    CHARSET
+        .iter()
+        .position(|&v| v as char == c)
+        .map(|v| v as u8)
+}
+
+fn gf32_log(a: u8) -> usize {
+    GF32_EXP
+        .iter()
+        .position(|&e| e == a)
+        .expect("nonzero field element")
+}
+
+fn gf32_mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    GF32_EXP[(gf32_log(a) + gf32_log(b)) % 31]
+}
+
+fn gf32_inv(a: u8) -> u8 {
+    GF32_EXP[(31 - gf32_log(a)) % 31]
+}
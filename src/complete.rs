@@ -0,0 +1,43 @@
+use crate::bip39::{Language, Mnemonic, MnemonicType};
+use crate::error::{Error, MnemonicError};
+use crate::wipe::wipe;
+
+impl Mnemonic {
+    /// List every final word that completes the first N-1 words of a phrase
+    /// into one with a valid checksum, in wordlist order. For phrases
+    /// made from dice or coin flips, where the last word can't be chosen
+    /// freely. A 12-word phrase has 128 completions, a 24-word one 8.
+    pub fn complete_last_word(
+        words: &[&str],
+        language: Language,
+    ) -> Result<Vec<&'static str>, Error> {
+        MnemonicType::from_word_count(words.len() + 1)?;
+        for (position, word) in words.iter().enumerate() {
+            if language.index_of(word).is_none() {
+                return Err(MnemonicError::UnknownWord {
+                    position,
+                    word: word.to_string(),
+                }
+                .into());
+            }
+        }
+
+        let mut phrase = words.to_vec();
+        phrase.push("");
+        Ok(language
+            .wordlist()
+            .iter()
+            .copied()
+            .filter(|&last| {
+                phrase[words.len()] = last;
+                match Mnemonic::words_to_entropy(&phrase, &language) {
+                    Ok(mut entropy) => {
+                        wipe(&mut entropy);
+                        true
+                    }
+                    Err(_) => false,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,170 @@
+use crate::address::AddressType;
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Network, PathParseMode};
+use crate::bip39::Seed;
+use crate::bip44::Purpose;
+use crate::error::Error;
+use crate::wallet::{AccountConfig, Wallet};
+use crate::watchonly::WatchOnlyWallet;
+use std::fmt::Write;
+
+/// Marker printed after hardened path components
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HardenedNotation {
+    /// `m/84'/0'/0'`
+    #[default]
+    Apostrophe,
+    /// `m/84h/0h/0h`, which needs no quoting in shells and descriptors
+    H,
+}
+
+/// Crate-wide defaults consumed by the CLI and the wallet facade, so that an
+/// embedding application configures them in one place
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Network of new master keys
+    pub network: Network,
+    /// BIP-43 purpose selecting the address type (44, 49 or 84)
+    pub purpose: Purpose,
+    /// Scanning parameters of new accounts, including the gap limit
+    pub account: AccountConfig,
+    /// How derivation paths are printed
+    pub notation: HardenedNotation,
+    /// How derivation path strings are parsed
+    pub path_mode: PathParseMode,
+    /// Whether extended keys may be re-tagged to [`Config::network`]
+    pub allow_retag: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            network: Network::Bitcoin,
+            purpose: Purpose(84),
+            account: AccountConfig::default(),
+            notation: HardenedNotation::Apostrophe,
+            path_mode: PathParseMode::Lenient,
+            allow_retag: false,
+        }
+    }
+}
+
+impl Config {
+    /// Create a configuration with the default values
+    pub fn new() -> Self {
+        Config::default()
+    }
+
+    /// Use a different network
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Use a different purpose
+    pub fn with_purpose(mut self, purpose: Purpose) -> Self {
+        self.purpose = purpose;
+        self
+    }
+
+    /// Stop discovery after `gap_limit` consecutive unused addresses
+    pub fn with_gap_limit(mut self, gap_limit: u32) -> Self {
+        self.account.gap_limit = gap_limit;
+        self
+    }
+
+    /// Use different scanning parameters for new accounts
+    pub fn with_account_config(mut self, account: AccountConfig) -> Self {
+        self.account = account;
+        self
+    }
+
+    /// Print hardened components with a different marker
+    pub fn with_notation(mut self, notation: HardenedNotation) -> Self {
+        self.notation = notation;
+        self
+    }
+
+    /// Only accept canonical derivation path strings
+    pub fn with_strict_paths(mut self, strict: bool) -> Self {
+        self.path_mode = if strict {
+            PathParseMode::Strict
+        } else {
+            PathParseMode::Lenient
+        };
+        self
+    }
+
+    /// Allow re-tagging extended keys to the configured network
+    pub fn with_allow_retag(mut self, allow_retag: bool) -> Self {
+        self.allow_retag = allow_retag;
+        self
+    }
+
+    /// Address type implied by the purpose
+    pub fn address_type(&self) -> Result<AddressType, Error> {
+        match self.purpose.0 {
+            44 => Ok(AddressType::P2pkh),
+            49 => Ok(AddressType::P2shP2wpkh),
+            84 => Ok(AddressType::P2wpkh),
+            other => Err(Error::InvalidConfig(format!(
+                "No address type for purpose {}",
+                other
+            ))),
+        }
+    }
+
+    /// Parse a derivation path with the configured strictness
+    pub fn parse_path(&self, path: &str) -> Result<DerivationPath, Error> {
+        DerivationPath::parse(path, self.path_mode)
+    }
+
+    /// Print a derivation path with the configured notation
+    pub fn format_path(&self, path: &DerivationPath) -> String {
+        format_path(path, self.notation)
+    }
+
+    /// Parse an extended public key for the configured network, re-tagging
+    /// a key of another network only when [`Config::allow_retag`] is set
+    pub fn parse_xpub(&self, xpub: &str) -> Result<ExtendedPubKey, Error> {
+        ExtendedPubKey::from_string(xpub)?.with_network(self.network, self.allow_retag)
+    }
+}
+
+/// Print a derivation path with a hardened notation
+pub(crate) fn format_path(path: &DerivationPath, notation: HardenedNotation) -> String {
+    let marker = match notation {
+        HardenedNotation::Apostrophe => '\'',
+        HardenedNotation::H => 'h',
+    };
+    let mut out = String::from("m");
+    for child in &path.path {
+        match child {
+            ChildNumber::Normal(i) => write!(out, "/{}", i),
+            ChildNumber::Hardened(i) => write!(out, "/{}{}", i, marker),
+        }
+        .expect("writing to a String cannot fail");
+    }
+    out
+}
+
+impl Wallet {
+    /// Create a wallet from a BIP-39 seed using the network, address type,
+    /// scanning parameters and path style of `config`
+    pub fn from_config(seed: &Seed, config: &Config) -> Result<Self, Error> {
+        let mut wallet = Wallet::from_seed(seed, config.network, config.address_type()?)?
+            .with_account_config(config.account)?;
+        wallet.set_path_style(config.notation, config.path_mode);
+        Ok(wallet)
+    }
+}
+
+impl WatchOnlyWallet {
+    /// Create a watch-only wallet from a serialized account xpub using the
+    /// network, address type and scanning parameters of `config`. A key of
+    /// another network is refused unless [`Config::allow_retag`] is set.
+    pub fn from_config(xpub: &str, config: &Config) -> Result<Self, Error> {
+        let xpub = config.parse_xpub(xpub)?;
+        WatchOnlyWallet::from_account_xpub(&xpub.to_string(), config.address_type()?)?
+            .with_account_config(config.account)
+    }
+}
@@ -0,0 +1,54 @@
+use crate::bip32::{ExtendedPrivKey, ExtendedPubKey};
+use crate::error::Error;
+use crate::utils;
+use secp256k1::{PublicKey, Scalar, SecretKey};
+
+/// Compute the pay-to-contract tweak SHA256(P || commitment)
+pub fn commitment_tweak(public_key: &PublicKey, commitment: &[u8]) -> Result<Scalar, Error> {
+    let mut data = Vec::with_capacity(33 + commitment.len());
+    data.extend_from_slice(&public_key.serialize());
+    data.extend_from_slice(commitment);
+
+    Scalar::from_be_bytes(utils::sha256(&data))
+        .map_err(|_| Error::InvalidKey("Commitment tweak out of range".to_string()))
+}
+
+/// Tweak a public key by a commitment: P' = P + SHA256(P || commitment)·G
+pub fn tweak_public_key(public_key: &PublicKey, commitment: &[u8]) -> Result<PublicKey, Error> {
+    let secp = utils::secp();
+    let tweak = commitment_tweak(public_key, commitment)?;
+
+    public_key
+        .add_exp_tweak(secp, &tweak)
+        .map_err(|_| Error::InvalidKey("Invalid tweaked public key".to_string()))
+}
+
+/// Verify that `tweaked` commits to `commitment` on top of `public_key`
+pub fn verify_commitment(public_key: &PublicKey, commitment: &[u8], tweaked: &PublicKey) -> bool {
+    tweak_public_key(public_key, commitment).is_ok_and(|expected| expected == *tweaked)
+}
+
+impl ExtendedPrivKey {
+    /// Get the private key tweaked by a pay-to-contract commitment
+    pub fn pay_to_contract(&self, commitment: &[u8]) -> Result<SecretKey, Error> {
+        let secp = utils::secp();
+        let public_key = PublicKey::from_secret_key(secp, &self.private_key);
+        let tweak = commitment_tweak(&public_key, commitment)?;
+
+        self.private_key
+            .add_tweak(&tweak)
+            .map_err(|_| Error::InvalidKey("Invalid tweaked private key".to_string()))
+    }
+}
+
+impl ExtendedPubKey {
+    /// Get the public key tweaked by a pay-to-contract commitment
+    pub fn pay_to_contract(&self, commitment: &[u8]) -> Result<PublicKey, Error> {
+        tweak_public_key(&self.public_key, commitment)
+    }
+
+    /// Verify that `tweaked` is this key tweaked by `commitment`
+    pub fn verify_commitment(&self, commitment: &[u8], tweaked: &PublicKey) -> bool {
+        verify_commitment(&self.public_key, commitment, tweaked)
+    }
+}
@@ -0,0 +1,148 @@
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPubKey};
+use crate::descriptor::KeyOrigin;
+use crate::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// One element of a path template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateElement {
+    /// Exactly this child number
+    Fixed(ChildNumber),
+    /// Any normal index (`*`)
+    AnyNormal,
+    /// Any hardened index (`*'`)
+    AnyHardened,
+}
+
+impl TemplateElement {
+    fn matches(&self, child: &ChildNumber) -> bool {
+        match self {
+            TemplateElement::Fixed(expected) => expected == child,
+            TemplateElement::AnyNormal => !child.is_hardened(),
+            TemplateElement::AnyHardened => child.is_hardened(),
+        }
+    }
+}
+
+impl fmt::Display for TemplateElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateElement::Fixed(child) => write!(f, "{}", child),
+            TemplateElement::AnyNormal => write!(f, "*"),
+            TemplateElement::AnyHardened => write!(f, "*'"),
+        }
+    }
+}
+
+/// A derivation path with wildcard levels, e.g. `m/48'/0'/*'/2'`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathTemplate {
+    pub elements: Vec<TemplateElement>,
+}
+
+impl PathTemplate {
+    /// Check whether a concrete path fits the template
+    pub fn matches(&self, path: &DerivationPath) -> bool {
+        self.elements.len() == path.path.len()
+            && self
+                .elements
+                .iter()
+                .zip(&path.path)
+                .all(|(element, child)| element.matches(child))
+    }
+}
+
+impl fmt::Display for PathTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for element in &self.elements {
+            write!(f, "/{}", element)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PathTemplate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix('m')
+            .ok_or_else(|| Error::InvalidDerivationPath("Path must start with 'm'".to_string()))?;
+        if rest.is_empty() {
+            return Ok(PathTemplate { elements: vec![] });
+        }
+
+        let elements = rest
+            .strip_prefix('/')
+            .ok_or_else(|| Error::InvalidDerivationPath("Invalid path format".to_string()))?
+            .split('/')
+            .map(|element| match element {
+                "*" => Ok(TemplateElement::AnyNormal),
+                "*'" | "*h" => Ok(TemplateElement::AnyHardened),
+                _ => element.parse().map(TemplateElement::Fixed),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(PathTemplate { elements })
+    }
+}
+
+/// Check that an xpub is consistent with the origin it was supplied with:
+/// its depth must equal the path length, its child number must equal the
+/// last path element, and at depth 0 or 1 the fingerprint must match
+pub fn validate_origin(xpub: &ExtendedPubKey, origin: &KeyOrigin) -> Result<(), Error> {
+    let path = &origin.path.path;
+
+    if xpub.depth as usize != path.len() {
+        return Err(Error::InvalidKeyOrigin(format!(
+            "Key depth {} does not match origin path length {}",
+            xpub.depth,
+            path.len()
+        )));
+    }
+
+    match path.last() {
+        None => {
+            if xpub.fingerprint() != origin.fingerprint {
+                return Err(Error::InvalidKeyOrigin(
+                    "Master key fingerprint does not match origin".to_string(),
+                ));
+            }
+        }
+        Some(last) => {
+            if xpub.child_number != last.to_u32() {
+                return Err(Error::InvalidKeyOrigin(format!(
+                    "Key child number {} does not match origin path element {}",
+                    ChildNumber::from(xpub.child_number),
+                    last
+                )));
+            }
+            if path.len() == 1 && xpub.parent_fingerprint != origin.fingerprint {
+                return Err(Error::InvalidKeyOrigin(
+                    "Parent fingerprint does not match origin".to_string(),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate a cosigner's xpub and origin against the path template agreed
+/// for the multisig setup
+pub fn validate_cosigner(
+    xpub: &ExtendedPubKey,
+    origin: &KeyOrigin,
+    template: &PathTemplate,
+) -> Result<(), Error> {
+    if !template.matches(&origin.path) {
+        return Err(Error::InvalidKeyOrigin(format!(
+            "Origin path {} does not match template {}",
+            origin.path, template
+        )));
+    }
+
+    validate_origin(xpub, origin)
+}
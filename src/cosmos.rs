@@ -0,0 +1,111 @@
+use crate::bip32::{DerivationPath, ExtendedPrivKey};
+use crate::bip44::{AccountLevel, AddressIndex, Bip44Path, Change, CoinType};
+use crate::error::Error;
+use crate::signer::{self, SigningOptions};
+use crate::utils;
+use crate::wallet::Wallet;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bech32::{Bech32, Hrp};
+use secp256k1::ecdsa::Signature;
+use secp256k1::PublicKey;
+
+/// Bech32 prefix of Cosmos Hub accounts
+pub const COSMOS_HRP: &str = "cosmos";
+/// Protobuf `Any` type URL of secp256k1 public keys
+pub const PUBKEY_TYPE_URL: &str = "/cosmos.crypto.secp256k1.PubKey";
+/// Amino type name of secp256k1 public keys
+pub const AMINO_PUBKEY_TYPE: &str = "tendermint/PubKeySecp256k1";
+
+/// Get the path of an account address: `m/44'/118'/account'/0/index`
+pub fn path(account: u32, index: u32) -> DerivationPath {
+    Bip44Path::standard(
+        CoinType::COSMOS,
+        AccountLevel::new(account),
+        Change::External,
+        AddressIndex::new(index),
+    )
+    .to_derivation_path()
+}
+
+/// Encode the bech32 account address of a public key with a chain's prefix
+pub fn address(public_key: &PublicKey, hrp: &str) -> Result<String, Error> {
+    let hrp = Hrp::parse(hrp).map_err(|e| Error::InvalidAddress(e.to_string()))?;
+    bech32::encode::<Bech32>(hrp, &utils::pubkey_hash(public_key))
+        .map_err(|e| Error::InvalidAddress(e.to_string()))
+}
+
+/// Decode a bech32 account address with a chain's prefix into its public key hash
+pub fn decode_address(address: &str, hrp: &str) -> Result<[u8; 20], Error> {
+    let (decoded_hrp, data) =
+        bech32::decode(address).map_err(|e| Error::InvalidAddress(e.to_string()))?;
+    if decoded_hrp.as_str() != hrp {
+        return Err(Error::InvalidAddress(format!(
+            "Expected prefix {}, got {}",
+            hrp, decoded_hrp
+        )));
+    }
+    data.try_into()
+        .map_err(|_| Error::InvalidAddress("Address must hold 20 bytes".to_string()))
+}
+
+/// Encode a public key the way Cosmos transactions carry it: base64 of the
+/// compressed point
+pub fn pubkey_base64(public_key: &PublicKey) -> String {
+    STANDARD.encode(public_key.serialize())
+}
+
+/// Encode a public key as amino JSON, as used by legacy sign docs
+pub fn amino_pubkey_json(public_key: &PublicKey) -> String {
+    format!(
+        r#"{{"type":"{}","value":"{}"}}"#,
+        AMINO_PUBKEY_TYPE,
+        pubkey_base64(public_key)
+    )
+}
+
+/// Digest signed for a sign doc: SHA-256 of its sign bytes
+pub fn sign_bytes_digest(sign_bytes: &[u8]) -> [u8; 32] {
+    utils::sha256(sign_bytes)
+}
+
+/// Canonicalize an amino JSON sign doc: keys sorted, no whitespace and
+/// `<`, `>` and `&` escaped like Go's encoder
+#[cfg(feature = "serde")]
+pub fn amino_sign_bytes(sign_doc: &str) -> Result<Vec<u8>, Error> {
+    let value: serde_json::Value =
+        serde_json::from_str(sign_doc).map_err(|e| Error::InvalidSignature(e.to_string()))?;
+    let json = value
+        .to_string()
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026");
+    Ok(json.into_bytes())
+}
+
+/// Verify a 64-byte `r || s` signature over sign bytes
+pub fn verify(
+    public_key: &PublicKey,
+    sign_bytes: &[u8],
+    signature: &[u8; 64],
+) -> Result<(), Error> {
+    let signature = Signature::from_compact(signature)
+        .map_err(|_| Error::InvalidSignature("Malformed signature".to_string()))?;
+    signer::verify_ecdsa(public_key, &sign_bytes_digest(sign_bytes), &signature)
+}
+
+impl ExtendedPrivKey {
+    /// Sign the sign bytes of a protobuf (`SIGN_MODE_DIRECT`) or amino JSON
+    /// sign doc, returning the 64-byte low-S `r || s` signature Cosmos expects
+    pub fn sign_cosmos(&self, sign_bytes: &[u8]) -> [u8; 64] {
+        self.sign_digest(&sign_bytes_digest(sign_bytes), SigningOptions::default())
+            .serialize_compact()
+    }
+}
+
+impl Wallet {
+    /// Get the Cosmos address at an index of the first account, with a chain's prefix
+    pub fn cosmos_address(&self, hrp: &str, index: u32) -> Result<String, Error> {
+        let key = self.master().derive_path(&path(0, index))?;
+        address(&key.to_extended_public_key().public_key, hrp)
+    }
+}
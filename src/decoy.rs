@@ -0,0 +1,47 @@
+use crate::address::AddressType;
+use crate::bip32::{ExtendedPubKey, Network};
+use crate::bip39::Mnemonic;
+use crate::error::Error;
+use crate::store::WalletState;
+use crate::wallet::Wallet;
+
+/// A wallet reachable from a mnemonic under one passphrase
+#[derive(Debug, Clone)]
+pub struct DecoyWallet {
+    /// Index of the passphrase in the candidate list, or `None` for the
+    /// empty passphrase
+    pub candidate: Option<usize>,
+    /// Master key fingerprint
+    pub fingerprint: [u8; 4],
+    /// Extended public key of account 0
+    pub xpub: ExtendedPubKey,
+    /// Whether the wallet's saved state shows it has been used
+    pub activated: bool,
+}
+
+/// Enumerate the wallets of a mnemonic under the empty passphrase and each
+/// candidate passphrase, in that order, for duress and decoy wallet
+/// features. `load_state` returns the saved state of the wallet with a
+/// given master fingerprint, if any, and decides which wallets are activated.
+pub fn enumerate_decoys(
+    mnemonic: &Mnemonic,
+    candidates: &[&str],
+    network: Network,
+    address_type: AddressType,
+    mut load_state: impl FnMut(&[u8; 4]) -> Result<Option<WalletState>, Error>,
+) -> Result<Vec<DecoyWallet>, Error> {
+    std::iter::once((None, ""))
+        .chain(candidates.iter().enumerate().map(|(i, p)| (Some(i), *p)))
+        .map(|(candidate, passphrase)| {
+            let wallet = Wallet::from_seed(&mnemonic.to_seed(passphrase), network, address_type)?;
+            let fingerprint = wallet.master().fingerprint();
+            let activated = load_state(&fingerprint)?.is_some_and(|state| state.is_activated());
+            Ok(DecoyWallet {
+                candidate,
+                fingerprint,
+                xpub: wallet.accounts()[0].xpub.clone(),
+                activated,
+            })
+        })
+        .collect()
+}
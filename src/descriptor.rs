@@ -0,0 +1,448 @@
+use crate::address::{self, AddressType};
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use crate::bip44::{Change, CoinType};
+use crate::error::Error;
+use crate::limits;
+use secp256k1::PublicKey;
+use std::fmt;
+use std::str::FromStr;
+
+/// Characters allowed in descriptors, ordered for the BIP-380 checksum
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+/// Characters used to encode the checksum
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn polymod(symbols: &[u64]) -> u64 {
+    const GENERATOR: [u64; 5] = [
+        0xf5dee51989,
+        0xa9fdca3312,
+        0x1bab10e32d,
+        0x3706b1677a,
+        0x644d626ffd,
+    ];
+
+    let mut chk = 1u64;
+    for &value in symbols {
+        let top = chk >> 35;
+        chk = ((chk & 0x7ffffffff) << 5) ^ value;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+/// Compute the BIP-380 checksum of a descriptor string (without `#`)
+pub fn checksum(descriptor: &str) -> Result<String, Error> {
+    let mut symbols = Vec::with_capacity(descriptor.len() * 4 / 3 + 9);
+    let mut groups = Vec::with_capacity(3);
+
+    for c in descriptor.chars() {
+        let value = INPUT_CHARSET
+            .find(c)
+            .ok_or_else(|| Error::InvalidDescriptor(format!("Invalid character '{}'", c)))?
+            as u64;
+        symbols.push(value & 31);
+        groups.push(value >> 5);
+        if groups.len() == 3 {
+            symbols.push(groups[0] * 9 + groups[1] * 3 + groups[2]);
+            groups.clear();
+        }
+    }
+    match groups.len() {
+        1 => symbols.push(groups[0]),
+        2 => symbols.push(groups[0] * 3 + groups[1]),
+        _ => {}
+    }
+    symbols.extend_from_slice(&[0; 8]);
+
+    let chk = polymod(&symbols) ^ 1;
+    Ok((0..8)
+        .map(|i| CHECKSUM_CHARSET[((chk >> (5 * (7 - i))) & 31) as usize] as char)
+        .collect())
+}
+
+/// Append the checksum to a descriptor string
+pub fn with_checksum(descriptor: &str) -> Result<String, Error> {
+    Ok(format!("{}#{}", descriptor, checksum(descriptor)?))
+}
+
+/// Strip and verify the checksum of a descriptor string, if it has one
+pub fn verify_checksum(descriptor: &str) -> Result<&str, Error> {
+    match descriptor.split_once('#') {
+        Some((body, chk)) => {
+            if checksum(body)? != chk {
+                return Err(Error::InvalidChecksum);
+            }
+            Ok(body)
+        }
+        None => Ok(descriptor),
+    }
+}
+
+/// Origin of a key: the master key fingerprint and the path it was derived at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyOrigin {
+    pub fingerprint: [u8; 4],
+    pub path: DerivationPath,
+}
+
+impl fmt::Display for KeyOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.fingerprint))?;
+        for child in &self.path.path {
+            write!(f, "/{}", child)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for KeyOrigin {
+    type Err = Error;
+
+    /// Parse an origin such as `d34db33f/84'/0'/0'`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (fingerprint, path) = match s.split_once('/') {
+            Some((fingerprint, path)) => (fingerprint, format!("m/{}", path)),
+            None => (s, "m".to_string()),
+        };
+
+        let fingerprint: [u8; 4] = hex::decode(fingerprint)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| Error::InvalidDescriptor("Invalid origin fingerprint".to_string()))?;
+
+        Ok(KeyOrigin {
+            fingerprint,
+            path: path.parse()?,
+        })
+    }
+}
+
+/// A ranged single-key descriptor for one chain of an account,
+/// e.g. `wpkh([d34db33f/84'/0'/0']xpub.../0/*)`
+#[derive(Debug, Clone)]
+pub struct Descriptor {
+    pub address_type: AddressType,
+    pub origin: KeyOrigin,
+    pub xpub: ExtendedPubKey,
+    pub change: Change,
+}
+
+impl Descriptor {
+    /// Build the descriptor for a chain of a BIP-44/49/84 account
+    pub fn for_account(
+        master: &ExtendedPrivKey,
+        address_type: AddressType,
+        account: u32,
+        change: Change,
+    ) -> Result<Self, Error> {
+        let path = DerivationPath {
+            path: vec![
+                address_type.purpose().child_number(),
+                CoinType::for_network(master.network).child_number(),
+                ChildNumber::Hardened(account),
+            ],
+        };
+        let xpub = master.derive_path(&path)?.to_extended_public_key();
+
+        Ok(Descriptor {
+            address_type,
+            origin: KeyOrigin {
+                fingerprint: master.fingerprint(),
+                path,
+            },
+            xpub,
+            change,
+        })
+    }
+
+    /// Whether this descriptor describes the change chain
+    pub fn is_internal(&self) -> bool {
+        self.change == Change::Internal
+    }
+
+    /// Render the descriptor without its checksum
+    fn body(&self) -> String {
+        let key = format!("[{}]{}/{}/*", self.origin, self.xpub, self.change);
+        match self.address_type {
+            AddressType::P2pkh => format!("pkh({})", key),
+            AddressType::P2shP2wpkh => format!("sh(wpkh({}))", key),
+            AddressType::P2wpkh => format!("wpkh({})", key),
+        }
+    }
+}
+
+impl fmt::Display for Descriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let body = self.body();
+        let chk = checksum(&body).map_err(|_| fmt::Error)?;
+        write!(f, "{}#{}", body, chk)
+    }
+}
+
+impl FromStr for Descriptor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address_type, origin, xpub, change) = parse_ranged(verify_checksum(s)?)?;
+        Ok(Descriptor {
+            address_type,
+            origin: origin.parse()?,
+            xpub: ExtendedPubKey::from_string(xpub)?,
+            change: change.parse()?,
+        })
+    }
+}
+
+/// Split a ranged single-key descriptor body into its script type, origin,
+/// xpub and chain step
+fn parse_ranged(body: &str) -> Result<(AddressType, &str, &str, &str), Error> {
+    let (address_type, key) = if let Some(key) = body
+        .strip_prefix("sh(wpkh(")
+        .and_then(|rest| rest.strip_suffix("))"))
+    {
+        (AddressType::P2shP2wpkh, key)
+    } else if let Some(key) = body
+        .strip_prefix("wpkh(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        (AddressType::P2wpkh, key)
+    } else if let Some(key) = body
+        .strip_prefix("pkh(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        (AddressType::P2pkh, key)
+    } else {
+        return Err(Error::InvalidDescriptor(
+            "Unsupported script expression".to_string(),
+        ));
+    };
+
+    let (origin, key) = key
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .ok_or_else(|| Error::InvalidDescriptor("Missing key origin".to_string()))?;
+
+    if key.ends_with("/*'") || key.ends_with("/*h") {
+        return Err(Error::InvalidDescriptor(
+            "Hardened wildcards cannot be derived from an xpub".to_string(),
+        ));
+    }
+    let (xpub, change) = key
+        .strip_suffix("/*")
+        .and_then(|rest| rest.split_once('/'))
+        .ok_or_else(|| Error::InvalidDescriptor("Expected a ranged key".to_string()))?;
+
+    Ok((address_type, origin, xpub, change))
+}
+
+impl Descriptor {
+    /// Parse a descriptor that may use a BIP-389 multipath step such as
+    /// `<0;1>`, returning one descriptor per path in order
+    pub fn parse_multipath(s: &str) -> Result<Vec<Descriptor>, Error> {
+        let (address_type, origin, xpub, change) = parse_ranged(verify_checksum(s)?)?;
+        let origin: KeyOrigin = origin.parse()?;
+        let xpub = ExtendedPubKey::from_string(xpub)?;
+
+        let changes = match change
+            .strip_prefix('<')
+            .and_then(|rest| rest.strip_suffix('>'))
+        {
+            Some(list) => list.split(';').collect(),
+            None => vec![change],
+        };
+        if changes.len() < 2 && change.starts_with('<') {
+            return Err(Error::InvalidDescriptor(
+                "A multipath step needs at least two paths".to_string(),
+            ));
+        }
+
+        let descriptors = changes
+            .into_iter()
+            .map(|change| {
+                Ok(Descriptor {
+                    address_type,
+                    origin: origin.clone(),
+                    xpub: xpub.clone(),
+                    change: change.parse()?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        if descriptors
+            .iter()
+            .enumerate()
+            .any(|(i, d)| descriptors[..i].iter().any(|e| e.change == d.change))
+        {
+            return Err(Error::InvalidDescriptor(
+                "Multipath steps must be distinct".to_string(),
+            ));
+        }
+        Ok(descriptors)
+    }
+
+    /// Render the receive and change chains as one `<0;1>` multipath
+    /// descriptor, with checksum
+    pub fn to_multipath_string(&self) -> String {
+        let key = format!("[{}]{}/<0;1>/*", self.origin, self.xpub);
+        let body = match self.address_type {
+            AddressType::P2pkh => format!("pkh({})", key),
+            AddressType::P2shP2wpkh => format!("sh(wpkh({}))", key),
+            AddressType::P2wpkh => format!("wpkh({})", key),
+        };
+        with_checksum(&body).expect("descriptor characters are valid")
+    }
+
+    /// Derive the public key at a wildcard index
+    pub fn derive_public_key(&self, index: u32) -> Result<PublicKey, Error> {
+        if index > ChildNumber::MAX_NORMAL_INDEX {
+            return Err(Error::InvalidDerivationPath(format!(
+                "Wildcard index {} is out of range",
+                index
+            )));
+        }
+        Ok(self
+            .xpub
+            .derive_child(self.change.child_number())?
+            .derive_child(ChildNumber::Normal(index))?
+            .public_key)
+    }
+
+    /// Derive the address at a wildcard index
+    pub fn derive_address(&self, index: u32) -> Result<String, Error> {
+        Ok(address::encode(
+            &self.derive_public_key(index)?,
+            self.address_type,
+            self.xpub.network,
+        ))
+    }
+
+    /// Derive the scriptPubKey at a wildcard index
+    pub fn derive_script_pubkey(&self, index: u32) -> Result<Vec<u8>, Error> {
+        Ok(address::script_pubkey(
+            &self.derive_public_key(index)?,
+            self.address_type,
+        ))
+    }
+}
+
+/// When `importdescriptors` should start rescanning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestamp {
+    /// Skip the rescan; the wallet has no history yet
+    Now,
+    /// Rescan from a UNIX timestamp
+    Time(u64),
+}
+
+/// One entry of a Bitcoin Core `importdescriptors` request
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImportRequest {
+    pub desc: String,
+    pub active: bool,
+    pub range: [u32; 2],
+    pub timestamp: Timestamp,
+    pub internal: bool,
+    /// Bitcoin Core only accepts labels on non-ranged, external descriptors
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub label: Option<String>,
+}
+
+impl ImportRequest {
+    /// Create an active import entry for a descriptor, covering indices
+    /// up to and including `range_end`
+    pub fn new(
+        descriptor: &Descriptor,
+        range_end: u32,
+        timestamp: Timestamp,
+    ) -> Result<Self, Error> {
+        limits::check_wildcard_range(0, range_end.saturating_add(1))?;
+        Ok(ImportRequest {
+            desc: descriptor.to_string(),
+            active: true,
+            range: [0, range_end],
+            timestamp,
+            internal: descriptor.is_internal(),
+            label: None,
+        })
+    }
+
+    /// Parse the descriptor back out of the entry
+    pub fn descriptor(&self) -> Result<Descriptor, Error> {
+        self.desc.parse()
+    }
+}
+
+/// Build the receive and change import entries for each of the given accounts
+pub fn import_requests(
+    master: &ExtendedPrivKey,
+    address_type: AddressType,
+    accounts: &[u32],
+    range_end: u32,
+    timestamp: Timestamp,
+) -> Result<Vec<ImportRequest>, Error> {
+    limits::check_wildcard_range(0, range_end.saturating_add(1))?;
+    let mut requests = Vec::with_capacity(accounts.len() * 2);
+    for &account in accounts {
+        for change in [Change::External, Change::Internal] {
+            let descriptor = Descriptor::for_account(master, address_type, account, change)?;
+            requests.push(ImportRequest::new(&descriptor, range_end, timestamp)?);
+        }
+    }
+    Ok(requests)
+}
+
+/// Serialize import entries as the JSON argument to `bitcoin-cli importdescriptors`
+#[cfg(feature = "serde")]
+pub fn import_descriptors_json(requests: &[ImportRequest]) -> Result<String, Error> {
+    serde_json::to_string(requests).map_err(|e| Error::InvalidDescriptor(e.to_string()))
+}
+
+/// Parse the JSON argument of a `bitcoin-cli importdescriptors` call
+#[cfg(feature = "serde")]
+pub fn parse_import_descriptors_json(json: &str) -> Result<Vec<ImportRequest>, Error> {
+    let requests: Vec<ImportRequest> =
+        serde_json::from_str(json).map_err(|e| Error::InvalidDescriptor(e.to_string()))?;
+    for request in &requests {
+        let [start, end] = request.range;
+        limits::check_wildcard_range(start, end.saturating_add(1))?;
+    }
+    Ok(requests)
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Timestamp {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Timestamp::Now => serializer.serialize_str("now"),
+            Timestamp::Time(time) => serializer.serialize_u64(*time),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Timestamp {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Time(u64),
+            Str(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Time(time) => Ok(Timestamp::Time(time)),
+            Raw::Str(s) if s == "now" => Ok(Timestamp::Now),
+            Raw::Str(s) => Err(serde::de::Error::custom(format!(
+                "Invalid timestamp '{}'",
+                s
+            ))),
+        }
+    }
+}
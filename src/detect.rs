@@ -0,0 +1,68 @@
+use crate::bip39::{Language, Mnemonic};
+use crate::error::{Error, MnemonicError};
+
+impl Language {
+    /// Identify the language of a phrase among the compiled-in wordlists.
+    ///
+    /// Some words appear in several lists (English and French share about a
+    /// hundred, the two Chinese lists over a thousand), so when more than one
+    /// list holds every word, only those whose checksum verifies are kept.
+    /// Lists still tied on the same entropy describe the same wallet, and the
+    /// first in [`Language::ALL`] is returned; a tie on different entropy is
+    /// an error. A phrase matching a single list isn't otherwise validated.
+    pub fn detect(phrase: &str) -> Result<Language, Error> {
+        let words: Vec<&str> = phrase.split_whitespace().collect();
+        if let Some((position, word)) = words
+            .iter()
+            .enumerate()
+            .find(|(_, word)| Language::ALL.iter().all(|l| l.index_of(word).is_none()))
+        {
+            return Err(MnemonicError::UnknownWord {
+                position,
+                word: word.to_string(),
+            }
+            .into());
+        }
+
+        let candidates: Vec<Language> = Language::ALL
+            .iter()
+            .copied()
+            .filter(|language| words.iter().all(|word| language.index_of(word).is_some()))
+            .collect();
+        let [first, ..] = candidates[..] else {
+            return Err(MnemonicError::MixedLanguages.into());
+        };
+        if candidates.len() == 1 {
+            return Ok(first);
+        }
+
+        let valid: Vec<(Language, Vec<u8>)> = candidates
+            .iter()
+            .filter_map(|&language| {
+                Mnemonic::words_to_entropy(&words, &language)
+                    .ok()
+                    .map(|entropy| (language, entropy))
+            })
+            .collect();
+        match &valid[..] {
+            // Report the checksum or length error of the first candidate
+            [] => Mnemonic::words_to_entropy(&words, &first).map(|_| first),
+            [(language, entropy), rest @ ..] => {
+                if rest.iter().all(|(_, other)| other == entropy) {
+                    Ok(*language)
+                } else {
+                    let languages = valid.iter().map(|&(l, _)| l).collect();
+                    Err(MnemonicError::AmbiguousLanguage(languages).into())
+                }
+            }
+        }
+    }
+}
+
+impl Mnemonic {
+    /// Create a mnemonic from a phrase in any compiled-in language, detected
+    /// with [`Language::detect`]
+    pub fn from_phrase_any_language(phrase: &str) -> Result<Self, Error> {
+        Mnemonic::from_phrase(phrase, Language::detect(phrase)?)
+    }
+}
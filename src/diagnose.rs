@@ -0,0 +1,120 @@
+use crate::bip39::{Language, Mnemonic, MnemonicType};
+use unicode_normalization::UnicodeNormalization;
+
+/// Most corrections suggested for a single word
+pub const MAX_SUGGESTIONS: usize = 5;
+/// Largest edit distance of a suggested correction
+pub const MAX_EDIT_DISTANCE: usize = 2;
+/// Leading characters that identify an English word; other lists get no
+/// unique-prefix guarantee, but the match is still a good hint
+const PREFIX_LEN: usize = 4;
+
+/// A word of a phrase missing from the wordlist, with likely corrections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordSuggestion {
+    /// Index of the word in the phrase, from 0
+    pub position: usize,
+    pub word: String,
+    /// Corrections, best first: a unique prefix match, then words by edit
+    /// distance and wordlist order
+    pub candidates: Vec<&'static str>,
+}
+
+/// What is wrong with a phrase, for "did you mean" prompts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnosis {
+    pub unknown_words: Vec<WordSuggestion>,
+    /// Whether the phrase has 12, 15, 18, 21 or 24 words
+    pub word_count_valid: bool,
+    /// Whether the checksum verifies; `false` when it can't be checked
+    /// because of unknown words or a bad word count
+    pub checksum_valid: bool,
+}
+
+impl Diagnosis {
+    /// Whether the phrase would parse
+    pub fn is_valid(&self) -> bool {
+        self.unknown_words.is_empty() && self.word_count_valid && self.checksum_valid
+    }
+}
+
+impl Mnemonic {
+    /// Examine a phrase word by word, suggesting corrections for words
+    /// missing from the wordlist by unique prefix and edit distance
+    pub fn diagnose(phrase: &str, language: Language) -> Diagnosis {
+        let words: Vec<&str> = phrase.split_whitespace().collect();
+        let unknown_words: Vec<WordSuggestion> = words
+            .iter()
+            .enumerate()
+            .filter(|(_, word)| language.index_of(word).is_none())
+            .map(|(position, word)| WordSuggestion {
+                position,
+                word: word.to_string(),
+                candidates: suggest(word, language),
+            })
+            .collect();
+        let word_count_valid = MnemonicType::from_word_count(words.len()).is_ok();
+        let checksum_valid = unknown_words.is_empty()
+            && word_count_valid
+            && Mnemonic::words_to_entropy(&words, &language).is_ok();
+
+        Diagnosis {
+            unknown_words,
+            word_count_valid,
+            checksum_valid,
+        }
+    }
+}
+
+/// Suggest wordlist words close to a misspelled word
+pub fn suggest(word: &str, language: Language) -> Vec<&'static str> {
+    let word: Vec<char> = word.to_lowercase().nfkd().collect();
+    let wordlist = language.wordlist();
+
+    let prefix: String = word.iter().take(PREFIX_LEN).collect();
+    let prefixed: Vec<&'static str> = wordlist
+        .iter()
+        .copied()
+        .filter(|w| w.starts_with(&prefix))
+        .collect();
+    let mut candidates = Vec::new();
+    if let [unique] = prefixed[..] {
+        candidates.push(unique);
+    }
+
+    let mut close: Vec<(usize, &'static str)> = wordlist
+        .iter()
+        .map(|&w| (edit_distance(&word, &w.chars().collect::<Vec<_>>()), w))
+        .filter(|&(distance, w)| distance <= MAX_EDIT_DISTANCE && !candidates.contains(&w))
+        .collect();
+    // Stable, so equally close words keep wordlist order
+    close.sort_by_key(|&(distance, _)| distance);
+    candidates.extend(close.into_iter().map(|(_, w)| w));
+    candidates.truncate(MAX_SUGGESTIONS);
+    candidates
+}
+
+/// Edit distance counting insertions, deletions, substitutions and swaps of
+/// adjacent characters, the usual transcription slips
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
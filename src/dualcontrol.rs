@@ -0,0 +1,202 @@
+use crate::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use crate::error::Error;
+use crate::keystore::{KdfLayer, TimeLock, TimeLockedKeystore};
+use crate::signer::{Signer, SigningOptions};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use secp256k1::{ecdsa::Signature, PublicKey};
+use std::sync::Mutex;
+
+/// Length of an encoded share: split id, share number and masked key
+const SHARE_LEN: usize = 8 + 1 + 78;
+/// Argon2id costs protecting password-encrypted shares
+const SHARE_KDF: KdfLayer = KdfLayer {
+    memory_kib: 19 * 1024,
+    iterations: 2,
+    lanes: 1,
+};
+
+/// One of two XOR shares of a serialized extended private key. Either share
+/// alone is uniformly random and reveals nothing about the key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct XprvShare {
+    /// Identifies the split both shares came from
+    pub split_id: [u8; 8],
+    /// 1 or 2
+    pub number: u8,
+    data: [u8; 78],
+}
+
+impl std::fmt::Debug for XprvShare {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("XprvShare")
+            .field("split_id", &hex::encode(self.split_id))
+            .field("number", &self.number)
+            .finish_non_exhaustive()
+    }
+}
+
+impl XprvShare {
+    /// Encode the share
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = self.split_id.to_vec();
+        data.push(self.number);
+        data.extend_from_slice(&self.data);
+        data
+    }
+
+    /// Decode a share produced by [`XprvShare::to_bytes`]
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != SHARE_LEN || !matches!(data[8], 1 | 2) {
+            return Err(Error::InvalidKey("Invalid key share".to_string()));
+        }
+        Ok(XprvShare {
+            split_id: data[..8].try_into().expect("split id length"),
+            number: data[8],
+            data: data[9..].try_into().expect("share length"),
+        })
+    }
+
+    /// Encrypt the share under its holder's password, in the keystore format
+    pub fn encrypt(&self, password: &str) -> Result<Vec<u8>, Error> {
+        let lock = TimeLock {
+            layers: vec![SHARE_KDF],
+            estimated_unlock_secs: 0,
+            note: format!("xprv share {}", self.number),
+        };
+        Ok(TimeLockedKeystore::seal(&self.to_bytes(), password, lock)?.to_bytes())
+    }
+
+    /// Decrypt a share produced by [`XprvShare::encrypt`]
+    pub fn decrypt(data: &[u8], password: &str) -> Result<Self, Error> {
+        XprvShare::from_bytes(&TimeLockedKeystore::from_bytes(data)?.open(password)?)
+    }
+}
+
+/// Split an extended private key into two XOR shares, for two people to
+/// hold separately
+pub fn split_xprv(xprv: &ExtendedPrivKey) -> [XprvShare; 2] {
+    let mut split_id = [0u8; 8];
+    OsRng.fill_bytes(&mut split_id);
+    let mut mask = [0u8; 78];
+    OsRng.fill_bytes(&mut mask);
+
+    let mut masked = xprv.encode();
+    for (m, k) in masked.iter_mut().zip(&mask) {
+        *m ^= k;
+    }
+
+    [
+        XprvShare {
+            split_id,
+            number: 1,
+            data: mask,
+        },
+        XprvShare {
+            split_id,
+            number: 2,
+            data: masked,
+        },
+    ]
+}
+
+/// Signer decorator enforcing the two-person rule: both shares of a split
+/// key must be supplied, independently, before each signature. The key is
+/// combined only for the signature and the shares are dropped afterwards.
+/// Public keys on non-hardened paths come from the split key's xpub and
+/// need no shares.
+pub struct TwoPersonSigner {
+    xpub: ExtendedPubKey,
+    shares: Mutex<[Option<XprvShare>; 2]>,
+}
+
+impl TwoPersonSigner {
+    /// Create a signer for the split key with the given extended public key
+    pub fn new(xpub: ExtendedPubKey) -> Self {
+        TwoPersonSigner {
+            xpub,
+            shares: Mutex::new([None, None]),
+        }
+    }
+
+    /// Fingerprint of the split key
+    pub fn key_fingerprint(&self) -> [u8; 4] {
+        self.xpub.fingerprint()
+    }
+
+    /// Supply one person's share for the next signature
+    pub fn supply(&self, share: XprvShare) -> Result<(), Error> {
+        let mut shares = self.shares.lock().unwrap_or_else(|e| e.into_inner());
+        let other = &shares[2 - share.number as usize];
+        if other
+            .as_ref()
+            .is_some_and(|other| other.split_id != share.split_id)
+        {
+            return Err(Error::InvalidKey(
+                "Shares come from different splits".to_string(),
+            ));
+        }
+        let slot = share.number as usize - 1;
+        shares[slot] = Some(share);
+        Ok(())
+    }
+
+    /// Whether both shares have been supplied
+    pub fn is_armed(&self) -> bool {
+        self.shares
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .all(Option::is_some)
+    }
+
+    /// Drop any supplied shares
+    pub fn disarm(&self) {
+        *self.shares.lock().unwrap_or_else(|e| e.into_inner()) = [None, None];
+    }
+
+    /// Combine the shares for one operation, consuming them
+    fn with_key<T>(
+        &self,
+        f: impl FnOnce(&ExtendedPrivKey) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let taken = std::mem::take(&mut *self.shares.lock().unwrap_or_else(|e| e.into_inner()));
+        let [Some(first), Some(second)] = taken else {
+            return Err(Error::Unsupported(
+                "Signing requires both key shares".to_string(),
+            ));
+        };
+
+        let mut data = first.data;
+        for (d, s) in data.iter_mut().zip(&second.data) {
+            *d ^= s;
+        }
+        let key = ExtendedPrivKey::decode(&data)?;
+        let xpub = key.to_extended_public_key();
+        if xpub.public_key != self.xpub.public_key || xpub.chain_code != self.xpub.chain_code {
+            return Err(Error::InvalidKey(
+                "Combined shares do not match the expected key".to_string(),
+            ));
+        }
+        f(&key)
+    }
+}
+
+impl Signer for TwoPersonSigner {
+    fn public_key(&self, path: &DerivationPath) -> Result<PublicKey, Error> {
+        if path.path.iter().any(|child| child.is_hardened()) {
+            // Hardened children need the private key, and so both shares
+            return self.with_key(|key| key.public_key(path));
+        }
+        Ok(self.xpub.derive_path(path)?.public_key)
+    }
+
+    fn sign_ecdsa(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        options: SigningOptions,
+    ) -> Result<Signature, Error> {
+        self.with_key(|key| key.sign_ecdsa(path, digest, options))
+    }
+}
@@ -0,0 +1,84 @@
+use crate::bip32::{ExtendedPrivKey, ExtendedPubKey};
+use crate::error::Error;
+use crate::utils;
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use secp256k1::ecdh::SharedSecret;
+use secp256k1::{PublicKey, SecretKey};
+use sha2::Sha256;
+
+/// Bytes a ciphertext adds to its plaintext: the ephemeral public key and
+/// the authentication tag
+pub const OVERHEAD: usize = 33 + 16;
+/// HKDF info of the message key
+const KEY_INFO: &[u8] = b"bip32hdwallet ECIES";
+/// ChaCha20-Poly1305 nonce; each message key is used once
+const NONCE: [u8; 12] = [0u8; 12];
+
+/// Encrypt a message to a secp256k1 public key: an ephemeral ECDH key,
+/// HKDF-SHA256 and ChaCha20-Poly1305. The output is the compressed
+/// ephemeral key followed by the ciphertext and tag.
+pub fn encrypt(recipient: &PublicKey, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    let ephemeral = SecretKey::new(&mut OsRng);
+    let ephemeral_pk = PublicKey::from_secret_key(utils::secp(), &ephemeral);
+    let key = message_key(
+        &SharedSecret::new(recipient, &ephemeral),
+        &ephemeral_pk,
+        recipient,
+    );
+
+    let ciphertext = ChaCha20Poly1305::new(&key.into())
+        .encrypt(&NONCE.into(), plaintext)
+        .map_err(|_| Error::Encryption("ECIES encryption failed".to_string()))?;
+
+    let mut message = ephemeral_pk.serialize().to_vec();
+    message.extend_from_slice(&ciphertext);
+    Ok(message)
+}
+
+/// Decrypt a message produced by [`encrypt`]
+pub fn decrypt(secret_key: &SecretKey, message: &[u8]) -> Result<Vec<u8>, Error> {
+    if message.len() < OVERHEAD {
+        return Err(Error::Encryption("Truncated ECIES message".to_string()));
+    }
+    let (ephemeral_pk, ciphertext) = message.split_at(33);
+    let ephemeral_pk = PublicKey::from_slice(ephemeral_pk)
+        .map_err(|_| Error::Encryption("Invalid ephemeral key".to_string()))?;
+    let recipient = PublicKey::from_secret_key(utils::secp(), secret_key);
+    let key = message_key(
+        &SharedSecret::new(&ephemeral_pk, secret_key),
+        &ephemeral_pk,
+        &recipient,
+    );
+
+    ChaCha20Poly1305::new(&key.into())
+        .decrypt(&NONCE.into(), ciphertext)
+        .map_err(|_| Error::Encryption("ECIES authentication failed".to_string()))
+}
+
+/// HKDF-SHA256 over the ECDH secret, salted with both public keys
+fn message_key(shared: &SharedSecret, ephemeral_pk: &PublicKey, recipient: &PublicKey) -> [u8; 32] {
+    let mut salt = ephemeral_pk.serialize().to_vec();
+    salt.extend_from_slice(&recipient.serialize());
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), &shared.secret_bytes())
+        .expand(KEY_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+impl ExtendedPubKey {
+    /// Encrypt a message that only the matching private key can read
+    pub fn ecies_encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        encrypt(&self.public_key, plaintext)
+    }
+}
+
+impl ExtendedPrivKey {
+    /// Decrypt a message encrypted to this key's public key
+    pub fn ecies_decrypt(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        decrypt(&self.private_key, message)
+    }
+}
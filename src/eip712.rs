@@ -0,0 +1,442 @@
+use crate::bip32::ExtendedPrivKey;
+use crate::error::Error;
+use crate::ethereum::{keccak256, EthSignature};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Name of the domain struct type
+pub const DOMAIN_TYPE: &str = "EIP712Domain";
+
+/// A member of a struct type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub name: String,
+    pub type_name: String,
+}
+
+/// A typed-data value, shaped like its JSON form and interpreted according to
+/// the declared member types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    /// Decimal or 0x-prefixed hex number
+    Number(String),
+    /// Strings, and hex-encoded addresses, bytes and numbers
+    String(String),
+    Array(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Build an object from name/value pairs
+    pub fn object<K: Into<String>, I: IntoIterator<Item = (K, Value)>>(members: I) -> Self {
+        Value::Object(members.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Number(value.to_string())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+/// EIP-712 typed data: struct types, the domain and the message to sign
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedData {
+    pub types: BTreeMap<String, Vec<Member>>,
+    pub primary_type: String,
+    pub domain: Value,
+    pub message: Value,
+}
+
+impl TypedData {
+    /// Create typed data without any declared types
+    pub fn new(primary_type: &str, domain: Value, message: Value) -> Self {
+        TypedData {
+            types: BTreeMap::new(),
+            primary_type: primary_type.to_string(),
+            domain,
+            message,
+        }
+    }
+
+    /// Declare a struct type from (name, type) member pairs
+    pub fn with_type(mut self, name: &str, members: &[(&str, &str)]) -> Self {
+        self.types.insert(
+            name.to_string(),
+            members
+                .iter()
+                .map(|&(name, type_name)| Member {
+                    name: name.to_string(),
+                    type_name: type_name.to_string(),
+                })
+                .collect(),
+        );
+        self
+    }
+
+    /// Encode a struct type with the types it references, e.g.
+    /// `Mail(Person from,Person to,string contents)Person(string name,address wallet)`
+    pub fn encode_type(&self, name: &str) -> Result<String, Error> {
+        let mut deps = BTreeSet::new();
+        self.collect_dependencies(name, &mut deps)?;
+        deps.remove(name);
+
+        let mut encoded = String::new();
+        for type_name in std::iter::once(name).chain(deps.iter().map(String::as_str)) {
+            let members = &self.types[type_name];
+            encoded.push_str(type_name);
+            encoded.push('(');
+            for (i, member) in members.iter().enumerate() {
+                if i > 0 {
+                    encoded.push(',');
+                }
+                encoded.push_str(&member.type_name);
+                encoded.push(' ');
+                encoded.push_str(&member.name);
+            }
+            encoded.push(')');
+        }
+        Ok(encoded)
+    }
+
+    /// Get the hash of a struct type's encoding
+    pub fn type_hash(&self, name: &str) -> Result<[u8; 32], Error> {
+        Ok(keccak256(self.encode_type(name)?.as_bytes()))
+    }
+
+    /// Encode a struct value as its type hash followed by one word per member
+    pub fn encode_data(&self, name: &str, value: &Value) -> Result<Vec<u8>, Error> {
+        let members = self.struct_members(name)?;
+        let fields = match value {
+            Value::Object(fields) => fields,
+            _ => return Err(invalid(format!("{} must be an object", name))),
+        };
+
+        let mut encoded = Vec::with_capacity(32 * (members.len() + 1));
+        encoded.extend_from_slice(&self.type_hash(name)?);
+        for member in members {
+            let field = fields
+                .get(&member.name)
+                .ok_or_else(|| invalid(format!("Missing {}.{}", name, member.name)))?;
+            encoded.extend_from_slice(&self.encode_field(&member.type_name, field)?);
+        }
+        Ok(encoded)
+    }
+
+    /// Hash a struct value
+    pub fn hash_struct(&self, name: &str, value: &Value) -> Result<[u8; 32], Error> {
+        Ok(keccak256(&self.encode_data(name, value)?))
+    }
+
+    /// Get the domain separator
+    pub fn domain_separator(&self) -> Result<[u8; 32], Error> {
+        self.hash_struct(DOMAIN_TYPE, &self.domain)
+    }
+
+    /// Get the digest to sign: `keccak256(0x19 0x01 || domainSeparator || hashStruct(message))`
+    pub fn signing_hash(&self) -> Result<[u8; 32], Error> {
+        let mut data = Vec::with_capacity(66);
+        data.extend_from_slice(&[0x19, 0x01]);
+        data.extend_from_slice(&self.domain_separator()?);
+        data.extend_from_slice(&self.hash_struct(&self.primary_type, &self.message)?);
+        Ok(keccak256(&data))
+    }
+
+    /// Parse the JSON accepted by `eth_signTypedData_v4`
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let root: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+
+        let mut types = BTreeMap::new();
+        for (name, members) in root["types"]
+            .as_object()
+            .ok_or_else(|| invalid("Missing types".to_string()))?
+        {
+            let members = members
+                .as_array()
+                .ok_or_else(|| invalid(format!("Type {} must be an array", name)))?
+                .iter()
+                .map(
+                    |member| match (member["name"].as_str(), member["type"].as_str()) {
+                        (Some(name), Some(type_name)) => Ok(Member {
+                            name: name.to_string(),
+                            type_name: type_name.to_string(),
+                        }),
+                        _ => Err(invalid(format!("Invalid member of {}", name))),
+                    },
+                )
+                .collect::<Result<Vec<_>, Error>>()?;
+            types.insert(name.clone(), members);
+        }
+
+        Ok(TypedData {
+            types,
+            primary_type: root["primaryType"]
+                .as_str()
+                .ok_or_else(|| invalid("Missing primaryType".to_string()))?
+                .to_string(),
+            domain: json_value(&root["domain"])?,
+            message: json_value(&root["message"])?,
+        })
+    }
+
+    fn struct_members(&self, name: &str) -> Result<&[Member], Error> {
+        self.types
+            .get(name)
+            .map(Vec::as_slice)
+            .ok_or_else(|| invalid(format!("Unknown type {}", name)))
+    }
+
+    fn collect_dependencies(&self, name: &str, deps: &mut BTreeSet<String>) -> Result<(), Error> {
+        if deps.contains(name) {
+            return Ok(());
+        }
+        deps.insert(name.to_string());
+
+        for member in self.struct_members(name)? {
+            let base = base_type(&member.type_name);
+            if self.types.contains_key(base) {
+                self.collect_dependencies(base, deps)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_field(&self, type_name: &str, value: &Value) -> Result<[u8; 32], Error> {
+        // Arrays hash the concatenated encodings of their elements
+        if let Some(open) = type_name.rfind('[') {
+            let element_type = &type_name[..open];
+            let length = type_name[open + 1..]
+                .strip_suffix(']')
+                .filter(|_| !element_type.is_empty())
+                .ok_or_else(|| invalid(format!("Malformed array type {}", type_name)))?;
+            let elements = match value {
+                Value::Array(elements) => elements,
+                _ => return Err(invalid(format!("{} must be an array", type_name))),
+            };
+            if !length.is_empty() && length.parse::<usize>().ok() != Some(elements.len()) {
+                return Err(invalid(format!(
+                    "{} has {} elements",
+                    type_name,
+                    elements.len()
+                )));
+            }
+
+            let mut encoded = Vec::with_capacity(32 * elements.len());
+            for element in elements {
+                encoded.extend_from_slice(&self.encode_field(element_type, element)?);
+            }
+            return Ok(keccak256(&encoded));
+        }
+
+        if self.types.contains_key(type_name) {
+            return self.hash_struct(type_name, value);
+        }
+
+        match type_name {
+            "string" => match value {
+                Value::String(s) => Ok(keccak256(s.as_bytes())),
+                _ => Err(invalid("string must be a string".to_string())),
+            },
+            "bytes" => Ok(keccak256(&hex_value(value)?)),
+            "bool" => match value {
+                Value::Bool(b) => Ok(word_from_u8(*b as u8)),
+                _ => Err(invalid("bool must be a boolean".to_string())),
+            },
+            "address" => {
+                let bytes = hex_value(value)?;
+                if bytes.len() != 20 {
+                    return Err(invalid("address must be 20 bytes".to_string()));
+                }
+                let mut word = [0u8; 32];
+                word[12..].copy_from_slice(&bytes);
+                Ok(word)
+            }
+            _ => {
+                if let Some(size) = type_name.strip_prefix("bytes") {
+                    let size = parse_size(type_name, size, 1, 32)?;
+                    let bytes = hex_value(value)?;
+                    if bytes.len() != size {
+                        return Err(invalid(format!("{} must be {} bytes", type_name, size)));
+                    }
+                    let mut word = [0u8; 32];
+                    word[..size].copy_from_slice(&bytes);
+                    Ok(word)
+                } else if let Some(bits) = type_name.strip_prefix("uint") {
+                    number_word(type_name, bits, value, false)
+                } else if let Some(bits) = type_name.strip_prefix("int") {
+                    number_word(type_name, bits, value, true)
+                } else {
+                    Err(invalid(format!("Unknown type {}", type_name)))
+                }
+            }
+        }
+    }
+}
+
+impl ExtendedPrivKey {
+    /// Sign EIP-712 typed data, returning `v` as 27 or 28 like `eth_signTypedData_v4`
+    pub fn sign_typed_data(&self, data: &TypedData) -> Result<EthSignature, Error> {
+        self.sign_eth_hash(&data.signing_hash()?, None)
+    }
+}
+
+fn invalid(reason: String) -> Error {
+    Error::InvalidTypedData(reason)
+}
+
+/// Strip array suffixes from a type name
+fn base_type(type_name: &str) -> &str {
+    type_name.split('[').next().unwrap_or(type_name)
+}
+
+fn word_from_u8(value: u8) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[31] = value;
+    word
+}
+
+/// Parse the bit or byte size suffix of a type name
+fn parse_size(type_name: &str, size: &str, min: usize, max: usize) -> Result<usize, Error> {
+    match size.parse::<usize>() {
+        Ok(size) if (min..=max).contains(&size) => Ok(size),
+        _ => Err(invalid(format!("Invalid type {}", type_name))),
+    }
+}
+
+fn hex_value(value: &Value) -> Result<Vec<u8>, Error> {
+    match value {
+        Value::String(s) => {
+            hex::decode(s.strip_prefix("0x").unwrap_or(s)).map_err(|e| invalid(e.to_string()))
+        }
+        _ => Err(invalid("Expected a hex string".to_string())),
+    }
+}
+
+/// Encode an integer value as a 256-bit two's complement word
+fn number_word(
+    type_name: &str,
+    bits: &str,
+    value: &Value,
+    signed: bool,
+) -> Result<[u8; 32], Error> {
+    let bits = if bits.is_empty() {
+        256
+    } else {
+        parse_size(type_name, bits, 8, 256)?
+    };
+    if bits % 8 != 0 {
+        return Err(invalid(format!("Invalid type {}", type_name)));
+    }
+
+    let text = match value {
+        Value::Number(s) | Value::String(s) => s.trim(),
+        _ => return Err(invalid(format!("{} must be a number", type_name))),
+    };
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) if signed => (true, digits),
+        Some(_) => return Err(invalid(format!("{} must not be negative", type_name))),
+        None => (false, text),
+    };
+
+    let mut word = [0u8; 32];
+    let (radix, digits) = match digits.strip_prefix("0x") {
+        Some(hex) => (16, hex),
+        None => (10, digits),
+    };
+    if digits.is_empty() {
+        return Err(invalid(format!("{} must be a number", type_name)));
+    }
+    for c in digits.chars() {
+        let digit = c
+            .to_digit(radix)
+            .ok_or_else(|| invalid(format!("Invalid digit in {}", text)))?;
+        // word = word * radix + digit
+        let mut carry = digit;
+        for byte in word.iter_mut().rev() {
+            let product = *byte as u32 * radix + carry;
+            *byte = product as u8;
+            carry = product >> 8;
+        }
+        if carry != 0 {
+            return Err(invalid(format!("{} overflows {}", text, type_name)));
+        }
+    }
+
+    // Check the magnitude fits: unsigned values below 2^bits, signed
+    // values within [-2^(bits-1), 2^(bits-1))
+    let magnitude_bits = if signed { bits - 1 } else { bits };
+    let limit_ok =
+        fits(&word, magnitude_bits) || (negative && is_power_of_two(&word, magnitude_bits));
+    if !limit_ok {
+        return Err(invalid(format!("{} overflows {}", text, type_name)));
+    }
+
+    if negative {
+        // Two's complement: invert and add one
+        let mut carry = 1u16;
+        for byte in word.iter_mut().rev() {
+            let sum = (!*byte) as u16 + carry;
+            *byte = sum as u8;
+            carry = sum >> 8;
+        }
+    }
+    Ok(word)
+}
+
+/// Whether a big-endian word is below 2^bits
+fn fits(word: &[u8; 32], bits: usize) -> bool {
+    if bits >= 256 {
+        return true;
+    }
+    let full_bytes = bits / 8;
+    let partial = bits % 8;
+    let high = 32 - full_bytes;
+    word[..high.saturating_sub(1)].iter().all(|&b| b == 0)
+        && (high == 0 || word[high - 1] >> partial == 0)
+}
+
+/// Whether a big-endian word equals 2^bits
+fn is_power_of_two(word: &[u8; 32], bits: usize) -> bool {
+    if bits >= 256 {
+        return false;
+    }
+    let mut expected = [0u8; 32];
+    expected[31 - bits / 8] = 1 << (bits % 8);
+    *word == expected
+}
+
+#[cfg(feature = "serde")]
+fn json_value(value: &serde_json::Value) -> Result<Value, Error> {
+    Ok(match value {
+        serde_json::Value::Bool(b) => Value::Bool(*b),
+        serde_json::Value::Number(n) => Value::Number(n.to_string()),
+        serde_json::Value::String(s) => Value::String(s.clone()),
+        serde_json::Value::Array(elements) => Value::Array(
+            elements
+                .iter()
+                .map(json_value)
+                .collect::<Result<Vec<_>, Error>>()?,
+        ),
+        serde_json::Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), json_value(v)?)))
+                .collect::<Result<BTreeMap<_, _>, Error>>()?,
+        ),
+        serde_json::Value::Null => return Err(invalid("Unexpected null".to_string())),
+    })
+}
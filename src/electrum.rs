@@ -0,0 +1,68 @@
+use crate::address::{self, AddressType};
+use crate::bip32::ExtendedPubKey;
+use crate::bip44::Change;
+use crate::error::Error;
+use crate::utils;
+use crate::wallet::{AddressLocation, Wallet};
+
+/// Electrum protocol script hash of a scriptPubKey: its SHA-256, byte-reversed
+/// and hex encoded, as `blockchain.scripthash.*` methods expect
+pub fn script_hash(script_pubkey: &[u8]) -> String {
+    let mut hash = utils::sha256(script_pubkey);
+    hash.reverse();
+    hex::encode(hash)
+}
+
+/// A wallet address to subscribe to on an Electrum server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElectrumSubscription {
+    pub location: AddressLocation,
+    pub address: String,
+    pub script_hash: String,
+}
+
+impl ExtendedPubKey {
+    /// Derive the Electrum script hashes of `count` consecutive addresses on
+    /// a chain of an account key
+    pub fn script_hashes(
+        &self,
+        change: Change,
+        address_type: AddressType,
+        start: u32,
+        count: u32,
+    ) -> Result<Vec<String>, Error> {
+        Ok(self
+            .script_pubkeys(change, address_type, start, count)?
+            .iter()
+            .map(|script| script_hash(script))
+            .collect())
+    }
+}
+
+impl Wallet {
+    /// Script hashes of every address in the watched range of each account's
+    /// chains, for `blockchain.scripthash.subscribe`
+    pub fn electrum_subscriptions(&self) -> Result<Vec<ElectrumSubscription>, Error> {
+        let mut subscriptions = Vec::new();
+        for account in self.accounts() {
+            for change in [Change::External, Change::Internal] {
+                for index in account.watch_range(change) {
+                    let Ok(key) = self.derive_key(&account.xpub, change, index) else {
+                        continue;
+                    };
+                    let script = address::script_pubkey(&key.public_key, self.address_type());
+                    subscriptions.push(ElectrumSubscription {
+                        location: AddressLocation {
+                            account: account.index,
+                            change,
+                            index,
+                        },
+                        address: key.address(self.address_type()),
+                        script_hash: script_hash(&script),
+                    });
+                }
+            }
+        }
+        Ok(subscriptions)
+    }
+}
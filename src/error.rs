@@ -1,3 +1,4 @@
+use crate::bip39::Language;
 use thiserror::Error;
 
 /// Error types for the HD wallet implementation
@@ -19,7 +20,7 @@ pub enum Error {
     InvalidChecksum,
 
     #[error("Invalid mnemonic: {0}")]
-    InvalidMnemonic(String),
+    InvalidMnemonic(#[from] MnemonicError),
 
     #[error("Invalid entropy: {0}")]
     InvalidEntropy(String),
@@ -36,9 +37,262 @@ pub enum Error {
     #[error("Hardened derivation requires private key")]
     HardenedDerivationRequiresPrivateKey,
 
-    #[error("Invalid word in mnemonic: {0}")]
-    InvalidWord(String),
-
     #[error("Unsupported language: {0}")]
     UnsupportedLanguage(String),
+
+    #[error("Network mismatch: {0}")]
+    NetworkMismatch(String),
+
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("Invalid descriptor: {0}")]
+    InvalidDescriptor(String),
+
+    #[error("Invalid key origin: {0}")]
+    InvalidKeyOrigin(String),
+
+    #[error("Invalid typed data: {0}")]
+    InvalidTypedData(String),
+
+    #[error("Invalid export: {0}")]
+    InvalidExport(String),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    #[error("Self-test failed: {0}")]
+    SelfTest(String),
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error("Invalid usage: {0}")]
+    Usage(String),
+
+    #[error("Cannot read input: {0}")]
+    Input(String),
+
+    #[error("Cannot write output: {0}")]
+    Output(String),
+
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
+    #[error("Remote signer error: {0}")]
+    Remote(String),
+
+    #[error("Unsupported operation: {0}")]
+    Unsupported(String),
+
+    #[error("Invalid block filter: {0}")]
+    InvalidFilter(String),
+
+    #[error("Invalid codex32 string: {0}")]
+    InvalidCodex32(String),
+
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(#[from] LimitError),
+}
+
+/// Why a mnemonic phrase is invalid, precise enough to point at the problem
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MnemonicError {
+    #[error("Unknown word '{word}' at position {position}")]
+    UnknownWord {
+        /// Index of the word in the phrase, from 0
+        position: usize,
+        word: String,
+    },
+
+    #[error("Invalid word count: {0} (expected 12, 15, 18, 21 or 24)")]
+    WordCount(usize),
+
+    #[error("Checksum mismatch")]
+    Checksum,
+
+    #[error("Words come from different wordlists")]
+    MixedLanguages,
+
+    #[error("Phrase is valid in several languages: {0:?}")]
+    AmbiguousLanguage(Vec<Language>),
+}
+
+/// A request beyond the configured [`crate::limits::Limits`]
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitError {
+    #[error("Path depth {depth} exceeds {max}")]
+    PathDepth { depth: usize, max: usize },
+
+    #[error("Batch of {size} exceeds {max}")]
+    BatchSize { size: u32, max: u32 },
+
+    #[error("Range of {size} indices exceeds {max}")]
+    WildcardRange { size: u32, max: u32 },
+}
+
+/// Broad category of an [`Error`], each with a stable process exit code so
+/// scripts wrapping the CLI can branch on failures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ErrorKind {
+    /// A bug or failed self-test (exit code 1)
+    Internal,
+    /// Bad command-line usage (exit code 2)
+    Usage,
+    /// Invalid mnemonic, entropy or language (exit code 3)
+    Mnemonic,
+    /// Invalid derivation path or key origin (exit code 4)
+    Path,
+    /// Invalid seed, key or extended key encoding (exit code 5)
+    Key,
+    /// Key or address for another network (exit code 6)
+    Network,
+    /// Malformed address, descriptor, signature or other input (exit code 7)
+    Input,
+    /// Encryption, decryption or signing failure (exit code 8)
+    Crypto,
+    /// Invalid configuration (exit code 9)
+    Config,
+    /// A configured or rate limit was exceeded (exit code 10)
+    Limit,
+    /// Persistence failure (exit code 11)
+    Storage,
+    /// Remote signer failure (exit code 12)
+    Remote,
+    /// Operation not supported for these keys or scripts (exit code 13)
+    Unsupported,
+}
+
+impl ErrorKind {
+    /// Process exit code of the category
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ErrorKind::Internal => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::Mnemonic => 3,
+            ErrorKind::Path => 4,
+            ErrorKind::Key => 5,
+            ErrorKind::Network => 6,
+            ErrorKind::Input => 7,
+            ErrorKind::Crypto => 8,
+            ErrorKind::Config => 9,
+            ErrorKind::Limit => 10,
+            ErrorKind::Storage => 11,
+            ErrorKind::Remote => 12,
+            ErrorKind::Unsupported => 13,
+        }
+    }
+
+    /// Lowercase name of the category, as used in JSON envelopes
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Internal => "internal",
+            ErrorKind::Usage => "usage",
+            ErrorKind::Mnemonic => "mnemonic",
+            ErrorKind::Path => "path",
+            ErrorKind::Key => "key",
+            ErrorKind::Network => "network",
+            ErrorKind::Input => "input",
+            ErrorKind::Crypto => "crypto",
+            ErrorKind::Config => "config",
+            ErrorKind::Limit => "limit",
+            ErrorKind::Storage => "storage",
+            ErrorKind::Remote => "remote",
+            ErrorKind::Unsupported => "unsupported",
+        }
+    }
+}
+
+impl Error {
+    /// Category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::InvalidMnemonic(_)
+            | Error::InvalidEntropy(_)
+            | Error::UnsupportedLanguage(_) => ErrorKind::Mnemonic,
+            Error::InvalidDerivationPath(_)
+            | Error::HardenedDerivationRequiresPrivateKey
+            | Error::InvalidKeyOrigin(_) => ErrorKind::Path,
+            Error::InvalidSeed(_)
+            | Error::InvalidKey(_)
+            | Error::InvalidExtendedKey(_)
+            | Error::InvalidChecksum
+            | Error::Base58DecodeError(_) => ErrorKind::Key,
+            Error::NetworkMismatch(_) => ErrorKind::Network,
+            Error::InvalidAddress(_)
+            | Error::InvalidDescriptor(_)
+            | Error::InvalidTypedData(_)
+            | Error::InvalidExport(_)
+            | Error::InvalidFilter(_)
+            | Error::InvalidCodex32(_)
+            | Error::Input(_) => ErrorKind::Input,
+            Error::Secp256k1(_)
+            | Error::HmacError
+            | Error::InvalidSignature(_)
+            | Error::Encryption(_) => ErrorKind::Crypto,
+            Error::InvalidConfig(_) => ErrorKind::Config,
+            Error::Usage(_) => ErrorKind::Usage,
+            Error::LimitExceeded(_) | Error::RateLimited(_) => ErrorKind::Limit,
+            Error::Storage(_) => ErrorKind::Storage,
+            Error::Remote(_) => ErrorKind::Remote,
+            Error::Unsupported(_) => ErrorKind::Unsupported,
+            Error::SelfTest(_) | Error::Output(_) => ErrorKind::Internal,
+        }
+    }
+
+    /// A suggestion for fixing the error, where one applies
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Error::InvalidMnemonic(MnemonicError::UnknownWord { .. }) => {
+                Some("check the spelling of the word; the first four letters identify it")
+            }
+            Error::InvalidMnemonic(MnemonicError::WordCount(_)) => {
+                Some("phrases have 12, 15, 18, 21 or 24 words")
+            }
+            Error::InvalidMnemonic(MnemonicError::Checksum) => {
+                Some("a word is wrong or out of order")
+            }
+            Error::InvalidMnemonic(MnemonicError::AmbiguousLanguage(_)) => {
+                Some("specify the language of the phrase")
+            }
+            Error::InvalidDerivationPath(_) => Some("paths look like m/84'/0'/0'/0/0"),
+            Error::HardenedDerivationRequiresPrivateKey => {
+                Some("derive hardened components from the extended private key")
+            }
+            Error::NetworkMismatch(_) => Some("select the network the key or address was made for"),
+            Error::LimitExceeded(_) => Some("request fewer items or raise the limits"),
+            Error::RateLimited(_) => Some("retry later"),
+            _ => None,
+        }
+    }
+}
+
+/// Machine-readable description of an error
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorEnvelope {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl From<&Error> for ErrorEnvelope {
+    fn from(error: &Error) -> Self {
+        ErrorEnvelope {
+            kind: error.kind(),
+            message: error.to_string(),
+            hint: error.hint().map(str::to_string),
+        }
+    }
 }
@@ -0,0 +1,173 @@
+use crate::bip32::{ExtendedPrivKey, ExtendedPubKey};
+use crate::error::Error;
+use crate::metrics::{self, SignatureKind};
+use crate::utils;
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{Message, PublicKey};
+use sha3::{Digest, Keccak256};
+
+/// Compute the Keccak-256 hash used by Ethereum
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(data);
+    let result = hasher.finalize();
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&result);
+    hash
+}
+
+/// Ethereum address of a public key: the last 20 bytes of the Keccak-256
+/// hash of the uncompressed key
+pub fn address_bytes(public_key: &PublicKey) -> [u8; 20] {
+    let hash = keccak256(&public_key.serialize_uncompressed()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// Encode an address as hex with EIP-55 checksum casing
+pub fn to_checksum_address(address: &[u8; 20]) -> String {
+    let lower = hex::encode(address);
+    checksum_casing(&lower, keccak256(lower.as_bytes()))
+}
+
+/// Encode an address as hex with EIP-1191 checksum casing, which mixes the
+/// chain id into the hash (used by e.g. Rootstock)
+pub fn to_checksum_address_eip1191(address: &[u8; 20], chain_id: u64) -> String {
+    let lower = hex::encode(address);
+    let hash = keccak256(format!("{}0x{}", chain_id, lower).as_bytes());
+    checksum_casing(&lower, hash)
+}
+
+/// Uppercase each hex letter whose hash nibble is 8 or more
+fn checksum_casing(lower: &str, hash: [u8; 32]) -> String {
+    let mut encoded = String::with_capacity(42);
+    encoded.push_str("0x");
+    for (i, c) in lower.chars().enumerate() {
+        let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+        if nibble >= 8 {
+            encoded.push(c.to_ascii_uppercase());
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
+
+/// Parse a 0x-prefixed hex address, checking the EIP-55 casing when it is mixed-case
+pub fn parse_address(address: &str) -> Result<[u8; 20], Error> {
+    parse_hex_address(address, to_checksum_address)
+}
+
+/// Parse a 0x-prefixed hex address, checking mixed casing against `checksum`
+pub(crate) fn parse_hex_address(
+    address: &str,
+    checksum: impl Fn(&[u8; 20]) -> String,
+) -> Result<[u8; 20], Error> {
+    let digits = address
+        .strip_prefix("0x")
+        .filter(|digits| digits.len() == 40)
+        .ok_or_else(|| Error::InvalidAddress("Expected 0x and 40 hex digits".to_string()))?;
+    let mut bytes = [0u8; 20];
+    hex::decode_to_slice(digits, &mut bytes).map_err(|e| Error::InvalidAddress(e.to_string()))?;
+
+    let lower = digits.chars().any(|c| c.is_ascii_lowercase());
+    let upper = digits.chars().any(|c| c.is_ascii_uppercase());
+    if lower && upper && checksum(&bytes) != address {
+        return Err(Error::InvalidAddress(
+            "Invalid address checksum".to_string(),
+        ));
+    }
+    Ok(bytes)
+}
+
+/// EIP-55 address of a public key
+pub fn encode_address(public_key: &PublicKey) -> String {
+    to_checksum_address(&address_bytes(public_key))
+}
+
+/// An ECDSA signature in Ethereum's (r, s, v) form
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EthSignature {
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    /// `27 + y` for legacy signatures, `35 + 2 * chain_id + y` with EIP-155
+    pub v: u64,
+}
+
+impl EthSignature {
+    /// Get the y-parity of R, as used by typed (EIP-2718) transactions
+    pub fn y_parity(&self) -> u8 {
+        if self.v >= 35 {
+            ((self.v - 35) % 2) as u8
+        } else {
+            (self.v.saturating_sub(27) % 2) as u8
+        }
+    }
+
+    /// Get the chain id encoded in `v`, if the signature uses EIP-155
+    pub fn chain_id(&self) -> Option<u64> {
+        (self.v >= 35).then(|| (self.v - 35) / 2)
+    }
+
+    /// Recover the public key that signed a 32-byte hash
+    pub fn recover(&self, hash: &[u8; 32]) -> Result<PublicKey, Error> {
+        let recovery_id = RecoveryId::try_from(i32::from(self.y_parity()))?;
+        let mut compact = [0u8; 64];
+        compact[..32].copy_from_slice(&self.r);
+        compact[32..].copy_from_slice(&self.s);
+        let signature = RecoverableSignature::from_compact(&compact, recovery_id)?;
+        Ok(utils::secp().recover_ecdsa(&Message::from_digest(*hash), &signature)?)
+    }
+
+    /// Recover the EIP-55 address that signed a 32-byte hash
+    pub fn recover_address(&self, hash: &[u8; 32]) -> Result<String, Error> {
+        Ok(encode_address(&self.recover(hash)?))
+    }
+}
+
+impl ExtendedPrivKey {
+    /// Sign a 32-byte hash (e.g. the Keccak-256 of an RLP-encoded transaction),
+    /// encoding `v` for EIP-155 replay protection when a chain id is given.
+    /// Fails if `v` would not fit in a `u64`.
+    pub fn sign_eth_hash(
+        &self,
+        hash: &[u8; 32],
+        chain_id: Option<u64>,
+    ) -> Result<EthSignature, Error> {
+        let v_base = match chain_id {
+            Some(chain_id) => chain_id
+                .checked_mul(2)
+                .and_then(|v| v.checked_add(35))
+                .filter(|&v| v < u64::MAX)
+                .ok_or_else(|| {
+                    Error::InvalidConfig(format!("Chain id {} is too large for EIP-155", chain_id))
+                })?,
+            None => 27,
+        };
+
+        metrics::signature(SignatureKind::EcdsaRecoverable);
+        let signature =
+            utils::secp().sign_ecdsa_recoverable(&Message::from_digest(*hash), &self.private_key);
+        let (recovery_id, compact) = signature.serialize_compact();
+        let y = i32::from(recovery_id) as u64;
+
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        r.copy_from_slice(&compact[..32]);
+        s.copy_from_slice(&compact[32..]);
+
+        Ok(EthSignature {
+            r,
+            s,
+            v: v_base + y,
+        })
+    }
+}
+
+impl ExtendedPubKey {
+    /// Get the EIP-55 Ethereum address of this key
+    pub fn eth_address(&self) -> String {
+        encode_address(&self.public_key)
+    }
+}
@@ -0,0 +1,169 @@
+use crate::bip32::DerivationPath;
+use crate::bip44::{AccountLevel, AddressIndex, Bip44Path, Change, CoinType};
+use crate::error::Error;
+use crate::ethereum;
+use crate::utils;
+use crate::wallet::Wallet;
+use secp256k1::PublicKey;
+
+/// How a chain renders Keccak-256 account addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvmAddressFormat {
+    /// 0x-prefixed hex with EIP-55 checksum casing
+    Eip55,
+    /// 0x-prefixed hex with EIP-1191 checksum casing over the chain id
+    Eip1191,
+    /// Base58Check with the 0x41 version byte
+    Tron,
+}
+
+/// Derivation and address conventions of an EVM-compatible chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvmChain {
+    pub name: &'static str,
+    pub chain_id: u64,
+    /// Coin type wallets conventionally derive at; many chains reuse Ethereum's 60'
+    pub coin_type: CoinType,
+    /// Coin type registered for the chain in SLIP-44
+    pub native_coin_type: CoinType,
+    pub address_format: EvmAddressFormat,
+}
+
+impl EvmChain {
+    pub const ETHEREUM: EvmChain = EvmChain {
+        name: "Ethereum",
+        chain_id: 1,
+        coin_type: CoinType::ETHEREUM,
+        native_coin_type: CoinType::ETHEREUM,
+        address_format: EvmAddressFormat::Eip55,
+    };
+    pub const ETHEREUM_CLASSIC: EvmChain = EvmChain {
+        name: "Ethereum Classic",
+        chain_id: 61,
+        coin_type: CoinType::ETHEREUM_CLASSIC,
+        native_coin_type: CoinType::ETHEREUM_CLASSIC,
+        address_format: EvmAddressFormat::Eip55,
+    };
+    pub const BNB_SMART_CHAIN: EvmChain = EvmChain {
+        name: "BNB Smart Chain",
+        chain_id: 56,
+        coin_type: CoinType::ETHEREUM,
+        native_coin_type: CoinType::BNB_SMART_CHAIN,
+        address_format: EvmAddressFormat::Eip55,
+    };
+    pub const POLYGON: EvmChain = EvmChain {
+        name: "Polygon",
+        chain_id: 137,
+        coin_type: CoinType::ETHEREUM,
+        native_coin_type: CoinType::POLYGON,
+        address_format: EvmAddressFormat::Eip55,
+    };
+    pub const AVALANCHE_C: EvmChain = EvmChain {
+        name: "Avalanche C-Chain",
+        chain_id: 43114,
+        coin_type: CoinType::ETHEREUM,
+        native_coin_type: CoinType::AVALANCHE,
+        address_format: EvmAddressFormat::Eip55,
+    };
+    pub const ROOTSTOCK: EvmChain = EvmChain {
+        name: "Rootstock",
+        chain_id: 30,
+        coin_type: CoinType::ROOTSTOCK,
+        native_coin_type: CoinType::ROOTSTOCK,
+        address_format: EvmAddressFormat::Eip1191,
+    };
+    pub const TRON: EvmChain = EvmChain {
+        name: "Tron",
+        chain_id: 728126428,
+        coin_type: CoinType::TRON,
+        native_coin_type: CoinType::TRON,
+        address_format: EvmAddressFormat::Tron,
+    };
+
+    /// Get all presets
+    pub fn all() -> &'static [EvmChain] {
+        &[
+            EvmChain::ETHEREUM,
+            EvmChain::ETHEREUM_CLASSIC,
+            EvmChain::BNB_SMART_CHAIN,
+            EvmChain::POLYGON,
+            EvmChain::AVALANCHE_C,
+            EvmChain::ROOTSTOCK,
+            EvmChain::TRON,
+        ]
+    }
+
+    /// Look up a preset by chain id
+    pub fn for_chain_id(chain_id: u64) -> Option<EvmChain> {
+        EvmChain::all()
+            .iter()
+            .find(|chain| chain.chain_id == chain_id)
+            .copied()
+    }
+
+    /// Use the chain's own SLIP-44 coin type instead of the conventional one
+    pub fn with_native_coin_type(self) -> Self {
+        EvmChain {
+            coin_type: self.native_coin_type,
+            ..self
+        }
+    }
+
+    /// Get the path of an address: `m/44'/coin'/account'/0/index`
+    pub fn path(&self, account: u32, index: u32) -> DerivationPath {
+        Bip44Path::standard(
+            self.coin_type,
+            AccountLevel::new(account),
+            Change::External,
+            AddressIndex::new(index),
+        )
+        .to_derivation_path()
+    }
+
+    /// Encode the address of a public key in the chain's format
+    pub fn encode_address(&self, public_key: &PublicKey) -> String {
+        let address = ethereum::address_bytes(public_key);
+        match self.address_format {
+            EvmAddressFormat::Eip55 => ethereum::to_checksum_address(&address),
+            EvmAddressFormat::Eip1191 => {
+                ethereum::to_checksum_address_eip1191(&address, self.chain_id)
+            }
+            EvmAddressFormat::Tron => {
+                let mut data = Vec::with_capacity(21);
+                data.push(0x41);
+                data.extend_from_slice(&address);
+                utils::base58check_encode(&data)
+            }
+        }
+    }
+
+    /// Decode an address in the chain's format into its 20 account bytes,
+    /// checking the checksum
+    pub fn decode_address(&self, address: &str) -> Result<[u8; 20], Error> {
+        match self.address_format {
+            EvmAddressFormat::Eip55 => ethereum::parse_address(address),
+            EvmAddressFormat::Eip1191 => ethereum::parse_hex_address(address, |bytes| {
+                ethereum::to_checksum_address_eip1191(bytes, self.chain_id)
+            }),
+            EvmAddressFormat::Tron => {
+                let data = utils::base58check_decode(address)?;
+                match data.split_first() {
+                    Some((0x41, bytes)) if bytes.len() == 20 => {
+                        Ok(bytes.try_into().expect("length checked"))
+                    }
+                    _ => Err(Error::InvalidAddress(
+                        "Not a Tron account address".to_string(),
+                    )),
+                }
+            }
+        }
+    }
+}
+
+impl Wallet {
+    /// Get the address at an index of the first account on an EVM-compatible chain
+    pub fn evm_address(&self, chain: &EvmChain, index: u32) -> Result<String, Error> {
+        let key = self.master().derive_path(&chain.path(0, index))?;
+        Ok(chain.encode_address(&key.to_extended_public_key().public_key))
+    }
+}
@@ -0,0 +1,281 @@
+use crate::address::AddressType;
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, Network};
+#[cfg(feature = "serde")]
+use crate::bip44::Change;
+#[cfg(feature = "serde")]
+use crate::descriptor::Descriptor;
+use crate::error::Error;
+use crate::signer::{self, SigningOptions};
+#[cfg(feature = "serde")]
+use crate::slip132;
+use crate::utils;
+use crate::wallet::{AccountConfig, ChangeSelection, Wallet};
+use secp256k1::ecdsa::Signature;
+use secp256k1::PublicKey;
+use std::collections::BTreeMap;
+
+/// Current export bundle format version. Version 2 added account presets.
+pub const EXPORT_VERSION: u32 = 2;
+
+/// Hardened purpose of the path the export signing key is derived at
+const EXPORT_KEY_PURPOSE: u32 = 0x4558;
+/// BIP-340 style tag for the export signature digest
+const EXPORT_TAG: &str = "bip32hdwallet/export";
+
+/// Path of the key that signs export bundles
+pub fn signing_key_path() -> DerivationPath {
+    DerivationPath {
+        path: vec![
+            ChildNumber::Hardened(EXPORT_KEY_PURPOSE),
+            ChildNumber::Hardened(0),
+        ],
+    }
+}
+
+/// Public data of one wallet account
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountExport {
+    pub index: u32,
+    pub path: String,
+    pub xpub: String,
+    pub receive_descriptor: String,
+    pub change_descriptor: String,
+    pub active: bool,
+    /// Scanning presets; version 1 bundles carry the defaults
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub config: AccountConfig,
+}
+
+/// Watch-only wallet data and free-form metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExportBundle {
+    pub version: u32,
+    pub network: Network,
+    pub address_type: AddressType,
+    pub master_fingerprint: String,
+    pub accounts: Vec<AccountExport>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ExportBundle {
+    /// Canonical byte encoding covered by the signature
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&self.version.to_be_bytes());
+        data.extend_from_slice(&self.network.xpub_version());
+        data.extend_from_slice(&self.address_type.purpose().0.to_be_bytes());
+        push_str(&mut data, &self.master_fingerprint);
+
+        utils::push_compact_size(&mut data, self.accounts.len());
+        for account in &self.accounts {
+            data.extend_from_slice(&account.index.to_be_bytes());
+            push_str(&mut data, &account.path);
+            push_str(&mut data, &account.xpub);
+            push_str(&mut data, &account.receive_descriptor);
+            push_str(&mut data, &account.change_descriptor);
+            data.push(account.active as u8);
+            if self.version >= 2 {
+                let config = &account.config;
+                data.extend_from_slice(&config.gap_limit.to_be_bytes());
+                data.extend_from_slice(&config.lookahead.to_be_bytes());
+                data.extend_from_slice(&config.start_index.to_be_bytes());
+                data.push((config.change_selection == ChangeSelection::Shuffled) as u8);
+            }
+        }
+
+        utils::push_compact_size(&mut data, self.metadata.len());
+        for (key, value) in &self.metadata {
+            push_str(&mut data, key);
+            push_str(&mut data, value);
+        }
+
+        data
+    }
+
+    /// Digest signed by the export key
+    pub fn signing_digest(&self) -> [u8; 32] {
+        utils::tagged_hash(EXPORT_TAG, &self.signing_bytes())
+    }
+}
+
+fn push_str(data: &mut Vec<u8>, s: &str) {
+    utils::push_compact_size(data, s.len());
+    data.extend_from_slice(s.as_bytes());
+}
+
+/// An export bundle signed by a key derived from the wallet's master key
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignedExport {
+    pub bundle: ExportBundle,
+    /// Compressed signing public key, hex encoded
+    pub public_key: String,
+    /// Compact ECDSA signature, hex encoded
+    pub signature: String,
+}
+
+impl SignedExport {
+    /// Sign a bundle with the export key of a master key
+    pub fn sign(bundle: ExportBundle, master: &ExtendedPrivKey) -> Result<Self, Error> {
+        let key = master.derive_path(&signing_key_path())?;
+        let signature = key.sign_digest(&bundle.signing_digest(), SigningOptions::bitcoin_core());
+
+        Ok(SignedExport {
+            bundle,
+            public_key: hex::encode(key.to_extended_public_key().public_key.serialize()),
+            signature: hex::encode(signature.serialize_compact()),
+        })
+    }
+
+    /// Check the bundle version and that the signature matches the embedded key
+    pub fn verify(&self) -> Result<(), Error> {
+        if self.bundle.version > EXPORT_VERSION {
+            return Err(Error::Unsupported(format!(
+                "Export version {} is newer than supported version {}",
+                self.bundle.version, EXPORT_VERSION
+            )));
+        }
+        for account in &self.bundle.accounts {
+            account.config.validate()?;
+        }
+
+        let public_key = hex::decode(&self.public_key)
+            .ok()
+            .and_then(|bytes| PublicKey::from_slice(&bytes).ok())
+            .ok_or_else(|| Error::InvalidKey("Invalid export signing key".to_string()))?;
+        let signature = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| Signature::from_compact(&bytes).ok())
+            .ok_or_else(|| Error::InvalidSignature("Malformed export signature".to_string()))?;
+
+        signer::verify_ecdsa(&public_key, &self.bundle.signing_digest(), &signature)
+            .map_err(|_| Error::InvalidSignature("Export signature does not match".to_string()))
+    }
+
+    /// Verify the bundle and that it was signed by the given master key,
+    /// e.g. after restoring the wallet from its mnemonic
+    pub fn verify_with_master(&self, master: &ExtendedPrivKey) -> Result<(), Error> {
+        self.verify()?;
+
+        let expected = master
+            .derive_path(&signing_key_path())?
+            .to_extended_public_key()
+            .public_key;
+        if hex::encode(expected.serialize()) != self.public_key {
+            return Err(Error::InvalidSignature(
+                "Export was not signed by this wallet".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Serialize to JSON
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::InvalidExport(e.to_string()))
+    }
+
+    /// Parse from JSON and verify the signature
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let export: SignedExport =
+            serde_json::from_str(json).map_err(|e| Error::InvalidExport(e.to_string()))?;
+        export.verify()?;
+        Ok(export)
+    }
+}
+
+impl Wallet {
+    /// Export the wallet's accounts and metadata as a signed bundle
+    pub fn export(&self, metadata: BTreeMap<String, String>) -> Result<SignedExport, Error> {
+        let accounts = self
+            .accounts()
+            .iter()
+            .map(|account| {
+                let [receive, change] = self.descriptors(account.index)?;
+                Ok(AccountExport {
+                    index: account.index,
+                    path: self.format_path(&self.account_path(account.index)),
+                    xpub: account.xpub.to_string(),
+                    receive_descriptor: receive.to_string(),
+                    change_descriptor: change.to_string(),
+                    active: account.is_active(),
+                    config: *account.config(),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let bundle = ExportBundle {
+            version: EXPORT_VERSION,
+            network: self.network(),
+            address_type: self.address_type(),
+            master_fingerprint: hex::encode(self.master().fingerprint()),
+            accounts,
+            metadata,
+        };
+
+        SignedExport::sign(bundle, self.master())
+    }
+}
+
+/// Generate the Coldcard/Passport "generic JSON" export of an account:
+/// BIP-44/49/84 xpubs with fingerprints, descriptors and first addresses
+#[cfg(feature = "serde")]
+pub fn coldcard_generic_json(master: &ExtendedPrivKey, account: u32) -> Result<String, Error> {
+    let mut export = serde_json::Map::new();
+    export.insert(
+        "chain".to_string(),
+        match master.network {
+            Network::Bitcoin => "BTC",
+            Network::Testnet => "XTN",
+        }
+        .into(),
+    );
+    export.insert(
+        "xfp".to_string(),
+        hex::encode_upper(master.fingerprint()).into(),
+    );
+    export.insert("account".to_string(), account.into());
+    export.insert(
+        "xpub".to_string(),
+        master.to_extended_public_key().to_string().into(),
+    );
+
+    for (section, name, address_type) in [
+        ("bip44", "p2pkh", AddressType::P2pkh),
+        ("bip49", "p2sh-p2wpkh", AddressType::P2shP2wpkh),
+        ("bip84", "p2wpkh", AddressType::P2wpkh),
+    ] {
+        let descriptor = Descriptor::for_account(master, address_type, account, Change::External)?;
+        let xpub = &descriptor.xpub;
+        let first = xpub.first_address(address_type)?;
+
+        let mut entry = serde_json::Map::new();
+        entry.insert("name".to_string(), name.into());
+        entry.insert(
+            "deriv".to_string(),
+            descriptor.origin.path.to_string().into(),
+        );
+        entry.insert("xpub".to_string(), xpub.to_string().into());
+        entry.insert(
+            "xfp".to_string(),
+            hex::encode_upper(xpub.fingerprint()).into(),
+        );
+        if address_type != AddressType::P2pkh {
+            entry.insert(
+                "_pub".to_string(),
+                slip132::encode_xpub(xpub, address_type).into(),
+            );
+        }
+        entry.insert("desc".to_string(), descriptor.to_string().into());
+        entry.insert("first".to_string(), first.into());
+
+        export.insert(section.to_string(), entry.into());
+    }
+
+    serde_json::to_string_pretty(&export).map_err(|e| Error::InvalidExport(e.to_string()))
+}
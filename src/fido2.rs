@@ -0,0 +1,106 @@
+use crate::error::Error;
+use crate::keystore::WrappedKeystore;
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+
+/// HKDF info string for wrapping keys derived from hmac-secret outputs
+const WRAP_INFO: &[u8] = b"bip32hdwallet fido2 wrapping key";
+/// Version byte of encoded FIDO2 keystores
+const FIDO2_VERSION: u8 = 1;
+
+/// A FIDO2 authenticator supporting the CTAP2 hmac-secret extension.
+/// Implement this over the platform's CTAP transport, such as libfido2 or
+/// the WebAuthn PRF extension; each call normally requires a user tap.
+pub trait HmacSecret {
+    /// Get the authenticator's hmac-secret output for a credential and salt
+    fn hmac_secret(&self, credential_id: &[u8], salt: &[u8; 32]) -> Result<[u8; 32], Error>;
+}
+
+/// A keystore whose content-encryption key is wrapped by a secret only a
+/// FIDO2 authenticator holding the credential can produce
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fido2Keystore {
+    pub credential_id: Vec<u8>,
+    salt: [u8; 32],
+    keystore: WrappedKeystore,
+}
+
+impl Fido2Keystore {
+    /// Encrypt a seed so that opening it requires the authenticator holding
+    /// `credential_id`
+    pub fn seal(
+        seed: &[u8],
+        authenticator: &dyn HmacSecret,
+        credential_id: &[u8],
+    ) -> Result<Self, Error> {
+        let mut salt = [0u8; 32];
+        OsRng.fill_bytes(&mut salt);
+        let wrapping_key = wrapping_key(authenticator, credential_id, &salt)?;
+        Ok(Fido2Keystore {
+            credential_id: credential_id.to_vec(),
+            salt,
+            keystore: WrappedKeystore::seal(seed, &wrapping_key)?,
+        })
+    }
+
+    /// Decrypt the seed with the authenticator
+    pub fn open(&self, authenticator: &dyn HmacSecret) -> Result<Vec<u8>, Error> {
+        self.keystore.open(&wrapping_key(
+            authenticator,
+            &self.credential_id,
+            &self.salt,
+        )?)
+    }
+
+    /// Encode for storage
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![FIDO2_VERSION];
+        data.extend_from_slice(&(self.credential_id.len() as u32).to_be_bytes());
+        data.extend_from_slice(&self.credential_id);
+        data.extend_from_slice(&self.salt);
+        data.extend_from_slice(&self.keystore.to_bytes());
+        data
+    }
+
+    /// Decode a keystore produced by [`Fido2Keystore::to_bytes`]
+    pub fn from_bytes(mut data: &[u8]) -> Result<Self, Error> {
+        let mut take = |len: usize| -> Result<&[u8], Error> {
+            if data.len() < len {
+                return Err(Error::Encryption("Truncated FIDO2 keystore".to_string()));
+            }
+            let (head, tail) = data.split_at(len);
+            data = tail;
+            Ok(head)
+        };
+
+        if take(1)?[0] != FIDO2_VERSION {
+            return Err(Error::Encryption(
+                "Unsupported FIDO2 keystore version".to_string(),
+            ));
+        }
+        let len = u32::from_be_bytes(take(4)?.try_into().expect("4 bytes")) as usize;
+        let credential_id = take(len)?.to_vec();
+        let salt = take(32)?.try_into().expect("salt length");
+
+        Ok(Fido2Keystore {
+            credential_id,
+            salt,
+            keystore: WrappedKeystore::from_bytes(data)?,
+        })
+    }
+}
+
+fn wrapping_key(
+    authenticator: &dyn HmacSecret,
+    credential_id: &[u8],
+    salt: &[u8; 32],
+) -> Result<[u8; 32], Error> {
+    let secret = authenticator.hmac_secret(credential_id, salt)?;
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(salt), &secret)
+        .expand(WRAP_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    Ok(key)
+}
@@ -0,0 +1,133 @@
+use crate::bip32::{DerivationPath, Network};
+use crate::bip44::{AccountLevel, AddressIndex, Bip44Path, Change, CoinType};
+use crate::error::Error;
+use crate::wallet::Wallet;
+use blake2::digest::consts::{U20, U4};
+use blake2::{Blake2b, Digest};
+use secp256k1::PublicKey;
+
+/// Address protocol of secp256k1 account addresses
+const PROTOCOL_SECP256K1: u8 = 1;
+/// Lowercase RFC 4648 base32 alphabet used by Filecoin addresses
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Get the path of an account address: `m/44'/461'/account'/0/index`
+pub fn path(account: u32, index: u32) -> DerivationPath {
+    Bip44Path::standard(
+        CoinType::FILECOIN,
+        AccountLevel::new(account),
+        Change::External,
+        AddressIndex::new(index),
+    )
+    .to_derivation_path()
+}
+
+fn network_prefix(network: Network) -> char {
+    match network {
+        Network::Bitcoin => 'f',
+        Network::Testnet => 't',
+    }
+}
+
+/// Checksum over the protocol byte and payload
+fn checksum(payload: &[u8; 20]) -> [u8; 4] {
+    let mut hasher = Blake2b::<U4>::new();
+    hasher.update([PROTOCOL_SECP256K1]);
+    hasher.update(payload);
+    hasher.finalize().into()
+}
+
+/// Payload of an f1 address: the 20-byte Blake2b hash of the uncompressed key
+pub fn payload(public_key: &PublicKey) -> [u8; 20] {
+    let mut hasher = Blake2b::<U20>::new();
+    hasher.update(public_key.serialize_uncompressed());
+    hasher.finalize().into()
+}
+
+/// Encode the secp256k1 (f1/t1) address of a public key
+pub fn encode_address(public_key: &PublicKey, network: Network) -> String {
+    let payload = payload(public_key);
+    let mut data = payload.to_vec();
+    data.extend_from_slice(&checksum(&payload));
+
+    let mut address = String::with_capacity(41);
+    address.push(network_prefix(network));
+    address.push(char::from(b'0' + PROTOCOL_SECP256K1));
+    address.push_str(&base32_encode(&data));
+    address
+}
+
+/// Decode an f1/t1 address into its network and payload, checking the checksum
+pub fn decode_address(address: &str) -> Result<(Network, [u8; 20]), Error> {
+    let network = match address.chars().next() {
+        Some('f') => Network::Bitcoin,
+        Some('t') => Network::Testnet,
+        _ => {
+            return Err(Error::InvalidAddress(
+                "Unknown Filecoin network prefix".to_string(),
+            ))
+        }
+    };
+    if address.get(1..2) != Some("1") {
+        return Err(Error::InvalidAddress(
+            "Only secp256k1 (protocol 1) addresses are supported".to_string(),
+        ));
+    }
+
+    let data = base32_decode(&address[2..])
+        .filter(|data| data.len() == 24)
+        .ok_or_else(|| Error::InvalidAddress("Invalid Filecoin address payload".to_string()))?;
+    let mut payload = [0u8; 20];
+    payload.copy_from_slice(&data[..20]);
+    if checksum(&payload) != data[20..] {
+        return Err(Error::InvalidChecksum);
+    }
+
+    Ok((network, payload))
+}
+
+pub(crate) fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let value = BASE32_ALPHABET.iter().position(|&a| a == c)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    // Leftover bits are padding and must be zero
+    (buffer & ((1 << bits) - 1) == 0).then_some(decoded)
+}
+
+impl Wallet {
+    /// Get the Filecoin f1/t1 address at an index of the first account
+    pub fn filecoin_address(&self, index: u32) -> Result<String, Error> {
+        let key = self.master().derive_path(&path(0, index))?;
+        Ok(encode_address(
+            &key.to_extended_public_key().public_key,
+            self.network(),
+        ))
+    }
+}
@@ -0,0 +1,132 @@
+use crate::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use crate::error::Error;
+use std::fmt::Write;
+
+/// A key of a hierarchy, holding public data only
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HierarchyNode {
+    /// Path of the node from the root
+    pub path: String,
+    pub depth: u8,
+    pub fingerprint: String,
+    pub xpub: String,
+    /// Child nodes, in the order they were first reached
+    pub children: Vec<HierarchyNode>,
+}
+
+impl HierarchyNode {
+    fn new(path: String, key: &ExtendedPubKey) -> Self {
+        HierarchyNode {
+            path,
+            depth: key.depth,
+            fingerprint: hex::encode(key.fingerprint()),
+            xpub: key.to_string(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Last element of the path, or `m` for the root
+    pub fn label(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+
+    /// Number of nodes in this subtree, including this one
+    pub fn node_count(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .map(HierarchyNode::node_count)
+            .sum::<usize>()
+    }
+}
+
+/// The tree of keys spanned by a set of paths under one root, for
+/// documenting and auditing multi-account setups
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyHierarchy {
+    pub root: HierarchyNode,
+}
+
+impl KeyHierarchy {
+    /// Build the hierarchy of paths under a private root, which may include
+    /// hardened steps
+    pub fn from_private(root: &ExtendedPrivKey, paths: &[DerivationPath]) -> Result<Self, Error> {
+        KeyHierarchy::build(&root.to_extended_public_key(), paths, |path| {
+            Ok(root.derive_path(path)?.to_extended_public_key())
+        })
+    }
+
+    /// Build the hierarchy of public derivation paths under an extended public key
+    pub fn from_public(root: &ExtendedPubKey, paths: &[DerivationPath]) -> Result<Self, Error> {
+        KeyHierarchy::build(root, paths, |path| root.derive_path(path))
+    }
+
+    fn build(
+        root: &ExtendedPubKey,
+        paths: &[DerivationPath],
+        derive: impl Fn(&DerivationPath) -> Result<ExtendedPubKey, Error>,
+    ) -> Result<Self, Error> {
+        let mut hierarchy = KeyHierarchy {
+            root: HierarchyNode::new("m".to_string(), root),
+        };
+
+        for path in paths {
+            let mut node = &mut hierarchy.root;
+            for i in 0..path.path.len() {
+                let prefix = DerivationPath {
+                    path: path.path[..=i].to_vec(),
+                };
+                let prefix_str = prefix.to_string();
+
+                let position = match node.children.iter().position(|c| c.path == prefix_str) {
+                    Some(position) => position,
+                    None => {
+                        let key = derive(&prefix)?;
+                        node.children.push(HierarchyNode::new(prefix_str, &key));
+                        node.children.len() - 1
+                    }
+                };
+                node = &mut node.children[position];
+            }
+        }
+
+        Ok(hierarchy)
+    }
+
+    /// Fingerprint of the root key
+    pub fn root_fingerprint(&self) -> &str {
+        &self.root.fingerprint
+    }
+
+    /// Render as a Graphviz DOT digraph, labelling each node with its path
+    /// element and fingerprint
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph hierarchy {\n    node [shape=box];\n");
+        write_dot(&self.root, &mut dot);
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Serialize to JSON
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::InvalidExport(e.to_string()))
+    }
+}
+
+fn write_dot(node: &HierarchyNode, dot: &mut String) {
+    let _ = writeln!(
+        dot,
+        "    \"{}\" [label=\"{}\\n{}\", tooltip=\"{}\"];",
+        node.path,
+        node.label(),
+        node.fingerprint,
+        node.xpub
+    );
+    for child in &node.children {
+        let _ = writeln!(dot, "    \"{}\" -> \"{}\";", node.path, child.path);
+        write_dot(child, dot);
+    }
+}
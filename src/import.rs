@@ -0,0 +1,231 @@
+use crate::address::AddressType;
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Network};
+use crate::bip44::{Change, Purpose};
+use crate::descriptor::{Descriptor, KeyOrigin};
+use crate::error::Error;
+use crate::slip132;
+use serde_json::Value;
+
+/// Wallet software an export file came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportSource {
+    Sparrow,
+    Coldcard,
+    Electrum,
+}
+
+/// A single-key account found in an export file
+#[derive(Debug, Clone)]
+pub struct ImportedAccount {
+    pub address_type: AddressType,
+    pub origin: KeyOrigin,
+    pub xpub: ExtendedPubKey,
+}
+
+impl ImportedAccount {
+    /// Get the receive and change descriptors of the account
+    pub fn descriptors(&self) -> [Descriptor; 2] {
+        [Change::External, Change::Internal].map(|change| Descriptor {
+            address_type: self.address_type,
+            origin: self.origin.clone(),
+            xpub: self.xpub.clone(),
+            change,
+        })
+    }
+}
+
+/// The watch-only contents of another wallet's export file
+#[derive(Debug, Clone)]
+pub struct ImportedWallet {
+    pub source: ExportSource,
+    pub label: Option<String>,
+    pub accounts: Vec<ImportedAccount>,
+}
+
+fn parse_json(json: &str) -> Result<Value, Error> {
+    serde_json::from_str(json).map_err(|e| Error::InvalidExport(e.to_string()))
+}
+
+fn field<'a>(value: &'a Value, name: &str) -> Result<&'a str, Error> {
+    value
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::InvalidExport(format!("Missing field '{}'", name)))
+}
+
+fn parse_fingerprint(hex_str: &str) -> Result<[u8; 4], Error> {
+    hex::decode(hex_str)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::InvalidExport(format!("Invalid fingerprint '{}'", hex_str)))
+}
+
+/// Build an account, taking the address type from the xpub prefix, then the
+/// explicit type, then the path's purpose
+fn account(
+    xpub: &str,
+    fingerprint: [u8; 4],
+    path: &str,
+    address_type: Option<AddressType>,
+) -> Result<ImportedAccount, Error> {
+    let (xpub, implied) = slip132::parse_xpub(xpub)?;
+    let path: DerivationPath = path.parse()?;
+
+    let address_type = implied
+        .or(address_type)
+        .or_else(|| match path.path.first() {
+            Some(ChildNumber::Hardened(purpose)) => AddressType::for_purpose(Purpose(*purpose)),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            Error::Unsupported("Cannot determine the account script type".to_string())
+        })?;
+
+    Ok(ImportedAccount {
+        address_type,
+        origin: KeyOrigin { fingerprint, path },
+        xpub,
+    })
+}
+
+/// Parse a Sparrow wallet JSON export (single-signature wallets only)
+pub fn parse_sparrow(json: &str) -> Result<ImportedWallet, Error> {
+    let value = parse_json(json)?;
+
+    if value.get("policyType").and_then(Value::as_str) != Some("SINGLE") {
+        return Err(Error::Unsupported(
+            "Only single-signature Sparrow wallets can be imported".to_string(),
+        ));
+    }
+    let address_type = match field(&value, "scriptType")? {
+        "P2PKH" => AddressType::P2pkh,
+        "P2SH_P2WPKH" => AddressType::P2shP2wpkh,
+        "P2WPKH" => AddressType::P2wpkh,
+        other => {
+            return Err(Error::Unsupported(format!(
+                "Unsupported Sparrow script type '{}'",
+                other
+            )))
+        }
+    };
+
+    let accounts = value
+        .get("keystores")
+        .and_then(Value::as_array)
+        .ok_or_else(|| Error::InvalidExport("Missing field 'keystores'".to_string()))?
+        .iter()
+        .map(|keystore| {
+            let derivation = keystore
+                .get("keyDerivation")
+                .ok_or_else(|| Error::InvalidExport("Missing field 'keyDerivation'".to_string()))?;
+            account(
+                field(keystore, "extendedPublicKey")?,
+                parse_fingerprint(field(derivation, "masterFingerprint")?)?,
+                field(derivation, "derivationPath")?,
+                Some(address_type),
+            )
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(ImportedWallet {
+        source: ExportSource::Sparrow,
+        label: value
+            .get("label")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        accounts,
+    })
+}
+
+/// Parse a Coldcard "generic JSON" export
+pub fn parse_coldcard(json: &str) -> Result<ImportedWallet, Error> {
+    let value = parse_json(json)?;
+    let fingerprint = parse_fingerprint(field(&value, "xfp")?)?;
+
+    let mut accounts = Vec::new();
+    for (section, address_type) in [
+        ("bip44", AddressType::P2pkh),
+        ("bip49", AddressType::P2shP2wpkh),
+        ("bip84", AddressType::P2wpkh),
+    ] {
+        if let Some(entry) = value.get(section) {
+            accounts.push(account(
+                field(entry, "xpub")?,
+                fingerprint,
+                field(entry, "deriv")?,
+                Some(address_type),
+            )?);
+        }
+    }
+
+    if accounts.is_empty() {
+        return Err(Error::InvalidExport(
+            "No single-signature accounts in Coldcard export".to_string(),
+        ));
+    }
+
+    Ok(ImportedWallet {
+        source: ExportSource::Coldcard,
+        label: None,
+        accounts,
+    })
+}
+
+/// Parse the watch-only part of an unencrypted Electrum wallet file.
+/// Older files lack a root fingerprint, in which case it is left as zero.
+pub fn parse_electrum(json: &str) -> Result<ImportedWallet, Error> {
+    let value = parse_json(json)?;
+
+    if value.get("wallet_type").and_then(Value::as_str) != Some("standard") {
+        return Err(Error::Unsupported(
+            "Only standard Electrum wallets can be imported".to_string(),
+        ));
+    }
+    let keystore = value
+        .get("keystore")
+        .ok_or_else(|| Error::InvalidExport("Missing field 'keystore'".to_string()))?;
+    if keystore.get("type").and_then(Value::as_str) != Some("bip32") {
+        return Err(Error::Unsupported(
+            "Only BIP-32 Electrum keystores can be imported".to_string(),
+        ));
+    }
+
+    let fingerprint = match keystore.get("root_fingerprint").and_then(Value::as_str) {
+        Some(fingerprint) => parse_fingerprint(fingerprint)?,
+        None => [0; 4],
+    };
+    let path = keystore
+        .get("derivation")
+        .and_then(Value::as_str)
+        .unwrap_or("m");
+
+    Ok(ImportedWallet {
+        source: ExportSource::Electrum,
+        label: None,
+        accounts: vec![account(field(keystore, "xpub")?, fingerprint, path, None)?],
+    })
+}
+
+/// Detect the format of an export file and parse it
+pub fn parse_export(json: &str) -> Result<ImportedWallet, Error> {
+    let value = parse_json(json)?;
+
+    if value.get("keystores").is_some() {
+        parse_sparrow(json)
+    } else if value.get("xfp").is_some() {
+        parse_coldcard(json)
+    } else if value.get("keystore").is_some() {
+        parse_electrum(json)
+    } else {
+        Err(Error::InvalidExport(
+            "Unrecognized wallet export format".to_string(),
+        ))
+    }
+}
+
+impl ImportedWallet {
+    /// Network of the imported accounts
+    pub fn network(&self) -> Option<Network> {
+        self.accounts.first().map(|account| account.xpub.network)
+    }
+}
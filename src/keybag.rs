@@ -0,0 +1,283 @@
+use crate::address::{self, AddressType};
+use crate::bip32::{DerivationPath, ExtendedPrivKey, Network};
+use crate::descriptor::KeyOrigin;
+use crate::error::Error;
+use crate::metrics::{self, SignatureKind};
+use crate::signer::SigningOptions;
+use crate::utils;
+use secp256k1::{ecdsa::Signature, Message, PublicKey, SecretKey};
+use std::collections::BTreeMap;
+
+/// Leading byte of WIF-encoded mainnet keys
+const WIF_MAINNET: u8 = 0x80;
+/// Leading byte of WIF-encoded testnet keys
+const WIF_TESTNET: u8 = 0xef;
+/// Trailing byte of WIF keys whose public key is compressed
+const WIF_COMPRESSED: u8 = 0x01;
+
+/// Encode a private key in Wallet Import Format
+pub fn encode_wif(secret_key: &SecretKey, network: Network, compressed: bool) -> String {
+    let mut data = Vec::with_capacity(34);
+    data.push(match network {
+        Network::Bitcoin => WIF_MAINNET,
+        Network::Testnet => WIF_TESTNET,
+    });
+    data.extend_from_slice(&secret_key.secret_bytes());
+    if compressed {
+        data.push(WIF_COMPRESSED);
+    }
+    utils::base58check_encode(&data)
+}
+
+/// Decode a WIF private key into the key, its network and whether its
+/// public key is compressed
+pub fn decode_wif(wif: &str) -> Result<(SecretKey, Network, bool), Error> {
+    let data = utils::base58check_decode(wif)?;
+    let network = match data.first() {
+        Some(&WIF_MAINNET) => Network::Bitcoin,
+        Some(&WIF_TESTNET) => Network::Testnet,
+        _ => return Err(Error::InvalidKey("Unknown WIF version".to_string())),
+    };
+    let compressed = match data.len() {
+        33 => false,
+        34 if data[33] == WIF_COMPRESSED => true,
+        _ => return Err(Error::InvalidKey("Invalid WIF length".to_string())),
+    };
+    let secret_key = SecretKey::from_slice(&data[1..33])?;
+    Ok((secret_key, network, compressed))
+}
+
+/// A private key held in a [`KeyBag`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BagKey {
+    pub secret_key: SecretKey,
+    pub public_key: PublicKey,
+    /// Whether scripts commit to the compressed public key. Uncompressed
+    /// keys only have P2PKH addresses.
+    pub compressed: bool,
+    /// Where an HD key was derived; `None` for loose imported keys
+    pub origin: Option<KeyOrigin>,
+}
+
+impl BagKey {
+    fn new(secret_key: SecretKey, compressed: bool, origin: Option<KeyOrigin>) -> Self {
+        BagKey {
+            public_key: PublicKey::from_secret_key(utils::secp(), &secret_key),
+            secret_key,
+            compressed,
+            origin,
+        }
+    }
+
+    /// Whether the key was imported rather than derived
+    pub fn is_loose(&self) -> bool {
+        self.origin.is_none()
+    }
+
+    /// HASH160 of the public key, in the serialization scripts use
+    pub fn pubkey_hash(&self) -> [u8; 20] {
+        if self.compressed {
+            utils::pubkey_hash(&self.public_key)
+        } else {
+            utils::hash160(&self.public_key.serialize_uncompressed())
+        }
+    }
+
+    /// Address types the key can pay to
+    pub fn address_types(&self) -> &'static [AddressType] {
+        if self.compressed {
+            &[
+                AddressType::P2pkh,
+                AddressType::P2shP2wpkh,
+                AddressType::P2wpkh,
+            ]
+        } else {
+            &[AddressType::P2pkh]
+        }
+    }
+
+    /// Build the scriptPubKey of an address type, if the key has one
+    pub fn script_pubkey(&self, address_type: AddressType) -> Option<Vec<u8>> {
+        self.address_types()
+            .contains(&address_type)
+            .then(|| address::hash_script_pubkey(&self.pubkey_hash(), address_type))
+    }
+
+    /// Encode the address of an address type, if the key has one
+    pub fn address(&self, address_type: AddressType, network: Network) -> Option<String> {
+        if !self.address_types().contains(&address_type) {
+            return None;
+        }
+        Some(match self.compressed {
+            true => address::encode(&self.public_key, address_type, network),
+            false => address::p2pkh_address(&self.pubkey_hash(), network),
+        })
+    }
+}
+
+/// Outcome of a bulk import
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Public key hashes of newly added keys
+    pub imported: Vec<[u8; 20]>,
+    /// Number of keys already in the bag
+    pub duplicates: usize,
+    /// Line numbers (from 1) that failed to import, with the reason
+    pub errors: Vec<(usize, Error)>,
+}
+
+/// Loose WIF or hex private keys and HD-derived keys of one network, indexed
+/// by public key hash for signing and ownership checks
+#[derive(Debug, Clone)]
+pub struct KeyBag {
+    network: Network,
+    keys: BTreeMap<[u8; 20], BagKey>,
+}
+
+impl KeyBag {
+    /// Create an empty bag for a network
+    pub fn new(network: Network) -> Self {
+        KeyBag {
+            network,
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Get the bag's network
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Import a WIF key, or 64 hex digits taken as a compressed key. Returns
+    /// the public key hash it is indexed under.
+    pub fn import(&mut self, key: &str) -> Result<[u8; 20], Error> {
+        let key = key.trim();
+        let (secret_key, compressed) = if key.len() == 64 {
+            let bytes = hex::decode(key).map_err(|e| Error::InvalidKey(e.to_string()))?;
+            (SecretKey::from_slice(&bytes)?, true)
+        } else {
+            let (secret_key, network, compressed) = decode_wif(key)?;
+            if network != self.network {
+                return Err(Error::NetworkMismatch(format!(
+                    "WIF key is for {:?}, the bag for {:?}",
+                    network, self.network
+                )));
+            }
+            (secret_key, compressed)
+        };
+        Ok(self.insert(BagKey::new(secret_key, compressed, None)))
+    }
+
+    /// Import one key per line, skipping blank lines and `#` comments.
+    /// Failures are reported per line and don't stop the import.
+    pub fn import_all<'a>(&mut self, lines: impl IntoIterator<Item = &'a str>) -> ImportReport {
+        let mut report = ImportReport::default();
+        for (number, line) in lines.into_iter().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let before = self.keys.len();
+            match self.import(line) {
+                Ok(hash) if self.keys.len() > before => report.imported.push(hash),
+                Ok(_) => report.duplicates += 1,
+                Err(e) => report.errors.push((number + 1, e)),
+            }
+        }
+        report
+    }
+
+    /// Add the key at a path of an HD master key, recording its origin
+    pub fn insert_hd(
+        &mut self,
+        master: &ExtendedPrivKey,
+        path: &DerivationPath,
+    ) -> Result<[u8; 20], Error> {
+        if master.network != self.network {
+            return Err(Error::NetworkMismatch(format!(
+                "Master key is for {:?}, the bag for {:?}",
+                master.network, self.network
+            )));
+        }
+        let origin = KeyOrigin {
+            fingerprint: master.fingerprint(),
+            path: path.clone(),
+        };
+        let secret_key = master.derive_path(path)?.private_key;
+        Ok(self.insert(BagKey::new(secret_key, true, Some(origin))))
+    }
+
+    /// Add a key unless already present. An HD origin is kept over none.
+    fn insert(&mut self, key: BagKey) -> [u8; 20] {
+        let hash = key.pubkey_hash();
+        let existing = self.keys.entry(hash).or_insert_with(|| key.clone());
+        if existing.origin.is_none() {
+            existing.origin = key.origin;
+        }
+        hash
+    }
+
+    /// Get the key with a public key hash
+    pub fn get(&self, pubkey_hash: &[u8; 20]) -> Option<&BagKey> {
+        self.keys.get(pubkey_hash)
+    }
+
+    /// Iterate over all keys, ordered by public key hash
+    pub fn iter(&self) -> impl Iterator<Item = &BagKey> {
+        self.keys.values()
+    }
+
+    /// Iterate over the loose imported keys
+    pub fn loose(&self) -> impl Iterator<Item = &BagKey> {
+        self.keys.values().filter(|key| key.is_loose())
+    }
+
+    /// Get the number of keys
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether the bag holds no keys
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Find the key a scriptPubKey pays to, and as which address type
+    pub fn find_script(&self, script_pubkey: &[u8]) -> Option<(&BagKey, AddressType)> {
+        self.keys.values().find_map(|key| {
+            key.address_types()
+                .iter()
+                .find(|&&t| key.script_pubkey(t).as_deref() == Some(script_pubkey))
+                .map(|&t| (key, t))
+        })
+    }
+
+    /// Find the key an address pays to
+    pub fn find_address(&self, address: &str) -> Option<(&BagKey, AddressType)> {
+        self.keys.values().find_map(|key| {
+            key.address_types()
+                .iter()
+                .find(|&&t| key.address(t, self.network).as_deref() == Some(address))
+                .map(|&t| (key, t))
+        })
+    }
+
+    /// Sign a 32-byte digest with the key of a public key hash
+    pub fn sign_ecdsa(
+        &self,
+        pubkey_hash: &[u8; 20],
+        digest: &[u8; 32],
+        options: SigningOptions,
+    ) -> Result<Signature, Error> {
+        let key = self.get(pubkey_hash).ok_or_else(|| {
+            Error::InvalidKey(format!("No key for hash {}", hex::encode(pubkey_hash)))
+        })?;
+        let message = Message::from_digest(*digest);
+        metrics::signature(SignatureKind::Ecdsa);
+        Ok(if options.grind_low_r {
+            utils::secp().sign_ecdsa_low_r(&message, &key.secret_key)
+        } else {
+            utils::secp().sign_ecdsa(&message, &key.secret_key)
+        })
+    }
+}
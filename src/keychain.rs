@@ -0,0 +1,127 @@
+use crate::error::Error;
+use crate::keystore::WrappedKeystore;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Storage for small secrets keyed by service and account name, such as the
+/// platform keychain
+pub trait SecretBackend {
+    /// Get a stored secret, if any
+    fn get(&self, service: &str, account: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Store a secret, replacing any previous one
+    fn set(&self, service: &str, account: &str, secret: &[u8]) -> Result<(), Error>;
+
+    /// Remove a stored secret, if any
+    fn delete(&self, service: &str, account: &str) -> Result<(), Error>;
+}
+
+/// Backend keeping secrets in memory, for tests and platforms without a keychain
+#[derive(Debug, Default)]
+pub struct MemorySecretBackend {
+    secrets: Mutex<HashMap<(String, String), Vec<u8>>>,
+}
+
+impl MemorySecretBackend {
+    pub fn new() -> Self {
+        MemorySecretBackend::default()
+    }
+}
+
+impl SecretBackend for MemorySecretBackend {
+    fn get(&self, service: &str, account: &str) -> Result<Option<Vec<u8>>, Error> {
+        let secrets = self.secrets.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(secrets
+            .get(&(service.to_string(), account.to_string()))
+            .cloned())
+    }
+
+    fn set(&self, service: &str, account: &str, secret: &[u8]) -> Result<(), Error> {
+        let mut secrets = self.secrets.lock().unwrap_or_else(|e| e.into_inner());
+        secrets.insert((service.to_string(), account.to_string()), secret.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<(), Error> {
+        let mut secrets = self.secrets.lock().unwrap_or_else(|e| e.into_inner());
+        secrets.remove(&(service.to_string(), account.to_string()));
+        Ok(())
+    }
+}
+
+/// The platform keychain: macOS Keychain, Windows Credential Manager
+/// (DPAPI-protected) or the Linux kernel keyring.
+///
+/// **The Linux kernel keyring does not survive a reboot.** A keystore
+/// sealed with it on Linux can't be opened after a restart, so keep a
+/// password-encrypted backup of the seed, or implement [`SecretBackend`]
+/// over the Secret Service for wrapping keys that persist.
+#[cfg(feature = "keychain")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsKeychain;
+
+#[cfg(feature = "keychain")]
+impl SecretBackend for OsKeychain {
+    fn get(&self, service: &str, account: &str) -> Result<Option<Vec<u8>>, Error> {
+        match keyring::Entry::new(service, account).and_then(|entry| entry.get_secret()) {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(Error::Storage(e.to_string())),
+        }
+    }
+
+    fn set(&self, service: &str, account: &str, secret: &[u8]) -> Result<(), Error> {
+        keyring::Entry::new(service, account)
+            .and_then(|entry| entry.set_secret(secret))
+            .map_err(|e| Error::Storage(e.to_string()))
+    }
+
+    fn delete(&self, service: &str, account: &str) -> Result<(), Error> {
+        match keyring::Entry::new(service, account).and_then(|entry| entry.delete_credential()) {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(Error::Storage(e.to_string())),
+        }
+    }
+}
+
+impl WrappedKeystore {
+    /// Encrypt a seed under a fresh random wrapping key kept in a secret
+    /// backend, so unlocking needs no password. Fails if the entry already
+    /// holds a key, which would lock out the keystore it was sealed for;
+    /// use an account name per keystore, or delete the old entry first.
+    pub fn seal_with_backend(
+        seed: &[u8],
+        backend: &dyn SecretBackend,
+        service: &str,
+        account: &str,
+    ) -> Result<Self, Error> {
+        if backend.get(service, account)?.is_some() {
+            return Err(Error::Storage(format!(
+                "A wrapping key is already stored for {}/{}",
+                service, account
+            )));
+        }
+        let mut wrapping_key = [0u8; 32];
+        OsRng.fill_bytes(&mut wrapping_key);
+        let keystore = WrappedKeystore::seal(seed, &wrapping_key)?;
+        backend.set(service, account, &wrapping_key)?;
+        Ok(keystore)
+    }
+
+    /// Decrypt the seed with the wrapping key kept in a secret backend
+    pub fn open_with_backend(
+        &self,
+        backend: &dyn SecretBackend,
+        service: &str,
+        account: &str,
+    ) -> Result<Vec<u8>, Error> {
+        let wrapping_key: [u8; 32] = backend
+            .get(service, account)?
+            .ok_or_else(|| Error::Storage("No wrapping key in the keychain".to_string()))?
+            .try_into()
+            .map_err(|_| Error::Storage("Invalid wrapping key in the keychain".to_string()))?;
+        self.open(&wrapping_key)
+    }
+}
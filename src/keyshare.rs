@@ -0,0 +1,232 @@
+use crate::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use crate::error::Error;
+use crate::signer::{Signer, SigningOptions};
+use crate::utils;
+use rand::rngs::OsRng;
+use secp256k1::{ecdsa::Signature, PublicKey, Scalar, SecretKey};
+use std::sync::Mutex;
+
+/// Length of an encoded share: index, count, extended public key and scalar
+const SHARE_LEN: usize = 2 + 78 + 32;
+
+/// One of `count` additive shares of an extended private key. The private
+/// scalars of all shares sum to the key's private key modulo the curve
+/// order; each share also carries the public extended key it belongs to.
+#[derive(Clone)]
+pub struct AdditiveShare {
+    index: u8,
+    count: u8,
+    xpub: ExtendedPubKey,
+    scalar: SecretKey,
+}
+
+impl std::fmt::Debug for AdditiveShare {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AdditiveShare")
+            .field("index", &self.index)
+            .field("count", &self.count)
+            .field("xpub", &self.xpub.to_string())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AdditiveShare {
+    /// Share number, from 1 to [`AdditiveShare::count`]
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Number of shares the key was split into
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+
+    /// Extended public key of the shared key
+    pub fn xpub(&self) -> &ExtendedPubKey {
+        &self.xpub
+    }
+
+    /// Public point of this share's scalar; the points of all shares sum to
+    /// the key's public key
+    pub fn public_share(&self) -> PublicKey {
+        PublicKey::from_secret_key(utils::secp(), &self.scalar)
+    }
+
+    /// Encode the share
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![self.index, self.count];
+        data.extend_from_slice(&self.xpub.encode());
+        data.extend_from_slice(&self.scalar.secret_bytes());
+        data
+    }
+
+    /// Decode a share produced by [`AdditiveShare::to_bytes`]
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != SHARE_LEN || data[1] < 2 || data[0] == 0 || data[0] > data[1] {
+            return Err(Error::InvalidKey("Invalid additive key share".to_string()));
+        }
+        Ok(AdditiveShare {
+            index: data[0],
+            count: data[1],
+            xpub: ExtendedPubKey::decode(&data[2..80])?,
+            scalar: SecretKey::from_slice(&data[80..])
+                .map_err(|e| Error::InvalidKey(e.to_string()))?,
+        })
+    }
+}
+
+/// Split an extended private key into `count` additive shares, all of which
+/// are needed to rebuild it
+pub fn split_additive(xprv: &ExtendedPrivKey, count: u8) -> Result<Vec<AdditiveShare>, Error> {
+    if count < 2 {
+        return Err(Error::InvalidConfig(
+            "Additive sharing needs at least two shares".to_string(),
+        ));
+    }
+
+    let xpub = xprv.to_extended_public_key();
+    loop {
+        let mut scalars: Vec<SecretKey> = (1..count).map(|_| SecretKey::new(&mut OsRng)).collect();
+        // The last share is the key minus the others; retry in the
+        // negligible case that it comes out as zero
+        let Ok(last) = scalars.iter().try_fold(xprv.private_key, |acc, s| {
+            acc.add_tweak(&Scalar::from(s.negate()))
+        }) else {
+            continue;
+        };
+        scalars.push(last);
+
+        return Ok(scalars
+            .into_iter()
+            .enumerate()
+            .map(|(i, scalar)| AdditiveShare {
+                index: i as u8 + 1,
+                count,
+                xpub: xpub.clone(),
+                scalar,
+            })
+            .collect());
+    }
+}
+
+/// Rebuild the extended private key from all of its shares
+pub fn combine_additive(shares: &[AdditiveShare]) -> Result<ExtendedPrivKey, Error> {
+    let first = shares
+        .first()
+        .ok_or_else(|| Error::InvalidKey("No key shares supplied".to_string()))?;
+    let mut seen = vec![false; first.count as usize];
+    for share in shares {
+        if share.count != first.count || share.xpub.encode() != first.xpub.encode() {
+            return Err(Error::InvalidKey(
+                "Shares belong to different keys".to_string(),
+            ));
+        }
+        let slot = (share.index as usize)
+            .checked_sub(1)
+            .and_then(|slot| seen.get_mut(slot))
+            .ok_or_else(|| Error::InvalidKey(format!("Invalid share number {}", share.index)))?;
+        *slot = true;
+    }
+    if shares.len() != first.count as usize || seen.contains(&false) {
+        return Err(Error::InvalidKey(format!(
+            "All {} distinct key shares are required",
+            first.count
+        )));
+    }
+
+    let mut private_key = first.scalar;
+    for share in &shares[1..] {
+        private_key = private_key
+            .add_tweak(&Scalar::from(share.scalar))
+            .map_err(|_| Error::InvalidKey("Shares sum to an invalid key".to_string()))?;
+    }
+    let xprv = ExtendedPrivKey {
+        depth: first.xpub.depth,
+        parent_fingerprint: first.xpub.parent_fingerprint,
+        child_number: first.xpub.child_number,
+        chain_code: first.xpub.chain_code,
+        private_key,
+        network: first.xpub.network,
+    };
+    if xprv.to_extended_public_key().public_key != first.xpub.public_key {
+        return Err(Error::InvalidKey(
+            "Shares do not combine to the expected key".to_string(),
+        ));
+    }
+    Ok(xprv)
+}
+
+/// Signer over an additively shared key. Shareholders supply their shares
+/// for each signature; the key is rebuilt only while signing and the shares
+/// are dropped afterwards. Public keys on unhardened paths need no shares.
+pub struct SharedKeySigner {
+    xpub: ExtendedPubKey,
+    shares: Mutex<Vec<AdditiveShare>>,
+}
+
+impl SharedKeySigner {
+    /// Create a signer for the shared key with the given extended public key
+    pub fn new(xpub: ExtendedPubKey) -> Self {
+        SharedKeySigner {
+            xpub,
+            shares: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Supply one share for the next signature
+    pub fn supply(&self, share: AdditiveShare) -> Result<(), Error> {
+        if share.xpub.encode() != self.xpub.encode() {
+            return Err(Error::InvalidKey(
+                "Share belongs to a different key".to_string(),
+            ));
+        }
+        let mut shares = self.shares.lock().unwrap_or_else(|e| e.into_inner());
+        shares.retain(|s| s.index != share.index);
+        shares.push(share);
+        Ok(())
+    }
+
+    /// Whether every share has been supplied
+    pub fn is_complete(&self) -> bool {
+        let shares = self.shares.lock().unwrap_or_else(|e| e.into_inner());
+        shares
+            .first()
+            .is_some_and(|share| shares.len() == share.count as usize)
+    }
+
+    /// Drop any supplied shares
+    pub fn clear(&self) {
+        self.shares
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Rebuild the key for one operation, consuming the shares
+    fn with_key<T>(
+        &self,
+        f: impl FnOnce(&ExtendedPrivKey) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let shares = std::mem::take(&mut *self.shares.lock().unwrap_or_else(|e| e.into_inner()));
+        f(&combine_additive(&shares)?)
+    }
+}
+
+impl Signer for SharedKeySigner {
+    fn public_key(&self, path: &DerivationPath) -> Result<PublicKey, Error> {
+        if path.path.iter().any(|child| child.is_hardened()) {
+            self.with_key(|key| key.public_key(path))
+        } else {
+            Ok(self.xpub.derive_path(path)?.public_key)
+        }
+    }
+
+    fn sign_ecdsa(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        options: SigningOptions,
+    ) -> Result<Signature, Error> {
+        self.with_key(|key| key.sign_ecdsa(path, digest, options))
+    }
+}
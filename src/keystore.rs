@@ -0,0 +1,384 @@
+use crate::error::Error;
+use crate::trace;
+use crate::wipe::wipe;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{AeadCore, ChaCha20Poly1305, KeyInit};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::time::{Duration, Instant};
+
+/// Version byte of encoded time-locked keystores
+const KEYSTORE_VERSION: u8 = 1;
+/// Argon2id salt length
+const SALT_LEN: usize = 16;
+/// ChaCha20-Poly1305 nonce length
+const NONCE_LEN: usize = 12;
+/// Most KDF layers a keystore may declare
+const MAX_LAYERS: usize = 1 << 16;
+
+/// Argon2id costs of one KDF layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfLayer {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub lanes: u32,
+}
+
+impl KdfLayer {
+    fn params(&self) -> Result<Params, Error> {
+        Params::new(self.memory_kib, self.iterations, self.lanes, None)
+            .map_err(|e| Error::Encryption(e.to_string()))
+    }
+
+    /// Stretch a key through this layer
+    fn stretch(&self, key: &[u8], salt: &[u8]) -> Result<[u8; 32], Error> {
+        let mut output = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params()?)
+            .hash_password_into(key, salt, &mut output)
+            .map_err(|e| Error::Encryption(e.to_string()))?;
+        Ok(output)
+    }
+
+    /// Time one run of this layer on this machine
+    pub fn measure(&self) -> Result<Duration, Error> {
+        let start = Instant::now();
+        self.stretch(b"calibration", &[0u8; SALT_LEN])?;
+        Ok(start.elapsed())
+    }
+}
+
+/// How slow a keystore is meant to be to open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeLock {
+    /// Layers run one after the other, so opening can't be parallelized
+    pub layers: Vec<KdfLayer>,
+    /// Unlock time the creator intended, in seconds on their hardware
+    pub estimated_unlock_secs: u64,
+    /// Free-form note for whoever opens the keystore
+    pub note: String,
+}
+
+impl TimeLock {
+    /// Repeat a layer enough times to take about `target` to open on this machine
+    pub fn calibrate(layer: KdfLayer, target: Duration) -> Result<Self, Error> {
+        let per_layer = layer.measure()?.max(Duration::from_micros(1));
+        let count = (target.as_secs_f64() / per_layer.as_secs_f64()).ceil() as usize;
+        let count = count.clamp(1, MAX_LAYERS);
+
+        Ok(TimeLock {
+            layers: vec![layer; count],
+            estimated_unlock_secs: (per_layer * count as u32).as_secs(),
+            note: String::new(),
+        })
+    }
+}
+
+/// A seed wrapped under a chain of Argon2id layers, for deliberately
+/// slow-to-open "deep cold" backups. Each layer stretches the previous
+/// layer's output with its own salt before the final key decrypts the seed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeLockedKeystore {
+    pub lock: TimeLock,
+    salts: Vec<[u8; SALT_LEN]>,
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+}
+
+impl TimeLockedKeystore {
+    /// Encrypt a seed under a passphrase and time lock
+    pub fn seal(seed: &[u8], passphrase: &str, lock: TimeLock) -> Result<Self, Error> {
+        trace::span!(DEBUG, "time_locked_seal", layers = lock.layers.len());
+        if lock.layers.is_empty() || lock.layers.len() > MAX_LAYERS {
+            return Err(Error::InvalidConfig(format!(
+                "A time lock needs 1 to {} layers",
+                MAX_LAYERS
+            )));
+        }
+
+        let salts = lock
+            .layers
+            .iter()
+            .map(|_| {
+                let mut salt = [0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                salt
+            })
+            .collect();
+        let mut keystore = TimeLockedKeystore {
+            lock,
+            salts,
+            nonce: [0u8; NONCE_LEN],
+            ciphertext: Vec::new(),
+        };
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        keystore.nonce.copy_from_slice(&nonce);
+
+        let mut key = keystore.unlock_key(passphrase, |_, _| {})?;
+        let ciphertext = ChaCha20Poly1305::new(&key.into()).encrypt(
+            &nonce,
+            Payload {
+                msg: seed,
+                aad: &keystore.header(),
+            },
+        );
+        wipe(&mut key);
+        keystore.ciphertext =
+            ciphertext.map_err(|_| Error::Encryption("Keystore encryption failed".to_string()))?;
+
+        Ok(keystore)
+    }
+
+    /// Decrypt the seed, running every layer
+    pub fn open(&self, passphrase: &str) -> Result<Vec<u8>, Error> {
+        self.open_with_progress(passphrase, |_, _| {})
+    }
+
+    /// Decrypt the seed, reporting `(completed, total)` layers after each one
+    pub fn open_with_progress(
+        &self,
+        passphrase: &str,
+        progress: impl FnMut(usize, usize),
+    ) -> Result<Vec<u8>, Error> {
+        trace::span!(DEBUG, "time_locked_open", layers = self.lock.layers.len());
+        let mut key = self.unlock_key(passphrase, progress)?;
+        let seed = ChaCha20Poly1305::new(&key.into()).decrypt(
+            (&self.nonce).into(),
+            Payload {
+                msg: &self.ciphertext,
+                aad: &self.header(),
+            },
+        );
+        wipe(&mut key);
+        seed.map_err(|_| {
+            trace::event!(WARN, "time-locked keystore failed to open");
+            Error::Encryption("Wrong passphrase or corrupted keystore".to_string())
+        })
+    }
+
+    fn unlock_key(
+        &self,
+        passphrase: &str,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<[u8; 32], Error> {
+        let total = self.lock.layers.len();
+        let mut key = self.lock.layers[0].stretch(passphrase.as_bytes(), &self.salts[0])?;
+        progress(1, total);
+        for (i, (layer, salt)) in self.lock.layers.iter().zip(&self.salts).enumerate().skip(1) {
+            let next = layer.stretch(&key, salt);
+            wipe(&mut key);
+            key = next?;
+            progress(i + 1, total);
+        }
+        Ok(key)
+    }
+
+    /// Authenticated header: version, layers with their salts and the metadata
+    fn header(&self) -> Vec<u8> {
+        let mut header = vec![KEYSTORE_VERSION];
+        header.extend_from_slice(&(self.lock.layers.len() as u32).to_be_bytes());
+        for (layer, salt) in self.lock.layers.iter().zip(&self.salts) {
+            for cost in [layer.memory_kib, layer.iterations, layer.lanes] {
+                header.extend_from_slice(&cost.to_be_bytes());
+            }
+            header.extend_from_slice(salt);
+        }
+        header.extend_from_slice(&self.lock.estimated_unlock_secs.to_be_bytes());
+        header.extend_from_slice(&(self.lock.note.len() as u32).to_be_bytes());
+        header.extend_from_slice(self.lock.note.as_bytes());
+        header.extend_from_slice(&self.nonce);
+        header
+    }
+
+    /// Encode for storage
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = self.header();
+        data.extend_from_slice(&self.ciphertext);
+        data
+    }
+
+    /// Decode a keystore produced by [`TimeLockedKeystore::to_bytes`]. The
+    /// metadata can be read without the passphrase.
+    pub fn from_bytes(mut data: &[u8]) -> Result<Self, Error> {
+        let mut take = |len: usize| -> Result<&[u8], Error> {
+            if data.len() < len {
+                return Err(Error::Encryption("Truncated keystore".to_string()));
+            }
+            let (head, tail) = data.split_at(len);
+            data = tail;
+            Ok(head)
+        };
+        let u32_at = |bytes: &[u8]| u32::from_be_bytes(bytes.try_into().expect("4 bytes"));
+
+        if take(1)?[0] != KEYSTORE_VERSION {
+            return Err(Error::Encryption(
+                "Unsupported keystore version".to_string(),
+            ));
+        }
+        let count = u32_at(take(4)?) as usize;
+        if count == 0 || count > MAX_LAYERS {
+            return Err(Error::Encryption(
+                "Invalid keystore layer count".to_string(),
+            ));
+        }
+
+        let mut layers = Vec::with_capacity(count);
+        let mut salts = Vec::with_capacity(count);
+        for _ in 0..count {
+            let layer = KdfLayer {
+                memory_kib: u32_at(take(4)?),
+                iterations: u32_at(take(4)?),
+                lanes: u32_at(take(4)?),
+            };
+            layer.params()?;
+            layers.push(layer);
+            salts.push(take(SALT_LEN)?.try_into().expect("salt length"));
+        }
+
+        let estimated_unlock_secs = u64::from_be_bytes(take(8)?.try_into().expect("8 bytes"));
+        let len = u32_at(take(4)?) as usize;
+        let note = String::from_utf8(take(len)?.to_vec())
+            .map_err(|_| Error::Encryption("Invalid UTF-8 in keystore note".to_string()))?;
+        let nonce = take(NONCE_LEN)?.try_into().expect("nonce length");
+
+        Ok(TimeLockedKeystore {
+            lock: TimeLock {
+                layers,
+                estimated_unlock_secs,
+                note,
+            },
+            salts,
+            nonce,
+            ciphertext: data.to_vec(),
+        })
+    }
+}
+
+/// Version byte of encoded wrapped keystores
+const WRAPPED_VERSION: u8 = 1;
+/// Length of a wrapped content-encryption key: the key plus its tag
+const WRAPPED_CEK_LEN: usize = 32 + 16;
+
+/// A seed encrypted under a random content-encryption key (CEK), with the
+/// CEK itself encrypted under a wrapping key held elsewhere, such as a
+/// hardware authenticator or the OS keychain. Changing the wrapping key
+/// only re-wraps the CEK.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrappedKeystore {
+    cek_nonce: [u8; NONCE_LEN],
+    wrapped_cek: [u8; WRAPPED_CEK_LEN],
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+}
+
+impl WrappedKeystore {
+    /// Encrypt a seed under a fresh CEK wrapped by `wrapping_key`
+    pub fn seal(seed: &[u8], wrapping_key: &[u8; 32]) -> Result<Self, Error> {
+        trace::span!(DEBUG, "wrapped_seal");
+        let mut cek = [0u8; 32];
+        OsRng.fill_bytes(&mut cek);
+        let mut keystore = WrappedKeystore {
+            cek_nonce: [0u8; NONCE_LEN],
+            wrapped_cek: [0u8; WRAPPED_CEK_LEN],
+            nonce: [0u8; NONCE_LEN],
+            ciphertext: Vec::new(),
+        };
+        if let Err(e) = keystore.wrap(&cek, wrapping_key) {
+            wipe(&mut cek);
+            return Err(e);
+        }
+
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        keystore.nonce.copy_from_slice(&nonce);
+        let ciphertext = ChaCha20Poly1305::new(&cek.into()).encrypt(
+            &nonce,
+            Payload {
+                msg: seed,
+                aad: &[WRAPPED_VERSION],
+            },
+        );
+        wipe(&mut cek);
+        keystore.ciphertext =
+            ciphertext.map_err(|_| Error::Encryption("Keystore encryption failed".to_string()))?;
+        Ok(keystore)
+    }
+
+    /// Decrypt the seed
+    pub fn open(&self, wrapping_key: &[u8; 32]) -> Result<Vec<u8>, Error> {
+        trace::span!(DEBUG, "wrapped_open");
+        let mut cek = self.unwrap_cek(wrapping_key)?;
+        let seed = ChaCha20Poly1305::new(&cek.into()).decrypt(
+            (&self.nonce).into(),
+            Payload {
+                msg: &self.ciphertext,
+                aad: &[WRAPPED_VERSION],
+            },
+        );
+        wipe(&mut cek);
+        seed.map_err(|_| Error::Encryption("Corrupted keystore".to_string()))
+    }
+
+    /// Wrap the CEK under a new wrapping key, leaving the seed ciphertext as is
+    pub fn rewrap(&mut self, old_key: &[u8; 32], new_key: &[u8; 32]) -> Result<(), Error> {
+        trace::span!(DEBUG, "wrapped_rewrap");
+        let mut cek = self.unwrap_cek(old_key)?;
+        let wrapped = self.wrap(&cek, new_key);
+        wipe(&mut cek);
+        wrapped
+    }
+
+    fn wrap(&mut self, cek: &[u8; 32], wrapping_key: &[u8; 32]) -> Result<(), Error> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let wrapped = ChaCha20Poly1305::new(wrapping_key.into())
+            .encrypt(&nonce, cek.as_slice())
+            .map_err(|_| Error::Encryption("Key wrapping failed".to_string()))?;
+        self.cek_nonce.copy_from_slice(&nonce);
+        self.wrapped_cek.copy_from_slice(&wrapped);
+        Ok(())
+    }
+
+    fn unwrap_cek(&self, wrapping_key: &[u8; 32]) -> Result<[u8; 32], Error> {
+        let mut decrypted = ChaCha20Poly1305::new(wrapping_key.into())
+            .decrypt((&self.cek_nonce).into(), self.wrapped_cek.as_slice())
+            .map_err(|_| {
+                trace::event!(WARN, "wrapped keystore rejected the wrapping key");
+                Error::Encryption("Wrong wrapping key".to_string())
+            })?;
+        let mut cek = [0u8; 32];
+        cek.copy_from_slice(&decrypted);
+        wipe(&mut decrypted);
+        Ok(cek)
+    }
+
+    /// Encode for storage
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![WRAPPED_VERSION];
+        data.extend_from_slice(&self.cek_nonce);
+        data.extend_from_slice(&self.wrapped_cek);
+        data.extend_from_slice(&self.nonce);
+        data.extend_from_slice(&self.ciphertext);
+        data
+    }
+
+    /// Decode a keystore produced by [`WrappedKeystore::to_bytes`]
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let header_len = 1 + NONCE_LEN + WRAPPED_CEK_LEN + NONCE_LEN;
+        if data.len() < header_len {
+            return Err(Error::Encryption("Truncated keystore".to_string()));
+        }
+        if data[0] != WRAPPED_VERSION {
+            return Err(Error::Encryption(
+                "Unsupported keystore version".to_string(),
+            ));
+        }
+        let (cek_nonce, rest) = data[1..].split_at(NONCE_LEN);
+        let (wrapped_cek, rest) = rest.split_at(WRAPPED_CEK_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        Ok(WrappedKeystore {
+            cek_nonce: cek_nonce.try_into().expect("nonce length"),
+            wrapped_cek: wrapped_cek.try_into().expect("wrapped key length"),
+            nonce: nonce.try_into().expect("nonce length"),
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+}
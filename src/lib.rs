@@ -2,20 +2,123 @@
 // This library implements the BIP-32, BIP-39, and BIP-44 specifications for
 // hierarchical deterministic wallets.
 
+pub mod address;
+pub mod antiexfil;
+pub mod audit;
+pub mod avalanche;
 pub mod bip32;
 pub mod bip39;
 pub mod bip44;
+pub mod blockfilter;
+pub mod bloom;
+pub mod cache;
+pub mod clock;
+pub mod codex32;
+pub mod complete;
+pub mod config;
+pub mod contract;
+pub mod cosigner;
+pub mod cosmos;
+pub mod decoy;
+pub mod descriptor;
+pub mod detect;
+pub mod diagnose;
+pub mod dualcontrol;
+pub mod ecies;
+pub mod eip712;
+pub mod electrum;
 pub mod error;
+pub mod ethereum;
+pub mod evm;
+pub mod export;
+#[cfg(feature = "fido2")]
+pub mod fido2;
+pub mod filecoin;
+pub mod hierarchy;
+#[cfg(feature = "serde")]
+pub mod import;
+pub mod keybag;
+pub mod keychain;
+pub mod keyshare;
+pub mod keystore;
+pub mod lightning;
+pub mod limits;
+pub mod lint;
+pub mod message;
+pub mod metrics;
+#[cfg(feature = "miniscript")]
+pub mod miniscript;
+pub mod nodekey;
+pub mod nostr;
+pub mod ownership;
+pub mod passphrase;
+pub mod payment;
+pub mod printkey;
+pub mod proof;
+pub mod psbt;
+pub mod ratelimit;
+pub mod rbf;
+pub mod readonly;
+pub mod reencode;
+#[cfg(feature = "remote-signer")]
+pub mod remote;
+pub mod repl;
+pub mod reserves;
+pub mod scheme;
+pub mod seedxor;
+pub mod signer;
+pub mod silentpayments;
+pub mod slip10;
+pub mod slip132;
+pub mod slip21;
+pub mod socialbackup;
+pub mod solana;
+pub mod statement;
+pub mod store;
+pub mod stretch;
+pub mod substrate;
+pub mod sweep;
+pub mod taproot;
+pub mod totp;
+pub mod transcript;
 pub mod utils;
+pub mod uuid;
+pub mod vectors;
+pub mod wallet;
+pub mod watchonly;
+pub mod wordlist;
 
+mod trace;
+mod wipe;
+
+pub use address::AddressType;
 pub use bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
 pub use bip39::{Language, Mnemonic, MnemonicType, Seed};
 pub use bip44::{AccountLevel, AddressIndex, CoinType, Purpose};
-pub use error::Error;
+pub use error::{Error, ErrorEnvelope, ErrorKind, LimitError, MnemonicError};
+pub use signer::{Signer, SigningOptions};
+pub use wallet::Wallet;
 
 // Re-export types from dependencies that are part of our public API
 pub use secp256k1::{self, PublicKey, Secp256k1, SecretKey};
 
+// Compile-time guarantee that keys and wallets can be shared across threads
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ExtendedPrivKey>();
+    assert_send_sync::<ExtendedPubKey>();
+    assert_send_sync::<DerivationPath>();
+    assert_send_sync::<Mnemonic>();
+    assert_send_sync::<Seed>();
+    assert_send_sync::<Wallet>();
+    assert_send_sync::<wallet::Account>();
+    assert_send_sync::<cache::DerivationCache>();
+    assert_send_sync::<descriptor::Descriptor>();
+    assert_send_sync::<stretch::SeedStretcher>();
+    assert_send_sync::<slip10::Ed25519ExtendedKey>();
+    assert_send_sync::<Error>();
+};
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,6 +207,27 @@ mod tests {
         assert_eq!(path.to_string(), path_str);
     }
 
+    #[test]
+    fn test_derivation_path_parse_modes() {
+        use bip32::PathParseMode;
+
+        let expected = DerivationPath::from_str("m/84'/0'/0'").unwrap();
+        assert_eq!(DerivationPath::from_str("M/84h/0h/0h/").unwrap(), expected);
+        assert_eq!(DerivationPath::from_str("m/").unwrap().path.len(), 0);
+
+        assert!(DerivationPath::parse("M/84'/0'/0'", PathParseMode::Strict).is_err());
+        assert!(DerivationPath::parse("m/84'/0'/0'/", PathParseMode::Strict).is_err());
+        assert_eq!(
+            DerivationPath::parse("m/84'/0'/0'", PathParseMode::Strict).unwrap(),
+            expected
+        );
+
+        let err = DerivationPath::from_str("m/84'//0'").unwrap_err();
+        assert!(err.to_string().contains("index 1"));
+        let err = DerivationPath::from_str("m/84'/x").unwrap_err();
+        assert!(err.to_string().contains("index 1"));
+    }
+
     #[test]
     fn test_bip44_path() {
         let path_str = "m/44'/0'/0'/0/0";
@@ -118,6 +242,40 @@ mod tests {
         assert_eq!(bip44_path.to_string(), path_str);
     }
 
+    #[test]
+    fn test_coin_info() {
+        let info = CoinType::BITCOIN.info().unwrap();
+        assert_eq!(info.symbol, "BTC");
+        assert_eq!(info.decimals, 8);
+        assert_eq!(info.bech32_hrp, Some("bc"));
+
+        let eth = CoinType::from_symbol("eth").unwrap();
+        assert_eq!(eth, CoinType::ETHEREUM);
+        assert_eq!(
+            eth.info().unwrap().address_encoding,
+            bip44::AddressEncoding::Eip55
+        );
+
+        assert!(CoinType::new(123456).info().is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_bip44_serde() {
+        let path = Bip44Path::from_str("m/44'/0'/0'/1/7").unwrap();
+        let json = serde_json::to_string(&path).unwrap();
+        assert_eq!(json, "\"m/44'/0'/0'/1/7\"");
+        assert_eq!(serde_json::from_str::<Bip44Path>(&json).unwrap(), path);
+
+        assert_eq!(serde_json::to_string(&Purpose::BIP44).unwrap(), "\"44'\"");
+        assert_eq!(
+            serde_json::from_str::<Change>("\"1\"").unwrap(),
+            Change::Internal
+        );
+        assert!(serde_json::from_str::<AccountLevel>("\"0\"").is_err());
+        assert!(serde_json::from_str::<AddressIndex>("\"5'\"").is_err());
+    }
+
     #[test]
     fn test_key_serialization() {
         let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
@@ -140,4 +298,4643 @@ mod tests {
         assert_eq!(parsed_pub.child_number, master_key.child_number);
         assert_eq!(parsed_pub.chain_code, master_key.chain_code);
     }
+
+    #[test]
+    fn test_with_network() {
+        let seed = [7u8; 32];
+        let master_key = ExtendedPrivKey::new_master(&seed, Network::Testnet).unwrap();
+        assert!(master_key.to_string().starts_with("tprv"));
+
+        assert!(master_key.with_network(Network::Bitcoin, false).is_err());
+        assert!(master_key.with_network(Network::Testnet, false).is_ok());
+
+        let mainnet = master_key.with_network(Network::Bitcoin, true).unwrap();
+        assert!(mainnet.to_string().starts_with("xprv"));
+        assert_eq!(mainnet.private_key, master_key.private_key);
+
+        let xpub = mainnet
+            .to_extended_public_key()
+            .with_network(Network::Testnet, true)
+            .unwrap();
+        assert!(xpub.to_string().starts_with("tpub"));
+    }
+
+    #[test]
+    fn test_low_r_signing() {
+        let master_key = ExtendedPrivKey::new_master(&[1u8; 32], Network::Bitcoin).unwrap();
+        let path = DerivationPath::from_str("m/84'/0'/0'/0/0").unwrap();
+        let public_key = master_key.public_key(&path).unwrap();
+
+        for i in 0..32u8 {
+            let digest = utils::sha256(&[i]);
+            let signature = master_key
+                .sign_ecdsa(&path, &digest, SigningOptions::bitcoin_core())
+                .unwrap();
+            assert!(signature.serialize_compact()[0] < 0x80);
+            assert!(signature.serialize_der().len() <= 70);
+            signer::verify_ecdsa(&public_key, &digest, &signature).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_pay_to_contract() {
+        let master_key = ExtendedPrivKey::new_master(&[2u8; 32], Network::Bitcoin).unwrap();
+        let xpub = master_key.to_extended_public_key();
+        let commitment = utils::sha256(b"contract terms");
+
+        let tweaked_secret = master_key.pay_to_contract(&commitment).unwrap();
+        let tweaked_public = xpub.pay_to_contract(&commitment).unwrap();
+        assert_eq!(
+            PublicKey::from_secret_key(&Secp256k1::new(), &tweaked_secret),
+            tweaked_public
+        );
+
+        assert!(xpub.verify_commitment(&commitment, &tweaked_public));
+        assert!(!xpub.verify_commitment(b"other terms", &tweaked_public));
+        assert!(!xpub.verify_commitment(&commitment, &xpub.public_key));
+    }
+
+    #[test]
+    fn test_addresses() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+        let master_key =
+            ExtendedPrivKey::new_master(mnemonic.to_seed("").as_bytes(), Network::Bitcoin).unwrap();
+
+        let address_at = |path: &str, address_type| {
+            master_key
+                .derive_path(&DerivationPath::from_str(path).unwrap())
+                .unwrap()
+                .to_extended_public_key()
+                .address(address_type)
+        };
+
+        assert_eq!(
+            address_at("m/44'/0'/0'/0/0", AddressType::P2pkh),
+            "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"
+        );
+        assert_eq!(
+            address_at("m/49'/0'/0'/0/0", AddressType::P2shP2wpkh),
+            "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf"
+        );
+        assert_eq!(
+            address_at("m/84'/0'/0'/0/0", AddressType::P2wpkh),
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+    }
+
+    #[test]
+    fn test_address_attestation() {
+        let master_key = ExtendedPrivKey::new_master(&[3u8; 32], Network::Bitcoin).unwrap();
+        let paths = vec![
+            DerivationPath::from_str("m/84'/0'/0'/0/0").unwrap(),
+            DerivationPath::from_str("m/84'/0'/0'/0/1").unwrap(),
+        ];
+        let challenge = "proof of reserves 2024-01-01";
+
+        let mut attestations =
+            reserves::attest_addresses(&master_key, &paths, AddressType::P2wpkh, challenge)
+                .unwrap();
+        assert_eq!(attestations.len(), 2);
+        reserves::verify_attestations(&attestations, challenge, Network::Bitcoin).unwrap();
+        assert!(
+            reserves::verify_attestations(&attestations, "other challenge", Network::Bitcoin)
+                .is_err()
+        );
+
+        attestations[0].address = attestations[1].address.clone();
+        assert!(reserves::verify_attestations(&attestations, challenge, Network::Bitcoin).is_err());
+    }
+
+    #[test]
+    fn test_taproot_outputs() {
+        use secp256k1::XOnlyPublicKey;
+        use taproot::{TapLeaf, TaprootOutput, TAPSCRIPT_LEAF_VERSION};
+
+        // BIP-86 first receive address
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+        let master_key =
+            ExtendedPrivKey::new_master(mnemonic.to_seed("").as_bytes(), Network::Bitcoin).unwrap();
+        let key = master_key
+            .derive_path(&DerivationPath::from_str("m/86'/0'/0'/0/0").unwrap())
+            .unwrap();
+        let output = key.to_extended_public_key().taproot_output(None).unwrap();
+        assert_eq!(
+            output.address(Network::Bitcoin),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+        assert_eq!(
+            key.taproot_keypair(None).unwrap().x_only_public_key().0,
+            output.output_key
+        );
+
+        // BIP-341 wallet test vector with a single script leaf
+        let internal_key = XOnlyPublicKey::from_slice(
+            &hex::decode("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27")
+                .unwrap(),
+        )
+        .unwrap();
+        let leaf = TapLeaf::new(
+            hex::decode("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")
+                .unwrap(),
+        );
+        let tree = taproot::build_tree(&[leaf]).unwrap();
+        assert_eq!(
+            hex::encode(tree.merkle_root),
+            "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"
+        );
+
+        let output = TaprootOutput::new(internal_key, Some(tree.merkle_root)).unwrap();
+        assert_eq!(
+            hex::encode(output.output_key.serialize()),
+            "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"
+        );
+        assert_eq!(
+            hex::encode(output.control_block(TAPSCRIPT_LEAF_VERSION, &tree.paths[0])),
+            "c1187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"
+        );
+    }
+
+    #[test]
+    fn test_anti_exfil_signing() {
+        let master_key = ExtendedPrivKey::new_master(&[4u8; 32], Network::Bitcoin).unwrap();
+        let path = DerivationPath::from_str("m/84'/0'/0'/0/3").unwrap();
+        let public_key = master_key.public_key(&path).unwrap();
+
+        for i in 0..8u8 {
+            let digest = utils::sha256(&[i]);
+            let host_nonce = utils::sha256(&[i, 0xaa]);
+
+            let commitment = master_key
+                .anti_exfil_commit(&path, &digest, &antiexfil::host_commitment(&host_nonce))
+                .unwrap();
+            let signature = master_key
+                .anti_exfil_sign(&path, &digest, &host_nonce)
+                .unwrap();
+            antiexfil::verify(&public_key, &digest, &commitment, &host_nonce, &signature).unwrap();
+
+            // A signature made without the host's contribution is rejected
+            let plain = master_key
+                .sign_ecdsa(&path, &digest, SigningOptions::default())
+                .unwrap();
+            assert!(
+                antiexfil::verify(&public_key, &digest, &commitment, &host_nonce, &plain).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_silent_payments() {
+        use silentpayments::{Outpoint, SenderInput, SilentPaymentAddress, SilentPaymentKeys};
+
+        let receiver_master = ExtendedPrivKey::new_master(&[5u8; 32], Network::Bitcoin).unwrap();
+        let receiver = SilentPaymentKeys::from_master(&receiver_master, 0).unwrap();
+
+        let address = receiver.address();
+        let encoded = address.to_string();
+        assert!(encoded.starts_with("sp1q"));
+        assert_eq!(SilentPaymentAddress::from_str(&encoded).unwrap(), address);
+        let labeled = receiver.labeled_address(1).unwrap();
+
+        let secp = Secp256k1::new();
+        let input_keys = [
+            SecretKey::from_slice(&[6u8; 32]).unwrap(),
+            SecretKey::from_slice(&[7u8; 32]).unwrap(),
+        ];
+        let inputs = [
+            SenderInput {
+                private_key: input_keys[0],
+                taproot: false,
+            },
+            SenderInput {
+                private_key: input_keys[1],
+                taproot: true,
+            },
+        ];
+        let outpoints = [
+            Outpoint {
+                txid: [0xbb; 32],
+                vout: 1,
+            },
+            Outpoint {
+                txid: [0xaa; 32],
+                vout: 3,
+            },
+        ];
+
+        let outputs =
+            silentpayments::sender_outputs(&inputs, &outpoints, &[address, labeled]).unwrap();
+        assert_eq!(outputs.len(), 2);
+        assert_ne!(outputs[0], outputs[1]);
+
+        // The receiver sees the taproot input key with even y
+        let input_public_keys = [
+            PublicKey::from_secret_key(&secp, &input_keys[0]),
+            PublicKey::from_x_only_public_key(
+                input_keys[1].x_only_public_key(&secp).0,
+                secp256k1::Parity::Even,
+            ),
+        ];
+        let found = receiver
+            .scan(&input_public_keys, &outpoints, &outputs, &[1])
+            .unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].label, None);
+        assert_eq!(found[1].label, Some(1));
+
+        for output in &found {
+            let spending_key = receiver.spending_key(output).unwrap();
+            assert_eq!(spending_key.x_only_public_key(&secp).0, output.output);
+        }
+
+        // Without the label the second output is not detected
+        let found = receiver
+            .scan(&input_public_keys, &outpoints, &outputs, &[])
+            .unwrap();
+        assert_eq!(found.len(), 1);
+
+        // BIP-352 receiving vector key material and address
+        let key = |s: &str| SecretKey::from_slice(&hex::decode(s).unwrap()).unwrap();
+        let vector = SilentPaymentKeys {
+            scan_key: key("0f694e068028a717f8af6b9411f9a133dd3565258714cc226594b34db90c1f2c"),
+            spend_key: key("9d6ad855ce3417ef84e836892e5a56392bfba05fa5d97ccea30e266f540e08b3"),
+            network: Network::Bitcoin,
+        };
+        let encoded = "sp1qqgste7k9hx0qftg6qmwlkqtwuy6cycyavzmzj85c6qdfhjdpdjtdgqjuexzk6murw56suy3e0rd2cgqvycxttddwsvgxe2usfpxumr70xc9pkqwv";
+        assert_eq!(vector.address().to_string(), encoded);
+        assert_eq!(
+            SilentPaymentAddress::from_str(encoded).unwrap(),
+            vector.address()
+        );
+    }
+
+    #[test]
+    fn test_nostr_keys() {
+        // NIP-06 test vector
+        let phrase =
+            "leader monkey parrot ring guide accident before fence cannon height naive bean";
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+        let master_key =
+            ExtendedPrivKey::new_master(mnemonic.to_seed("").as_bytes(), Network::Bitcoin).unwrap();
+
+        let keys = nostr::NostrKeys::from_master(&master_key, 0).unwrap();
+        assert_eq!(
+            hex::encode(keys.secret_key.secret_bytes()),
+            "7f7ff03d123792d6ac594bfa67bf6d0c0ab55b6b1fdb6249303fe861f1ccba9a"
+        );
+        assert_eq!(
+            hex::encode(keys.public_key.serialize()),
+            "17162c921dc4d2518f9a101db33695df1afb56ab82f5ff3e5da6eec3ca5cd917"
+        );
+
+        assert!(keys.npub().starts_with("npub1"));
+        assert_eq!(nostr::decode_npub(&keys.npub()).unwrap(), keys.public_key);
+        assert_eq!(nostr::decode_nsec(&keys.nsec()).unwrap(), keys.secret_key);
+        assert!(nostr::decode_npub(&keys.nsec()).is_err());
+    }
+
+    #[test]
+    fn test_lightning_keys() {
+        let keys = lightning::LightningKeys::new(&[8u8; 32]).unwrap();
+        let master_key = ExtendedPrivKey::new_master(&[8u8; 32], Network::Testnet).unwrap();
+
+        assert_eq!(
+            keys.node_secret().unwrap(),
+            master_key
+                .derive_child(ChildNumber::Hardened(0))
+                .unwrap()
+                .private_key
+        );
+        assert_ne!(keys.node_secret().unwrap(), keys.destination_key().unwrap());
+
+        let mut channel_id = [0u8; 32];
+        let first = keys.channel_keys(&channel_id).unwrap();
+        assert_eq!(first, keys.channel_keys(&channel_id).unwrap());
+        assert_ne!(first.funding_key, first.payment_key);
+
+        channel_id[7] = 1;
+        assert_ne!(first, keys.channel_keys(&channel_id).unwrap());
+
+        // The LDK node secret is m/0' of the seed: BIP-32 test vector 4
+        let seed: [u8; 32] =
+            hex::decode("3ddd5602285899a946114506157c7997e5444528f3003f6134712147db19b678")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let node = ExtendedPrivKey::from_string("xprv9vB7xEWwNp9kh1wQRfCCQMnZUEG21LpbR9NPCNN1dwhiZkjjeGRnaALmPXCX7SgjFTiCTT6bXes17boXtjq3xLpcDjzEuGLQBM5ohqkao9G").unwrap();
+        assert_eq!(
+            lightning::LightningKeys::new(&seed)
+                .unwrap()
+                .node_secret()
+                .unwrap(),
+            node.private_key
+        );
+
+        // Core Lightning derives everything from hsm_secret with HKDF
+        let cln = lightning::ClnKeys::new(&seed);
+        assert_ne!(cln.node_secret(), node.private_key);
+        assert_eq!(
+            cln.node_id(),
+            PublicKey::from_secret_key(&Secp256k1::new(), &cln.node_secret())
+        );
+        let base = cln.wallet_base_key(Network::Bitcoin).unwrap();
+        assert_eq!(base.depth, 2);
+        assert_eq!(
+            base.parent_fingerprint,
+            cln.bip32_master(Network::Bitcoin)
+                .derive_child(ChildNumber::Normal(0))
+                .unwrap()
+                .fingerprint()
+        );
+        let peer = keys.node_id().unwrap();
+        let channel = cln.channel_keys(&peer, 1).unwrap();
+        assert_eq!(channel, cln.channel_keys(&peer, 1).unwrap());
+        assert_ne!(channel, cln.channel_keys(&peer, 2).unwrap());
+        assert_ne!(channel.funding_key, channel.htlc_base_key);
+
+        // Debug output never shows the seeds
+        assert!(!format!("{:?}", keys).contains(&format!("{:?}", [8u8; 32])));
+        assert!(!format!("{:?}", first).contains(&format!("{:?}", first.commitment_seed)));
+        assert!(!format!("{:?}", cln).contains(&format!("{:?}", seed)));
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        use bloom::{BloomFilter, BLOOM_UPDATE_ALL};
+
+        // Vector from Bitcoin Core's bloom tests
+        let mut filter = BloomFilter::new(3, 0.01, 0, BLOOM_UPDATE_ALL);
+        filter.insert(&hex::decode("99108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap());
+        assert!(filter.contains(&hex::decode("99108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()));
+        assert!(!filter.contains(&hex::decode("19108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()));
+        filter.insert(&hex::decode("b5a2c786d9ef4658287ced5914b37a1b4aa32eee").unwrap());
+        filter.insert(&hex::decode("b9300670b4c5366e95b2699e8b18bc75e5f729c5").unwrap());
+        assert_eq!(
+            hex::encode(filter.serialize()),
+            "03614e9b050000000000000001"
+        );
+
+        let account = ExtendedPrivKey::new_master(&[9u8; 32], Network::Bitcoin)
+            .unwrap()
+            .derive_path(&DerivationPath::from_str("m/84'/0'/0'").unwrap())
+            .unwrap()
+            .to_extended_public_key();
+        let filter =
+            bloom::prefetch_filter(&account, AddressType::P2wpkh, 0, 20, 0.0001, 42).unwrap();
+        let scripts = account
+            .script_pubkeys(Change::Internal, AddressType::P2wpkh, 0, 20)
+            .unwrap();
+        assert!(scripts.iter().all(|script| filter.contains(&script[2..])));
+    }
+
+    #[test]
+    fn test_descriptors() {
+        use descriptor::{Descriptor, Timestamp};
+
+        assert_eq!(descriptor::checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        assert!(descriptor::verify_checksum("raw(deadbeef)#89f8spxn").is_err());
+
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedPrivKey::new_master(&seed, Network::Bitcoin).unwrap();
+        assert_eq!(hex::encode(master.fingerprint()), "3442193e");
+
+        let descriptor =
+            Descriptor::for_account(&master, AddressType::P2wpkh, 0, Change::Internal).unwrap();
+        let desc = descriptor.to_string();
+        assert!(desc.starts_with("wpkh([3442193e/84'/0'/0']xpub"));
+        assert!(desc.contains("/1/*)#"));
+
+        let parsed = Descriptor::from_str(&desc).unwrap();
+        assert_eq!(parsed.to_string(), desc);
+        assert!(parsed.is_internal());
+
+        let requests = descriptor::import_requests(
+            &master,
+            AddressType::P2shP2wpkh,
+            &[0, 1],
+            999,
+            Timestamp::Now,
+        )
+        .unwrap();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[0].desc.starts_with("sh(wpkh([3442193e/49'/0'/0']"));
+        assert!(!requests[0].internal && requests[1].internal);
+        assert_eq!(
+            requests[3].descriptor().unwrap().to_string(),
+            requests[3].desc
+        );
+
+        #[cfg(feature = "serde")]
+        {
+            let json = descriptor::import_descriptors_json(&requests).unwrap();
+            assert!(json.contains(r#""range":[0,999],"timestamp":"now","internal":false}"#));
+            let parsed: Vec<descriptor::ImportRequest> =
+                descriptor::parse_import_descriptors_json(&json).unwrap();
+            assert_eq!(parsed, requests);
+        }
+    }
+
+    #[test]
+    fn test_cosigner_validation() {
+        use cosigner::PathTemplate;
+        use descriptor::KeyOrigin;
+
+        let master = ExtendedPrivKey::new_master(&[3u8; 32], Network::Bitcoin).unwrap();
+        let template = PathTemplate::from_str("m/48'/0'/*'/2'").unwrap();
+        assert_eq!(template.to_string(), "m/48'/0'/*'/2'");
+
+        let path = DerivationPath::from_str("m/48'/0'/5'/2'").unwrap();
+        let xpub = master.derive_path(&path).unwrap().to_extended_public_key();
+        let origin = KeyOrigin {
+            fingerprint: master.fingerprint(),
+            path,
+        };
+        cosigner::validate_cosigner(&xpub, &origin, &template).unwrap();
+
+        // Origin claims a different script type than the key was derived at
+        let wrong_script = KeyOrigin::from_str(&format!(
+            "{}/48'/0'/5'/1'",
+            hex::encode(master.fingerprint())
+        ))
+        .unwrap();
+        assert!(cosigner::validate_origin(&xpub, &wrong_script).is_err());
+
+        // Origin path too short for the key depth, and outside the template
+        let short = KeyOrigin::from_str("00000000/48'/0'/5'").unwrap();
+        assert!(cosigner::validate_origin(&xpub, &short).is_err());
+        assert!(cosigner::validate_cosigner(&xpub, &short, &template).is_err());
+
+        let master_xpub = master.to_extended_public_key();
+        let master_origin = KeyOrigin::from_str(&hex::encode(master.fingerprint())).unwrap();
+        cosigner::validate_origin(&master_xpub, &master_origin).unwrap();
+        assert!(cosigner::validate_origin(&master_xpub, &short).is_err());
+
+        // A depth-1 key carries the master fingerprint as its parent's
+        let depth1 = master
+            .derive_child(ChildNumber::Hardened(48))
+            .unwrap()
+            .to_extended_public_key();
+        let depth1_origin =
+            KeyOrigin::from_str(&format!("{}/48'", hex::encode(master.fingerprint()))).unwrap();
+        cosigner::validate_origin(&depth1, &depth1_origin).unwrap();
+    }
+
+    #[test]
+    fn test_account_rotation() {
+        let master = ExtendedPrivKey::new_master(&[4u8; 32], Network::Bitcoin).unwrap();
+        let mut wallet = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+
+        let first = wallet.next_address(Change::External).unwrap();
+        assert_eq!(wallet.next_address(Change::External).unwrap(), {
+            let key = master
+                .derive_path(&DerivationPath::from_str("m/84'/0'/0'/0/1").unwrap())
+                .unwrap();
+            key.to_extended_public_key().address(AddressType::P2wpkh)
+        });
+
+        let account = wallet.rotate().unwrap();
+        assert_eq!(account.index, 1);
+        assert_eq!(account.next_index(Change::External), 0);
+
+        let retired = wallet.account(0).unwrap();
+        assert!(!retired.is_active());
+        assert_eq!(
+            retired
+                .address(AddressType::P2wpkh, Change::External, 0)
+                .unwrap(),
+            first
+        );
+        assert_eq!(retired.next_index(Change::External), 2);
+
+        assert_eq!(wallet.active_account().index, 1);
+        assert_ne!(wallet.next_address(Change::External).unwrap(), first);
+        let [receive, change] = wallet.descriptors(1).unwrap();
+        assert!(receive.to_string().contains("/84'/0'/1']"));
+        assert!(change.is_internal());
+    }
+
+    #[test]
+    fn test_backup_encryption() {
+        use slip21::Slip21Node;
+
+        // SLIP-21 test vector for the "all all ... all" mnemonic
+        let seed = hex::decode("c76c4ac4f4e4a00d6b274d5c39c700bb4a7ddc04fbc6f78e85ca75007b5b495f74a9043eeb77bdd53aa6fc3a0e31462270316fa04b8c19114c8798706cd02ac8").unwrap();
+        let master = Slip21Node::from_seed(&seed);
+        assert_eq!(
+            hex::encode(master.key()),
+            "dbf12b44133eaab506a740f6565cc117228cbf1dd70635cfa8ddfdc9af734756"
+        );
+        assert_eq!(
+            hex::encode(
+                master
+                    .derive_path(&[b"SLIP-0021", b"Master encryption key"])
+                    .key()
+            ),
+            "ea163130e35bbafdf5ddee97a17b39cef2be4b4f390180d65b54cf05c6a82fde"
+        );
+
+        let mnemonic = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap();
+        let wallet =
+            Wallet::from_seed(&mnemonic.to_seed(""), Network::Bitcoin, AddressType::P2wpkh)
+                .unwrap();
+        let backup = wallet.backup_encrypt(b"labels and settings").unwrap();
+
+        // A wallet restored from the same mnemonic can decrypt the backup
+        let restored =
+            Wallet::from_seed(&mnemonic.to_seed(""), Network::Bitcoin, AddressType::P2wpkh)
+                .unwrap();
+        assert_eq!(
+            restored.backup_decrypt(&backup).unwrap(),
+            b"labels and settings"
+        );
+
+        let mut tampered = backup.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(restored.backup_decrypt(&tampered).is_err());
+
+        let other = Wallet::from_seed(
+            &mnemonic.to_seed("x"),
+            Network::Bitcoin,
+            AddressType::P2wpkh,
+        )
+        .unwrap();
+        assert!(other.backup_decrypt(&backup).is_err());
+
+        // Debug output never shows node secrets or the backup key
+        assert_eq!(master, Slip21Node::from_seed(&seed));
+        assert_ne!(master, master.derive(b"SLIP-0021"));
+        assert!(!format!("{:?}", master).contains(&hex::encode(master.key())));
+        let backup_key = Slip21Node::from_seed(&mnemonic.to_seed("").0)
+            .derive_path(&[b"bip32hdwallet", b"Backup encryption key"])
+            .key();
+        assert!(!format!("{:?}", wallet).contains(&format!("{:?}", backup_key)));
+        assert!(!format!("{:?}", wallet).contains("backup_key"));
+    }
+
+    #[test]
+    fn test_signed_export() {
+        use std::collections::BTreeMap;
+
+        let master = ExtendedPrivKey::new_master(&[5u8; 32], Network::Testnet).unwrap();
+        let mut wallet = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+        wallet.rotate().unwrap();
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert("label".to_string(), "savings".to_string());
+        let export = wallet.export(metadata).unwrap();
+        assert_eq!(export.bundle.accounts.len(), 2);
+        assert!(!export.bundle.accounts[0].active && export.bundle.accounts[1].active);
+        assert_eq!(export.bundle.accounts[1].path, "m/84'/1'/1'");
+        export.verify_with_master(&master).unwrap();
+
+        let other = ExtendedPrivKey::new_master(&[6u8; 32], Network::Testnet).unwrap();
+        assert!(export.verify_with_master(&other).is_err());
+
+        let mut tampered = export.clone();
+        tampered
+            .bundle
+            .metadata
+            .insert("label".to_string(), "spending".to_string());
+        assert!(tampered.verify().is_err());
+
+        let mut future = export.clone();
+        future.bundle.version = export::EXPORT_VERSION + 1;
+        assert!(future.verify().is_err());
+
+        // Account presets are exported and covered by the signature
+        let config = wallet::AccountConfig {
+            gap_limit: 50,
+            ..Default::default()
+        };
+        wallet.set_account_config(1, config).unwrap();
+        let export = wallet.export(BTreeMap::new()).unwrap();
+        assert_eq!(export.bundle.accounts[1].config, config);
+        let mut tampered = export.clone();
+        tampered.bundle.accounts[1].config.gap_limit = 20;
+        assert!(tampered.verify().is_err());
+
+        // Version 1 bundles predate presets and still verify
+        let mut bundle = export.bundle.clone();
+        bundle.version = 1;
+        let v1 = export::SignedExport::sign(bundle, &master).unwrap();
+        let mut restored = v1.clone();
+        restored.bundle.accounts[1].config = Default::default();
+        restored.verify().unwrap();
+
+        #[cfg(feature = "serde")]
+        {
+            let json = export.to_json().unwrap();
+            assert!(json.contains("\"address_type\": \"p2wpkh\""));
+            assert_eq!(export::SignedExport::from_json(&json).unwrap(), export);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_import_wallet_exports() {
+        use import::ExportSource;
+
+        let master = ExtendedPrivKey::new_master(&[7u8; 32], Network::Bitcoin).unwrap();
+        let xfp = hex::encode(master.fingerprint());
+        let account = |path: &str| {
+            master
+                .derive_path(&DerivationPath::from_str(path).unwrap())
+                .unwrap()
+                .to_extended_public_key()
+        };
+        let xpub84 = account("m/84'/0'/0'");
+        let xpub49 = account("m/49'/0'/0'");
+
+        let sparrow = format!(
+            r#"{{"label":"hot","network":"MAINNET","policyType":"SINGLE","scriptType":"P2WPKH",
+              "keystores":[{{"keyDerivation":{{"masterFingerprint":"{}","derivationPath":"m/84'/0'/0'"}},
+              "extendedPublicKey":"{}"}}]}}"#,
+            xfp, xpub84
+        );
+        let wallet = import::parse_export(&sparrow).unwrap();
+        assert_eq!(wallet.source, ExportSource::Sparrow);
+        assert_eq!(wallet.label.as_deref(), Some("hot"));
+        let [receive, _] = wallet.accounts[0].descriptors();
+        assert_eq!(
+            receive.to_string(),
+            descriptor::Descriptor::for_account(&master, AddressType::P2wpkh, 0, Change::External)
+                .unwrap()
+                .to_string()
+        );
+
+        let coldcard = format!(
+            r#"{{"chain":"BTC","xfp":"{}","bip49":{{"deriv":"m/49h/0h/0h","xpub":"{}"}},
+              "bip84":{{"deriv":"m/84h/0h/0h","xpub":"{}"}}}}"#,
+            xfp.to_uppercase(),
+            xpub49,
+            xpub84
+        );
+        let wallet = import::parse_export(&coldcard).unwrap();
+        assert_eq!(wallet.source, ExportSource::Coldcard);
+        assert_eq!(wallet.accounts.len(), 2);
+        assert_eq!(wallet.accounts[0].address_type, AddressType::P2shP2wpkh);
+        assert_eq!(wallet.accounts[1].origin.fingerprint, master.fingerprint());
+
+        // Electrum stores SLIP-132 zpubs, which imply the script type
+        let mut data = utils::base58check_decode(&xpub84.to_string()).unwrap();
+        data[0..4].copy_from_slice(&[0x04, 0xb2, 0x47, 0x46]);
+        let zpub = utils::base58check_encode(&data);
+        assert!(zpub.starts_with("zpub"));
+        let electrum = format!(
+            r#"{{"wallet_type":"standard","seed_version":18,
+              "keystore":{{"type":"bip32","xpub":"{}","derivation":"m/0'","root_fingerprint":"{}"}}}}"#,
+            zpub, xfp
+        );
+        let wallet = import::parse_export(&electrum).unwrap();
+        assert_eq!(wallet.source, ExportSource::Electrum);
+        assert_eq!(wallet.accounts[0].address_type, AddressType::P2wpkh);
+        assert_eq!(wallet.accounts[0].xpub.to_string(), xpub84.to_string());
+
+        assert!(import::parse_export(r#"{"foo":1}"#).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_coldcard_export() {
+        let master = ExtendedPrivKey::new_master(&[8u8; 32], Network::Bitcoin).unwrap();
+        let json = export::coldcard_generic_json(&master, 0).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["chain"], "BTC");
+        assert_eq!(value["xfp"], hex::encode_upper(master.fingerprint()));
+        assert!(value["bip84"]["_pub"].as_str().unwrap().starts_with("zpub"));
+        assert!(value["bip49"]["_pub"].as_str().unwrap().starts_with("ypub"));
+        assert_eq!(value["bip44"]["deriv"], "m/44'/0'/0'");
+
+        let first = master
+            .derive_path(&DerivationPath::from_str("m/84'/0'/0'/0/0").unwrap())
+            .unwrap()
+            .to_extended_public_key()
+            .address(AddressType::P2wpkh);
+        assert_eq!(value["bip84"]["first"], first);
+
+        // The export can be read back by the Coldcard importer
+        let imported = import::parse_coldcard(&json).unwrap();
+        assert_eq!(imported.accounts.len(), 3);
+        assert_eq!(
+            imported.accounts[2].descriptors()[0].to_string(),
+            value["bip84"]["desc"].as_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_first_address() {
+        // BIP-84 test vector account
+        let xpub = ExtendedPubKey::from_string("xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V").unwrap();
+        assert_eq!(
+            xpub.first_address(AddressType::P2wpkh).unwrap(),
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+        assert_eq!(
+            address::verify_first_address(&xpub, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu")
+                .unwrap(),
+            AddressType::P2wpkh
+        );
+        assert!(
+            address::verify_first_address(&xpub, "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g")
+                .is_err()
+        );
+
+        let wallet = Wallet::new(
+            ExtendedPrivKey::new_master(&[1u8; 32], Network::Bitcoin).unwrap(),
+            AddressType::P2shP2wpkh,
+        )
+        .unwrap();
+        let first = wallet.first_address(0).unwrap();
+        assert_eq!(
+            address::verify_first_address(&wallet.active_account().xpub, &first).unwrap(),
+            AddressType::P2shP2wpkh
+        );
+        assert!(wallet.first_address(1).is_err());
+    }
+
+    #[test]
+    fn test_extended_key_transport_encodings() {
+        let master = ExtendedPrivKey::new_master(&[2u8; 32], Network::Testnet).unwrap();
+        let xpub = master.to_extended_public_key();
+
+        let hex_xprv = master.to_hex();
+        assert_eq!(hex_xprv.len(), 156);
+        assert!(hex_xprv.starts_with("04358394"));
+        assert_eq!(
+            ExtendedPrivKey::from_hex(&hex_xprv).unwrap().to_string(),
+            master.to_string()
+        );
+        assert_eq!(
+            ExtendedPrivKey::from_base64(&master.to_base64())
+                .unwrap()
+                .to_string(),
+            master.to_string()
+        );
+
+        let decoded = ExtendedPubKey::from_base64(&xpub.to_base64()).unwrap();
+        assert_eq!(decoded.network, Network::Testnet);
+        assert_eq!(decoded.to_string(), xpub.to_string());
+        assert_eq!(
+            ExtendedPubKey::from_hex(&xpub.to_hex())
+                .unwrap()
+                .to_string(),
+            xpub.to_string()
+        );
+
+        // Private payloads are rejected as public keys and vice versa
+        assert!(ExtendedPubKey::from_hex(&hex_xprv).is_err());
+        assert!(ExtendedPrivKey::from_base64(&xpub.to_base64()).is_err());
+        assert!(ExtendedPubKey::from_hex("0435").is_err());
+    }
+
+    #[test]
+    fn test_tagged_hash() {
+        use sha2::{Digest, Sha256};
+
+        let data = b"tagged hash input";
+        for tag in [
+            utils::TAG_TAP_TWEAK,
+            utils::TAG_BIP340_CHALLENGE,
+            "custom/tag",
+        ] {
+            let tag_hash = Sha256::digest(tag.as_bytes());
+            let expected = Sha256::new()
+                .chain_update(tag_hash)
+                .chain_update(tag_hash)
+                .chain_update(data)
+                .finalize();
+            assert_eq!(utils::tagged_hash(tag, data)[..], expected[..]);
+        }
+
+        // Cached midstates are reusable across calls
+        let mut engine = utils::tagged_engine(utils::TAG_TAP_LEAF);
+        engine.update(data);
+        assert_eq!(
+            engine.finalize()[..],
+            utils::tagged_hash(utils::TAG_TAP_LEAF, data)[..]
+        );
+    }
+
+    #[test]
+    fn test_hash160_fingerprints() {
+        assert_eq!(
+            hex::encode(utils::ripemd160(b"")),
+            "9c1185a5c5e9fc54612808977ee8f548b2258d31"
+        );
+
+        // BIP-32 test vector 1: fingerprints are HASH160-based
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedPrivKey::new_master(&seed, Network::Bitcoin).unwrap();
+        let child = master.derive_child(ChildNumber::Hardened(0)).unwrap();
+        assert_eq!(child.parent_fingerprint, master.fingerprint());
+        assert_eq!(
+            child.to_string(),
+            "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7"
+        );
+        assert_eq!(
+            child.to_extended_public_key().to_string(),
+            "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw"
+        );
+
+        let grandchild = child
+            .to_extended_public_key()
+            .derive_child(ChildNumber::Normal(1))
+            .unwrap();
+        assert_eq!(
+            grandchild.to_string(),
+            "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ"
+        );
+        assert_eq!(
+            utils::pubkey_hash(&grandchild.public_key)[..4],
+            grandchild.fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_seed_stretching_progress() {
+        use stretch::SeedStretcher;
+
+        let mnemonic = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap();
+        let expected = mnemonic.to_seed("TREZOR");
+
+        let mut reports = Vec::new();
+        let seed =
+            mnemonic.to_seed_with_progress("TREZOR", |done, total| reports.push((done, total)));
+        assert_eq!(seed, expected);
+        assert_eq!(reports.last(), Some(&(2048, 2048)));
+        assert!(reports.windows(2).all(|w| w[0].0 < w[1].0));
+
+        // Stretching can be paused and resumed across calls
+        let mut stretcher = SeedStretcher::for_mnemonic(&mnemonic, "TREZOR");
+        assert!(!stretcher.step(1000));
+        assert!(stretcher.seed().is_none());
+        let paused = stretcher.clone();
+        assert!(stretcher.step(5000));
+        assert_eq!(stretcher.seed(), Some(expected.clone()));
+        assert_eq!(paused.progress(), (1001, 2048));
+
+        #[cfg(feature = "async")]
+        {
+            use std::future::Future;
+            use std::task::{Context, Poll, Waker};
+
+            let mut future = Box::pin(mnemonic.to_seed_async("TREZOR", |_, _| {}));
+            let mut cx = Context::from_waker(Waker::noop());
+            let mut polls = 0;
+            let seed = loop {
+                polls += 1;
+                if let Poll::Ready(seed) = future.as_mut().poll(&mut cx) {
+                    break seed;
+                }
+            };
+            assert_eq!(seed, expected);
+            assert!(polls > 1);
+        }
+    }
+
+    #[test]
+    fn test_derivation_cache() {
+        use cache::DerivationCache;
+
+        let account = ExtendedPrivKey::new_master(&[10u8; 32], Network::Bitcoin)
+            .unwrap()
+            .derive_path(&DerivationPath::from_str("m/84'/0'/0'").unwrap())
+            .unwrap()
+            .to_extended_public_key();
+        let path = DerivationPath::from_str("m/0/5").unwrap();
+
+        let cache = DerivationCache::new(16);
+        let derived = cache.derive(&account, &path).unwrap();
+        assert_eq!(
+            derived.to_string(),
+            account.derive_path(&path).unwrap().to_string()
+        );
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats(), (0, 1));
+
+        cache.derive(&account, &path).unwrap();
+        assert_eq!(cache.stats(), (1, 1));
+
+        // Sibling reuses the cached chain key
+        cache
+            .derive(&account, &DerivationPath::from_str("m/0/6").unwrap())
+            .unwrap();
+        assert_eq!(cache.len(), 3);
+
+        let wallet = Wallet::new(
+            ExtendedPrivKey::new_master(&[10u8; 32], Network::Bitcoin).unwrap(),
+            AddressType::P2wpkh,
+        )
+        .unwrap();
+        let cached = wallet.clone().with_derivation_cache(64);
+        let expected = wallet.addresses(0, Change::External, 0, 10).unwrap();
+        assert_eq!(
+            cached.addresses(0, Change::External, 0, 10).unwrap(),
+            expected
+        );
+        assert_eq!(
+            cached.addresses(0, Change::External, 0, 10).unwrap(),
+            expected
+        );
+        assert_eq!(cached.derivation_cache().unwrap().stats(), (10, 10));
+        assert_eq!(expected[5], derived.address(AddressType::P2wpkh));
+
+        // Batch scans over the watched range share the cache
+        assert_eq!(
+            cached.electrum_subscriptions().unwrap(),
+            wallet.electrum_subscriptions().unwrap()
+        );
+        assert_eq!(cached.derivation_cache().unwrap().stats(), (20, 40));
+        cached.classify_outputs(&[]).unwrap();
+        assert_eq!(cached.derivation_cache().unwrap().stats(), (60, 40));
+    }
+
+    #[test]
+    fn test_shared_wallet_across_threads() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let wallet = Arc::new(
+            Wallet::new(
+                ExtendedPrivKey::new_master(&[11u8; 32], Network::Bitcoin).unwrap(),
+                AddressType::P2wpkh,
+            )
+            .unwrap()
+            .with_derivation_cache(128),
+        );
+        let expected = wallet.addresses(0, Change::External, 0, 8).unwrap();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let wallet = Arc::clone(&wallet);
+                thread::spawn(move || wallet.addresses(0, Change::External, 0, 8).unwrap())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_wallet_store() {
+        use store::{MemoryStore, WalletStore};
+
+        let master = ExtendedPrivKey::new_master(&[12u8; 32], Network::Bitcoin).unwrap();
+        let mut wallet = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+        let address = wallet.next_address(Change::External).unwrap();
+        wallet.set_label(&address, "donations");
+        wallet.rotate().unwrap();
+        wallet.next_address(Change::Internal).unwrap();
+
+        let store = MemoryStore::new();
+        assert!(store.load().unwrap().is_none());
+        wallet.save(&store).unwrap();
+        // Plaintext state is never handed out as an opaque payload
+        assert!(store.load_payload().is_err());
+
+        let mut restored = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+        assert!(restored.load(&store).unwrap());
+        assert_eq!(restored.state(), wallet.state());
+        assert_eq!(restored.label(&address), Some("donations"));
+        assert_eq!(restored.active_account().index, 1);
+        assert_eq!(
+            restored.next_address(Change::Internal).unwrap(),
+            wallet.next_address(Change::Internal).unwrap()
+        );
+
+        #[cfg(feature = "serde")]
+        {
+            let path = std::env::temp_dir()
+                .join(format!("bip32hdwallet-store-{}.json", std::process::id()));
+            let file = store::JsonFileStore::new(&path);
+            assert!(file.load().unwrap().is_none());
+            wallet.save(&file).unwrap();
+            assert_eq!(file.load().unwrap(), Some(wallet.state()));
+            assert!(file.load_payload().is_err());
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[cfg(feature = "store-sqlite")]
+        {
+            let db = store::SqliteStore::open_in_memory().unwrap();
+            assert_eq!(db.schema_version().unwrap(), 2);
+            assert!(db.load().unwrap().is_none());
+            wallet.save(&db).unwrap();
+            assert_eq!(db.load().unwrap(), Some(wallet.state()));
+            assert!(db.load_payload().is_err());
+        }
+    }
+
+    #[test]
+    fn test_encrypted_store() {
+        use store::{EncryptedStore, MemoryStore, WalletStore};
+
+        let master = ExtendedPrivKey::new_master(&[13u8; 32], Network::Bitcoin).unwrap();
+        let mut wallet = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+        let address = wallet.next_address(Change::External).unwrap();
+        wallet.set_label(&address, "savings");
+
+        let store = EncryptedStore::new(MemoryStore::new(), "correct horse")
+            .with_costs(64, 1, 1)
+            .unwrap();
+        wallet.save(&store).unwrap();
+
+        let payload = store.inner().load_payload().unwrap().unwrap();
+        assert!(!payload.windows(7).any(|w| w == b"savings"));
+        assert!(store.inner().load().is_err());
+
+        let mut restored = Wallet::new(master, AddressType::P2wpkh).unwrap();
+        assert!(restored.load(&store).unwrap());
+        assert_eq!(restored.state(), wallet.state());
+
+        let wrong = EncryptedStore::new(MemoryStore::new(), "wrong horse");
+        wrong.inner().save_payload(&payload).unwrap();
+        assert!(wrong.load().is_err());
+    }
+
+    #[test]
+    fn test_bip39_vectors() {
+        assert_eq!(
+            vectors::verify_bip39_vectors().unwrap(),
+            vectors::BIP39_VECTORS.len()
+        );
+
+        let mut tampered = vectors::BIP39_VECTORS[0];
+        tampered.passphrase = "trezor";
+        assert_eq!(
+            vectors::verify_vector(&tampered),
+            Err("seed mismatch".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_extended_key_str() {
+        use bip32::{validate_extended_key_str, KeyKind};
+
+        let master = ExtendedPrivKey::new_master(&[14u8; 32], Network::Testnet).unwrap();
+        let xprv = master.to_string();
+        let xpub = master.to_extended_public_key().to_string();
+        assert_eq!(
+            validate_extended_key_str(&xprv).unwrap(),
+            KeyKind::Private(Network::Testnet)
+        );
+        assert_eq!(
+            validate_extended_key_str(&xpub).unwrap(),
+            KeyKind::Public(Network::Testnet)
+        );
+
+        // Corrupted checksum
+        let mut corrupted = xpub.clone().into_bytes();
+        let last = corrupted.len() - 1;
+        corrupted[last] = if corrupted[last] == b'1' { b'2' } else { b'1' };
+        assert!(validate_extended_key_str(std::str::from_utf8(&corrupted).unwrap()).is_err());
+
+        // Master key with a parent fingerprint, from the BIP-32 invalid vectors
+        assert!(validate_extended_key_str("xpub661MyMwAuDcm6CRQ5N4qiHKrJ39Xe1R1NyfouMKTTWcguwVcfrZJaNvhpebzGerh7gucBvzEQWRugZDuDXjNDRmXzSZe4c7mnTK97pTvGS8").is_err());
+    }
+
+    #[test]
+    fn test_normalize_xpub_batch() {
+        use slip132::{encode_xpub, normalize_xpub_batch, BatchIssue};
+
+        let key = |seed: u8, network| {
+            ExtendedPrivKey::new_master(&[seed; 32], network)
+                .unwrap()
+                .to_extended_public_key()
+        };
+        let a = key(1, Network::Bitcoin);
+        let b = key(2, Network::Bitcoin);
+        let c = key(3, Network::Testnet);
+
+        let inputs = [
+            encode_xpub(&a, AddressType::P2wpkh),
+            format!(" {} ", b),
+            encode_xpub(&a, AddressType::P2wpkh),
+            encode_xpub(&b, AddressType::P2shP2wpkh),
+            c.to_string(),
+            "xpub-not-a-key".to_string(),
+        ];
+        let batch = normalize_xpub_batch(&inputs);
+
+        assert_eq!(batch.canonical(), [a.to_string(), b.to_string()]);
+        assert_eq!(batch.keys[0].address_type, Some(AddressType::P2wpkh));
+        assert_eq!(batch.issues.len(), 4);
+        assert_eq!(
+            batch.issues[0],
+            BatchIssue::Duplicate { index: 2, first: 0 }
+        );
+        assert_eq!(
+            batch.issues[1],
+            BatchIssue::AddressTypeConflict { index: 3, first: 1 }
+        );
+        assert_eq!(
+            batch.issues[2],
+            BatchIssue::NetworkMismatch { index: 4, first: 0 }
+        );
+        assert!(matches!(
+            batch.issues[3],
+            BatchIssue::Invalid { index: 5, .. }
+        ));
+        assert!(!batch.is_clean());
+    }
+
+    #[test]
+    fn test_prove_child() {
+        use proof::{prove_address, prove_child, ChildProof};
+
+        let master = ExtendedPrivKey::new_master(&[15u8; 32], Network::Bitcoin).unwrap();
+        let account = master
+            .derive_path(&DerivationPath::from_str("m/84'/0'/0'").unwrap())
+            .unwrap()
+            .to_extended_public_key();
+        let path = DerivationPath::from_str("m/0/7").unwrap();
+        let child = account.derive_path(&path).unwrap();
+
+        let proof = prove_child(&account, &child.public_key, &path).unwrap();
+        assert!(proof.is_verified());
+        assert!(prove_address(
+            &account,
+            &child.address(AddressType::P2wpkh),
+            &path,
+            AddressType::P2wpkh
+        )
+        .unwrap()
+        .is_verified());
+
+        let other = DerivationPath::from_str("m/0/8").unwrap();
+        match prove_child(&account, &child.public_key, &other).unwrap() {
+            ChildProof::Mismatch { derived } => assert_ne!(derived, child.public_key),
+            ChildProof::Verified(_) => panic!("wrong path must not verify"),
+        }
+
+        let hardened = DerivationPath::from_str("m/0'/7").unwrap();
+        assert!(prove_child(&account, &child.public_key, &hardened).is_err());
+    }
+
+    #[test]
+    fn test_derivation_transcript() {
+        use transcript::DerivationTranscript;
+
+        // BIP-32 test vector 1, chain m/0'/1
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedPrivKey::new_master(&seed, Network::Bitcoin).unwrap();
+        let path = DerivationPath::from_str("m/0'/1").unwrap();
+        let transcript = DerivationTranscript::from_private(&master, &path).unwrap();
+
+        assert_eq!(transcript.steps.len(), 3);
+        assert_eq!(transcript.steps[1].path, "m/0'");
+        assert_eq!(transcript.steps[1].fingerprint, "5c1bd648");
+        assert_eq!(transcript.steps[2].parent_fingerprint, "5c1bd648");
+        assert_eq!(
+            transcript.steps[2].chain_code,
+            "2a7857631386ba23dacac34180dd1983734e444fdbf774041578e9b6adb37c19"
+        );
+
+        // The public half of the derivation produces the same steps
+        let hardened = master
+            .derive_path(&DerivationPath::from_str("m/0'").unwrap())
+            .unwrap();
+        let public = DerivationTranscript::from_public(
+            &hardened.to_extended_public_key(),
+            &DerivationPath::from_str("m/1").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(public.steps[1].public_key, transcript.steps[2].public_key);
+
+        let mut tampered = transcript.clone();
+        tampered.steps[2].chain_code = "00".repeat(32);
+        assert_eq!(transcript.first_divergence(&tampered), Some(2));
+        assert_eq!(transcript.first_divergence(&transcript), None);
+
+        #[cfg(feature = "serde")]
+        {
+            let json = transcript.to_json().unwrap();
+            assert!(!json.contains("xprv"));
+            assert_eq!(DerivationTranscript::from_json(&json).unwrap(), transcript);
+        }
+    }
+
+    #[test]
+    fn test_sign_eth_hash() {
+        use ethereum::{keccak256, to_checksum_address};
+
+        assert_eq!(
+            hex::encode(keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        let address: [u8; 20] = hex::decode("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            to_checksum_address(&address),
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+
+        // EIP-155 example transaction
+        let mut key = ExtendedPrivKey::new_master(&[16u8; 32], Network::Bitcoin).unwrap();
+        key.private_key = secp256k1::SecretKey::from_slice(&[0x46; 32]).unwrap();
+        let hash: [u8; 32] =
+            hex::decode("daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let signature = key.sign_eth_hash(&hash, Some(1)).unwrap();
+        assert_eq!(signature.v, 37);
+        assert_eq!(signature.chain_id(), Some(1));
+        assert_eq!(
+            hex::encode(signature.r),
+            "28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276"
+        );
+        assert_eq!(
+            hex::encode(signature.s),
+            "67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        assert_eq!(
+            signature.recover_address(&hash).unwrap(),
+            key.to_extended_public_key().eth_address()
+        );
+        assert_eq!(
+            key.to_extended_public_key().eth_address(),
+            "0x9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F"
+        );
+
+        let legacy = key.sign_eth_hash(&hash, None).unwrap();
+        assert_eq!(legacy.v, 27 + signature.y_parity() as u64);
+        assert_eq!(legacy.chain_id(), None);
+
+        // v must fit in a u64
+        let largest = (u64::MAX - 36) / 2;
+        assert_eq!(
+            key.sign_eth_hash(&hash, Some(largest)).unwrap().chain_id(),
+            Some(largest)
+        );
+        assert!(key.sign_eth_hash(&hash, Some(largest + 1)).is_err());
+        assert!(key.sign_eth_hash(&hash, Some(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_eip712() {
+        use eip712::{TypedData, Value};
+
+        // The "Ether Mail" example from EIP-712
+        let person = |name: &str, wallet: &str| {
+            Value::object([("name", Value::from(name)), ("wallet", Value::from(wallet))])
+        };
+        let data = TypedData::new(
+            "Mail",
+            Value::object([
+                ("name", Value::from("Ether Mail")),
+                ("version", Value::from("1")),
+                ("chainId", Value::from(1u64)),
+                (
+                    "verifyingContract",
+                    Value::from("0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"),
+                ),
+            ]),
+            Value::object([
+                (
+                    "from",
+                    person("Cow", "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"),
+                ),
+                (
+                    "to",
+                    person("Bob", "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"),
+                ),
+                ("contents", Value::from("Hello, Bob!")),
+            ]),
+        )
+        .with_type(
+            "EIP712Domain",
+            &[
+                ("name", "string"),
+                ("version", "string"),
+                ("chainId", "uint256"),
+                ("verifyingContract", "address"),
+            ],
+        )
+        .with_type("Person", &[("name", "string"), ("wallet", "address")])
+        .with_type(
+            "Mail",
+            &[("from", "Person"), ("to", "Person"), ("contents", "string")],
+        );
+
+        assert_eq!(
+            data.encode_type("Mail").unwrap(),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            hex::encode(data.domain_separator().unwrap()),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+        assert_eq!(
+            hex::encode(data.hash_struct("Mail", &data.message).unwrap()),
+            "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+        );
+        assert_eq!(
+            hex::encode(data.signing_hash().unwrap()),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+
+        let mut key = ExtendedPrivKey::new_master(&[17u8; 32], Network::Bitcoin).unwrap();
+        key.private_key = secp256k1::SecretKey::from_slice(&ethereum::keccak256(b"cow")).unwrap();
+        assert_eq!(
+            key.to_extended_public_key().eth_address(),
+            "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"
+        );
+        let signature = key.sign_typed_data(&data).unwrap();
+        assert_eq!(signature.v, 28);
+        assert_eq!(
+            hex::encode(signature.r),
+            "4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d"
+        );
+        assert_eq!(
+            hex::encode(signature.s),
+            "07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562"
+        );
+
+        // Integers are range checked and encoded in two's complement
+        let numbers = |a: &str, b: &str| {
+            let data = TypedData::new(
+                "N",
+                Value::object::<&str, _>([]),
+                Value::object([("a", Value::from(a)), ("b", Value::from(b))]),
+            )
+            .with_type("N", &[("a", "int8"), ("b", "uint8")]);
+            data.encode_data("N", &data.message)
+        };
+        let encoded = numbers("-128", "0xff").unwrap();
+        assert_eq!(
+            hex::encode(&encoded[32..64]),
+            format!("{}80", "ff".repeat(31))
+        );
+        assert_eq!(encoded[95], 0xff);
+        assert!(numbers("128", "0").is_err());
+        assert!(numbers("0", "256").is_err());
+        assert!(numbers("0", "-1").is_err());
+
+        // Malformed array types are errors rather than panics
+        for type_name in ["uint8[", "[]", "uint8[2", "uint8[x]"] {
+            let data = TypedData::new(
+                "A",
+                Value::object::<&str, _>([]),
+                Value::object([("a", Value::Array(vec![Value::from("1")]))]),
+            )
+            .with_type("A", &[("a", type_name)]);
+            assert!(data.encode_data("A", &data.message).is_err());
+        }
+
+        #[cfg(feature = "serde")]
+        {
+            let json = r#"{
+                "types": {
+                    "EIP712Domain": [
+                        {"name": "name", "type": "string"},
+                        {"name": "version", "type": "string"},
+                        {"name": "chainId", "type": "uint256"},
+                        {"name": "verifyingContract", "type": "address"}
+                    ],
+                    "Person": [
+                        {"name": "name", "type": "string"},
+                        {"name": "wallet", "type": "address"}
+                    ],
+                    "Mail": [
+                        {"name": "from", "type": "Person"},
+                        {"name": "to", "type": "Person"},
+                        {"name": "contents", "type": "string"}
+                    ]
+                },
+                "primaryType": "Mail",
+                "domain": {
+                    "name": "Ether Mail",
+                    "version": "1",
+                    "chainId": 1,
+                    "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+                },
+                "message": {
+                    "from": {"name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"},
+                    "to": {"name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"},
+                    "contents": "Hello, Bob!"
+                }
+            }"#;
+            assert_eq!(eip712::TypedData::from_json(json).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_evm_presets() {
+        use ethereum::to_checksum_address_eip1191;
+        use evm::EvmChain;
+
+        let address: [u8; 20] = hex::decode("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            to_checksum_address_eip1191(&address, 30),
+            "0x5aaEB6053f3e94c9b9a09f33669435E7ef1bEAeD"
+        );
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+        let master =
+            ExtendedPrivKey::new_master(mnemonic.to_seed("").as_bytes(), Network::Bitcoin).unwrap();
+        let wallet = Wallet::new(master, AddressType::P2wpkh).unwrap();
+
+        let eth = wallet.evm_address(&EvmChain::ETHEREUM, 0).unwrap();
+        assert_eq!(eth, "0x9858EfFD232B4033E47d90003D41EC34EcaEda94");
+        // Chains reusing 60' share Ethereum's addresses unless the native coin type is used
+        assert_eq!(wallet.evm_address(&EvmChain::POLYGON, 0).unwrap(), eth);
+        let native = EvmChain::POLYGON.with_native_coin_type();
+        assert_eq!(native.path(0, 0).to_string(), "m/44'/966'/0'/0/0");
+        assert_ne!(wallet.evm_address(&native, 0).unwrap(), eth);
+
+        assert_eq!(EvmChain::for_chain_id(56), Some(EvmChain::BNB_SMART_CHAIN));
+        let tron = wallet.evm_address(&EvmChain::TRON, 0).unwrap();
+        assert_eq!(tron, "TUEZSdKsoDHQMeZwihtdoBiN46zxhGWYdH");
+        assert_eq!(utils::base58check_decode(&tron).unwrap()[0], 0x41);
+    }
+
+    #[test]
+    fn test_cosmos() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+        let master =
+            ExtendedPrivKey::new_master(mnemonic.to_seed("").as_bytes(), Network::Bitcoin).unwrap();
+        let wallet = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+
+        assert_eq!(
+            wallet.cosmos_address(cosmos::COSMOS_HRP, 0).unwrap(),
+            "cosmos19rl4cm2hmr8afy4kldpxz3fka4jguq0auqdal4"
+        );
+
+        let key = master.derive_path(&cosmos::path(0, 0)).unwrap();
+        let public_key = key.to_extended_public_key().public_key;
+        assert_eq!(
+            cosmos::amino_pubkey_json(&public_key),
+            format!(
+                r#"{{"type":"tendermint/PubKeySecp256k1","value":"{}"}}"#,
+                cosmos::pubkey_base64(&public_key)
+            )
+        );
+
+        let sign_bytes = b"\x0a\x02hi";
+        let signature = key.sign_cosmos(sign_bytes);
+        cosmos::verify(&public_key, sign_bytes, &signature).unwrap();
+        assert!(cosmos::verify(&public_key, b"other", &signature).is_err());
+
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            cosmos::amino_sign_bytes(r#"{ "memo": "a<b", "chain_id": "cosmoshub-4" }"#).unwrap(),
+            br#"{"chain_id":"cosmoshub-4","memo":"a\u003cb"}"#.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_slip10_solana() {
+        use slip10::Ed25519ExtendedKey;
+
+        // SLIP-10 ed25519 test vector 1
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = Ed25519ExtendedKey::new_master(&seed).unwrap();
+        assert_eq!(
+            hex::encode(master.secret_key),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            hex::encode(master.verifying_key().as_bytes()),
+            "a4b2856bfec510abab89753fac1ac0e1112364e7d250545963f135f2a33188ed"
+        );
+        let child = master
+            .derive_path(&DerivationPath::from_str("m/0'").unwrap())
+            .unwrap();
+        assert_eq!(hex::encode(child.parent_fingerprint), "ddebc675");
+        let debug = format!("{:?}", child);
+        assert!(debug.contains("ddebc675"));
+        assert!(!debug.contains(&format!("{:?}", child.secret_key)));
+        assert!(!debug.contains(&format!("{:?}", child.chain_code)));
+        assert_eq!(
+            hex::encode(child.chain_code),
+            "8b59aa11380b624e81507a27fedda59fea6d0b779a778918a2fd3590e16e9c69"
+        );
+        assert!(master
+            .derive_path(&DerivationPath::from_str("m/0").unwrap())
+            .is_err());
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+        let key = Ed25519ExtendedKey::new_master(mnemonic.to_seed("").as_bytes())
+            .unwrap()
+            .derive_path(&solana::path(0))
+            .unwrap();
+        let address = key.solana_address();
+        assert_eq!(address, "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk");
+
+        let signature = key.sign(b"transaction message");
+        solana::verify(&address, b"transaction message", &signature).unwrap();
+        assert!(solana::verify(&address, b"other message", &signature).is_err());
+    }
+
+    #[test]
+    fn test_substrate() {
+        use substrate::{ss58_decode, ss58_encode, Ed25519Pair, Junction};
+
+        let dev = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+        let mnemonic = Mnemonic::from_phrase(dev, Language::English).unwrap();
+        let alice = Junction::parse_path("//Alice").unwrap();
+
+        let pair = Ed25519Pair::from_mnemonic(&mnemonic, "")
+            .unwrap()
+            .derive(&alice)
+            .unwrap();
+        assert_eq!(
+            hex::encode(pair.public()),
+            "88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee"
+        );
+        assert!(!format!("{:?}", Ed25519Pair::from_seed([4u8; 32]))
+            .contains(&format!("{:?}", [4u8; 32])));
+        let address = ss58_encode(&pair.public(), substrate::GENERIC_SUBSTRATE).unwrap();
+        assert_eq!(address, "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu");
+        assert_eq!(
+            ss58_decode(&address).unwrap(),
+            (substrate::GENERIC_SUBSTRATE, pair.public())
+        );
+
+        // Two-byte prefixes round-trip
+        let long = ss58_encode(&pair.public(), 1284).unwrap();
+        assert_eq!(ss58_decode(&long).unwrap(), (1284, pair.public()));
+
+        let soft = Junction::parse_path("//Alice/0").unwrap();
+        assert!(matches!(soft[1], Junction::Soft(_)));
+        assert!(Ed25519Pair::from_mnemonic(&mnemonic, "")
+            .unwrap()
+            .derive(&soft)
+            .is_err());
+
+        #[cfg(feature = "sr25519")]
+        {
+            let pair = substrate::Sr25519Pair::from_mnemonic(&mnemonic, "")
+                .unwrap()
+                .derive(&alice);
+            assert_eq!(
+                ss58_encode(&pair.public(), substrate::GENERIC_SUBSTRATE).unwrap(),
+                "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+            );
+            let signature = pair.sign(b"remark");
+            substrate::Sr25519Pair::verify(&pair.public(), b"remark", &signature).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_filecoin_address() {
+        // Public key of the private key 1 (the generator point)
+        let one = secp256k1::SecretKey::from_slice(&[[0u8; 31].as_slice(), &[1]].concat()).unwrap();
+        let public_key = secp256k1::PublicKey::from_secret_key(utils::secp(), &one);
+        let address = filecoin::encode_address(&public_key, Network::Bitcoin);
+        assert_eq!(address, "f1wcuzrs736zqzbbjjdgl2wvyyufuk4pefbymzf2i");
+        assert_eq!(
+            filecoin::decode_address(&address).unwrap(),
+            (Network::Bitcoin, filecoin::payload(&public_key))
+        );
+        assert!(filecoin::decode_address("f1wcuzrs736zqzbbjjdgl2wvyyufuk4pefbymzf2j").is_err());
+
+        let master = ExtendedPrivKey::new_master(&[18u8; 32], Network::Testnet).unwrap();
+        let wallet = Wallet::new(master, AddressType::P2wpkh).unwrap();
+        assert!(wallet.filecoin_address(0).unwrap().starts_with("t1"));
+    }
+
+    #[test]
+    fn test_avalanche_address() {
+        use avalanche::AvalancheChain;
+
+        // Public key of the private key 1 (the generator point)
+        let one = secp256k1::SecretKey::from_slice(&[[0u8; 31].as_slice(), &[1]].concat()).unwrap();
+        let public_key = secp256k1::PublicKey::from_secret_key(utils::secp(), &one);
+        let address = avalanche::encode_address(&public_key, AvalancheChain::X, Network::Bitcoin);
+        assert_eq!(address, "X-avax1w508d6qejxtdg4y5r3zarvary0c5xw7k0l6nk9");
+        assert_eq!(
+            avalanche::decode_address(&address).unwrap(),
+            (
+                AvalancheChain::X,
+                Network::Bitcoin,
+                utils::pubkey_hash(&public_key)
+            )
+        );
+        assert!(
+            avalanche::decode_address("C-avax1w508d6qejxtdg4y5r3zarvary0c5xw7k0l6nk9").is_err()
+        );
+
+        let master = ExtendedPrivKey::new_master(&[19u8; 32], Network::Testnet).unwrap();
+        let wallet = Wallet::new(master, AddressType::P2wpkh).unwrap();
+        let x = wallet.avalanche_address(AvalancheChain::X, 0).unwrap();
+        let p = wallet.avalanche_address(AvalancheChain::P, 0).unwrap();
+        assert!(x.starts_with("X-fuji1"));
+        assert_eq!(x[2..], p[2..]);
+    }
+
+    #[test]
+    fn test_address_scheme() {
+        use avalanche::AvalancheChain;
+        use evm::EvmChain;
+        use scheme::{AddressScheme, AvalancheScheme, BitcoinScheme, CosmosScheme, FilecoinScheme};
+
+        let master = ExtendedPrivKey::new_master(&[23u8; 32], Network::Bitcoin).unwrap();
+        let wallet = Wallet::new(master, AddressType::P2wpkh).unwrap();
+
+        let schemes: Vec<Box<dyn AddressScheme>> = vec![
+            Box::new(BitcoinScheme {
+                address_type: AddressType::P2wpkh,
+                network: Network::Bitcoin,
+            }),
+            Box::new(BitcoinScheme {
+                address_type: AddressType::P2shP2wpkh,
+                network: Network::Bitcoin,
+            }),
+            Box::new(EvmChain::ETHEREUM),
+            Box::new(EvmChain::ROOTSTOCK),
+            Box::new(EvmChain::TRON),
+            Box::new(CosmosScheme::hub()),
+            Box::new(FilecoinScheme {
+                network: Network::Bitcoin,
+            }),
+            Box::new(AvalancheScheme {
+                chain: AvalancheChain::X,
+                network: Network::Bitcoin,
+            }),
+        ];
+        let addresses: Vec<String> = schemes
+            .iter()
+            .map(|scheme| wallet.scheme_address(scheme.as_ref(), 0).unwrap())
+            .collect();
+
+        // Schemes agree with the chain-specific helpers
+        assert_eq!(addresses[0], wallet.first_address(0).unwrap());
+        assert_eq!(
+            addresses[2],
+            wallet.evm_address(&EvmChain::ETHEREUM, 0).unwrap()
+        );
+        assert_eq!(addresses[5], wallet.cosmos_address("cosmos", 0).unwrap());
+        assert_eq!(addresses[6], wallet.filecoin_address(0).unwrap());
+
+        // Each address validates only under its own scheme
+        for (i, scheme) in schemes.iter().enumerate() {
+            for (j, address) in addresses.iter().enumerate() {
+                assert_eq!(
+                    scheme.validate(address).is_ok(),
+                    i == j,
+                    "{} validating {}",
+                    scheme.name(),
+                    address
+                );
+            }
+        }
+
+        // Mixed-case hex must carry the right checksum
+        let eth = &addresses[2];
+        let flipped: String = eth
+            .char_indices()
+            .map(|(i, c)| match c {
+                'a'..='f' if i > 1 => c.to_ascii_uppercase(),
+                'A'..='F' => c.to_ascii_lowercase(),
+                _ => c,
+            })
+            .collect();
+        assert!(EvmChain::ETHEREUM.validate(&eth.to_lowercase()).is_ok());
+        assert!(EvmChain::ETHEREUM.validate(&flipped).is_err());
+        assert!(CosmosScheme {
+            hrp: "osmo".to_string()
+        }
+        .validate(&addresses[5])
+        .is_err());
+    }
+
+    #[test]
+    fn test_key_hierarchy() {
+        use hierarchy::KeyHierarchy;
+
+        let master = ExtendedPrivKey::new_master(&[29u8; 32], Network::Bitcoin).unwrap();
+        let paths: Vec<DerivationPath> = [
+            "m/44'/0'/0'/0/0",
+            "m/44'/0'/0'/0/1",
+            "m/44'/0'/1'",
+            "m/84'/0'/0'",
+        ]
+        .iter()
+        .map(|path| DerivationPath::from_str(path).unwrap())
+        .collect();
+
+        let tree = KeyHierarchy::from_private(&master, &paths).unwrap();
+        assert_eq!(tree.root_fingerprint(), hex::encode(master.fingerprint()));
+        // m, 44', 0', 0', 0, 0, 1, 1', 84', 0', 0'
+        assert_eq!(tree.root.node_count(), 11);
+        assert_eq!(tree.root.children.len(), 2);
+        let leaf = &tree.root.children[0].children[0].children[0].children[0].children[1];
+        assert_eq!(leaf.path, "m/44'/0'/0'/0/1");
+        assert_eq!(leaf.label(), "1");
+        assert_eq!(
+            leaf.xpub,
+            master
+                .derive_path(&paths[1])
+                .unwrap()
+                .to_extended_public_key()
+                .to_string()
+        );
+
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph hierarchy {"));
+        assert_eq!(dot.matches(" -> ").count(), 10);
+        assert!(dot.contains("\"m/44'/0'\" -> \"m/44'/0'/1'\";"));
+        assert!(!dot.contains("xprv"));
+
+        // Public trees can't contain hardened steps
+        let xpub = master.to_extended_public_key();
+        assert!(KeyHierarchy::from_public(&xpub, &paths).is_err());
+        let public =
+            KeyHierarchy::from_public(&xpub, &[DerivationPath::from_str("m/0/5").unwrap()])
+                .unwrap();
+        assert_eq!(public.root.node_count(), 3);
+
+        #[cfg(feature = "serde")]
+        {
+            let json = tree.to_json().unwrap();
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(value["root"]["children"][1]["path"], "m/84'");
+        }
+    }
+
+    #[test]
+    fn test_derivation_path_normalize() {
+        use std::collections::HashSet;
+
+        let canonical = "m/84'/0'/0'/1/5";
+        for variant in [
+            "m/84'/0'/0'/1/5",
+            "m/84h/0h/0h/1/5",
+            "M/84H/0H/0'/1/5/",
+            " m / 84' / 0h / 0' / 1 / 5 ",
+            "m/084'/0'/00'/1/05",
+        ] {
+            assert_eq!(DerivationPath::normalize(variant).unwrap(), canonical);
+        }
+        assert!(DerivationPath::normalize("m/84x/0").is_err());
+
+        // Raw indices past 2^31 are the hardened index they encode
+        let raw = DerivationPath {
+            path: vec![ChildNumber::Normal(0x8000_0054), ChildNumber::Normal(0)],
+        };
+        let hardened = DerivationPath::from_str("m/84'/0").unwrap();
+        assert_eq!(raw, hardened);
+        assert_eq!(raw.canonical().to_string(), "m/84'/0");
+
+        let paths: HashSet<DerivationPath> = [
+            raw,
+            hardened,
+            DerivationPath::from_str("m/84h/0/").unwrap(),
+            DerivationPath::from_str("m/84'/0/0").unwrap(),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(paths.len(), 2);
+    }
+
+    #[test]
+    fn test_derive_helpers() {
+        let master = ExtendedPrivKey::new_master(&[31u8; 32], Network::Bitcoin).unwrap();
+        let path = Bip44Path::standard(
+            CoinType::BITCOIN,
+            AccountLevel::new(2),
+            Change::Internal,
+            AddressIndex::new(7),
+        );
+
+        let stepwise = master
+            .derive_hardened(44)
+            .unwrap()
+            .derive_hardened(0)
+            .unwrap()
+            .derive_hardened(2)
+            .unwrap()
+            .derive_normal(1)
+            .unwrap()
+            .derive_normal(7)
+            .unwrap();
+        let direct = master.derive_bip44(&path).unwrap();
+        assert_eq!(stepwise.to_string(), direct.to_string());
+        assert_eq!(
+            direct.to_string(),
+            master
+                .derive_path(&DerivationPath::from_str("m/44'/0'/2'/1/7").unwrap())
+                .unwrap()
+                .to_string()
+        );
+
+        // Public derivation matches on the normal levels
+        let account = master
+            .derive_hardened(44)
+            .unwrap()
+            .derive_hardened(0)
+            .unwrap()
+            .derive_hardened(2)
+            .unwrap();
+        let public = account
+            .to_extended_public_key()
+            .derive_normal(1)
+            .unwrap()
+            .derive_normal(7)
+            .unwrap();
+        assert_eq!(
+            public.to_string(),
+            direct.to_extended_public_key().to_string()
+        );
+
+        // Indices must leave room for the hardened bit
+        assert!(master.derive_hardened(0x8000_0000).is_err());
+        assert!(master.derive_normal(0x8000_0000).is_err());
+        assert!(master
+            .to_extended_public_key()
+            .derive_normal(u32::MAX)
+            .is_err());
+    }
+
+    #[test]
+    fn test_account_config() {
+        use wallet::{AccountConfig, AddressLocation};
+
+        let master = ExtendedPrivKey::new_master(&[37u8; 32], Network::Bitcoin).unwrap();
+        let reference = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+        let external = reference.addresses(0, Change::External, 0, 40).unwrap();
+        let internal = reference.addresses(0, Change::Internal, 0, 40).unwrap();
+
+        let config = AccountConfig {
+            gap_limit: 5,
+            lookahead: 3,
+            start_index: 2,
+            ..AccountConfig::default()
+        };
+        let mut wallet = Wallet::new(master.clone(), AddressType::P2wpkh)
+            .unwrap()
+            .with_account_config(config)
+            .unwrap();
+        let account = wallet.account(0).unwrap();
+        assert_eq!(account.config(), &config);
+        assert_eq!(account.watch_range(Change::External), 2..5);
+        let iterated: Vec<(u32, String)> = account
+            .address_iter(AddressType::P2wpkh, Change::External)
+            .unwrap()
+            .take(2)
+            .collect();
+        assert_eq!(
+            iterated,
+            vec![(2, external[2].clone()), (3, external[3].clone())]
+        );
+        assert_eq!(wallet.next_address(Change::External).unwrap(), external[2]);
+
+        // Ownership checks only look inside the watched range
+        assert_eq!(
+            wallet.find_address(&external[5]).unwrap(),
+            Some(AddressLocation {
+                account: 0,
+                change: Change::External,
+                index: 5
+            })
+        );
+        assert_eq!(wallet.find_address(&external[1]).unwrap(), None);
+        assert_eq!(wallet.find_address(&external[6]).unwrap(), None);
+
+        // Discovery stops after gap_limit unused addresses
+        let used = [
+            external[4].clone(),
+            external[9].clone(),
+            external[15].clone(),
+            internal[3].clone(),
+        ];
+        wallet
+            .discover(|address| used.iter().any(|u| u == address))
+            .unwrap();
+        let account = wallet.account(0).unwrap();
+        assert_eq!(account.next_index(Change::External), 10);
+        assert_eq!(account.next_index(Change::Internal), 4);
+
+        // Per-account overrides
+        wallet
+            .set_account_config(
+                0,
+                AccountConfig {
+                    gap_limit: 20,
+                    ..config
+                },
+            )
+            .unwrap();
+        wallet
+            .discover(|address| used.iter().any(|u| u == address))
+            .unwrap();
+        assert_eq!(wallet.account(0).unwrap().next_index(Change::External), 16);
+
+        assert!(wallet
+            .set_account_config(
+                0,
+                AccountConfig {
+                    gap_limit: 0,
+                    ..config
+                }
+            )
+            .is_err());
+        assert!(wallet.set_account_config(9, config).is_err());
+    }
+
+    #[test]
+    fn test_shuffled_change_selection() {
+        use std::collections::BTreeSet;
+        use wallet::{AccountConfig, ChangeSelection};
+
+        let master = ExtendedPrivKey::new_master(&[41u8; 32], Network::Bitcoin).unwrap();
+        let config = AccountConfig {
+            gap_limit: 5,
+            change_selection: ChangeSelection::Shuffled,
+            ..AccountConfig::default()
+        };
+        let new_wallet = || {
+            Wallet::new(master.clone(), AddressType::P2wpkh)
+                .unwrap()
+                .with_account_config(config)
+                .unwrap()
+        };
+        let internal = new_wallet().addresses(0, Change::Internal, 0, 10).unwrap();
+
+        let mut wallet = new_wallet();
+        let issued: Vec<String> = (0..7)
+            .map(|_| wallet.next_address(Change::Internal).unwrap())
+            .collect();
+        let indices: Vec<usize> = issued
+            .iter()
+            .map(|address| internal.iter().position(|a| a == address).unwrap())
+            .collect();
+
+        // Each window of gap_limit indices is used up, in shuffled order, before the next
+        let first: BTreeSet<usize> = indices[..5].iter().copied().collect();
+        assert_eq!(first, (0..5).collect());
+        assert_ne!(indices[..5], [0, 1, 2, 3, 4]);
+        assert!(indices[5..].iter().all(|&i| (5..10).contains(&i)));
+
+        // The order is reproducible from the seed
+        let mut again = new_wallet();
+        for address in &issued {
+            assert_eq!(&again.next_address(Change::Internal).unwrap(), address);
+        }
+
+        // Receive addresses stay sequential and issued change stays watched
+        assert_eq!(
+            wallet.next_address(Change::External).unwrap(),
+            new_wallet().addresses(0, Change::External, 0, 1).unwrap()[0]
+        );
+        for address in &issued {
+            assert!(wallet.find_address(address).unwrap().is_some());
+        }
+
+        // Discovery with the same gap limit recovers past every issued index
+        let mut restored = new_wallet();
+        restored
+            .discover(|address| issued.iter().any(|a| a == address))
+            .unwrap();
+        let next = restored.next_address(Change::Internal).unwrap();
+        assert!(!issued.contains(&next));
+
+        // The cached window order follows gap limit changes
+        let smaller = AccountConfig {
+            gap_limit: 3,
+            ..config
+        };
+        let mut resized = new_wallet();
+        resized.next_address(Change::Internal).unwrap();
+        resized.set_account_config(0, smaller).unwrap();
+        let mut fresh = new_wallet().with_account_config(smaller).unwrap();
+        fresh.next_address(Change::Internal).unwrap();
+        assert_eq!(
+            resized.next_address(Change::Internal).unwrap(),
+            fresh.next_address(Change::Internal).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_mnemonic_reencode() {
+        use reencode::{reencode_hex, Reencoding};
+
+        let short = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap();
+        let long = short.reencode(MnemonicType::Words24, false).unwrap();
+        assert_eq!(long.reencoding, Reencoding::ZeroPadded { added_bits: 128 });
+        assert!(long.changes_seed());
+        assert_eq!(
+            long.mnemonic.phrase(),
+            format!("{} art", vec!["abandon"; 23].join(" "))
+        );
+
+        // Padding is undone by truncating back
+        assert!(long
+            .mnemonic
+            .reencode(MnemonicType::Words12, false)
+            .is_err());
+        let back = long.mnemonic.reencode(MnemonicType::Words12, true).unwrap();
+        assert_eq!(back.reencoding, Reencoding::Truncated { dropped_bits: 128 });
+        assert_eq!(back.mnemonic, short);
+
+        let same = short.reencode(MnemonicType::Words12, false).unwrap();
+        assert_eq!(same.reencoding, Reencoding::Identical);
+        assert!(!same.changes_seed());
+
+        let truncated = reencode_hex(
+            &"7f".repeat(32),
+            MnemonicType::Words12,
+            Language::English,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            truncated.mnemonic.phrase(),
+            "legal winner thank year wave sausage worth useful legal winner thank yellow"
+        );
+        assert!(reencode_hex("7f7f", MnemonicType::Words12, Language::English, true).is_err());
+        assert!(reencode_hex("zz", MnemonicType::Words12, Language::English, true).is_err());
+    }
+
+    #[test]
+    fn test_generate_passphrase() {
+        use passphrase::{generate_passphrase, PassphraseOptions};
+
+        let generated = generate_passphrase(&PassphraseOptions::default()).unwrap();
+        let words: Vec<&str> = generated.passphrase.split(' ').collect();
+        assert_eq!(words.len(), 6);
+        assert!(words
+            .iter()
+            .all(|word| Language::English.wordlist().contains(word)));
+        assert_eq!(generated.entropy_bits, 66);
+
+        let options = PassphraseOptions {
+            separator: "-".to_string(),
+            ..PassphraseOptions::for_entropy_bits(80)
+        };
+        assert_eq!(options.words, 8);
+        let generated = generate_passphrase(&options).unwrap();
+        assert_eq!(generated.passphrase.split('-').count(), 8);
+        assert_eq!(generated.entropy_bits, 88);
+        assert_ne!(generate_passphrase(&options).unwrap(), generated);
+
+        assert!(generate_passphrase(&PassphraseOptions {
+            words: 0,
+            ..PassphraseOptions::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_social_backup() {
+        use socialbackup::SocialBackup;
+
+        let secp = utils::secp();
+        let keys: Vec<SecretKey> = (1..=4u8)
+            .map(|i| SecretKey::from_slice(&[i; 32]).unwrap())
+            .collect();
+        let recipients: Vec<PublicKey> = keys
+            .iter()
+            .map(|key| PublicKey::from_secret_key(secp, key))
+            .collect();
+        let seed = [0x5au8; 64];
+
+        let backup = SocialBackup::create(&seed, &recipients, 2).unwrap();
+        let backup = SocialBackup::from_bytes(&backup.to_bytes()).unwrap();
+        assert_eq!(backup.threshold, 2);
+
+        // Each recipient holds a share in the 3 groups of 2 they belong to
+        let shares: Vec<_> = keys
+            .iter()
+            .map(|key| backup.decrypt_shares(key).unwrap())
+            .collect();
+        assert!(shares.iter().all(|s| s.len() == 3));
+
+        // Any two recipients recover the seed, one alone does not
+        let pair: Vec<_> = shares[1].iter().chain(&shares[3]).cloned().collect();
+        assert_eq!(backup.recover(&pair).unwrap(), seed);
+        assert!(backup.recover(&shares[2]).is_err());
+
+        // Tampered shares or outsiders fail
+        let mut tampered = pair.clone();
+        for share in &mut tampered[..3] {
+            share.share[0] ^= 1;
+        }
+        assert!(backup.recover(&tampered).is_err());
+        let outsider = SecretKey::from_slice(&[9u8; 32]).unwrap();
+        assert!(backup.decrypt_shares(&outsider).is_err());
+
+        assert!(SocialBackup::create(&seed, &recipients, 5).is_err());
+        assert!(SocialBackup::create(&seed, &recipients, 0).is_err());
+        let bytes = backup.to_bytes();
+        assert!(SocialBackup::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_ecies() {
+        let master = ExtendedPrivKey::new_master(&[43u8; 32], Network::Bitcoin).unwrap();
+        let path = DerivationPath::from_str("m/0'/7").unwrap();
+        let bob = master.derive_path(&path).unwrap();
+        let bob_xpub = bob.to_extended_public_key();
+
+        let message = bob_xpub.ecies_encrypt(b"meet at noon").unwrap();
+        assert_eq!(message.len(), ecies::OVERHEAD + 12);
+        assert_eq!(bob.ecies_decrypt(&message).unwrap(), b"meet at noon");
+
+        // Fresh ephemeral keys make every ciphertext different
+        assert_ne!(bob_xpub.ecies_encrypt(b"meet at noon").unwrap(), message);
+
+        let mallory = master.derive_hardened(1).unwrap();
+        assert!(mallory.ecies_decrypt(&message).is_err());
+        let mut tampered = message.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(bob.ecies_decrypt(&tampered).is_err());
+        assert!(bob.ecies_decrypt(&message[..40]).is_err());
+
+        let empty = ecies::encrypt(&bob_xpub.public_key, b"").unwrap();
+        assert!(ecies::decrypt(&bob.private_key, &empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_time_locked_keystore() {
+        use keystore::{KdfLayer, TimeLock, TimeLockedKeystore};
+        use std::time::Duration;
+
+        let layer = KdfLayer {
+            memory_kib: 8,
+            iterations: 1,
+            lanes: 1,
+        };
+        let lock = TimeLock {
+            layers: vec![layer; 3],
+            estimated_unlock_secs: 3600,
+            note: "open after 2030".to_string(),
+        };
+        let seed = [0x42u8; 64];
+        let keystore = TimeLockedKeystore::seal(&seed, "correct horse", lock.clone()).unwrap();
+
+        // Metadata is readable without the passphrase
+        let keystore = TimeLockedKeystore::from_bytes(&keystore.to_bytes()).unwrap();
+        assert_eq!(keystore.lock, lock);
+
+        let mut reports = Vec::new();
+        let opened = keystore
+            .open_with_progress("correct horse", |done, total| reports.push((done, total)))
+            .unwrap();
+        assert_eq!(opened, seed);
+        assert_eq!(reports, vec![(1, 3), (2, 3), (3, 3)]);
+        assert!(keystore.open("wrong horse").is_err());
+
+        // The metadata is authenticated
+        let mut bytes = keystore.to_bytes();
+        let position = bytes.len() - 64 - 16 - 12 - 1;
+        bytes[position] ^= 1;
+        let edited = TimeLockedKeystore::from_bytes(&bytes).unwrap();
+        assert_ne!(edited.lock.note, lock.note);
+        assert!(edited.open("correct horse").is_err());
+
+        let calibrated = TimeLock::calibrate(layer, Duration::from_millis(1)).unwrap();
+        assert!(!calibrated.layers.is_empty());
+        assert!(TimeLockedKeystore::seal(
+            &seed,
+            "x",
+            TimeLock {
+                layers: vec![],
+                ..lock
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_account_statement() {
+        use statement::StatementRange;
+
+        let master = ExtendedPrivKey::new_master(&[47u8; 32], Network::Bitcoin).unwrap();
+        let mut wallet = Wallet::new(master.clone(), AddressType::P2shP2wpkh).unwrap();
+        wallet.rotate().unwrap();
+
+        let statement = wallet
+            .statement(StatementRange {
+                start: 0,
+                count: 2,
+                include_change: true,
+            })
+            .unwrap();
+        assert_eq!(statement.rows.len(), 8);
+        let first = &statement.rows[0];
+        assert_eq!(first.path, "m/49'/0'/0'/0/0");
+        assert_eq!(first.address, wallet.first_address(0).unwrap());
+        assert_eq!(first.script_type, "p2sh-p2wpkh");
+        let last = statement.rows.last().unwrap();
+        assert_eq!((last.account, last.change, last.index), (1, 1, 1));
+        assert_eq!(
+            last.address,
+            master
+                .derive_path(&DerivationPath::from_str(&last.path).unwrap())
+                .unwrap()
+                .to_extended_public_key()
+                .address(AddressType::P2shP2wpkh)
+        );
+
+        let csv = statement.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[0], "account,change,index,path,address,script_type");
+        assert_eq!(
+            lines[1],
+            format!("0,0,0,m/49'/0'/0'/0/0,{},p2sh-p2wpkh", first.address)
+        );
+
+        let receive_only = wallet
+            .statement(StatementRange {
+                start: 5,
+                count: 3,
+                include_change: false,
+            })
+            .unwrap();
+        assert_eq!(receive_only.rows.len(), 6);
+        assert_eq!(receive_only.rows[0].index, 5);
+
+        #[cfg(feature = "serde")]
+        {
+            let value: serde_json::Value =
+                serde_json::from_str(&statement.to_json().unwrap()).unwrap();
+            assert_eq!(value["rows"][0]["script_type"], "p2sh-p2wpkh");
+        }
+    }
+
+    #[test]
+    fn test_deterministic_uuids() {
+        use uuid::Uuid;
+
+        let master = ExtendedPrivKey::new_master(&[53u8; 32], Network::Bitcoin).unwrap();
+        let wallet = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+        let other = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+
+        let account = wallet.account_uuid(0);
+        assert_eq!(account, other.account_uuid(0));
+        assert_eq!(account.version(), 8);
+        assert_eq!(account.0[8] >> 6, 0b10);
+        assert_ne!(account, wallet.account_uuid(1));
+
+        let address = wallet.address_uuid(0, Change::External, 3);
+        assert_eq!(
+            address,
+            Uuid::for_path(
+                master.fingerprint(),
+                &DerivationPath::from_str("m/84h/0h/0h/0/3").unwrap()
+            )
+        );
+        assert_ne!(address, wallet.address_uuid(0, Change::Internal, 3));
+
+        let text = address.to_string();
+        assert_eq!(text.len(), 36);
+        assert_eq!(&text[14..15], "8");
+        assert_eq!(Uuid::from_str(&text).unwrap(), address);
+        assert!(Uuid::from_str("not-a-uuid").is_err());
+    }
+
+    #[test]
+    fn test_rate_limited_signer() {
+        use clock::ManualClock;
+        use ratelimit::{RateLimit, RateLimitedSigner};
+        use std::time::Duration;
+
+        let master = ExtendedPrivKey::new_master(&[59u8; 32], Network::Bitcoin).unwrap();
+        let clock = ManualClock::new(Duration::from_secs(1_700_000_000));
+        let signer = RateLimitedSigner::new(master.clone())
+            .with_global_limit(RateLimit {
+                max_signatures: 3,
+                window: Duration::from_secs(60),
+            })
+            .with_path_limit(RateLimit {
+                max_signatures: 2,
+                window: Duration::from_secs(60),
+            })
+            .with_clock(&clock);
+
+        let a = DerivationPath::from_str("m/0/1").unwrap();
+        let b = DerivationPath::from_str("m/0/2").unwrap();
+        let digest = [7u8; 32];
+        let sign =
+            |path: &DerivationPath| signer.sign_ecdsa(path, &digest, SigningOptions::default());
+
+        let signature = sign(&a).unwrap();
+        let public_key = signer.public_key(&a).unwrap();
+        signer::verify_ecdsa(&public_key, &digest, &signature).unwrap();
+        sign(&a).unwrap();
+        assert!(matches!(sign(&a), Err(Error::RateLimited(_))));
+        sign(&b).unwrap();
+        // The global limit is now reached too
+        assert!(matches!(sign(&b), Err(Error::RateLimited(_))));
+
+        // The window slides
+        clock.advance(Duration::from_secs(61));
+        sign(&a).unwrap();
+
+        let counters = signer.counters();
+        assert_eq!(counters.signed, 4);
+        assert_eq!(counters.rejected, 2);
+        assert_eq!(counters.per_path[&a], 3);
+        assert_eq!(counters.per_path[&b], 1);
+
+        // Per-path tracking is bounded; expired paths make room again
+        let signer = RateLimitedSigner::new(master)
+            .with_path_limit(RateLimit {
+                max_signatures: 1,
+                window: Duration::from_secs(60),
+            })
+            .with_clock(&clock);
+        let path = |i: usize| DerivationPath::from_str(&format!("m/1/{}", i)).unwrap();
+        for i in 0..ratelimit::MAX_TRACKED_PATHS {
+            signer
+                .sign_ecdsa(&path(i), &digest, SigningOptions::default())
+                .unwrap();
+        }
+        let overflow = path(ratelimit::MAX_TRACKED_PATHS);
+        assert!(matches!(
+            signer.sign_ecdsa(&overflow, &digest, SigningOptions::default()),
+            Err(Error::RateLimited(_))
+        ));
+        clock.advance(Duration::from_secs(61));
+        signer
+            .sign_ecdsa(&overflow, &digest, SigningOptions::default())
+            .unwrap();
+        assert!(signer.counters().per_path.len() <= ratelimit::MAX_TRACKED_PATHS);
+    }
+
+    #[test]
+    fn test_two_person_signer() {
+        use dualcontrol::{split_xprv, TwoPersonSigner, XprvShare};
+
+        let master = ExtendedPrivKey::new_master(&[61u8; 32], Network::Bitcoin).unwrap();
+        let [alice, bob] = split_xprv(&master);
+        assert_ne!(alice.to_bytes()[9..], master.encode()[..]);
+
+        // Each person keeps their share under their own password
+        let alice_file = alice.encrypt("alice pw").unwrap();
+        let bob_file = bob.encrypt("bob pw").unwrap();
+        assert!(XprvShare::decrypt(&alice_file, "bob pw").is_err());
+
+        let signer = TwoPersonSigner::new(master.to_extended_public_key());
+        let path = DerivationPath::from_str("m/84'/0'/0'/0/0").unwrap();
+        let digest = [3u8; 32];
+        let sign = || signer.sign_ecdsa(&path, &digest, SigningOptions::default());
+
+        signer
+            .supply(XprvShare::decrypt(&alice_file, "alice pw").unwrap())
+            .unwrap();
+        assert!(!signer.is_armed());
+        assert!(sign().is_err());
+
+        // A failed attempt consumes the supplied share
+        signer.supply(alice.clone()).unwrap();
+        signer
+            .supply(XprvShare::decrypt(&bob_file, "bob pw").unwrap())
+            .unwrap();
+        assert!(signer.is_armed());
+        let signature = sign().unwrap();
+        assert_eq!(
+            signature,
+            master
+                .sign_ecdsa(&path, &digest, SigningOptions::default())
+                .unwrap()
+        );
+
+        // Shares are used once
+        assert!(!signer.is_armed());
+        assert!(sign().is_err());
+
+        // Shares of different splits or keys are refused
+        let [_, other_bob] = split_xprv(&master);
+        signer.supply(alice.clone()).unwrap();
+        assert!(signer.supply(other_bob).is_err());
+        signer.disarm();
+        let stranger = ExtendedPrivKey::new_master(&[62u8; 32], Network::Bitcoin).unwrap();
+        let [s1, s2] = split_xprv(&stranger);
+        signer.supply(s1).unwrap();
+        signer.supply(s2).unwrap();
+        assert!(sign().is_err());
+
+        // Non-hardened public keys come from the xpub without using shares
+        assert_eq!(signer.key_fingerprint(), master.fingerprint());
+        signer.supply(alice.clone()).unwrap();
+        signer.supply(bob.clone()).unwrap();
+        let receive = DerivationPath::from_str("m/0/7").unwrap();
+        assert_eq!(
+            signer.public_key(&receive).unwrap(),
+            master.public_key(&receive).unwrap()
+        );
+        assert!(signer.is_armed());
+        assert_eq!(
+            signer.public_key(&path).unwrap(),
+            master.public_key(&path).unwrap()
+        );
+        assert!(!signer.is_armed());
+    }
+
+    #[test]
+    fn test_additive_key_shares() {
+        use keyshare::{combine_additive, split_additive, AdditiveShare, SharedKeySigner};
+
+        let master = ExtendedPrivKey::new_master(&[63u8; 32], Network::Bitcoin).unwrap();
+        let account = master
+            .derive_path(&DerivationPath::from_str("m/84'/0'/0'").unwrap())
+            .unwrap();
+        let shares = split_additive(&account, 3).unwrap();
+        assert!(split_additive(&account, 1).is_err());
+
+        // The public shares add up to the key's public key
+        let points: Vec<PublicKey> = shares.iter().map(AdditiveShare::public_share).collect();
+        let refs: Vec<&PublicKey> = points.iter().collect();
+        assert_eq!(
+            PublicKey::combine_keys(&refs).unwrap(),
+            account.to_extended_public_key().public_key
+        );
+
+        let combined = combine_additive(&shares).unwrap();
+        assert_eq!(combined.encode(), account.encode());
+        assert!(combine_additive(&shares[..2]).is_err());
+        let round_trip = AdditiveShare::from_bytes(&shares[0].to_bytes()).unwrap();
+        assert!(combine_additive(&[round_trip, shares[1].clone(), shares[0].clone()]).is_err());
+        assert_eq!((shares[2].index(), shares[2].count()), (3, 3));
+        for (index, count) in [(0, 3), (4, 3)] {
+            let mut bytes = shares[0].to_bytes();
+            bytes[..2].copy_from_slice(&[index, count]);
+            assert!(AdditiveShare::from_bytes(&bytes).is_err());
+        }
+
+        let signer = SharedKeySigner::new(account.to_extended_public_key());
+        let path = DerivationPath::from_str("m/0/7").unwrap();
+        // Unhardened public keys need no shares
+        assert_eq!(
+            signer.public_key(&path).unwrap(),
+            account.public_key(&path).unwrap()
+        );
+
+        let digest = [9u8; 32];
+        for share in &shares {
+            assert!(!signer.is_complete());
+            signer.supply(share.clone()).unwrap();
+        }
+        assert!(signer.is_complete());
+        assert_eq!(
+            signer
+                .sign_ecdsa(&path, &digest, SigningOptions::default())
+                .unwrap(),
+            account
+                .sign_ecdsa(&path, &digest, SigningOptions::default())
+                .unwrap()
+        );
+        // Shares are consumed by each signature
+        assert!(signer
+            .sign_ecdsa(&path, &digest, SigningOptions::default())
+            .is_err());
+
+        let other = split_additive(&master, 2).unwrap();
+        assert!(signer.supply(other[0].clone()).is_err());
+    }
+
+    #[cfg(feature = "remote-signer")]
+    #[test]
+    fn test_remote_signer() {
+        use remote::{RemoteSigner, SignerServer};
+        use std::net::{TcpListener, TcpStream};
+
+        let master = ExtendedPrivKey::new_master(&[64u8; 32], Network::Bitcoin).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn({
+            let master = master.clone();
+            move || {
+                let (mut connection, _) = listener.accept().unwrap();
+                SignerServer::new(master).serve(&mut connection)
+            }
+        });
+
+        let signer = RemoteSigner::new(TcpStream::connect(addr).unwrap());
+        let path = DerivationPath::from_str("m/84'/0'/0'/0/3").unwrap();
+        let digest = [5u8; 32];
+        assert_eq!(
+            signer.public_key(&path).unwrap(),
+            master.public_key(&path).unwrap()
+        );
+        for options in [SigningOptions::default(), SigningOptions::bitcoin_core()] {
+            assert_eq!(
+                signer.sign_ecdsa(&path, &digest, options).unwrap(),
+                master.sign_ecdsa(&path, &digest, options).unwrap()
+            );
+        }
+
+        // Server-side failures come back as errors without closing the connection
+        let out_of_range = DerivationPath {
+            path: vec![ChildNumber::Normal(u32::MAX)],
+        };
+        assert!(matches!(
+            signer.public_key(&out_of_range),
+            Err(Error::Remote(_))
+        ));
+        assert!(signer.public_key(&path).is_ok());
+
+        drop(signer.into_inner());
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_audited_signer() {
+        use audit::{AuditLog, AuditOperation, AuditedSigner};
+        use clock::ManualClock;
+        use std::time::Duration;
+
+        let master = ExtendedPrivKey::new_master(&[65u8; 32], Network::Bitcoin).unwrap();
+        let clock = ManualClock::new(Duration::from_secs(1_700_000_000));
+        let signer = AuditedSigner::new(master.clone()).with_clock(&clock);
+        let path = DerivationPath::from_str("m/84'/0'/0'/0/0").unwrap();
+        let digest = [7u8; 32];
+
+        let key = signer.public_key(&path).unwrap();
+        clock.advance(Duration::from_secs(5));
+        signer
+            .sign_ecdsa(&path, &digest, SigningOptions::default())
+            .unwrap();
+        signer.anti_exfil_sign(&path, &digest, &[1u8; 32]).unwrap();
+
+        let log = signer.log().clone();
+        log.verify().unwrap();
+        let entries = log.entries();
+        assert_eq!(entries[0].operation, AuditOperation::Derive);
+        assert_eq!(
+            entries[0].result_fingerprint.as_deref(),
+            Some(hex::encode(&utils::pubkey_hash(&key)[..4]).as_str())
+        );
+        assert_eq!(entries[2].operation, AuditOperation::AntiExfilSign);
+        assert_eq!(entries[1].timestamp_ms, 1_700_000_005_000);
+        assert_eq!(
+            entries[1].digest.as_deref(),
+            Some(hex::encode(digest).as_str())
+        );
+        assert_eq!(entries[1].prev_hash, entries[0].hash);
+        assert_eq!(log.head(), entries.last().unwrap().hash);
+        assert_eq!(log.to_csv().lines().count(), entries.len() + 1);
+
+        // Editing or dropping an entry breaks the chain
+        let mut edited = entries.to_vec();
+        edited[1].digest = Some(hex::encode([8u8; 32]));
+        assert!(AuditLog::from_entries(edited).is_err());
+        assert!(AuditLog::from_entries(entries[1..].to_vec()).is_err());
+        assert_eq!(AuditLog::from_entries(entries.to_vec()).unwrap(), log);
+
+        // Failures are logged too
+        let locked = AuditedSigner::new(dualcontrol::TwoPersonSigner::new(
+            master.to_extended_public_key(),
+        ));
+        assert!(locked
+            .sign_ecdsa(&path, &digest, SigningOptions::default())
+            .is_err());
+        let failed = locked.log().entries()[0].clone();
+        assert!(failed.result_fingerprint.is_none() && failed.error.is_some());
+
+        // Taking the log for archiving continues the chain in a fresh one
+        let archived = signer.take_log();
+        assert_eq!(archived, log);
+        assert!(signer.log().entries().is_empty());
+        signer.public_key(&path).unwrap();
+        let current = signer.log().clone();
+        current.verify().unwrap();
+        assert!(current.follows(&archived));
+        assert_eq!(current.entries()[0].sequence, 3);
+        assert_eq!(current.entries()[0].prev_hash, archived.head());
+
+        // A full log refuses operations until it is taken
+        let bounded = AuditedSigner::new(master.clone()).with_max_entries(1);
+        bounded.public_key(&path).unwrap();
+        assert!(bounded.public_key(&path).is_err());
+        assert_eq!(bounded.take_log().entries().len(), 1);
+        bounded.public_key(&path).unwrap();
+
+        #[cfg(feature = "serde")]
+        {
+            let json = log.to_json().unwrap();
+            let parsed: AuditLog = serde_json::from_str(&json).unwrap();
+            parsed.verify().unwrap();
+            let parsed: AuditLog = serde_json::from_str(&current.to_json().unwrap()).unwrap();
+            assert!(parsed.follows(&archived));
+
+            // Deserializing checks the chain
+            let tampered = json.replace(&hex::encode(digest), &hex::encode([8u8; 32]));
+            assert!(serde_json::from_str::<AuditLog>(&tampered).is_err());
+        }
+    }
+
+    #[test]
+    fn test_decoy_enumeration() {
+        use decoy::enumerate_decoys;
+        use store::{MemoryStore, WalletStore};
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+        let candidates = ["duress", "vault"];
+
+        // Only the "vault" wallet has been used and saved
+        let mut vault = Wallet::from_seed(
+            &mnemonic.to_seed("vault"),
+            Network::Bitcoin,
+            AddressType::P2wpkh,
+        )
+        .unwrap();
+        vault.next_address(Change::External).unwrap();
+        let vault_store = MemoryStore::new();
+        vault.save(&vault_store).unwrap();
+        let vault_fingerprint = vault.master().fingerprint();
+
+        let decoys = enumerate_decoys(
+            &mnemonic,
+            &candidates,
+            Network::Bitcoin,
+            AddressType::P2wpkh,
+            |fingerprint| {
+                if *fingerprint == vault_fingerprint {
+                    vault_store.load()
+                } else {
+                    Ok(None)
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(decoys.len(), 3);
+        assert_eq!(decoys[0].candidate, None);
+        assert_eq!(hex::encode(decoys[0].fingerprint), "73c5da0a");
+        assert_eq!(
+            decoys[0].xpub.to_string(),
+            "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V"
+        );
+        assert_eq!(decoys[2].candidate, Some(1));
+        assert_eq!(decoys[2].fingerprint, vault_fingerprint);
+        let activated: Vec<bool> = decoys.iter().map(|d| d.activated).collect();
+        assert_eq!(activated, [false, false, true]);
+    }
+
+    #[cfg(feature = "fido2")]
+    #[test]
+    fn test_fido2_keystore() {
+        use fido2::{Fido2Keystore, HmacSecret};
+        use keystore::WrappedKeystore;
+
+        /// Software stand-in for an authenticator's hmac-secret extension
+        struct SoftAuthenticator([u8; 32]);
+
+        impl HmacSecret for SoftAuthenticator {
+            fn hmac_secret(
+                &self,
+                credential_id: &[u8],
+                salt: &[u8; 32],
+            ) -> Result<[u8; 32], Error> {
+                if credential_id != b"cred-1" {
+                    return Err(Error::Unsupported("Unknown credential".to_string()));
+                }
+                Ok(utils::hmac_sha512(&self.0, salt)[..32].try_into().unwrap())
+            }
+        }
+
+        let seed = [66u8; 64];
+        let device = SoftAuthenticator([1u8; 32]);
+        let keystore = Fido2Keystore::seal(&seed, &device, b"cred-1").unwrap();
+        let decoded = Fido2Keystore::from_bytes(&keystore.to_bytes()).unwrap();
+        assert_eq!(decoded, keystore);
+        assert_eq!(decoded.open(&device).unwrap(), seed);
+        assert!(decoded.open(&SoftAuthenticator([2u8; 32])).is_err());
+        assert!(Fido2Keystore::seal(&seed, &device, b"cred-2").is_err());
+
+        // The underlying envelope can be re-wrapped without touching the seed
+        let mut wrapped = WrappedKeystore::seal(&seed, &[3u8; 32]).unwrap();
+        wrapped.rewrap(&[3u8; 32], &[4u8; 32]).unwrap();
+        assert!(wrapped.open(&[3u8; 32]).is_err());
+        let wrapped = WrappedKeystore::from_bytes(&wrapped.to_bytes()).unwrap();
+        assert_eq!(wrapped.open(&[4u8; 32]).unwrap(), seed);
+    }
+
+    #[test]
+    fn test_keychain_wrapped_keystore() {
+        use keychain::{MemorySecretBackend, SecretBackend};
+        use keystore::WrappedKeystore;
+
+        let seed = [67u8; 64];
+        let backend = MemorySecretBackend::new();
+        let keystore =
+            WrappedKeystore::seal_with_backend(&seed, &backend, "bip32hdwallet", "main").unwrap();
+        assert_eq!(
+            keystore
+                .open_with_backend(&backend, "bip32hdwallet", "main")
+                .unwrap(),
+            seed
+        );
+        assert!(keystore
+            .open_with_backend(&backend, "bip32hdwallet", "other")
+            .is_err());
+
+        // Sealing another keystore under the same entry is refused, leaving
+        // the existing wrapping key in place
+        assert!(
+            WrappedKeystore::seal_with_backend(&[68u8; 64], &backend, "bip32hdwallet", "main")
+                .is_err()
+        );
+        assert_eq!(
+            keystore
+                .open_with_backend(&backend, "bip32hdwallet", "main")
+                .unwrap(),
+            seed
+        );
+
+        backend.delete("bip32hdwallet", "main").unwrap();
+        assert!(backend.get("bip32hdwallet", "main").unwrap().is_none());
+        WrappedKeystore::seal_with_backend(&seed, &backend, "bip32hdwallet", "main").unwrap();
+    }
+
+    #[test]
+    fn test_descriptor_derivation() {
+        use descriptor::Descriptor;
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = Mnemonic::from_phrase(phrase, Language::English)
+            .unwrap()
+            .to_seed("");
+        let master = ExtendedPrivKey::new_master(seed.as_bytes(), Network::Bitcoin).unwrap();
+        let receive =
+            Descriptor::for_account(&master, AddressType::P2wpkh, 0, Change::External).unwrap();
+        assert_eq!(
+            receive.derive_address(0).unwrap(),
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+        assert_eq!(
+            hex::encode(receive.derive_script_pubkey(0).unwrap()),
+            "0014c0cebcd6c3d3ca8c75dc5ec62ebe55330ef910e2"
+        );
+        assert!(receive.derive_address(0x8000_0000).is_err());
+
+        // A multipath descriptor expands to the receive and change chains
+        let multipath = receive.to_multipath_string();
+        let chains = Descriptor::parse_multipath(&multipath).unwrap();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0].to_string(), receive.to_string());
+        assert_eq!(
+            chains[1].derive_address(0).unwrap(),
+            "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el"
+        );
+        assert!(multipath.parse::<Descriptor>().is_err());
+        assert_eq!(
+            Descriptor::parse_multipath(&receive.to_string())
+                .unwrap()
+                .len(),
+            1
+        );
+
+        for steps in ["<0;0>", "<0;1;1>", "<0;1;0>"] {
+            let bad = multipath.split('#').next().unwrap().replace("<0;1>", steps);
+            assert!(Descriptor::parse_multipath(&bad).is_err());
+        }
+        let hardened = receive
+            .to_string()
+            .split('#')
+            .next()
+            .unwrap()
+            .replace("/*)", "/*')");
+        assert!(Descriptor::parse_multipath(&hardened).is_err());
+    }
+
+    #[cfg(feature = "miniscript")]
+    #[test]
+    fn test_miniscript_policy() {
+        use miniscript::{Policy, PolicyKey};
+        use std::collections::BTreeMap;
+
+        let master = ExtendedPrivKey::new_master(&[68u8; 32], Network::Bitcoin).unwrap();
+        let mut keys = BTreeMap::new();
+        let mut pubkeys = Vec::new();
+        for (name, account) in [("A", 0), ("B", 1), ("C", 2)] {
+            let path = DerivationPath::from_str(&format!("m/48'/0'/{}'/2'", account)).unwrap();
+            let key = PolicyKey::from_master(&master, &path).unwrap();
+            pubkeys.push(hex::encode(
+                key.xpub
+                    .derive_path(&DerivationPath::from_str("m/0/5").unwrap())
+                    .unwrap()
+                    .public_key
+                    .serialize(),
+            ));
+            keys.insert(name.to_string(), key);
+        }
+        let (a, b, c) = (&pubkeys[0], &pubkeys[1], &pubkeys[2]);
+        let script = |policy: &str| {
+            let compiled = policy.parse::<Policy>().unwrap().compile(&keys).unwrap();
+            hex::encode(compiled.witness_script(Change::External, 5).unwrap())
+        };
+
+        assert_eq!(script("and(pk(A),pk(B))"), format!("21{}ad21{}ac", a, b));
+        // and_v(v:and_v(v:pk(A),pk(B)),pk(C)), as Bitcoin Core and
+        // rust-miniscript encode it
+        assert_eq!(
+            script("and(and(pk(A),pk(B)),pk(C))"),
+            format!("21{}ad21{}ad21{}ac", a, b, c)
+        );
+        assert_eq!(
+            script("or(pk(A),pk(B))"),
+            format!("21{}ac736421{}ac68", a, b)
+        );
+        assert_eq!(
+            script("thresh(2,pk(A),pk(B),pk(C))"),
+            format!("5221{}21{}21{}53ae", a, b, c)
+        );
+        assert_eq!(
+            script("thresh(1,pk(A),or(pk(B),pk(C)))"),
+            format!("21{}ac6b21{}ac736421{}ac686c935187", a, b, c)
+        );
+        assert!("thresh(2,pk(A),and(pk(B),pk(C)))"
+            .parse::<Policy>()
+            .unwrap()
+            .compile(&keys)
+            .is_err());
+        assert!("and(pk(A),pk(D))"
+            .parse::<Policy>()
+            .unwrap()
+            .compile(&keys)
+            .is_err());
+        assert!("thresh(4,pk(A),pk(B),pk(C))".parse::<Policy>().is_err());
+        assert!("or(pk(A),pk(A))"
+            .parse::<Policy>()
+            .unwrap()
+            .compile(&keys)
+            .is_err());
+
+        let policy: Policy = "or(pk(A), and(pk(B), pk(C)))".parse().unwrap();
+        assert_eq!(policy.to_string(), "or(pk(A),and(pk(B),pk(C)))");
+        let compiled = policy.compile(&keys).unwrap();
+        let descriptor = compiled.descriptor(Change::Internal);
+        let body = descriptor::verify_checksum(&descriptor).unwrap();
+        assert!(body.starts_with("wsh(or_d(pk(["));
+        assert!(body.contains("and_v(v:pk(["));
+        assert!(body.contains("/1/*"));
+
+        let script_pubkey = compiled.script_pubkey(Change::External, 0).unwrap();
+        assert_eq!(&script_pubkey[..2], &[0x00, 0x20]);
+        let address = compiled.address(Change::External, 0).unwrap();
+        assert!(address.starts_with("bc1q") && address.len() == 62);
+    }
+
+    #[cfg(feature = "miniscript")]
+    #[test]
+    fn test_miniscript_timelocks() {
+        use miniscript::{Policy, PolicyKey};
+        use std::collections::BTreeMap;
+
+        let owner = ExtendedPrivKey::new_master(&[69u8; 32], Network::Bitcoin).unwrap();
+        let heir = ExtendedPrivKey::new_master(&[70u8; 32], Network::Bitcoin).unwrap();
+        let path = DerivationPath::from_str("m/48'/0'/0'/2'").unwrap();
+        let keys = BTreeMap::from([
+            (
+                "Owner".to_string(),
+                PolicyKey::from_master(&owner, &path).unwrap(),
+            ),
+            (
+                "Heir".to_string(),
+                PolicyKey::from_master(&heir, &path).unwrap(),
+            ),
+        ]);
+        let pubkey = |key: &ExtendedPrivKey| {
+            let full = path.to_string() + "/0/3";
+            hex::encode(
+                key.derive_path(&DerivationPath::from_str(&full).unwrap())
+                    .unwrap()
+                    .to_extended_public_key()
+                    .public_key
+                    .serialize(),
+            )
+        };
+
+        // The heir can spend alone once the coins are a year old (52560 blocks)
+        let policy: Policy = "or(pk(Owner),and(pk(Heir),older(52560)))".parse().unwrap();
+        let compiled = policy.compile(&keys).unwrap();
+        assert_eq!(
+            hex::encode(compiled.witness_script(Change::External, 3).unwrap()),
+            format!(
+                "21{}ac736421{}ad0350cd00b268",
+                pubkey(&owner),
+                pubkey(&heir)
+            )
+        );
+        let descriptor = compiled.descriptor(Change::External);
+        assert!(descriptor.contains("and_v(v:pk(") && descriptor.contains("),older(52560))"));
+
+        let after: Policy = "and(after(900000),pk(Heir))".parse().unwrap();
+        assert_eq!(
+            hex::encode(
+                after
+                    .compile(&keys)
+                    .unwrap()
+                    .witness_script(Change::External, 3)
+                    .unwrap()
+            ),
+            format!("03a0bb0db16921{}ac", pubkey(&heir))
+        );
+
+        assert!("older(0)".parse::<Policy>().is_err());
+        assert!("after(2147483648)".parse::<Policy>().is_err());
+        assert!("older(10)"
+            .parse::<Policy>()
+            .unwrap()
+            .compile(&keys)
+            .is_err());
+        assert!("thresh(1,pk(Owner),older(10))"
+            .parse::<Policy>()
+            .unwrap()
+            .compile(&keys)
+            .is_err());
+    }
+
+    #[test]
+    fn test_psbt_from_utxos() {
+        use descriptor::Descriptor;
+        use psbt::{AccountKey, OutPoint, Psbt, TxOut, Utxo, SEQUENCE_RBF};
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = Mnemonic::from_phrase(phrase, Language::English)
+            .unwrap()
+            .to_seed("");
+        let master = ExtendedPrivKey::new_master(seed.as_bytes(), Network::Bitcoin).unwrap();
+        let receive =
+            Descriptor::for_account(&master, AddressType::P2wpkh, 0, Change::External).unwrap();
+        let account = AccountKey::from(&receive);
+
+        let utxos: Vec<Utxo> = (0..2)
+            .map(|index| Utxo {
+                outpoint: format!("{}:{}", hex::encode([index as u8 + 1; 32]), index)
+                    .parse()
+                    .unwrap(),
+                amount: 50_000,
+                script_pubkey: receive.derive_script_pubkey(index).unwrap(),
+                path: DerivationPath::from_str(&format!("m/84'/0'/0'/0/{}", index)).unwrap(),
+            })
+            .collect();
+        let payment = TxOut {
+            amount: 70_000,
+            script_pubkey: hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap(),
+        };
+        let change_path = DerivationPath::from_str("m/84'/0'/0'/1/0").unwrap();
+        let psbt = Psbt::from_utxos(
+            &account,
+            &utxos,
+            std::slice::from_ref(&payment),
+            Some((&change_path, 29_000)),
+        )
+        .unwrap();
+
+        assert_eq!(psbt.fee().unwrap(), 1_000);
+        assert_eq!(psbt.inputs[0].sequence, SEQUENCE_RBF);
+        assert_eq!(
+            psbt.inputs[1].bip32_derivation[0].origin.to_string(),
+            "73c5da0a/84'/0'/0'/0/1"
+        );
+        let change = &psbt.outputs[1];
+        assert_eq!(change.bip32_derivation[0].origin.path, change_path);
+        assert_eq!(
+            address::encode(
+                &change.bip32_derivation[0].public_key,
+                AddressType::P2wpkh,
+                Network::Bitcoin
+            ),
+            "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el"
+        );
+
+        // Version 2, two inputs with the txid byte-reversed, RBF sequences
+        let tx = hex::encode(psbt.unsigned_tx());
+        assert!(tx.starts_with(&format!("0200000002{}00000000", hex::encode([1u8; 32]))));
+        assert!(tx.contains("00fdffffff"));
+        assert!(tx.ends_with("00000000"));
+
+        let encoded = psbt.to_base64();
+        assert!(encoded.starts_with("cHNidP8B"));
+        let parsed = Psbt::from_base64(&encoded).unwrap();
+        assert_eq!(parsed, psbt);
+        assert_eq!(parsed.txid(), psbt.txid());
+        assert_eq!(
+            OutPoint::from_str(&utxos[1].outpoint.to_string()).unwrap(),
+            utxos[1].outpoint
+        );
+
+        // The script must belong to the key at the given path
+        let mut wrong = utxos.clone();
+        wrong[0].path = DerivationPath::from_str("m/84'/0'/0'/0/9").unwrap();
+        assert!(Psbt::from_utxos(&account, &wrong, std::slice::from_ref(&payment), None).is_err());
+        let mut overspend = payment.clone();
+        overspend.amount = 200_000;
+        assert!(Psbt::from_utxos(&account, &utxos, &[overspend], None).is_err());
+
+        // Nested segwit inputs carry their redeem script
+        let nested =
+            Descriptor::for_account(&master, AddressType::P2shP2wpkh, 0, Change::External).unwrap();
+        let nested_utxo = Utxo {
+            outpoint: utxos[0].outpoint,
+            amount: 10_000,
+            script_pubkey: nested.derive_script_pubkey(0).unwrap(),
+            path: DerivationPath::from_str("m/49'/0'/0'/0/0").unwrap(),
+        };
+        let psbt = Psbt::from_utxos(&AccountKey::from(&nested), &[nested_utxo], &[], None).unwrap();
+        assert_eq!(psbt.inputs[0].redeem_script.as_ref().unwrap().len(), 22);
+        assert_eq!(Psbt::from_bytes(&psbt.to_bytes()).unwrap(), psbt);
+    }
+
+    #[test]
+    fn test_psbt_bump_fee() {
+        use descriptor::Descriptor;
+        use psbt::{AccountKey, Psbt, TxOut, Utxo};
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = Mnemonic::from_phrase(phrase, Language::English)
+            .unwrap()
+            .to_seed("");
+        let master = ExtendedPrivKey::new_master(seed.as_bytes(), Network::Bitcoin).unwrap();
+        let receive =
+            Descriptor::for_account(&master, AddressType::P2wpkh, 0, Change::External).unwrap();
+        let account = AccountKey::from(&receive);
+
+        let utxos: Vec<Utxo> = (0..3)
+            .map(|index| Utxo {
+                outpoint: format!("{}:0", hex::encode([index as u8 + 1; 32]))
+                    .parse()
+                    .unwrap(),
+                amount: 50_000,
+                script_pubkey: receive.derive_script_pubkey(index).unwrap(),
+                path: DerivationPath::from_str(&format!("m/84'/0'/0'/0/{}", index)).unwrap(),
+            })
+            .collect();
+        let payment = TxOut {
+            amount: 70_000,
+            script_pubkey: hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap(),
+        };
+        let change_path = DerivationPath::from_str("m/84'/0'/0'/1/0").unwrap();
+        let mut psbt = Psbt::from_utxos(
+            &account,
+            &utxos[..2],
+            std::slice::from_ref(&payment),
+            Some((&change_path, 29_000)),
+        )
+        .unwrap();
+        // A partial signature from an earlier signing round
+        psbt.inputs[0]
+            .unknown
+            .push((vec![0x02; 34], vec![0x30; 71]));
+        psbt.inputs[1]
+            .unknown
+            .push((vec![0x14; 65], vec![0x40; 64]));
+        assert!(psbt.signals_rbf());
+        assert_eq!(psbt.change_index(&account), Some(1));
+
+        // Paid out of change
+        let bumped = psbt.bump_fee(&account, 3_000, &utxos[2..]).unwrap();
+        assert_eq!(bumped.fee().unwrap(), 3_000);
+        assert_eq!(bumped.inputs.len(), 2);
+        assert!(bumped.inputs[0].unknown.is_empty());
+        assert!(bumped.inputs[1].unknown.is_empty());
+        assert_eq!(bumped.outputs[0], psbt.outputs[0]);
+        assert_eq!(bumped.outputs[1].txout.amount, 27_000);
+        assert_eq!(
+            bumped.outputs[1].bip32_derivation,
+            psbt.outputs[1].bip32_derivation
+        );
+
+        // Change would drop below dust, so another UTXO is brought in
+        let bumped = psbt.bump_fee(&account, 29_900, &utxos[2..]).unwrap();
+        assert_eq!(bumped.fee().unwrap(), 29_900);
+        assert_eq!(bumped.inputs.len(), 3);
+        assert_eq!(bumped.inputs[2].outpoint, utxos[2].outpoint);
+        assert_eq!(
+            bumped.inputs[2].bip32_derivation[0].origin.to_string(),
+            "73c5da0a/84'/0'/0'/0/2"
+        );
+        assert_eq!(bumped.outputs[1].txout.amount, 50_100);
+        let decoded = Psbt::from_base64(&bumped.to_base64()).unwrap();
+        assert_eq!(decoded, bumped);
+
+        assert!(psbt.bump_fee(&account, 1_000, &utxos[2..]).is_err());
+        // The fee must also rise by 1 sat/vB of the 209 vB replacement
+        assert_eq!(psbt.estimated_vsize(AddressType::P2wpkh), 209);
+        assert!(psbt.bump_fee(&account, 1_208, &utxos[2..]).is_err());
+        psbt.bump_fee(&account, 1_209, &utxos[2..]).unwrap();
+        assert!(psbt.bump_fee(&account, 100_000, &utxos[2..]).is_err());
+        let mut final_psbt = psbt.clone();
+        for input in &mut final_psbt.inputs {
+            input.sequence = 0xffff_ffff;
+        }
+        assert!(final_psbt.bump_fee(&account, 3_000, &[]).is_err());
+        let no_change = Psbt::from_utxos(&account, &utxos[..2], &[payment], None).unwrap();
+        assert_eq!(no_change.change_index(&account), None);
+        assert!(no_change.bump_fee(&account, 31_000, &[]).is_err());
+    }
+
+    #[test]
+    fn test_mnemonic_entropy_round_trip() {
+        // BIP-39 test vectors
+        let mnemonic = Mnemonic::from_entropy(&[0u8; 16], Language::English).unwrap();
+        assert_eq!(
+            mnemonic.phrase(),
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+        );
+        let entropy = hex::decode("7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f").unwrap();
+        let mnemonic = Mnemonic::from_entropy(&entropy, Language::English).unwrap();
+        assert_eq!(
+            mnemonic.phrase(),
+            "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal will"
+        );
+        assert_eq!(mnemonic.to_entropy(), entropy);
+
+        for len in [16, 20, 24, 28, 32] {
+            let entropy: Vec<u8> = (0..len as u8).collect();
+            let mnemonic = Mnemonic::from_entropy(&entropy, Language::English).unwrap();
+            assert_eq!(mnemonic.phrase().split(' ').count(), len * 3 / 4);
+            assert_eq!(mnemonic.to_entropy(), entropy);
+            let parsed = Mnemonic::from_phrase(mnemonic.phrase(), Language::English).unwrap();
+            assert_eq!(parsed.to_entropy(), entropy);
+        }
+
+        assert!(matches!(
+            Mnemonic::from_entropy(&[0u8; 17], Language::English),
+            Err(Error::InvalidEntropy(_))
+        ));
+        assert!(Mnemonic::from_entropy(&[], Language::English).is_err());
+    }
+
+    #[test]
+    fn test_all_mnemonic_word_counts() {
+        let expected = [(12, 4), (15, 5), (18, 6), (21, 7), (24, 8)];
+        for (mnemonic_type, (words, checksum)) in MnemonicType::ALL.into_iter().zip(expected) {
+            assert_eq!(MnemonicType::from_word_count(words).unwrap(), mnemonic_type);
+            assert_eq!(mnemonic_type.checksum_bits(), checksum);
+            assert_eq!(
+                mnemonic_type.entropy_bits() + checksum,
+                words * 11,
+                "{:?}",
+                mnemonic_type
+            );
+
+            let mnemonic = Mnemonic::generate(mnemonic_type, Language::English).unwrap();
+            assert_eq!(mnemonic.phrase().split(' ').count(), words);
+            Mnemonic::from_phrase(mnemonic.phrase(), Language::English).unwrap();
+        }
+        assert!(MnemonicType::from_word_count(13).is_err());
+
+        let phrase = "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor accident";
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+        assert_eq!(mnemonic.to_entropy(), [0x80; 20]);
+        let phrase = "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd apart";
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+        assert_eq!(mnemonic.to_entropy(), [0x80; 28]);
+        assert!(matches!(
+            Mnemonic::from_phrase(&phrase.replace("apart", "actor"), Language::English),
+            Err(Error::InvalidMnemonic(MnemonicError::Checksum))
+        ));
+    }
+
+    #[test]
+    fn test_classify_outputs() {
+        use ownership::OutputOwnership;
+        use psbt::{AccountKey, TxOut};
+        use wallet::Wallet;
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = Mnemonic::from_phrase(phrase, Language::English)
+            .unwrap()
+            .to_seed("");
+        let master = ExtendedPrivKey::new_master(seed.as_bytes(), Network::Bitcoin).unwrap();
+        let wallet = Wallet::new(master, AddressType::P2wpkh).unwrap();
+        let [receive, change] = wallet.descriptors(0).unwrap();
+
+        let outputs = vec![
+            TxOut {
+                amount: 70_000,
+                script_pubkey: hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap(),
+            },
+            TxOut {
+                amount: 20_000,
+                script_pubkey: receive.derive_script_pubkey(3).unwrap(),
+            },
+            TxOut {
+                amount: 9_000,
+                script_pubkey: change.derive_script_pubkey(0).unwrap(),
+            },
+        ];
+        let classes = wallet.classify_outputs(&outputs).unwrap();
+        assert_eq!(classes[0], OutputOwnership::External);
+        assert_eq!(
+            classes[1],
+            OutputOwnership::Receive(DerivationPath::from_str("m/84'/0'/0'/0/3").unwrap())
+        );
+        assert_eq!(
+            classes[2],
+            OutputOwnership::Change(DerivationPath::from_str("m/84'/0'/0'/1/0").unwrap())
+        );
+        assert!(!classes[0].is_ours() && classes[2].is_ours());
+
+        // Watch-only, limited to the given indices
+        let account = AccountKey::from(&receive);
+        assert_eq!(account.classify_outputs(&outputs, 0..10).unwrap(), classes);
+        let narrow = account.classify_outputs(&outputs, 0..3).unwrap();
+        assert_eq!(narrow[1], OutputOwnership::External);
+        assert_eq!(narrow[2].path(), classes[2].path());
+    }
+
+    #[test]
+    fn test_electrum_script_hashes() {
+        use electrum::script_hash;
+
+        // Example from the Electrum protocol documentation
+        let script = hex::decode("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap();
+        assert_eq!(
+            script_hash(&script),
+            "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161"
+        );
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = Mnemonic::from_phrase(phrase, Language::English)
+            .unwrap()
+            .to_seed("");
+        let master = ExtendedPrivKey::new_master(seed.as_bytes(), Network::Bitcoin).unwrap();
+        let wallet = Wallet::new(master, AddressType::P2wpkh).unwrap();
+        let xpub = &wallet.account(0).unwrap().xpub;
+        let scripts = xpub
+            .script_pubkeys(Change::Internal, AddressType::P2wpkh, 0, 3)
+            .unwrap();
+        let hashes = xpub
+            .script_hashes(Change::Internal, AddressType::P2wpkh, 0, 3)
+            .unwrap();
+        assert_eq!(hashes[2], script_hash(&scripts[2]));
+
+        // Default lookahead of 20 on both chains
+        let subscriptions = wallet.electrum_subscriptions().unwrap();
+        assert_eq!(subscriptions.len(), 40);
+        assert_eq!(
+            subscriptions[0].address,
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+        let change = &subscriptions[22];
+        assert_eq!(change.location.change, Change::Internal);
+        assert_eq!(change.location.index, 2);
+        assert_eq!(change.script_hash, hashes[2]);
+    }
+
+    #[cfg(feature = "all-languages")]
+    #[test]
+    fn test_wordlist_languages() {
+        for &language in Language::ALL {
+            let wordlist = language.wordlist();
+            assert_eq!(wordlist.len(), 2048, "{:?}", language);
+            assert_eq!(language.index_of(wordlist[2047]), Some(2047));
+
+            let mnemonic = Mnemonic::generate(MnemonicType::Words15, language).unwrap();
+            let parsed = Mnemonic::from_phrase(mnemonic.phrase(), language).unwrap();
+            assert_eq!(parsed.to_entropy(), mnemonic.to_entropy());
+        }
+        assert_eq!(Language::ALL.len(), 10);
+
+        // Japanese vector from the BIP-39 reference test vectors, phrase in NFKD
+        let mnemonic = Mnemonic::from_entropy(&[0u8; 16], Language::Japanese).unwrap();
+        assert_eq!(
+            mnemonic.phrase(),
+            "あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あおそ\u{3099}ら"
+        );
+        assert_eq!(
+            hex::encode(mnemonic.to_seed("㍍ガバヴァぱばぐゞちぢ十人十色").as_bytes()),
+            "a262d6fb6122ecf45be09c50492b31f92e9beb7d9a845987a02cefda57a15f9c467a17872029a9e92299b5cbdf306e3a0ee620245cbd508959b6cb7ca637bd55"
+        );
+
+        // Precomposed accents match the NFKD wordlist
+        let composed = "\u{e1}baco ".repeat(11) + "abierto";
+        let mnemonic = Mnemonic::from_phrase(&composed, Language::Spanish).unwrap();
+        assert_eq!(mnemonic.to_entropy(), [0u8; 16]);
+        assert!(Mnemonic::from_phrase(&composed, Language::English).is_err());
+    }
+
+    #[cfg(all(
+        feature = "french",
+        feature = "spanish",
+        feature = "chinese-simplified",
+        feature = "chinese-traditional"
+    ))]
+    #[test]
+    fn test_detect_language() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        assert_eq!(Language::detect(phrase).unwrap(), Language::English);
+        let spanish = "\u{e1}baco ".repeat(11) + "abierto";
+        let mnemonic = Mnemonic::from_phrase_any_language(&spanish).unwrap();
+        assert_eq!(mnemonic.language(), Language::Spanish);
+
+        // Every word is also French, but only the English checksum verifies
+        let shared =
+            "suspect rival impact surface social muscle bonus village humble coyote exact bicycle";
+        assert_eq!(Language::detect(shared).unwrap(), Language::English);
+        let both = "civil festival festival palace rival concert distance panda junior unique spatial science";
+        assert!(matches!(
+            Language::detect(both),
+            Err(Error::InvalidMnemonic(MnemonicError::AmbiguousLanguage(languages)))
+                if languages == [Language::English, Language::French]
+        ));
+
+        // Characters at the same index in both Chinese lists decode alike
+        let chinese = "泥 首 罐 漫 介 家 棚 共 婚 桌 喜 思";
+        assert_eq!(
+            Language::detect(chinese).unwrap(),
+            Language::ChineseSimplified
+        );
+
+        assert!(matches!(
+            Language::detect("abandon zzz"),
+            Err(Error::InvalidMnemonic(MnemonicError::UnknownWord { position: 1, word }))
+                if word == "zzz"
+        ));
+        assert!(matches!(
+            Language::detect(&phrase.replace("about", "ábaco")),
+            Err(Error::InvalidMnemonic(MnemonicError::MixedLanguages))
+        ));
+        // Detection alone doesn't need a valid checksum
+        let bad_checksum = phrase.replace("about", "zoo");
+        assert_eq!(Language::detect(&bad_checksum).unwrap(), Language::English);
+        assert!(matches!(
+            Mnemonic::from_phrase_any_language(&bad_checksum),
+            Err(Error::InvalidMnemonic(MnemonicError::Checksum))
+        ));
+    }
+
+    #[test]
+    fn test_block_filter_matching() {
+        use blockfilter::{BlockFilter, ScriptMatcher};
+
+        // Testnet genesis block, from the BIP-158 test vectors
+        let mut genesis =
+            hex::decode("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943")
+                .unwrap();
+        genesis.reverse();
+        let genesis: [u8; 32] = genesis.try_into().unwrap();
+        let coinbase = hex::decode("4104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac").unwrap();
+        let filter = BlockFilter::build(&genesis, &[&coinbase]);
+        assert_eq!(hex::encode(filter.as_bytes()), "019dfca8");
+        assert!(filter.match_any(&genesis, &[&coinbase]).unwrap());
+        assert!(!filter.match_any(&genesis, &[vec![0x51]]).unwrap());
+
+        let block_hash: [u8; 32] = std::array::from_fn(|i| i as u8);
+        let scripts: Vec<Vec<u8>> = (0..5u8)
+            .map(|i| utils::sha256(&[i])[..22].to_vec())
+            .collect();
+        let filter = BlockFilter::build(&block_hash, &scripts);
+        assert_eq!(
+            hex::encode(filter.as_bytes()),
+            "058f563d48865da3a73701669973"
+        );
+        let decoded = BlockFilter::new(filter.as_bytes().to_vec());
+        assert!(decoded.match_any(&block_hash, &scripts[3..4]).unwrap());
+
+        let master = ExtendedPrivKey::new_master(&[7u8; 32], Network::Bitcoin).unwrap();
+        let account = master
+            .derive_path(&DerivationPath::from_str("m/84'/0'/0'").unwrap())
+            .unwrap()
+            .to_extended_public_key();
+        let matcher = ScriptMatcher::for_account(&account, AddressType::P2wpkh, 0, 10).unwrap();
+        assert_eq!(matcher.scripts().len(), 20);
+        let mut block = scripts.clone();
+        block.push(matcher.scripts()[13].clone());
+        assert!(matcher
+            .matches(&block_hash, &BlockFilter::build(&block_hash, &block))
+            .unwrap());
+        assert!(!matcher.matches(&block_hash, &filter).unwrap());
+
+        assert!(!BlockFilter::new(vec![0])
+            .match_any(&block_hash, &scripts)
+            .unwrap());
+        assert!(BlockFilter::new(vec![0xfd, 1])
+            .match_any(&block_hash, &scripts)
+            .is_err());
+        assert!(BlockFilter::new(vec![9, 0xff, 0xff])
+            .match_any(&block_hash, &scripts)
+            .is_err());
+    }
+
+    #[test]
+    fn test_node_identity_keys() {
+        use nodekey::{node_identity, NodeKeyFamily};
+
+        let master = ExtendedPrivKey::new_master(&[9u8; 32], Network::Bitcoin).unwrap();
+        let wallet = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+
+        let lightning = wallet.node_identity(NodeKeyFamily::Lightning, 0).unwrap();
+        assert_eq!(lightning.path.to_string(), "m/1017'/0'/6'/0/0");
+        let expected = master
+            .derive_path(&lightning.path)
+            .unwrap()
+            .to_extended_public_key()
+            .public_key;
+        assert_eq!(lightning.public_key, expected);
+        assert_eq!(lightning.node_id(), hex::encode(expected.serialize()));
+        assert_eq!(
+            node_identity(&master, NodeKeyFamily::Lightning, 0).unwrap(),
+            lightning
+        );
+
+        let transport = wallet
+            .node_identity(NodeKeyFamily::P2pTransport, 0)
+            .unwrap();
+        assert_eq!(transport.path.to_string(), "m/1017'/0'/324'/0/0");
+        assert_ne!(transport.public_key, lightning.public_key);
+        assert_ne!(
+            wallet
+                .node_identity(NodeKeyFamily::P2pTransport, 1)
+                .unwrap(),
+            transport
+        );
+
+        let testnet = ExtendedPrivKey::new_master(&[9u8; 32], Network::Testnet).unwrap();
+        let identity = node_identity(&testnet, NodeKeyFamily::Lightning, 0).unwrap();
+        assert_eq!(identity.path.to_string(), "m/1017'/1'/6'/0/0");
+    }
+
+    #[test]
+    fn test_custom_wordlist() {
+        use wordlist::{CustomMnemonic, CustomWordlist, Wordlist};
+
+        let words: Vec<String> = (0..2048).map(|i| format!("w{:04}", i)).collect();
+        let custom = CustomWordlist::new(&words).unwrap();
+        let mnemonic = CustomMnemonic::from_entropy(&[0u8; 16], &custom).unwrap();
+        assert_eq!(mnemonic.phrase(), "w0000 ".repeat(11) + "w0003");
+        assert_eq!(mnemonic.to_entropy(), [0u8; 16]);
+        assert!(CustomMnemonic::from_phrase(&"w0000 ".repeat(12), &custom).is_err());
+
+        // Runtime-selected wordlists, sharing the checksum machinery
+        let dynamic: &dyn Wordlist = &custom;
+        let generated = CustomMnemonic::generate(MnemonicType::Words18, dynamic).unwrap();
+        let parsed = CustomMnemonic::from_phrase(generated.phrase(), dynamic).unwrap();
+        assert_eq!(parsed.to_entropy(), generated.to_entropy());
+
+        // The English list as a custom list behaves like the built-in one
+        let english = CustomWordlist::new(Language::English.wordlist()).unwrap();
+        let entropy = [0x7fu8; 16];
+        let custom = CustomMnemonic::from_entropy(&entropy, english).unwrap();
+        let standard = Mnemonic::from_entropy(&entropy, Language::English).unwrap();
+        assert_eq!(custom.phrase(), standard.phrase());
+        assert_eq!(custom.to_seed("TREZOR"), standard.to_seed("TREZOR"));
+        let built_in = CustomMnemonic::from_phrase(standard.phrase(), Language::English).unwrap();
+        assert_eq!(built_in.wordlist(), &Language::English);
+
+        assert!(CustomWordlist::new(&words[..2047]).is_err());
+        let mut duplicated = words.clone();
+        duplicated[5] = "w0004".to_string();
+        assert!(CustomWordlist::new(&duplicated).is_err());
+        duplicated[5] = "two words".to_string();
+        assert!(CustomWordlist::new(&duplicated).is_err());
+    }
+
+    #[test]
+    fn test_printable_key_export() {
+        use printkey::PrintableKey;
+
+        let key = PrintableKey {
+            secret_key: secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap(),
+            network: Network::Bitcoin,
+            address_type: AddressType::P2wpkh,
+        };
+        let encoded = key.encode();
+        assert!(encoded.starts_with("bkey1"));
+        assert_eq!(PrintableKey::decode(&encoded).unwrap(), key);
+        assert_eq!(
+            key.address(),
+            address::encode(&key.public_key(), AddressType::P2wpkh, Network::Bitcoin)
+        );
+
+        // Grouped and upper-cased transcriptions decode the same
+        let grouped = key.encode_grouped(4);
+        assert_eq!(grouped.len(), encoded.len() + (encoded.len() - 5 - 1) / 4);
+        assert_eq!(grouped.replace('-', ""), encoded);
+        assert_eq!(PrintableKey::decode(&grouped).unwrap(), key);
+        assert_eq!(
+            PrintableKey::decode(&grouped.to_uppercase().replace('-', " ")).unwrap(),
+            key
+        );
+
+        // A single mistyped character is caught
+        let mut typo: Vec<char> = encoded.chars().collect();
+        typo[10] = if typo[10] == 'q' { 'p' } else { 'q' };
+        assert!(PrintableKey::decode(&typo.into_iter().collect::<String>()).is_err());
+
+        let testnet = PrintableKey {
+            network: Network::Testnet,
+            address_type: AddressType::P2pkh,
+            ..key
+        };
+        assert!(testnet.encode().starts_with("tkey1"));
+        assert_eq!(PrintableKey::decode(&testnet.encode()).unwrap(), testnet);
+        let npub = nostr::encode_npub(&key.secret_key.x_only_public_key(utils::secp()).0);
+        assert!(PrintableKey::decode(&npub).is_err());
+    }
+
+    #[test]
+    fn test_key_bag_import() {
+        use keybag::{decode_wif, encode_wif, KeyBag};
+
+        // Bitcoin wiki WIF vector
+        let secret = "0c28fca386c7a227600b2fe50b7cae11ec86d3bf1fbe471be89827e19d72aa1d";
+        let uncompressed = "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ";
+        let compressed = "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617";
+        let (key, network, is_compressed) = decode_wif(uncompressed).unwrap();
+        assert_eq!(hex::encode(key.secret_bytes()), secret);
+        assert_eq!((network, is_compressed), (Network::Bitcoin, false));
+        assert_eq!(encode_wif(&key, Network::Bitcoin, true), compressed);
+
+        let mut bag = KeyBag::new(Network::Bitcoin);
+        let report = bag.import_all(
+            format!(
+                "# paper wallet\n{}\n\n{}\n{}\nnot-a-key\n{}",
+                uncompressed,
+                compressed,
+                secret,
+                encode_wif(&key, Network::Testnet, true)
+            )
+            .lines(),
+        );
+        assert_eq!(report.imported.len(), 2);
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(
+            report
+                .errors
+                .iter()
+                .map(|(line, _)| *line)
+                .collect::<Vec<_>>(),
+            vec![6, 7]
+        );
+        assert!(matches!(report.errors[1].1, Error::NetworkMismatch(_)));
+
+        // Uncompressed keys only pay to P2PKH
+        let loose = bag.get(&report.imported[0]).unwrap();
+        assert_eq!(
+            loose.address(AddressType::P2pkh, Network::Bitcoin).unwrap(),
+            "1GAehh7TsJAHuUAeKZcXf5CnwuGuGgyX2S"
+        );
+        assert!(loose.script_pubkey(AddressType::P2wpkh).is_none());
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = Mnemonic::from_phrase(phrase, Language::English)
+            .unwrap()
+            .to_seed("");
+        let master = ExtendedPrivKey::new_master(seed.as_bytes(), Network::Bitcoin).unwrap();
+        let path = DerivationPath::from_str("m/84'/0'/0'/0/0").unwrap();
+        let hd = bag.insert_hd(&master, &path).unwrap();
+        assert_eq!(bag.len(), 3);
+        assert_eq!(bag.loose().count(), 2);
+        assert_eq!(bag.get(&hd).unwrap().origin.as_ref().unwrap().path, path);
+
+        let child = master
+            .derive_path(&path)
+            .unwrap()
+            .to_extended_public_key()
+            .public_key;
+        let script = address::script_pubkey(&child, AddressType::P2wpkh);
+        let (found, address_type) = bag.find_script(&script).unwrap();
+        assert_eq!(
+            (found.public_key, address_type),
+            (child, AddressType::P2wpkh)
+        );
+        assert!(bag.find_script(&[0x6a]).is_none());
+
+        let digest = [7u8; 32];
+        let sig = bag
+            .sign_ecdsa(&hd, &digest, SigningOptions::bitcoin_core())
+            .unwrap();
+        let message = secp256k1::Message::from_digest(digest);
+        assert!(utils::secp().verify_ecdsa(&message, &sig, &child).is_ok());
+        assert!(bag
+            .sign_ecdsa(&[0u8; 20], &digest, SigningOptions::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_mnemonic_diagnose() {
+        use diagnose::suggest;
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let diagnosis = Mnemonic::diagnose(phrase, Language::English);
+        assert!(diagnosis.is_valid());
+
+        // A slip, a swap and a truncation
+        let typos = phrase
+            .replacen("abandon", "abandom", 1)
+            .replacen("about", "abuot", 1)
+            .replacen("abandon abandon abandon\u{20}", "abandon abandon aban ", 1);
+        let diagnosis = Mnemonic::diagnose(&typos, Language::English);
+        assert!(!diagnosis.is_valid());
+        assert!(diagnosis.word_count_valid);
+        assert!(!diagnosis.checksum_valid);
+        let positions: Vec<usize> = diagnosis.unknown_words.iter().map(|w| w.position).collect();
+        assert_eq!(positions, vec![0, 3, 11]);
+        for suggestion in &diagnosis.unknown_words {
+            assert!(suggestion.candidates.len() <= diagnose::MAX_SUGGESTIONS);
+        }
+        assert_eq!(diagnosis.unknown_words[0].candidates[0], "abandon");
+        assert_eq!(diagnosis.unknown_words[1].candidates[0], "abandon");
+        assert_eq!(diagnosis.unknown_words[2].candidates[0], "about");
+
+        // Valid words with a bad checksum or word count
+        let diagnosis = Mnemonic::diagnose(&phrase.replace("about", "abandon"), Language::English);
+        assert!(diagnosis.unknown_words.is_empty() && !diagnosis.checksum_valid);
+        let diagnosis = Mnemonic::diagnose("abandon about", Language::English);
+        assert!(!diagnosis.word_count_valid);
+
+        assert!(suggest("qqqqqqqq", Language::English).is_empty());
+        assert_eq!(suggest("ZOO", Language::English)[0], "zoo");
+    }
+
+    #[test]
+    fn test_key_bag_sweep() {
+        use keybag::{encode_wif, KeyBag};
+        use psbt::OutPoint;
+        use sweep::SweepUtxo;
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = Mnemonic::from_phrase(phrase, Language::English)
+            .unwrap()
+            .to_seed("");
+        let mut wallet = Wallet::from_seed(&seed, Network::Bitcoin, AddressType::P2wpkh).unwrap();
+
+        let mut bag = KeyBag::new(Network::Bitcoin);
+        let first = bag.import(&hex::encode([1u8; 32])).unwrap();
+        let second = bag
+            .import(&encode_wif(
+                &secp256k1::SecretKey::from_slice(&[2u8; 32]).unwrap(),
+                Network::Bitcoin,
+                false,
+            ))
+            .unwrap();
+        let path = DerivationPath::from_str("m/84'/0'/0'/0/5").unwrap();
+        bag.insert_hd(wallet.master(), &path).unwrap();
+
+        let third = bag.import(&hex::encode([3u8; 32])).unwrap();
+        let unfunded = bag.import(&hex::encode([4u8; 32])).unwrap();
+
+        let utxo = |key: &[u8; 20], vout, amount, address_type| SweepUtxo {
+            outpoint: OutPoint {
+                txid: [9u8; 32],
+                vout,
+            },
+            amount,
+            script_pubkey: bag.get(key).unwrap().script_pubkey(address_type).unwrap(),
+        };
+        let utxos = [
+            utxo(&first, 0, 40_000, AddressType::P2wpkh),
+            utxo(&first, 1, 20_000, AddressType::P2shP2wpkh),
+            utxo(&second, 2, 30_000, AddressType::P2pkh),
+            utxo(&third, 3, 10_000, AddressType::P2wpkh),
+        ];
+
+        // HD and unfunded keys aren't swept; each funded loose key gets its
+        // own fresh address, and legacy outputs are set aside
+        let plan = wallet.plan_sweep(&bag, &utxos).unwrap();
+        assert_eq!(plan.entries.len(), 2);
+        let entry = plan.entry(&first).unwrap();
+        assert_eq!(entry.sources.len(), 3);
+        assert!(plan.entry(&second).is_none() && plan.entry(&unfunded).is_none());
+        assert_eq!(plan.legacy, [utxos[2].outpoint]);
+        let mut destinations: Vec<&str> = plan
+            .entries
+            .iter()
+            .map(|e| e.destination.as_str())
+            .collect();
+        destinations.sort();
+        let mut expected = wallet.addresses(0, Change::External, 0, 2).unwrap();
+        expected.sort();
+        assert_eq!(destinations, expected);
+        assert_eq!(wallet.active_account().next_index(Change::External), 2);
+
+        // One PSBT per funded key; the legacy output is skipped
+        let psbts = plan.psbts(&wallet, &bag, &utxos, 1_000).unwrap();
+        assert_eq!(psbts.len(), 2);
+        let psbt = psbts.iter().find(|psbt| psbt.inputs.len() == 2).unwrap();
+        assert!(psbt.inputs[1].redeem_script.is_some());
+        assert_eq!(psbt.fee().unwrap(), 1_000);
+        let output = &psbt.outputs[0];
+        assert_eq!(
+            address::encode(
+                &output.bip32_derivation[0].public_key,
+                AddressType::P2wpkh,
+                Network::Bitcoin
+            ),
+            entry.destination
+        );
+        assert_eq!(
+            output.bip32_derivation[0].origin.path.to_string(),
+            format!("m/84'/0'/0'/0/{}", entry.location.index)
+        );
+
+        assert!(plan.psbts(&wallet, &bag, &utxos, 9_500).is_err());
+        assert!(plan
+            .psbts(&wallet, &bag, &utxos[2..3], 1_000)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_mnemonic_error_positions() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let error =
+            Mnemonic::from_phrase(&phrase.replacen("abandon", "abandn", 3), Language::English)
+                .unwrap_err();
+        assert!(matches!(
+            &error,
+            Error::InvalidMnemonic(MnemonicError::UnknownWord { position: 0, word }) if word == "abandn"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Invalid mnemonic: Unknown word 'abandn' at position 0"
+        );
+
+        let typo = phrase.replace("about", "abuot");
+        assert!(matches!(
+            Mnemonic::from_phrase(&typo, Language::English),
+            Err(Error::InvalidMnemonic(MnemonicError::UnknownWord {
+                position: 11,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            Mnemonic::from_phrase("abandon about", Language::English),
+            Err(Error::InvalidMnemonic(MnemonicError::WordCount(2)))
+        ));
+        assert!(matches!(
+            Mnemonic::from_phrase(&phrase.replace("about", "zoo"), Language::English),
+            Err(Error::InvalidMnemonic(MnemonicError::Checksum))
+        ));
+    }
+
+    #[test]
+    fn test_complete_last_word() {
+        let words = ["abandon"; 11];
+        let completions = Mnemonic::complete_last_word(&words, Language::English).unwrap();
+        assert_eq!(completions.len(), 128);
+        assert_eq!(completions[0], "about");
+        for last in &completions {
+            let phrase = format!("{} {}", words.join(" "), last);
+            assert!(Mnemonic::from_phrase(&phrase, Language::English).is_ok());
+        }
+
+        let words = ["zoo"; 23];
+        let completions = Mnemonic::complete_last_word(&words, Language::English).unwrap();
+        assert_eq!(completions.len(), 8);
+        assert!(completions.contains(&"vote"));
+
+        assert!(matches!(
+            Mnemonic::complete_last_word(&["abandon"; 12], Language::English),
+            Err(Error::InvalidMnemonic(MnemonicError::WordCount(13)))
+        ));
+        let mut typo = ["abandon"; 11];
+        typo[4] = "abandn";
+        assert!(matches!(
+            Mnemonic::complete_last_word(&typo, Language::English),
+            Err(Error::InvalidMnemonic(MnemonicError::UnknownWord {
+                position: 4,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_dos_limits() {
+        use limits::Limits;
+
+        assert_eq!(limits::limits(), Limits::default());
+        let max = Limits::DEFAULT;
+
+        let deep = format!("m{}", "/0".repeat(max.max_path_depth + 1));
+        assert!(matches!(
+            DerivationPath::from_str(&deep),
+            Err(Error::LimitExceeded(LimitError::PathDepth {
+                depth: 256,
+                max: 255
+            }))
+        ));
+        let master = ExtendedPrivKey::new_master(&[7u8; 32], Network::Bitcoin).unwrap();
+        let long = DerivationPath {
+            path: vec![ChildNumber::Normal(0); max.max_path_depth + 1],
+        };
+        assert!(matches!(
+            master.derive_path(&long),
+            Err(Error::LimitExceeded(LimitError::PathDepth { .. }))
+        ));
+
+        let account = master
+            .derive_path(&DerivationPath::from_str("m/84'/0'/0'").unwrap())
+            .unwrap()
+            .to_extended_public_key();
+        let error = account
+            .script_pubkeys(
+                Change::External,
+                AddressType::P2wpkh,
+                0,
+                max.max_batch_size + 1,
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Limit exceeded: Batch of 10001 exceeds 10000"
+        );
+        let wallet = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+        assert!(matches!(
+            wallet.addresses(0, Change::External, 0, u32::MAX),
+            Err(Error::LimitExceeded(LimitError::BatchSize { .. }))
+        ));
+
+        assert!(matches!(
+            descriptor::import_requests(
+                &master,
+                AddressType::P2wpkh,
+                &[0],
+                u32::MAX,
+                descriptor::Timestamp::Now
+            ),
+            Err(Error::LimitExceeded(LimitError::WildcardRange { .. }))
+        ));
+        let key = psbt::AccountKey::from(&wallet.descriptors(0).unwrap()[0]);
+        assert!(matches!(
+            key.classify_outputs(&[], 0..u32::MAX),
+            Err(Error::LimitExceeded(LimitError::WildcardRange { .. }))
+        ));
+        assert!(key.classify_outputs(&[], 0..20).is_ok());
+
+        // Statements count every listed chain towards the batch size
+        let range = statement::StatementRange {
+            start: 0,
+            count: max.max_batch_size / 2 + 1,
+            include_change: true,
+        };
+        assert!(matches!(
+            wallet.statement(range),
+            Err(Error::LimitExceeded(LimitError::BatchSize { .. }))
+        ));
+
+        // Scanning presets are checked however they arrive
+        let config = config::Config::new().with_gap_limit(max.max_batch_size + 1);
+        assert!(matches!(
+            Wallet::from_config(
+                &Mnemonic::from_entropy(&[7u8; 16], Language::English)
+                    .unwrap()
+                    .to_seed(""),
+                &config
+            ),
+            Err(Error::LimitExceeded(LimitError::BatchSize { .. }))
+        ));
+        let lookahead = wallet::AccountConfig {
+            lookahead: u32::MAX,
+            ..wallet::AccountConfig::default()
+        };
+        assert!(matches!(
+            Wallet::new(master.clone(), AddressType::P2wpkh)
+                .unwrap()
+                .with_account_config(lookahead),
+            Err(Error::LimitExceeded(LimitError::WildcardRange { .. }))
+        ));
+        let mut export = wallet.export(Default::default()).unwrap();
+        export.bundle.accounts[0].config = lookahead;
+        let export = export::SignedExport::sign(export.bundle, &master).unwrap();
+        assert!(matches!(
+            export.verify(),
+            Err(Error::LimitExceeded(LimitError::WildcardRange { .. }))
+        ));
+
+        let receive = wallet.descriptors(0).unwrap()[0].clone();
+        assert!(matches!(
+            descriptor::ImportRequest::new(&receive, u32::MAX, descriptor::Timestamp::Now),
+            Err(Error::LimitExceeded(LimitError::WildcardRange { .. }))
+        ));
+        assert!(descriptor::ImportRequest::new(&receive, 999, descriptor::Timestamp::Now).is_ok());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_never_records_secrets() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        /// Collects every recorded field as `name=value`
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        impl Visit for Recorder {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}={:?}", field.name(), value));
+            }
+        }
+
+        impl tracing::Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(span.metadata().name().to_string());
+                span.record(&mut self.clone());
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, values: &Record<'_>) {
+                values.record(&mut self.clone());
+            }
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                event.record(&mut self.clone());
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let recorder = Recorder::default();
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let master = tracing::subscriber::with_default(recorder.clone(), || {
+            let mnemonic = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+            assert!(
+                Mnemonic::from_phrase(&phrase.replace("about", "abuot"), Language::English)
+                    .is_err()
+            );
+            let master =
+                ExtendedPrivKey::new_master(mnemonic.to_seed("").as_bytes(), Network::Bitcoin)
+                    .unwrap();
+            let xprv = master.to_string();
+            ExtendedPrivKey::from_string(&xprv).unwrap();
+            master
+                .derive_path(&DerivationPath::from_str("m/84'/0'/0'").unwrap())
+                .unwrap();
+            let mut wallet = Wallet::new(master.clone(), AddressType::P2wpkh).unwrap();
+            wallet.next_address(Change::External).unwrap();
+            master
+        });
+
+        let records = recorder.0.lock().unwrap().join("\n");
+        for expected in [
+            "mnemonic_from_phrase",
+            "position=11",
+            "derive_path",
+            "path=m/84'/0'/0'",
+            "issued address",
+        ] {
+            assert!(records.contains(expected), "missing {}", expected);
+        }
+        assert!(!records.contains("abandon") && !records.contains("abuot"));
+        assert!(!records.contains(&master.to_string()));
+        assert!(!records.contains(&hex::encode(master.private_key.secret_bytes())));
+    }
+
+    #[test]
+    fn test_metrics_observer() {
+        use metrics::{CountingObserver, MetricsObserver, SignatureKind};
+        use std::sync::{Arc, Mutex};
+
+        /// Records signature kinds, to check each signing path reports its own
+        #[derive(Default)]
+        struct Kinds(Mutex<Vec<SignatureKind>>);
+
+        impl MetricsObserver for Kinds {
+            fn signature(&self, kind: SignatureKind) {
+                self.0.lock().unwrap().push(kind);
+            }
+        }
+
+        // Other tests run concurrently, so totals are only bounded below
+        let counter = Arc::new(CountingObserver::new());
+        metrics::set_observer(counter.clone());
+        let master = ExtendedPrivKey::new_master(&[3u8; 32], Network::Bitcoin).unwrap();
+        let path = DerivationPath::from_str("m/84'/0'/0'/0/0").unwrap();
+        master
+            .sign_ecdsa(&path, &[1u8; 32], SigningOptions::default())
+            .unwrap();
+        let mut wallet = Wallet::new(master.clone(), AddressType::P2wpkh)
+            .unwrap()
+            .with_derivation_cache(16);
+        wallet.addresses(0, Change::External, 0, 2).unwrap();
+        wallet.addresses(0, Change::External, 0, 2).unwrap();
+        wallet.discover(|_| false).unwrap();
+        let snapshot = counter.snapshot();
+        assert!(snapshot.derivations >= 1);
+        assert!(snapshot.signatures >= 1);
+        assert!(snapshot.cache_hits >= 2 && snapshot.cache_misses >= 2);
+        assert!(snapshot.addresses_scanned >= 40);
+
+        let kinds = Arc::new(Kinds::default());
+        metrics::set_observer(kinds.clone());
+        master.sign_eth_hash(&[2u8; 32], None).unwrap();
+        let slip10 = slip10::Ed25519ExtendedKey::new_master(&[3u8; 32]).unwrap();
+        slip10.sign(b"hello");
+        metrics::clear_observer();
+        let recorded = kinds.0.lock().unwrap().clone();
+        assert!(recorded.contains(&SignatureKind::EcdsaRecoverable));
+        assert!(recorded.contains(&SignatureKind::Ed25519));
+
+        // Substrate keys report their own kinds
+        let substrate = Arc::new(Kinds::default());
+        metrics::set_observer(substrate.clone());
+        substrate::Ed25519Pair::from_seed([4u8; 32]).sign(b"hello");
+        #[cfg(feature = "sr25519")]
+        substrate::Sr25519Pair::from_seed([4u8; 32])
+            .unwrap()
+            .sign(b"hello");
+        metrics::clear_observer();
+        let recorded = substrate.0.lock().unwrap().clone();
+        assert!(recorded.contains(&SignatureKind::Ed25519));
+        #[cfg(feature = "sr25519")]
+        assert!(recorded.contains(&SignatureKind::Sr25519));
+    }
+
+    #[test]
+    fn test_generate_with_rng() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        /// Returns the same byte forever
+        struct ConstantRng(u8);
+
+        impl rand::RngCore for ConstantRng {
+            fn next_u32(&mut self) -> u32 {
+                u32::from_ne_bytes([self.0; 4])
+            }
+            fn next_u64(&mut self) -> u64 {
+                u64::from_ne_bytes([self.0; 8])
+            }
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                dest.fill(self.0);
+            }
+            fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+                self.fill_bytes(dest);
+                Ok(())
+            }
+        }
+
+        impl rand::CryptoRng for ConstantRng {}
+
+        let mnemonic = Mnemonic::generate_with_rng(
+            MnemonicType::Words12,
+            Language::English,
+            &mut ConstantRng(0),
+        )
+        .unwrap();
+        assert_eq!(
+            mnemonic.phrase(),
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+        );
+        let mnemonic = Mnemonic::generate_with_rng(
+            MnemonicType::Words24,
+            Language::English,
+            &mut ConstantRng(0xff),
+        )
+        .unwrap();
+        assert_eq!(mnemonic.to_entropy(), [0xff; 32]);
+
+        // A seeded RNG reproduces its phrase
+        let seeded = |seed| {
+            Mnemonic::generate_with_rng(
+                MnemonicType::Words18,
+                Language::English,
+                &mut StdRng::seed_from_u64(seed),
+            )
+            .unwrap()
+            .phrase()
+            .to_string()
+        };
+        assert_eq!(seeded(7), seeded(7));
+        assert_ne!(seeded(7), seeded(8));
+        assert!(Mnemonic::from_phrase(&seeded(7), Language::English).is_ok());
+    }
+
+    #[test]
+    fn test_config_defaults_and_wallet() {
+        use crate::bip44::Purpose;
+        use crate::config::{Config, HardenedNotation};
+        use crate::wallet::Wallet;
+
+        let config = Config::new()
+            .with_network(Network::Testnet)
+            .with_purpose(Purpose(49))
+            .with_gap_limit(5)
+            .with_notation(HardenedNotation::H)
+            .with_strict_paths(true);
+        assert_eq!(config.address_type().unwrap(), AddressType::P2shP2wpkh);
+
+        let path = config.parse_path("m/49'/1'/0'/0/3").unwrap();
+        assert_eq!(config.format_path(&path), "m/49h/1h/0h/0/3");
+        assert!(config.parse_path("M/49'/1'/0'").is_err());
+        assert!(Config::default().parse_path("M/49'/1'/0'").is_ok());
+
+        let seed = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap()
+        .to_seed("");
+        let wallet = Wallet::from_config(&seed, &config).unwrap();
+        let expected = Wallet::from_seed(&seed, Network::Testnet, AddressType::P2shP2wpkh).unwrap();
+        assert_eq!(wallet.address_type(), AddressType::P2shP2wpkh);
+        assert_eq!(
+            wallet.first_address(0).unwrap(),
+            expected.first_address(0).unwrap()
+        );
+        assert_eq!(wallet.account(0).unwrap().config().gap_limit, 5);
+
+        // The wallet prints and parses paths in the configured style
+        let statement = wallet
+            .statement(statement::StatementRange::default())
+            .unwrap();
+        assert_eq!(statement.rows[3].path, "m/49h/1h/0h/0/3");
+        assert_eq!(
+            wallet.format_path(&wallet.parse_path("m/49'/1'/0'").unwrap()),
+            "m/49h/1h/0h"
+        );
+        assert!(wallet.parse_path("M/49'/1'/0'").is_err());
+
+        // Watch-only keys of another network need explicit re-tagging
+        let xpub = Wallet::from_seed(&seed, Network::Bitcoin, AddressType::P2shP2wpkh)
+            .unwrap()
+            .account(0)
+            .unwrap()
+            .xpub
+            .to_string();
+        assert!(matches!(
+            watchonly::WatchOnlyWallet::from_config(&xpub, &config),
+            Err(Error::NetworkMismatch(_))
+        ));
+        let watch_only =
+            watchonly::WatchOnlyWallet::from_config(&xpub, &config.with_allow_retag(true)).unwrap();
+        assert_eq!(watch_only.account().xpub.network, Network::Testnet);
+        assert_eq!(watch_only.account().config().gap_limit, 5);
+
+        assert!(Config::new()
+            .with_purpose(Purpose(86))
+            .address_type()
+            .is_err());
+        assert!(Wallet::from_config(&seed, &Config::new().with_gap_limit(0)).is_err());
+    }
+
+    #[test]
+    fn test_error_kinds_and_exit_codes() {
+        use crate::error::{ErrorEnvelope, ErrorKind, LimitError};
+
+        let error = Mnemonic::from_phrase("abandon abandon", Language::English).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Mnemonic);
+        assert_eq!(error.kind().exit_code(), 3);
+        assert!(error.hint().is_some());
+
+        let error = DerivationPath::from_str("m/x").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Path);
+        let error = Error::from(LimitError::BatchSize { size: 2, max: 1 });
+        assert_eq!(error.kind().exit_code(), 10);
+        assert_eq!(Error::SelfTest("x".into()).kind().exit_code(), 1);
+        assert_eq!(Error::Input("stdin".into()).kind().exit_code(), 7);
+        assert_eq!(Error::Output("stdout".into()).kind().exit_code(), 1);
+        assert!(Error::Input("stdin".into()).hint().is_none());
+
+        let envelope = ErrorEnvelope::from(&Error::NetworkMismatch("testnet key".into()));
+        assert_eq!(envelope.kind.name(), "network");
+        assert_eq!(envelope.message, "Network mismatch: testnet key");
+        assert!(envelope.hint.is_some());
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_secrets_wiped() {
+        use std::mem::ManuallyDrop;
+
+        let mut mnemonic = ManuallyDrop::new(
+            Mnemonic::from_phrase(
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+                Language::English,
+            )
+            .unwrap(),
+        );
+        let mut seed = ManuallyDrop::new(mnemonic.to_seed(""));
+        assert_ne!(seed.0, [0u8; 64]);
+        assert!(!mnemonic.phrase().is_empty());
+
+        // SAFETY: neither value is used after its destructor runs, except to
+        // inspect the inline bytes it leaves behind. The seed is a plain
+        // array and the phrase is only checked for length, so no freed heap
+        // memory is read.
+        unsafe {
+            ManuallyDrop::drop(&mut seed);
+            ManuallyDrop::drop(&mut mnemonic);
+        }
+        assert_eq!(seed.0, [0u8; 64]);
+        assert!(mnemonic.phrase().is_empty());
+    }
+
+    #[test]
+    fn test_repl_session() {
+        use crate::config::Config;
+        use crate::message::verify_message;
+        use crate::repl::{Reply, Session};
+
+        let seed = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap()
+        .to_seed("");
+        let session = Session::from_seed(seed.as_bytes(), Config::default()).unwrap();
+
+        let output = |line: &str| match session.execute(line).unwrap() {
+            Reply::Output(output) => output,
+            Reply::Quit => panic!("unexpected quit"),
+        };
+        assert_eq!(
+            output("derive m/84'/0'/0'"),
+            "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V"
+        );
+        let address = output("address m/84'/0'/0'/0/0");
+        assert_eq!(address, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+        let signature = output("sign m/84'/0'/0'/0/0 hello world");
+        verify_message(&address, "hello world", &signature, Network::Bitcoin).unwrap();
+        assert!(output("inspect").contains("depth: 0"));
+
+        assert_eq!(session.execute("quit").unwrap(), Reply::Quit);
+        let usage = session.execute("derive").unwrap_err();
+        assert_eq!(usage.kind(), crate::ErrorKind::Usage);
+        assert_eq!(usage.to_string(), "Invalid usage: expected 'derive <path>'");
+        let unknown = session.execute("frobnicate").unwrap_err();
+        assert_eq!(unknown.kind().exit_code(), 2);
+    }
+
+    #[test]
+    fn test_totp_secret_provisioning() {
+        use crate::totp::TotpSecret;
+
+        let seed = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap()
+        .to_seed("");
+        let secret = TotpSecret::derive(&seed, "Example Co", "alice@example.com", 0).unwrap();
+        assert_eq!(
+            secret,
+            TotpSecret::derive(&seed, "Example Co", "alice@example.com", 0).unwrap()
+        );
+        assert_ne!(
+            secret.secret(),
+            TotpSecret::derive(&seed, "Example Co", "alice@example.com", 1)
+                .unwrap()
+                .secret()
+        );
+        assert_ne!(
+            secret.secret(),
+            TotpSecret::derive(&seed, "Example Co", "bob@example.com", 0)
+                .unwrap()
+                .secret()
+        );
+
+        let base32 = secret.secret_base32();
+        assert_eq!(base32, "EP3DNU2J6C4VTSI2YB7PVGQVKR46X5MJ");
+        assert!(!format!("{:?}", secret).contains(&hex::encode(secret.secret())));
+        assert_eq!(
+            secret.to_uri(),
+            format!(
+                "otpauth://totp/Example%20Co:alice%40example.com?secret={}&issuer=Example%20Co&algorithm=SHA1&digits=6&period=30",
+                base32
+            )
+        );
+        assert!(secret.clone().with_digits(9).is_err());
+        assert!(TotpSecret::derive(&seed, "a:b", "alice", 0).is_err());
+    }
+
+    #[test]
+    fn test_wallet_from_extended_keys() {
+        use crate::wallet::Wallet;
+
+        let seed = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap()
+        .to_seed("");
+        let wallet = Wallet::from_seed(&seed, Network::Bitcoin, AddressType::P2wpkh).unwrap();
+
+        let from_xprv =
+            Wallet::from_xprv(&wallet.master().to_string(), AddressType::P2wpkh).unwrap();
+        assert_eq!(
+            from_xprv.first_address(0).unwrap(),
+            wallet.first_address(0).unwrap()
+        );
+        let account_xprv = wallet
+            .master()
+            .derive_path(&wallet.account_path(0))
+            .unwrap();
+        assert!(Wallet::from_xprv(&account_xprv.to_string(), AddressType::P2wpkh).is_err());
+
+        let xpub = wallet.account(0).unwrap().xpub.to_string();
+        let mut watch = Wallet::from_account_xpub(&xpub, AddressType::P2wpkh)
+            .unwrap()
+            .with_master_fingerprint(wallet.master().fingerprint());
+        assert_eq!(
+            watch.addresses(Change::Internal, 0, 3).unwrap(),
+            wallet.addresses(0, Change::Internal, 0, 3).unwrap()
+        );
+        let [receive, change] = watch.descriptors().unwrap();
+        let [expected_receive, expected_change] = wallet.descriptors(0).unwrap();
+        assert_eq!(receive.to_string(), expected_receive.to_string());
+        assert_eq!(change.to_string(), expected_change.to_string());
+
+        let used = wallet
+            .addresses(0, Change::External, 4, 1)
+            .unwrap()
+            .remove(0);
+        watch.discover(|address| address == used).unwrap();
+        assert_eq!(
+            watch.next_address(Change::External).unwrap(),
+            wallet.addresses(0, Change::External, 5, 1).unwrap()[0]
+        );
+        assert_eq!(watch.find_address(&used).unwrap().unwrap().index, 4);
+        assert!(Wallet::from_account_xpub(
+            &wallet.master().to_extended_public_key().to_string(),
+            AddressType::P2wpkh
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_read_only_wallet_trait() {
+        use crate::readonly::ReadOnlyWallet;
+        use crate::wallet::Wallet;
+
+        fn receive_addresses(wallet: &dyn ReadOnlyWallet) -> Vec<String> {
+            wallet.addresses(0, Change::External, 0, 2).unwrap()
+        }
+
+        let seed = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap()
+        .to_seed("");
+        let wallet = Wallet::from_seed(&seed, Network::Bitcoin, AddressType::P2wpkh).unwrap();
+        let watch = Wallet::from_account_xpub(
+            &wallet.account(0).unwrap().xpub.to_string(),
+            AddressType::P2wpkh,
+        )
+        .unwrap();
+
+        let addresses = receive_addresses(&wallet);
+        assert_eq!(addresses, receive_addresses(&watch));
+        let other = wallet
+            .master()
+            .derive_path(&DerivationPath::from_str("m/84'/0'/1'/0/0").unwrap())
+            .unwrap()
+            .to_extended_public_key()
+            .address(AddressType::P2wpkh);
+        for readonly in [&wallet as &dyn ReadOnlyWallet, &watch] {
+            assert_eq!(readonly.account_indices(), vec![0]);
+            assert!(readonly.is_mine(&addresses[1]).unwrap());
+            assert!(!readonly.is_mine(&other).unwrap());
+        }
+        assert!(ReadOnlyWallet::descriptors(&wallet, 0).is_ok());
+        assert!(ReadOnlyWallet::descriptors(&watch, 0).is_err());
+        assert!(ReadOnlyWallet::addresses(&watch, 1, Change::External, 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_seed_xor_split_and_combine() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mnemonic = Mnemonic::from_entropy(&[0x5au8; 32], Language::English).unwrap();
+        let mut shares = mnemonic
+            .xor_split_with_rng(3, &mut StdRng::seed_from_u64(7))
+            .unwrap();
+        assert_eq!(shares.len(), 3);
+        for share in &shares {
+            assert_eq!(share.phrase().split_whitespace().count(), 24);
+            assert_ne!(share.phrase(), mnemonic.phrase());
+        }
+        shares.reverse();
+        assert_eq!(Mnemonic::xor_combine(&shares).unwrap(), mnemonic);
+        assert_ne!(Mnemonic::xor_combine(&shares[..2]).unwrap(), mnemonic);
+        assert_eq!(mnemonic.xor_split(2).unwrap().len(), 2);
+
+        // XOR with all-zero entropy is the identity
+        let zero = Mnemonic::from_entropy(&[0u8; 16], Language::English).unwrap();
+        let other = Mnemonic::from_entropy(&[0xc3u8; 16], Language::English).unwrap();
+        assert_eq!(
+            Mnemonic::xor_combine(&[zero.clone(), other.clone()]).unwrap(),
+            other
+        );
+
+        assert!(mnemonic.xor_split(1).is_err());
+        assert!(Mnemonic::xor_combine(std::slice::from_ref(&zero)).is_err());
+        assert!(Mnemonic::xor_combine(&[zero, shares[0].clone()]).is_err());
+        let words15 = Mnemonic::from_entropy(&[1u8; 20], Language::English).unwrap();
+        assert!(words15.xor_split(2).is_err());
+        assert!(Mnemonic::xor_combine(&[words15.clone(), words15]).is_err());
+    }
+
+    #[test]
+    fn test_codex32_vectors_and_shares() {
+        use crate::codex32::Codex32Share;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        // BIP-93 test vector 1
+        let secret: Codex32Share = "ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlw"
+            .parse()
+            .unwrap();
+        assert_eq!(secret.threshold(), 0);
+        assert_eq!(secret.identifier(), "test");
+        assert_eq!(
+            hex::encode(secret.seed().unwrap()),
+            "318c6318c6318c6318c6318c6318c631"
+        );
+        let debug = format!("{:?}", secret);
+        assert!(debug.contains("\"test\""));
+        assert!(!debug.contains(&format!("{:?}", secret.seed().unwrap())));
+        assert!("ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlx"
+            .parse::<Codex32Share>()
+            .is_err());
+
+        // BIP-93 test vector 2: recover share S from shares A and C
+        let shares: Vec<Codex32Share> = [
+            "MS12NAMEA320ZYXWVUTSRQPNMLKJHGFEDCAXRPP870HKKQRM",
+            "MS12NAMECACDEFGHJKLMNPQRSTUVWXYZ023FTR2GDZMPY6PN",
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+        let recovered = Codex32Share::recover(&shares).unwrap();
+        assert_eq!(
+            recovered.to_string(),
+            "ms12names6xqguzttxkeqnjsjzv4jv3nz5k3kwgsphuh6evw"
+        );
+        assert_eq!(
+            hex::encode(recovered.seed().unwrap()),
+            "d1808e096b35b209ca12132b264662a5"
+        );
+        assert!(Codex32Share::recover(&shares[..1]).is_err());
+
+        // Split a 64-byte seed, which needs the long checksum, and recover it
+        // from any three shares
+        let seed = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap()
+        .to_seed("");
+        let shares =
+            Codex32Share::split(seed.as_bytes(), "cash", 3, 5, &mut StdRng::seed_from_u64(1))
+                .unwrap();
+        assert_eq!(shares.len(), 5);
+        let reparsed: Vec<Codex32Share> = shares
+            .iter()
+            .map(|share| share.to_string().parse().unwrap())
+            .collect();
+        assert_eq!(reparsed, shares);
+        let recovered =
+            Codex32Share::recover(&[shares[4].clone(), shares[1].clone(), shares[2].clone()])
+                .unwrap();
+        assert_eq!(recovered.seed().unwrap(), seed.as_bytes());
+        assert_eq!(
+            recovered.master_key(Network::Bitcoin).unwrap().to_string(),
+            ExtendedPrivKey::new_master(seed.as_bytes(), Network::Bitcoin)
+                .unwrap()
+                .to_string()
+        );
+
+        let single = Codex32Share::from_seed(&[7u8; 16], "acdc").unwrap();
+        assert_eq!(single.to_string().parse::<Codex32Share>().unwrap(), single);
+    }
+
+    #[test]
+    fn test_payment_request_uris() {
+        use evm::EvmChain;
+        use payment::{PaymentRequest, PaymentScheme};
+
+        let mnemonic = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap();
+        let mut wallet =
+            Wallet::from_seed(&mnemonic.to_seed(""), Network::Bitcoin, AddressType::P2wpkh)
+                .unwrap();
+
+        let request = wallet.payment_request("Coffee & cake").unwrap();
+        let address = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+        assert_eq!(request.address(), address);
+        assert_eq!(wallet.label(address), Some("Coffee & cake"));
+        assert_eq!(
+            request.to_uri(),
+            format!("bitcoin:{}?label=Coffee%20%26%20cake", address)
+        );
+        let request = request
+            .with_amount(50_000)
+            .unwrap()
+            .with_message("Order 42");
+        assert_eq!(
+            request.to_uri(),
+            format!(
+                "bitcoin:{}?amount=0.0005&label=Coffee%20%26%20cake&message=Order%2042",
+                address
+            )
+        );
+        assert_eq!(
+            PaymentRequest::bitcoin(address)
+                .with_amount(2_100_000_000_000_000)
+                .unwrap()
+                .to_uri(),
+            format!("bitcoin:{}?amount=21000000", address)
+        );
+        assert!(PaymentRequest::bitcoin(address)
+            .with_amount(2_100_000_000_000_001)
+            .is_err());
+
+        // The next request gets a fresh address
+        assert_ne!(wallet.payment_request("Tea").unwrap().address(), address);
+
+        let request = wallet
+            .evm_payment_request(&EvmChain::POLYGON, 0, "Invoice 7")
+            .unwrap();
+        let eth_address = wallet.evm_address(&EvmChain::POLYGON, 0).unwrap();
+        assert_eq!(request.scheme(), PaymentScheme::Ethereum { chain_id: 137 });
+        assert_eq!(wallet.label(&eth_address), Some("Invoice 7"));
+        assert_eq!(
+            request
+                .with_amount(1_000_000_000_000_000_000)
+                .unwrap()
+                .to_uri(),
+            format!("ethereum:{}@137?value=1000000000000000000", eth_address)
+        );
+        assert!(wallet
+            .evm_payment_request(&EvmChain::TRON, 0, "Tron")
+            .is_err());
+    }
+
+    #[test]
+    fn test_mnemonic_audit() {
+        use lint::MnemonicWarning;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mnemonic = Mnemonic::from_phrase(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            Language::English,
+        )
+        .unwrap();
+        assert_eq!(
+            mnemonic.audit(),
+            vec![
+                MnemonicWarning::RepeatedWord {
+                    word: "abandon".to_string(),
+                    count: 11
+                },
+                MnemonicWarning::OrderedSequence {
+                    position: 0,
+                    len: 11
+                },
+                MnemonicWarning::KnownTestVector,
+                MnemonicWarning::LowHammingWeight { ones: 0, bits: 128 },
+            ]
+        );
+
+        // Published vectors are flagged even when the words look random
+        let vector = Mnemonic::from_phrase(
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+            Language::English,
+        )
+        .unwrap();
+        let codes: Vec<&str> = vector.audit().iter().map(|w| w.code()).collect();
+        assert_eq!(codes, ["known_test_vector", "low_hamming_weight"]);
+
+        let mut entropy = [0u8; 16];
+        entropy[..2].copy_from_slice(&[0xa5, 0x5a]);
+        let sparse = Mnemonic::from_entropy(&entropy, Language::English).unwrap();
+        assert!(sparse
+            .audit()
+            .contains(&MnemonicWarning::LowHammingWeight { ones: 8, bits: 128 }));
+
+        let random = Mnemonic::generate_with_rng(
+            MnemonicType::Words24,
+            Language::English,
+            &mut StdRng::seed_from_u64(3),
+        )
+        .unwrap();
+        assert!(random.audit().is_empty());
+    }
 }
@@ -0,0 +1,255 @@
+use crate::bip32::{ChildNumber, ExtendedPrivKey, Network};
+use crate::bip39::Seed;
+use crate::error::Error;
+use crate::utils;
+use hkdf::Hkdf;
+use secp256k1::{PublicKey, SecretKey};
+use sha2::Sha256;
+
+/// Lightning node secrets laid out like LDK's `KeysManager`:
+/// node secret at m/0', destination at m/1', shutdown at m/2',
+/// channel master at m/3' and inbound payment key at m/5'
+#[derive(Clone)]
+pub struct LightningKeys {
+    seed: [u8; 32],
+    master: ExtendedPrivKey,
+}
+
+impl std::fmt::Debug for LightningKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("LightningKeys")
+            .field("master", &self.master)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Per-channel base keys and commitment (shachain) seed
+#[derive(Clone, PartialEq, Eq)]
+pub struct ChannelKeys {
+    pub funding_key: SecretKey,
+    pub revocation_base_key: SecretKey,
+    pub payment_key: SecretKey,
+    pub delayed_payment_base_key: SecretKey,
+    pub htlc_base_key: SecretKey,
+    /// Seed of the per-commitment secret shachain
+    pub commitment_seed: [u8; 32],
+}
+
+impl std::fmt::Debug for ChannelKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ChannelKeys")
+            .field("funding_key", &self.funding_key)
+            .field("revocation_base_key", &self.revocation_base_key)
+            .field("payment_key", &self.payment_key)
+            .field("delayed_payment_base_key", &self.delayed_payment_base_key)
+            .field("htlc_base_key", &self.htlc_base_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LightningKeys {
+    /// Create Lightning keys from a 32-byte node seed
+    pub fn new(seed: &[u8; 32]) -> Result<Self, Error> {
+        // The network only affects serialization, which is never used here
+        Ok(LightningKeys {
+            seed: *seed,
+            master: ExtendedPrivKey::new_master(seed, Network::Testnet)?,
+        })
+    }
+
+    /// Create Lightning keys using the BIP-32 master private key of a BIP-39 seed
+    /// as the node seed
+    pub fn from_seed(seed: &Seed) -> Result<Self, Error> {
+        let master = ExtendedPrivKey::new_master(seed.as_bytes(), Network::Bitcoin)?;
+        LightningKeys::new(&master.private_key.secret_bytes())
+    }
+
+    fn hardened_child(&self, index: u32) -> Result<SecretKey, Error> {
+        Ok(self
+            .master
+            .derive_child(ChildNumber::Hardened(index))?
+            .private_key)
+    }
+
+    /// Get the node secret (m/0')
+    pub fn node_secret(&self) -> Result<SecretKey, Error> {
+        self.hardened_child(0)
+    }
+
+    /// Get the node id (public key of the node secret)
+    pub fn node_id(&self) -> Result<PublicKey, Error> {
+        Ok(PublicKey::from_secret_key(
+            utils::secp(),
+            &self.node_secret()?,
+        ))
+    }
+
+    /// Get the key for the on-chain destination script (m/1')
+    pub fn destination_key(&self) -> Result<SecretKey, Error> {
+        self.hardened_child(1)
+    }
+
+    /// Get the key for cooperative close shutdown scripts (m/2')
+    pub fn shutdown_key(&self) -> Result<SecretKey, Error> {
+        self.hardened_child(2)
+    }
+
+    /// Get the channel master key (m/3')
+    pub fn channel_master_key(&self) -> Result<ExtendedPrivKey, Error> {
+        self.master.derive_child(ChildNumber::Hardened(3))
+    }
+
+    /// Get the inbound payment key (m/5')
+    pub fn inbound_payment_key(&self) -> Result<SecretKey, Error> {
+        self.hardened_child(5)
+    }
+
+    /// Derive the keys of a channel from its 32-byte channel keys id.
+    /// The first 8 bytes (big-endian) select the child of the channel master key.
+    pub fn channel_keys(&self, channel_keys_id: &[u8; 32]) -> Result<ChannelKeys, Error> {
+        let mut index_bytes = [0u8; 8];
+        index_bytes.copy_from_slice(&channel_keys_id[..8]);
+        let index = (u64::from_be_bytes(index_bytes) as u32) & ChildNumber::MAX_NORMAL_INDEX;
+
+        let child = self
+            .channel_master_key()?
+            .derive_child(ChildNumber::Hardened(index))?;
+
+        let mut unique = Vec::with_capacity(96);
+        unique.extend_from_slice(channel_keys_id);
+        unique.extend_from_slice(&self.seed);
+        unique.extend_from_slice(&child.private_key[..]);
+        let channel_seed = utils::sha256(&unique);
+
+        let mut data = channel_seed.to_vec();
+        data.extend_from_slice(b"commitment seed");
+        let commitment_seed = utils::sha256(&data);
+
+        // Each key hashes the channel seed, the previous key and a label
+        let key_step = |label: &[u8], previous: &[u8]| -> Result<SecretKey, Error> {
+            let mut data = channel_seed.to_vec();
+            data.extend_from_slice(previous);
+            data.extend_from_slice(label);
+            SecretKey::from_slice(&utils::sha256(&data))
+                .map_err(|_| Error::InvalidKey("Invalid channel key".to_string()))
+        };
+
+        let funding_key = key_step(b"funding key", &commitment_seed)?;
+        let revocation_base_key = key_step(b"revocation base key", &funding_key[..])?;
+        let payment_key = key_step(b"payment key", &revocation_base_key[..])?;
+        let delayed_payment_base_key = key_step(b"delayed payment base key", &payment_key[..])?;
+        let htlc_base_key = key_step(b"HTLC base key", &delayed_payment_base_key[..])?;
+
+        Ok(ChannelKeys {
+            funding_key,
+            revocation_base_key,
+            payment_key,
+            delayed_payment_base_key,
+            htlc_base_key,
+            commitment_seed,
+        })
+    }
+}
+
+/// Lightning node secrets laid out like Core Lightning's `hsmd`, each
+/// expanded from the 32-byte `hsm_secret` with HKDF-SHA256
+#[derive(Clone)]
+pub struct ClnKeys {
+    hsm_secret: [u8; 32],
+}
+
+impl std::fmt::Debug for ClnKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ClnKeys").finish_non_exhaustive()
+    }
+}
+
+impl ClnKeys {
+    /// Create keys from the contents of an `hsm_secret` file
+    pub fn new(hsm_secret: &[u8; 32]) -> Self {
+        ClnKeys {
+            hsm_secret: *hsm_secret,
+        }
+    }
+
+    /// Create keys from a BIP-39 seed, whose first 32 bytes are the
+    /// `hsm_secret`, as `lightning-hsmtool generatehsm` writes it
+    pub fn from_seed(seed: &Seed) -> Self {
+        let mut hsm_secret = [0u8; 32];
+        hsm_secret.copy_from_slice(&seed.as_bytes()[..32]);
+        ClnKeys::new(&hsm_secret)
+    }
+
+    /// Expand the `hsm_secret` under `info`, bumping a little-endian
+    /// counter salt until `accept` takes the output
+    fn expand_until<T>(&self, info: &[u8], accept: impl Fn(&[u8; 32]) -> Option<T>) -> T {
+        (0u32..)
+            .find_map(|salt| accept(&hkdf(Some(&salt.to_le_bytes()), &self.hsm_secret, info)))
+            .expect("an HKDF output is a valid key")
+    }
+
+    /// Get the node secret (HKDF info `nodeid`)
+    pub fn node_secret(&self) -> SecretKey {
+        self.expand_until(b"nodeid", |okm| SecretKey::from_slice(okm).ok())
+    }
+
+    /// Get the node id (public key of the node secret)
+    pub fn node_id(&self) -> PublicKey {
+        PublicKey::from_secret_key(utils::secp(), &self.node_secret())
+    }
+
+    /// Get the master key of the on-chain wallet (HKDF info `bip32 seed`)
+    pub fn bip32_master(&self, network: Network) -> ExtendedPrivKey {
+        self.expand_until(b"bip32 seed", |okm| {
+            ExtendedPrivKey::new_master(okm, network).ok()
+        })
+    }
+
+    /// Get the base key of on-chain addresses (m/0/0 of the wallet master),
+    /// whose children are the wallet's keys
+    pub fn wallet_base_key(&self, network: Network) -> Result<ExtendedPrivKey, Error> {
+        self.bip32_master(network)
+            .derive_child(ChildNumber::Normal(0))?
+            .derive_child(ChildNumber::Normal(0))
+    }
+
+    /// Derive the keys of a channel from the peer's node id and the
+    /// channel's database id
+    pub fn channel_keys(&self, peer_id: &PublicKey, dbid: u64) -> Result<ChannelKeys, Error> {
+        let peer_base = hkdf(None, &self.hsm_secret, b"peer seed");
+        let mut input = peer_id.serialize().to_vec();
+        input.extend_from_slice(&dbid.to_le_bytes());
+        let channel_seed = hkdf(Some(&input), &peer_base, b"per-peer seed");
+
+        // Funding, revocation, HTLC, payment and delayed payment keys,
+        // then the shachain seed
+        let mut keys = [0u8; 192];
+        Hkdf::<Sha256>::new(None, &channel_seed)
+            .expand(b"c-lightning", &mut keys)
+            .expect("192 bytes is a valid HKDF-SHA256 output length");
+        let key = |i: usize| {
+            SecretKey::from_slice(&keys[i * 32..(i + 1) * 32])
+                .map_err(|_| Error::InvalidKey("Invalid channel key".to_string()))
+        };
+        let mut commitment_seed = [0u8; 32];
+        commitment_seed.copy_from_slice(&keys[160..]);
+
+        Ok(ChannelKeys {
+            funding_key: key(0)?,
+            revocation_base_key: key(1)?,
+            htlc_base_key: key(2)?,
+            payment_key: key(3)?,
+            delayed_payment_base_key: key(4)?,
+            commitment_seed,
+        })
+    }
+}
+
+/// HKDF-SHA256 with a 32-byte output
+fn hkdf(salt: Option<&[u8]>, ikm: &[u8], info: &[u8]) -> [u8; 32] {
+    let mut okm = [0u8; 32];
+    Hkdf::<Sha256>::new(salt, ikm)
+        .expand(info, &mut okm)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    okm
+}
@@ -0,0 +1,72 @@
+use crate::error::{Error, LimitError};
+use std::sync::RwLock;
+
+/// Bounds on work a single call may be asked to do, so services passing
+/// untrusted input to the crate can't be driven into unbounded CPU work.
+/// Process-wide; set once at startup with [`set_limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Most components of a derivation path
+    pub max_path_depth: usize,
+    /// Most keys, addresses or scripts derived by a batch call
+    pub max_batch_size: u32,
+    /// Most indices covered by a descriptor or watch range
+    pub max_wildcard_range: u32,
+}
+
+impl Limits {
+    /// Default limits: the BIP-32 depth of 255, and batches and ranges well
+    /// beyond any real gap limit
+    pub const DEFAULT: Limits = Limits {
+        max_path_depth: 255,
+        max_batch_size: 10_000,
+        max_wildcard_range: 100_000,
+    };
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits::DEFAULT
+    }
+}
+
+static LIMITS: RwLock<Limits> = RwLock::new(Limits::DEFAULT);
+
+/// Get the limits in force
+pub fn limits() -> Limits {
+    *LIMITS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Replace the limits in force for the whole process
+pub fn set_limits(limits: Limits) {
+    *LIMITS.write().unwrap_or_else(|e| e.into_inner()) = limits;
+}
+
+/// Check the number of components of a derivation path
+pub(crate) fn check_path_depth(depth: usize) -> Result<(), Error> {
+    let max = limits().max_path_depth;
+    if depth > max {
+        return Err(LimitError::PathDepth { depth, max }.into());
+    }
+    Ok(())
+}
+
+/// Check the number of items of a batch derivation
+pub(crate) fn check_batch_size(size: u32) -> Result<(), Error> {
+    let max = limits().max_batch_size;
+    if size > max {
+        return Err(LimitError::BatchSize { size, max }.into());
+    }
+    Ok(())
+}
+
+/// Check the number of indices of a range, from `start` up to but excluding
+/// `end`
+pub(crate) fn check_wildcard_range(start: u32, end: u32) -> Result<(), Error> {
+    let size = end.saturating_sub(start);
+    let max = limits().max_wildcard_range;
+    if size > max {
+        return Err(LimitError::WildcardRange { size, max }.into());
+    }
+    Ok(())
+}
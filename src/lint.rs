@@ -0,0 +1,98 @@
+use crate::bip39::Mnemonic;
+use crate::vectors::BIP39_VECTORS;
+use crate::wipe::wipe;
+
+/// Most times a word may appear before it is flagged. A random 24 word
+/// phrase repeats some word one time in eight, but almost never thrice.
+pub const MAX_WORD_REPEATS: usize = 2;
+/// Shortest run of words adjacent in the wordlist that is flagged
+pub const MIN_ORDERED_RUN: usize = 4;
+
+/// Why a phrase should not hold funds, for wallet UIs to surface before
+/// a phrase is accepted
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "code", rename_all = "snake_case")
+)]
+pub enum MnemonicWarning {
+    /// A word appears more than [`MAX_WORD_REPEATS`] times
+    RepeatedWord { word: String, count: usize },
+    /// Consecutive words that are equal or neighbours in the wordlist,
+    /// as in "abandon abandon … about"
+    OrderedSequence { position: usize, len: usize },
+    /// The entropy is that of a published BIP-39 test vector
+    KnownTestVector,
+    /// Fewer than a quarter of the entropy bits are ones, or zeros
+    LowHammingWeight { ones: usize, bits: usize },
+}
+
+impl MnemonicWarning {
+    /// Stable identifier of the warning kind
+    pub fn code(&self) -> &'static str {
+        match self {
+            MnemonicWarning::RepeatedWord { .. } => "repeated_word",
+            MnemonicWarning::OrderedSequence { .. } => "ordered_sequence",
+            MnemonicWarning::KnownTestVector => "known_test_vector",
+            MnemonicWarning::LowHammingWeight { .. } => "low_hamming_weight",
+        }
+    }
+}
+
+impl Mnemonic {
+    /// Check the phrase for signs of weak or well-known entropy. Valid
+    /// phrases can still be unsafe; an empty list means none were found.
+    pub fn audit(&self) -> Vec<MnemonicWarning> {
+        let words: Vec<&str> = self.phrase().split_whitespace().collect();
+        let mut warnings = Vec::new();
+
+        let mut seen: Vec<(&str, usize)> = Vec::new();
+        for &word in &words {
+            match seen.iter_mut().find(|(w, _)| *w == word) {
+                Some((_, count)) => *count += 1,
+                None => seen.push((word, 1)),
+            }
+        }
+        for (word, count) in seen {
+            if count > MAX_WORD_REPEATS {
+                warnings.push(MnemonicWarning::RepeatedWord {
+                    word: word.to_string(),
+                    count,
+                });
+            }
+        }
+
+        let indices: Vec<usize> = words
+            .iter()
+            .filter_map(|word| self.language().index_of(word))
+            .collect();
+        let mut start = 0;
+        for end in 1..=indices.len() {
+            let adjacent = end < indices.len() && indices[end].abs_diff(indices[end - 1]) <= 1;
+            if !adjacent {
+                if end - start >= MIN_ORDERED_RUN {
+                    warnings.push(MnemonicWarning::OrderedSequence {
+                        position: start,
+                        len: end - start,
+                    });
+                }
+                start = end;
+            }
+        }
+
+        let mut entropy = self.to_entropy();
+        let mut hex = hex::encode(&entropy);
+        if BIP39_VECTORS.iter().any(|vector| vector.entropy == hex) {
+            warnings.push(MnemonicWarning::KnownTestVector);
+        }
+        let bits = entropy.len() * 8;
+        let ones: usize = entropy.iter().map(|b| b.count_ones() as usize).sum();
+        if ones.min(bits - ones) < bits / 4 {
+            warnings.push(MnemonicWarning::LowHammingWeight { ones, bits });
+        }
+        wipe(&mut entropy);
+        wipe(&mut hex);
+        warnings
+    }
+}
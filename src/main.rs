@@ -1,70 +1,330 @@
-use bip32hdwallet::bip32::{DerivationPath, ExtendedPrivKey, Network};
+use bip32hdwallet::bip32::{ExtendedPrivKey, Network};
 use bip32hdwallet::bip39::{Language, Mnemonic, MnemonicType};
-use bip32hdwallet::bip44::{AccountLevel, AddressIndex, Bip44Path, Change, CoinType};
+use bip32hdwallet::bip44::{Change, Purpose};
+use bip32hdwallet::config::Config;
+use bip32hdwallet::keystore::TimeLockedKeystore;
+use bip32hdwallet::repl::{Reply, Session};
+use bip32hdwallet::wallet::Wallet;
+use bip32hdwallet::{Error, ErrorEnvelope, ErrorKind};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use zeroize::Zeroize;
 
-fn main() {
-    // Example 1: Generate a mnemonic and use it to derive a Bitcoin wallet
-    println!("Example 1: Generate new mnemonic and wallet");
-    println!("-------------------------------------------");
+/// BIP-32/39/44 hierarchical deterministic wallet tool.
+///
+/// Exit codes: 0 success, 1 internal, 2 usage, 3 mnemonic, 4 path, 5 key,
+/// 6 network, 7 input, 8 crypto, 9 config, 10 limit, 11 storage,
+/// 12 remote, 13 unsupported.
+#[derive(Debug, Parser)]
+#[command(name = "hdwallet", version)]
+struct Cli {
+    /// Print errors to stdout as a JSON object with kind, message and hint
+    #[arg(long, global = true)]
+    json: bool,
 
-    // Generate a new random mnemonic
-    let mnemonic = Mnemonic::generate(MnemonicType::Words12, Language::English)
-        .expect("Failed to generate mnemonic");
+    /// Network of derived keys and addresses
+    #[arg(long, global = true, value_enum, default_value_t = NetworkArg::Bitcoin)]
+    network: NetworkArg,
 
-    println!("Mnemonic: {}", mnemonic);
+    /// Only accept canonical `m/...` derivation paths
+    #[arg(long, global = true)]
+    strict_paths: bool,
 
-    // Generate a seed from the mnemonic
-    let seed = mnemonic.to_seed("");
+    #[command(subcommand)]
+    command: Command,
+}
 
-    // Create a master key from the seed (for Bitcoin mainnet)
-    let master_key = ExtendedPrivKey::new_master(seed.as_bytes(), Network::Bitcoin)
-        .expect("Failed to create master key");
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum NetworkArg {
+    Bitcoin,
+    Testnet,
+}
 
-    // Create a BIP-44 path for the first account, first external address
-    let path = Bip44Path::standard(
-        CoinType::BITCOIN,
-        AccountLevel::new(0),
-        Change::External,
-        AddressIndex::new(0),
-    );
+impl From<NetworkArg> for Network {
+    fn from(network: NetworkArg) -> Self {
+        match network {
+            NetworkArg::Bitcoin => Network::Bitcoin,
+            NetworkArg::Testnet => Network::Testnet,
+        }
+    }
+}
 
-    // Derive the child key
-    let child_key = master_key
-        .derive_path(&path.to_derivation_path())
-        .expect("Failed to derive child key");
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate a random English mnemonic
+    Generate {
+        /// Number of words
+        #[arg(long, default_value_t = 12)]
+        words: usize,
+    },
+    /// Check a mnemonic phrase, read from stdin when not given
+    Validate {
+        /// The phrase
+        phrase: Option<String>,
+    },
+    /// Print the extended keys at a derivation path
+    Derive {
+        /// Derivation path, such as m/84'/0'/0'
+        path: String,
+        /// Mnemonic phrase, read from stdin when not given
+        #[arg(long)]
+        phrase: Option<String>,
+        /// BIP-39 passphrase
+        #[arg(long, default_value = "")]
+        passphrase: String,
+    },
+    /// Print addresses of account 0
+    Address {
+        /// Purpose selecting the address type (44, 49 or 84)
+        #[arg(long, default_value_t = 84)]
+        purpose: u32,
+        /// Print change addresses instead of receive addresses
+        #[arg(long)]
+        change: bool,
+        /// First address index
+        #[arg(long, default_value_t = 0)]
+        index: u32,
+        /// Number of addresses
+        #[arg(long, default_value_t = 1)]
+        count: u32,
+        /// Mnemonic phrase, read from stdin when not given
+        #[arg(long)]
+        phrase: Option<String>,
+        /// BIP-39 passphrase
+        #[arg(long, default_value = "")]
+        passphrase: String,
+    },
+    /// Start an interactive session over a key unlocked once, reading
+    /// commands from stdin (type `help` for the list)
+    Repl {
+        /// Time-locked keystore file; its passphrase is read from stdin.
+        /// Without it, the mnemonic phrase is read from stdin.
+        #[arg(long)]
+        keystore: Option<PathBuf>,
+        /// Purpose selecting the address type (44, 49 or 84)
+        #[arg(long, default_value_t = 84)]
+        purpose: u32,
+        /// BIP-39 passphrase, when starting from a mnemonic
+        #[arg(long, default_value = "")]
+        passphrase: String,
+    },
+    /// Print a shell completion script
+    Completions {
+        /// Shell to complete for
+        shell: Shell,
+    },
+    /// Print the man page in roff format
+    Man,
+}
 
-    // Get the extended public key
-    let xpub = child_key.to_extended_public_key();
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) if !e.use_stderr() => {
+            let _ = e.print();
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            if std::env::args_os().any(|arg| arg == "--json") {
+                // Clap's message without the usage block that follows it
+                let rendered = e.to_string();
+                let message = rendered.split("\n\n").next().unwrap_or_default();
+                let envelope = ErrorEnvelope {
+                    kind: ErrorKind::Usage,
+                    message: message.trim_start_matches("error: ").to_string(),
+                    hint: Some("run with --help for usage".to_string()),
+                };
+                print_envelope(&envelope);
+            } else {
+                let _ = e.print();
+            }
+            return ExitCode::from(ErrorKind::Usage.exit_code());
+        }
+    };
 
-    println!("Extended Private Key: {}", child_key.to_string());
-    println!("Extended Public Key: {}", xpub.to_string());
-    println!("Derivation Path: {}", path);
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let envelope = ErrorEnvelope::from(&e);
+            if cli.json {
+                print_envelope(&envelope);
+            } else {
+                eprintln!("error: {}", envelope.message);
+                if let Some(hint) = &envelope.hint {
+                    eprintln!("hint: {}", hint);
+                }
+            }
+            ExitCode::from(envelope.kind.exit_code())
+        }
+    }
+}
 
-    // Example 2: Import a mnemonic and use a custom derivation path
-    println!("\nExample 2: Import mnemonic and use custom path");
-    println!("-------------------------------------------");
+fn print_envelope(envelope: &ErrorEnvelope) {
+    match serde_json::to_string(envelope) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("error: {}", e),
+    }
+}
 
-    let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
-    let mnemonic =
-        Mnemonic::from_phrase(phrase, Language::English).expect("Failed to import mnemonic");
+fn run(cli: &Cli) -> Result<(), Error> {
+    let config = Config::new()
+        .with_network(cli.network.into())
+        .with_strict_paths(cli.strict_paths);
 
-    println!("Mnemonic: {}", mnemonic);
+    match &cli.command {
+        Command::Generate { words } => {
+            let mnemonic =
+                Mnemonic::generate(MnemonicType::from_word_count(*words)?, Language::English)?;
+            println!("{}", mnemonic);
+        }
+        Command::Validate { phrase } => {
+            Mnemonic::from_phrase_any_language(&read_phrase(phrase.as_deref())?)?;
+            println!("valid");
+        }
+        Command::Derive {
+            path,
+            phrase,
+            passphrase,
+        } => {
+            let path = config.parse_path(path)?;
+            let mnemonic = Mnemonic::from_phrase_any_language(&read_phrase(phrase.as_deref())?)?;
+            let seed = mnemonic.to_seed(passphrase);
+            let key =
+                ExtendedPrivKey::new_master(seed.as_bytes(), config.network)?.derive_path(&path)?;
+            println!("path: {}", config.format_path(&path));
+            println!("xprv: {}", key);
+            println!("xpub: {}", key.to_extended_public_key());
+        }
+        Command::Address {
+            purpose,
+            change,
+            index,
+            count,
+            phrase,
+            passphrase,
+        } => {
+            let config = config.with_purpose(Purpose(*purpose));
+            let mnemonic = Mnemonic::from_phrase_any_language(&read_phrase(phrase.as_deref())?)?;
+            let wallet = Wallet::from_config(&mnemonic.to_seed(passphrase), &config)?;
+            let change = if *change {
+                Change::Internal
+            } else {
+                Change::External
+            };
+            for address in wallet.addresses(0, change, *index, *count)? {
+                println!("{}", address);
+            }
+        }
+        Command::Repl {
+            keystore,
+            purpose,
+            passphrase,
+        } => {
+            let config = config.with_purpose(Purpose(*purpose));
+            let session = match keystore {
+                Some(file) => {
+                    let data = std::fs::read(file)
+                        .map_err(|e| Error::Storage(format!("Cannot read keystore: {}", e)))?;
+                    let keystore = TimeLockedKeystore::from_bytes(&data)?;
+                    let mut passphrase = read_passphrase("keystore passphrase: ")?;
+                    let seed = keystore.open(&passphrase);
+                    passphrase.zeroize();
+                    let mut seed = seed?;
+                    let session = Session::from_seed(&seed, config);
+                    seed.zeroize();
+                    session?
+                }
+                None => {
+                    let mnemonic = Mnemonic::from_phrase_any_language(&read_phrase(None)?)?;
+                    Session::from_seed(mnemonic.to_seed(passphrase).as_bytes(), config)?
+                }
+            };
+            repl(&session, cli.json);
+        }
+        Command::Completions { shell } => write_completions(*shell, &mut std::io::stdout()),
+        Command::Man => write_man(&mut std::io::stdout())?,
+    }
+    Ok(())
+}
 
-    let seed = mnemonic.to_seed("passphrase");
+/// Run session commands from stdin until `quit` or end of input. Errors
+/// are reported without ending the session.
+fn repl(session: &Session, json: bool) {
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        eprint!("hdwallet> ");
+        let _ = std::io::stderr().flush();
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        match session.execute(&line) {
+            Ok(Reply::Output(output)) if output.is_empty() => {}
+            Ok(Reply::Output(output)) => println!("{}", output),
+            Ok(Reply::Quit) => break,
+            Err(e) if json => print_envelope(&ErrorEnvelope::from(&e)),
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+}
 
-    let master_key = ExtendedPrivKey::new_master(seed.as_bytes(), Network::Bitcoin)
-        .expect("Failed to create master key");
+/// Write the completion script of a shell
+fn write_completions(shell: Shell, out: &mut impl Write) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, out);
+}
+
+/// Write the man page in roff format
+fn write_man(out: &mut impl Write) -> Result<(), Error> {
+    clap_mangen::Man::new(Cli::command())
+        .render(out)
+        .map_err(|e| Error::Output(format!("man page: {}", e)))
+}
+
+/// Prompt for a passphrase on the terminal without echoing it, or read one
+/// line from stdin when it is not a terminal
+fn read_passphrase(prompt: &str) -> Result<String, Error> {
+    if !std::io::stdin().is_terminal() {
+        return read_phrase(None);
+    }
+    rpassword::prompt_password(prompt).map_err(|e| Error::Input(format!("passphrase: {}", e)))
+}
+
+/// Use the phrase given as an argument, or read one line from stdin
+fn read_phrase(phrase: Option<&str>) -> Result<String, Error> {
+    if let Some(phrase) = phrase {
+        return Ok(phrase.to_string());
+    }
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| Error::Input(format!("phrase from stdin: {}", e)))?;
+    Ok(line.trim().to_string())
+}
 
-    // Custom derivation path (m/49'/0'/0'/0/0 for SegWit)
-    let path = DerivationPath::from_str("m/49'/0'/0'/0/0").expect("Failed to parse path");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let child_key = master_key
-        .derive_path(&path)
-        .expect("Failed to derive child key");
+    #[test]
+    fn test_completions_and_man_page() {
+        let mut script = Vec::new();
+        write_completions(Shell::Bash, &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("complete -F _hdwallet"));
 
-    let xpub = child_key.to_extended_public_key();
+        let mut man = Vec::new();
+        write_man(&mut man).unwrap();
+        let man = String::from_utf8(man).unwrap();
+        assert!(man.contains(".TH hdwallet 1"));
 
-    println!("Extended Private Key: {}", child_key.to_string());
-    println!("Extended Public Key: {}", xpub.to_string());
-    println!("Derivation Path: {}", path);
+        for subcommand in ["derive", "address", "repl"] {
+            assert!(script.contains(subcommand), "{} not completed", subcommand);
+            assert!(man.contains(subcommand), "{} not in man page", subcommand);
+        }
+    }
 }
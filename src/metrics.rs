@@ -0,0 +1,138 @@
+use crate::bip44::Change;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Kind of signature reported to a [`MetricsObserver`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignatureKind {
+    /// Bitcoin-style ECDSA over a digest
+    Ecdsa,
+    /// Recoverable ECDSA, as for Ethereum and signed messages
+    EcdsaRecoverable,
+    /// ECDSA with the anti-exfil nonce protocol
+    AntiExfil,
+    /// Ed25519, from SLIP-10 or Substrate keys
+    Ed25519,
+    /// Substrate sr25519 (Schnorr over Ristretto)
+    Sr25519,
+}
+
+/// Receives counts and timings of wallet operations, so hot-wallet operators
+/// can alert on unusual signing activity. Every method does nothing by
+/// default; implement the ones to export.
+pub trait MetricsObserver: Send + Sync {
+    /// A key was derived along `depth` path components in `elapsed`
+    fn derivation(&self, _depth: usize, _elapsed: Duration) {}
+
+    /// A signature was produced
+    fn signature(&self, _kind: SignatureKind) {}
+
+    /// A [`crate::cache::DerivationCache`] lookup hit or missed
+    fn cache_lookup(&self, _hit: bool) {}
+
+    /// Discovery finished scanning a chain of an account
+    fn scan_progress(&self, _account: u32, _change: Change, _scanned: u32, _used: u32) {}
+}
+
+static OBSERVER: RwLock<Option<Arc<dyn MetricsObserver>>> = RwLock::new(None);
+
+/// Report metrics of the whole process to an observer
+pub fn set_observer(observer: Arc<dyn MetricsObserver>) {
+    *OBSERVER.write().unwrap_or_else(|e| e.into_inner()) = Some(observer);
+}
+
+/// Stop reporting metrics
+pub fn clear_observer() {
+    *OBSERVER.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Call the observer, if one is set
+pub(crate) fn observe(f: impl FnOnce(&dyn MetricsObserver)) {
+    let observer = OBSERVER.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(observer) = observer {
+        f(observer.as_ref());
+    }
+}
+
+/// Report a derivation started at `start`
+pub(crate) fn derivation(depth: usize, start: Instant) {
+    observe(|o| o.derivation(depth, start.elapsed()));
+}
+
+/// Report a signature
+pub(crate) fn signature(kind: SignatureKind) {
+    observe(|o| o.signature(kind));
+}
+
+/// Totals collected by a [`CountingObserver`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub derivations: u64,
+    pub derivation_time: Duration,
+    pub signatures: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub addresses_scanned: u64,
+    pub addresses_used: u64,
+}
+
+/// An observer keeping running totals, for polling exporters and tests
+#[derive(Debug, Default)]
+pub struct CountingObserver {
+    derivations: AtomicU64,
+    derivation_nanos: AtomicU64,
+    signatures: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    addresses_scanned: AtomicU64,
+    addresses_used: AtomicU64,
+}
+
+impl CountingObserver {
+    /// Create an observer with all totals at zero
+    pub fn new() -> Self {
+        CountingObserver::default()
+    }
+
+    /// Read the totals so far
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            derivations: self.derivations.load(Ordering::Relaxed),
+            derivation_time: Duration::from_nanos(self.derivation_nanos.load(Ordering::Relaxed)),
+            signatures: self.signatures.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            addresses_scanned: self.addresses_scanned.load(Ordering::Relaxed),
+            addresses_used: self.addresses_used.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl MetricsObserver for CountingObserver {
+    fn derivation(&self, _depth: usize, elapsed: Duration) {
+        self.derivations.fetch_add(1, Ordering::Relaxed);
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.derivation_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    fn signature(&self, _kind: SignatureKind) {
+        self.signatures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn cache_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn scan_progress(&self, _account: u32, _change: Change, scanned: u32, used: u32) {
+        self.addresses_scanned
+            .fetch_add(scanned as u64, Ordering::Relaxed);
+        self.addresses_used
+            .fetch_add(used as u64, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,504 @@
+use crate::address;
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use crate::bip44::Change;
+use crate::descriptor::{self, KeyOrigin};
+use crate::error::Error;
+use crate::utils;
+use bech32::segwit;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Most keys a `multi` fragment may hold
+const MAX_MULTI_KEYS: usize = 20;
+/// Timelock values at or above this are locktime-disabling flags or out of range
+const MAX_TIMELOCK: u32 = 1 << 31;
+
+/// A spending policy over named keys, e.g. `or(pk(A),and(pk(B),pk(C)))`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Policy {
+    /// A signature by the named key
+    Key(String),
+    /// Both sub-policies
+    And(Box<Policy>, Box<Policy>),
+    /// Either sub-policy
+    Or(Box<Policy>, Box<Policy>),
+    /// At least `k` of the sub-policies
+    Threshold(usize, Vec<Policy>),
+    /// A relative timelock (`OP_CHECKSEQUENCEVERIFY`) of `n` blocks, or of
+    /// `n & 0xffff` units of 512 seconds when bit 22 is set
+    Older(u32),
+    /// An absolute timelock (`OP_CHECKLOCKTIMEVERIFY`): a block height below
+    /// 500000000, a Unix time otherwise
+    After(u32),
+}
+
+impl FromStr for Policy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        let (name, args) = s
+            .strip_suffix(')')
+            .and_then(|rest| rest.split_once('('))
+            .ok_or_else(|| Error::InvalidDescriptor(format!("Invalid policy '{}'", s)))?;
+        let args = split_args(args)?;
+
+        let subs = |args: &[&str]| -> Result<Vec<Policy>, Error> {
+            args.iter().map(|arg| arg.parse()).collect()
+        };
+        match (name, args.as_slice()) {
+            ("pk", [key]) if !key.is_empty() && !key.contains(['(', ')']) => {
+                Ok(Policy::Key(key.to_string()))
+            }
+            ("older", [n]) => Ok(Policy::Older(parse_timelock(n)?)),
+            ("after", [n]) => Ok(Policy::After(parse_timelock(n)?)),
+            ("and", [a, b]) => Ok(Policy::And(Box::new(a.parse()?), Box::new(b.parse()?))),
+            ("or", [a, b]) => Ok(Policy::Or(Box::new(a.parse()?), Box::new(b.parse()?))),
+            ("thresh", [k, rest @ ..]) => {
+                let k = k
+                    .parse()
+                    .map_err(|_| Error::InvalidDescriptor(format!("Invalid threshold '{}'", k)))?;
+                let subs = subs(rest)?;
+                if k == 0 || k > subs.len() {
+                    return Err(Error::InvalidDescriptor(format!(
+                        "Invalid {}-of-{} threshold",
+                        k,
+                        subs.len()
+                    )));
+                }
+                Ok(Policy::Threshold(k, subs))
+            }
+            _ => Err(Error::InvalidDescriptor(format!(
+                "Unsupported policy fragment '{}'",
+                name
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Policy::Key(name) => write!(f, "pk({})", name),
+            Policy::And(a, b) => write!(f, "and({},{})", a, b),
+            Policy::Or(a, b) => write!(f, "or({},{})", a, b),
+            Policy::Older(n) => write!(f, "older({})", n),
+            Policy::After(n) => write!(f, "after({})", n),
+            Policy::Threshold(k, subs) => {
+                write!(f, "thresh({}", k)?;
+                for sub in subs {
+                    write!(f, ",{}", sub)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+fn parse_timelock(n: &str) -> Result<u32, Error> {
+    n.parse()
+        .ok()
+        .filter(|n| (1..MAX_TIMELOCK).contains(n))
+        .ok_or_else(|| Error::InvalidDescriptor(format!("Invalid timelock '{}'", n)))
+}
+
+/// Split comma-separated arguments at the top nesting level
+fn split_args(args: &str) -> Result<Vec<&str>, Error> {
+    let mut result = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| Error::InvalidDescriptor("Unbalanced parentheses".to_string()))?
+            }
+            ',' if depth == 0 => {
+                result.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(Error::InvalidDescriptor(
+            "Unbalanced parentheses".to_string(),
+        ));
+    }
+    result.push(&args[start..]);
+    Ok(result)
+}
+
+/// A key a policy refers to: an account-level xpub and its origin. Scripts
+/// use its `change/index` children.
+#[derive(Debug, Clone)]
+pub struct PolicyKey {
+    pub origin: KeyOrigin,
+    pub xpub: ExtendedPubKey,
+}
+
+impl PolicyKey {
+    /// Derive the key at an account path of a master key
+    pub fn from_master(master: &ExtendedPrivKey, path: &DerivationPath) -> Result<Self, Error> {
+        Ok(PolicyKey {
+            origin: KeyOrigin {
+                fingerprint: master.fingerprint(),
+                path: path.clone(),
+            },
+            xpub: master.derive_path(path)?.to_extended_public_key(),
+        })
+    }
+}
+
+/// A miniscript fragment, referring to keys by position
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Fragment {
+    /// `pk(K)`, i.e. `c:pk_k(K)`
+    Pk(usize),
+    Multi(usize, Vec<usize>),
+    Older(u32),
+    After(u32),
+    /// `v:X`
+    Verify(Box<Fragment>),
+    /// `s:X`
+    Swap(Box<Fragment>),
+    /// `a:X`
+    Alt(Box<Fragment>),
+    AndV(Box<Fragment>, Box<Fragment>),
+    OrD(Box<Fragment>, Box<Fragment>),
+    OrI(Box<Fragment>, Box<Fragment>),
+    Thresh(usize, Vec<Fragment>),
+}
+
+/// A compiled base-type fragment and whether it can be dissatisfied
+/// without a signature (`d`) and leaves exactly 1 on success (`u`)
+struct Compiled {
+    fragment: Fragment,
+    du: bool,
+}
+
+/// A policy compiled to a P2WSH miniscript over HD keys
+#[derive(Debug, Clone)]
+pub struct CompiledPolicy {
+    fragment: Fragment,
+    keys: Vec<PolicyKey>,
+}
+
+impl Policy {
+    /// Compile the policy, looking up each named key
+    pub fn compile(&self, keys: &BTreeMap<String, PolicyKey>) -> Result<CompiledPolicy, Error> {
+        let mut names = Vec::new();
+        let compiled = compile(self, &mut names)?;
+        if names.is_empty() {
+            return Err(Error::InvalidDescriptor(
+                "Policy needs at least one key".to_string(),
+            ));
+        }
+
+        let keys = names
+            .iter()
+            .map(|name| {
+                keys.get(name)
+                    .cloned()
+                    .ok_or_else(|| Error::InvalidDescriptor(format!("Unknown key '{}'", name)))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        if keys
+            .windows(2)
+            .any(|pair| pair[0].xpub.network != pair[1].xpub.network)
+        {
+            return Err(Error::NetworkMismatch(
+                "Policy keys are on different networks".to_string(),
+            ));
+        }
+
+        Ok(CompiledPolicy {
+            fragment: compiled.fragment,
+            keys,
+        })
+    }
+}
+
+fn compile(policy: &Policy, names: &mut Vec<String>) -> Result<Compiled, Error> {
+    // Repeated keys would make satisfactions malleable
+    let mut key_index = |name: &str| {
+        if names.iter().any(|n| n == name) {
+            return Err(Error::InvalidDescriptor(format!(
+                "Key '{}' is used more than once",
+                name
+            )));
+        }
+        names.push(name.to_string());
+        Ok(names.len() - 1)
+    };
+
+    Ok(match policy {
+        Policy::Key(name) => Compiled {
+            fragment: Fragment::Pk(key_index(name)?),
+            du: true,
+        },
+        Policy::Older(n) | Policy::After(n) if !(1..MAX_TIMELOCK).contains(n) => {
+            return Err(Error::InvalidDescriptor(format!("Invalid timelock {}", n)))
+        }
+        Policy::Older(n) => Compiled {
+            fragment: Fragment::Older(*n),
+            du: false,
+        },
+        Policy::After(n) => Compiled {
+            fragment: Fragment::After(*n),
+            du: false,
+        },
+        Policy::And(a, b) => {
+            let (a, b) = (compile(a, names)?, compile(b, names)?);
+            Compiled {
+                fragment: Fragment::AndV(
+                    Box::new(Fragment::Verify(Box::new(a.fragment))),
+                    Box::new(b.fragment),
+                ),
+                du: false,
+            }
+        }
+        Policy::Or(a, b) => {
+            let (a, b) = (compile(a, names)?, compile(b, names)?);
+            // or_d needs a dissatisfiable left branch; or_i takes any two
+            let (a, b) = if !a.du && b.du { (b, a) } else { (a, b) };
+            if a.du {
+                Compiled {
+                    du: b.du,
+                    fragment: Fragment::OrD(Box::new(a.fragment), Box::new(b.fragment)),
+                }
+            } else {
+                Compiled {
+                    fragment: Fragment::OrI(Box::new(a.fragment), Box::new(b.fragment)),
+                    du: false,
+                }
+            }
+        }
+        Policy::Threshold(k, subs) => {
+            if *k == 0 || *k > subs.len() {
+                return Err(Error::InvalidDescriptor(format!(
+                    "Invalid {}-of-{} threshold",
+                    k,
+                    subs.len()
+                )));
+            }
+            if subs.len() <= MAX_MULTI_KEYS && subs.iter().all(|sub| matches!(sub, Policy::Key(_)))
+            {
+                let keys = subs
+                    .iter()
+                    .map(|sub| match sub {
+                        Policy::Key(name) => key_index(name),
+                        _ => unreachable!("all subs are keys"),
+                    })
+                    .collect::<Result<_, Error>>()?;
+                return Ok(Compiled {
+                    fragment: Fragment::Multi(*k, keys),
+                    du: true,
+                });
+            }
+
+            let mut fragments = Vec::with_capacity(subs.len());
+            for (i, sub) in subs.iter().enumerate() {
+                let sub = compile(sub, names)?;
+                if !sub.du {
+                    return Err(Error::InvalidDescriptor(
+                        "Threshold sub-policies must be dissatisfiable".to_string(),
+                    ));
+                }
+                fragments.push(match (i, sub.fragment) {
+                    (0, fragment) => fragment,
+                    (_, Fragment::Pk(key)) => Fragment::Swap(Box::new(Fragment::Pk(key))),
+                    (_, fragment) => Fragment::Alt(Box::new(fragment)),
+                });
+            }
+            Compiled {
+                fragment: Fragment::Thresh(*k, fragments),
+                du: true,
+            }
+        }
+    })
+}
+
+impl CompiledPolicy {
+    /// Keys of the policy, in the order they first appear
+    pub fn keys(&self) -> &[PolicyKey] {
+        &self.keys
+    }
+
+    /// The miniscript of one chain, with keys as descriptor key expressions
+    pub fn miniscript(&self, change: Change) -> String {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| format!("[{}]{}/{}/*", key.origin, key.xpub, change))
+            .collect();
+        render(&self.fragment, &keys)
+    }
+
+    /// The `wsh()` descriptor of one chain, with checksum
+    pub fn descriptor(&self, change: Change) -> String {
+        descriptor::with_checksum(&format!("wsh({})", self.miniscript(change)))
+            .expect("descriptor characters are valid")
+    }
+
+    /// The witness script at an address index
+    pub fn witness_script(&self, change: Change, index: u32) -> Result<Vec<u8>, Error> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| {
+                Ok(key
+                    .xpub
+                    .derive_child(change.child_number())?
+                    .derive_child(ChildNumber::Normal(index))?
+                    .public_key
+                    .serialize())
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut script = Vec::new();
+        encode(&self.fragment, &keys, &mut script);
+        Ok(script)
+    }
+
+    /// The P2WSH scriptPubKey at an address index
+    pub fn script_pubkey(&self, change: Change, index: u32) -> Result<Vec<u8>, Error> {
+        let mut script = vec![0x00, 0x20];
+        script.extend_from_slice(&utils::sha256(&self.witness_script(change, index)?));
+        Ok(script)
+    }
+
+    /// The P2WSH address at an address index
+    pub fn address(&self, change: Change, index: u32) -> Result<String, Error> {
+        let program = utils::sha256(&self.witness_script(change, index)?);
+        let network = self.keys[0].xpub.network;
+        segwit::encode_v0(address::segwit_hrp(network), &program)
+            .map_err(|e| Error::InvalidAddress(e.to_string()))
+    }
+}
+
+fn render(fragment: &Fragment, keys: &[String]) -> String {
+    match fragment {
+        Fragment::Pk(key) => format!("pk({})", keys[*key]),
+        Fragment::Multi(k, members) => {
+            let members: Vec<&str> = members.iter().map(|m| keys[*m].as_str()).collect();
+            format!("multi({},{})", k, members.join(","))
+        }
+        Fragment::Older(n) => format!("older({})", n),
+        Fragment::After(n) => format!("after({})", n),
+        Fragment::Verify(x) => format!("v:{}", render(x, keys)),
+        Fragment::Swap(x) => format!("s:{}", render(x, keys)),
+        Fragment::Alt(x) => format!("a:{}", render(x, keys)),
+        Fragment::AndV(x, y) => format!("and_v({},{})", render(x, keys), render(y, keys)),
+        Fragment::OrD(x, z) => format!("or_d({},{})", render(x, keys), render(z, keys)),
+        Fragment::OrI(x, z) => format!("or_i({},{})", render(x, keys), render(z, keys)),
+        Fragment::Thresh(k, subs) => {
+            let subs: Vec<String> = subs.iter().map(|sub| render(sub, keys)).collect();
+            format!("thresh({},{})", k, subs.join(","))
+        }
+    }
+}
+
+fn encode(fragment: &Fragment, keys: &[[u8; 33]], script: &mut Vec<u8>) {
+    match fragment {
+        Fragment::Pk(key) => {
+            push_key(&keys[*key], script);
+            script.push(0xac); // OP_CHECKSIG
+        }
+        Fragment::Multi(k, members) => {
+            push_int(*k as i64, script);
+            for member in members {
+                push_key(&keys[*member], script);
+            }
+            push_int(members.len() as i64, script);
+            script.push(0xae); // OP_CHECKMULTISIG
+        }
+        Fragment::Older(n) => {
+            push_int(*n as i64, script);
+            script.push(0xb2); // OP_CHECKSEQUENCEVERIFY
+        }
+        Fragment::After(n) => {
+            push_int(*n as i64, script);
+            script.push(0xb1); // OP_CHECKLOCKTIMEVERIFY
+        }
+        Fragment::Verify(x) => {
+            encode(x, keys, script);
+            // Fold into the VERIFY form of the final opcode where one exists.
+            // Every fragment's encoding ends in an opcode, never in pushed data.
+            let last = script.last_mut().expect("fragments are never empty");
+            match *last {
+                0xac => *last = 0xad,   // OP_CHECKSIG
+                0xae => *last = 0xaf,   // OP_CHECKMULTISIG
+                0x87 => *last = 0x88,   // OP_EQUAL
+                0x9c => *last = 0x9d,   // OP_NUMEQUAL
+                _ => script.push(0x69), // OP_VERIFY
+            }
+        }
+        Fragment::Swap(x) => {
+            script.push(0x7c); // OP_SWAP
+            encode(x, keys, script);
+        }
+        Fragment::Alt(x) => {
+            script.push(0x6b); // OP_TOALTSTACK
+            encode(x, keys, script);
+            script.push(0x6c); // OP_FROMALTSTACK
+        }
+        Fragment::AndV(x, y) => {
+            encode(x, keys, script);
+            encode(y, keys, script);
+        }
+        Fragment::OrD(x, z) => {
+            encode(x, keys, script);
+            script.extend_from_slice(&[0x73, 0x64]); // OP_IFDUP OP_NOTIF
+            encode(z, keys, script);
+            script.push(0x68); // OP_ENDIF
+        }
+        Fragment::OrI(x, z) => {
+            script.push(0x63); // OP_IF
+            encode(x, keys, script);
+            script.push(0x67); // OP_ELSE
+            encode(z, keys, script);
+            script.push(0x68); // OP_ENDIF
+        }
+        Fragment::Thresh(k, subs) => {
+            for (i, sub) in subs.iter().enumerate() {
+                encode(sub, keys, script);
+                if i > 0 {
+                    script.push(0x93); // OP_ADD
+                }
+            }
+            push_int(*k as i64, script);
+            script.push(0x87); // OP_EQUAL
+        }
+    }
+}
+
+fn push_key(key: &[u8; 33], script: &mut Vec<u8>) {
+    script.push(33);
+    script.extend_from_slice(key);
+}
+
+/// Push a number as a minimally encoded script integer
+fn push_int(n: i64, script: &mut Vec<u8>) {
+    match n {
+        0 => script.push(0x00),
+        1..=16 => script.push(0x50 + n as u8),
+        _ => {
+            let mut bytes = Vec::new();
+            let mut abs = n.unsigned_abs();
+            while abs > 0 {
+                bytes.push(abs as u8);
+                abs >>= 8;
+            }
+            if bytes.last().is_some_and(|b| b & 0x80 != 0) {
+                bytes.push(if n < 0 { 0x80 } else { 0x00 });
+            } else if n < 0 {
+                *bytes.last_mut().expect("non-zero") |= 0x80;
+            }
+            script.push(bytes.len() as u8);
+            script.extend_from_slice(&bytes);
+        }
+    }
+}
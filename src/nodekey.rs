@@ -0,0 +1,79 @@
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey};
+use crate::bip44::CoinType;
+use crate::error::Error;
+use crate::utils;
+use crate::wallet::Wallet;
+use secp256k1::{PublicKey, SecretKey};
+
+/// Purpose of node identity paths, following lnd's `m/1017'/coin'/family'/0/index`
+pub const NODE_KEY_PURPOSE: u32 = 1017;
+
+/// What a node identity key is for, selecting the key family of its path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKeyFamily {
+    /// Lightning node id, lnd's node key family
+    Lightning,
+    /// Static identity for BIP-324 v2 transport peers
+    P2pTransport,
+}
+
+impl NodeKeyFamily {
+    /// Key family index in the path
+    pub fn index(&self) -> u32 {
+        match self {
+            NodeKeyFamily::Lightning => 6,
+            NodeKeyFamily::P2pTransport => 324,
+        }
+    }
+}
+
+/// A node identity key pair and where it was derived
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeIdentity {
+    pub path: DerivationPath,
+    pub secret_key: SecretKey,
+    pub public_key: PublicKey,
+}
+
+impl NodeIdentity {
+    /// Hex-encoded compressed public key, as node ids are shown
+    pub fn node_id(&self) -> String {
+        hex::encode(self.public_key.serialize())
+    }
+}
+
+/// Get the path of a node identity: `m/1017'/coin'/family'/0/index`
+pub fn path(coin_type: CoinType, family: NodeKeyFamily, index: u32) -> DerivationPath {
+    DerivationPath {
+        path: vec![
+            ChildNumber::Hardened(NODE_KEY_PURPOSE),
+            coin_type.child_number(),
+            ChildNumber::Hardened(family.index()),
+            ChildNumber::Normal(0),
+            ChildNumber::Normal(index),
+        ],
+    }
+}
+
+/// Derive the node identity at an index of a key family
+pub fn node_identity(
+    master: &ExtendedPrivKey,
+    family: NodeKeyFamily,
+    index: u32,
+) -> Result<NodeIdentity, Error> {
+    let path = path(CoinType::for_network(master.network), family, index);
+    let secret_key = master.derive_path(&path)?.private_key;
+    Ok(NodeIdentity {
+        path,
+        public_key: PublicKey::from_secret_key(utils::secp(), &secret_key),
+        secret_key,
+    })
+}
+
+impl Wallet {
+    /// Get the node identity at an index of a key family, so networking
+    /// layers are restored from the same backup as the wallet
+    pub fn node_identity(&self, family: NodeKeyFamily, index: u32) -> Result<NodeIdentity, Error> {
+        node_identity(self.master(), family, index)
+    }
+}
@@ -0,0 +1,76 @@
+use crate::bip32::ExtendedPrivKey;
+use crate::bip44::{AccountLevel, AddressIndex, Bip44Path, Change, CoinType};
+use crate::error::Error;
+use crate::utils;
+use bech32::{Bech32, Hrp};
+use secp256k1::{SecretKey, XOnlyPublicKey};
+
+const NPUB_HRP: Hrp = Hrp::parse_unchecked("npub");
+const NSEC_HRP: Hrp = Hrp::parse_unchecked("nsec");
+
+/// A Nostr identity keypair (NIP-06)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NostrKeys {
+    pub secret_key: SecretKey,
+    pub public_key: XOnlyPublicKey,
+}
+
+impl NostrKeys {
+    /// Derive the Nostr keys at m/44'/1237'/account'/0/0
+    pub fn from_master(master: &ExtendedPrivKey, account: u32) -> Result<Self, Error> {
+        let path = Bip44Path::standard(
+            CoinType::NOSTR,
+            AccountLevel::new(account),
+            Change::External,
+            AddressIndex::new(0),
+        );
+        let secret_key = master.derive_path(&path.to_derivation_path())?.private_key;
+
+        Ok(NostrKeys {
+            secret_key,
+            public_key: secret_key.x_only_public_key(utils::secp()).0,
+        })
+    }
+
+    /// Get the bech32 public key (npub)
+    pub fn npub(&self) -> String {
+        encode_npub(&self.public_key)
+    }
+
+    /// Get the bech32 secret key (nsec)
+    pub fn nsec(&self) -> String {
+        encode_nsec(&self.secret_key)
+    }
+}
+
+fn decode(s: &str, expected_hrp: Hrp) -> Result<Vec<u8>, Error> {
+    let (hrp, data) = bech32::decode(s).map_err(|e| Error::InvalidKey(e.to_string()))?;
+    if hrp != expected_hrp {
+        return Err(Error::InvalidKey(format!(
+            "Expected {} prefix",
+            expected_hrp.as_str()
+        )));
+    }
+    Ok(data)
+}
+
+/// Encode an x-only public key as npub
+pub fn encode_npub(public_key: &XOnlyPublicKey) -> String {
+    bech32::encode::<Bech32>(NPUB_HRP, &public_key.serialize())
+        .expect("32-byte payload is always valid")
+}
+
+/// Decode an npub into an x-only public key
+pub fn decode_npub(npub: &str) -> Result<XOnlyPublicKey, Error> {
+    Ok(XOnlyPublicKey::from_slice(&decode(npub, NPUB_HRP)?)?)
+}
+
+/// Encode a secret key as nsec
+pub fn encode_nsec(secret_key: &SecretKey) -> String {
+    bech32::encode::<Bech32>(NSEC_HRP, &secret_key[..]).expect("32-byte payload is always valid")
+}
+
+/// Decode an nsec into a secret key
+pub fn decode_nsec(nsec: &str) -> Result<SecretKey, Error> {
+    Ok(SecretKey::from_slice(&decode(nsec, NSEC_HRP)?)?)
+}
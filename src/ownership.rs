@@ -0,0 +1,121 @@
+use crate::address::{self, AddressType};
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPubKey};
+use crate::bip44::Change;
+use crate::error::Error;
+use crate::limits;
+use crate::psbt::{AccountKey, TxOut};
+use crate::wallet::Wallet;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Who a transaction output pays, for wallet history and accounting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputOwnership {
+    /// Pays someone else
+    External,
+    /// Pays a receive address of ours, at the given full path
+    Receive(DerivationPath),
+    /// Pays a change address of ours, at the given full path
+    Change(DerivationPath),
+}
+
+impl OutputOwnership {
+    /// Whether the output pays one of our addresses
+    pub fn is_ours(&self) -> bool {
+        !matches!(self, OutputOwnership::External)
+    }
+
+    /// Full derivation path of the address paid, if ours
+    pub fn path(&self) -> Option<&DerivationPath> {
+        match self {
+            OutputOwnership::External => None,
+            OutputOwnership::Receive(path) | OutputOwnership::Change(path) => Some(path),
+        }
+    }
+}
+
+/// Script pubkeys of an account's addresses, mapped to their chain and path
+#[derive(Debug, Default)]
+struct OwnedScripts(HashMap<Vec<u8>, (Change, DerivationPath)>);
+
+impl OwnedScripts {
+    /// Add the addresses at `indices` of a chain of an account, skipping
+    /// indices whose key is invalid
+    fn insert_chain(
+        &mut self,
+        account_path: &DerivationPath,
+        address_type: AddressType,
+        change: Change,
+        indices: Range<u32>,
+        derive: impl Fn(u32) -> Result<ExtendedPubKey, Error>,
+    ) {
+        for index in indices {
+            let Ok(key) = derive(index) else {
+                continue;
+            };
+            let mut path = account_path.clone();
+            path.path
+                .extend([change.child_number(), ChildNumber::Normal(index)]);
+            self.0.insert(
+                address::script_pubkey(&key.public_key, address_type),
+                (change, path),
+            );
+        }
+    }
+
+    fn classify(&self, outputs: &[TxOut]) -> Vec<OutputOwnership> {
+        outputs
+            .iter()
+            .map(|output| match self.0.get(&output.script_pubkey) {
+                None => OutputOwnership::External,
+                Some((Change::External, path)) => OutputOwnership::Receive(path.clone()),
+                Some((Change::Internal, path)) => OutputOwnership::Change(path.clone()),
+            })
+            .collect()
+    }
+}
+
+impl Wallet {
+    /// Classify each output of a transaction as external, or as paying a
+    /// receive or change address within the watched range of an account
+    pub fn classify_outputs(&self, outputs: &[TxOut]) -> Result<Vec<OutputOwnership>, Error> {
+        let mut owned = OwnedScripts::default();
+        for account in self.accounts() {
+            let account_path = self.account_path(account.index);
+            for change in [Change::External, Change::Internal] {
+                owned.insert_chain(
+                    &account_path,
+                    self.address_type(),
+                    change,
+                    account.watch_range(change),
+                    |index| self.derive_key(&account.xpub, change, index),
+                );
+            }
+        }
+        Ok(owned.classify(outputs))
+    }
+}
+
+impl AccountKey {
+    /// Classify each output of a transaction as external, or as paying a
+    /// receive or change address of this account at one of `indices`
+    pub fn classify_outputs(
+        &self,
+        outputs: &[TxOut],
+        indices: Range<u32>,
+    ) -> Result<Vec<OutputOwnership>, Error> {
+        limits::check_wildcard_range(indices.start, indices.end)?;
+        let mut owned = OwnedScripts::default();
+        for change in [Change::External, Change::Internal] {
+            let chain = self.xpub.derive_child(change.child_number())?;
+            owned.insert_chain(
+                &self.origin.path,
+                self.address_type,
+                change,
+                indices.clone(),
+                |index| chain.derive_child(ChildNumber::Normal(index)),
+            );
+        }
+        Ok(owned.classify(outputs))
+    }
+}
@@ -0,0 +1,66 @@
+use crate::bip39::Language;
+use crate::error::Error;
+use rand::{rngs::OsRng, RngCore};
+
+/// Bits of entropy each uniformly chosen word adds (log2 of 2048)
+pub const BITS_PER_WORD: u32 = 11;
+
+/// Settings for generating a diceware-style passphrase
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassphraseOptions {
+    /// Number of words
+    pub words: usize,
+    pub separator: String,
+    pub language: Language,
+}
+
+impl Default for PassphraseOptions {
+    /// Six English words separated by spaces (66 bits)
+    fn default() -> Self {
+        PassphraseOptions {
+            words: 6,
+            separator: " ".to_string(),
+            language: Language::English,
+        }
+    }
+}
+
+impl PassphraseOptions {
+    /// Options with the fewest words reaching at least `bits` of entropy
+    pub fn for_entropy_bits(bits: u32) -> Self {
+        PassphraseOptions {
+            words: bits.div_ceil(BITS_PER_WORD).max(1) as usize,
+            ..PassphraseOptions::default()
+        }
+    }
+}
+
+/// A generated passphrase and the entropy it carries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedPassphrase {
+    pub passphrase: String,
+    /// Entropy of the passphrase against an attacker who knows the wordlist,
+    /// word count and separator
+    pub entropy_bits: u32,
+}
+
+/// Generate a passphrase of words drawn uniformly from a BIP-39 wordlist,
+/// suitable as the optional "25th word"
+pub fn generate_passphrase(options: &PassphraseOptions) -> Result<GeneratedPassphrase, Error> {
+    if options.words == 0 {
+        return Err(Error::InvalidConfig(
+            "Passphrase needs at least one word".to_string(),
+        ));
+    }
+
+    let wordlist = options.language.wordlist();
+    let words: Vec<&str> = (0..options.words)
+        // 2048 is a power of two, so masking keeps the choice uniform
+        .map(|_| wordlist[(OsRng.next_u32() & 0x7ff) as usize])
+        .collect();
+
+    Ok(GeneratedPassphrase {
+        passphrase: words.join(&options.separator),
+        entropy_bits: options.words as u32 * BITS_PER_WORD,
+    })
+}
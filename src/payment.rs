@@ -0,0 +1,165 @@
+use crate::bip44::Change;
+use crate::error::Error;
+use crate::evm::{EvmAddressFormat, EvmChain};
+use crate::utils::percent_encode;
+use crate::wallet::Wallet;
+
+/// Satoshis in a bitcoin
+const SATS_PER_BTC: u128 = 100_000_000;
+/// Largest amount BIP-21 can ask for: the 21 million coin supply
+const MAX_SATS: u128 = 21_000_000 * SATS_PER_BTC;
+
+/// URI scheme a payment request is encoded in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentScheme {
+    /// BIP-21 `bitcoin:` URIs, amounts in satoshis
+    Bitcoin,
+    /// EIP-681 `ethereum:` URIs on a chain, amounts in wei
+    Ethereum { chain_id: u64 },
+}
+
+/// A request to be paid at an address, rendered as a payment URI for QR
+/// codes and links. Amounts are kept in the chain's smallest unit and
+/// only formatted when the URI is built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentRequest {
+    scheme: PaymentScheme,
+    address: String,
+    amount: Option<u128>,
+    label: Option<String>,
+    message: Option<String>,
+}
+
+impl PaymentRequest {
+    /// Create a BIP-21 request for a Bitcoin address
+    pub fn bitcoin(address: &str) -> Self {
+        PaymentRequest::new(PaymentScheme::Bitcoin, address)
+    }
+
+    /// Create an EIP-681 request for an account address on an EVM chain
+    pub fn ethereum(address: &str, chain_id: u64) -> Self {
+        PaymentRequest::new(PaymentScheme::Ethereum { chain_id }, address)
+    }
+
+    fn new(scheme: PaymentScheme, address: &str) -> Self {
+        PaymentRequest {
+            scheme,
+            address: address.to_string(),
+            amount: None,
+            label: None,
+            message: None,
+        }
+    }
+
+    /// Ask for an amount in the chain's smallest unit: satoshis or wei
+    pub fn with_amount(mut self, amount: u128) -> Result<Self, Error> {
+        if self.scheme == PaymentScheme::Bitcoin && amount > MAX_SATS {
+            return Err(Error::InvalidConfig(
+                "Amount exceeds the 21 million BTC supply".to_string(),
+            ));
+        }
+        self.amount = Some(amount);
+        Ok(self)
+    }
+
+    /// Name the recipient. EIP-681 has no label field, so it is only
+    /// included in BIP-21 URIs.
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// Describe the payment. Only included in BIP-21 URIs.
+    pub fn with_message(mut self, message: &str) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    /// Get the URI scheme
+    pub fn scheme(&self) -> PaymentScheme {
+        self.scheme
+    }
+
+    /// Get the address to pay
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Get the requested amount in the chain's smallest unit
+    pub fn amount(&self) -> Option<u128> {
+        self.amount
+    }
+
+    /// Get the label
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Build the payment URI
+    pub fn to_uri(&self) -> String {
+        let mut params = Vec::new();
+        let uri = match self.scheme {
+            PaymentScheme::Bitcoin => {
+                if let Some(amount) = self.amount {
+                    params.push(format!("amount={}", format_btc(amount)));
+                }
+                if let Some(label) = &self.label {
+                    params.push(format!("label={}", percent_encode(label)));
+                }
+                if let Some(message) = &self.message {
+                    params.push(format!("message={}", percent_encode(message)));
+                }
+                format!("bitcoin:{}", self.address)
+            }
+            PaymentScheme::Ethereum { chain_id } => {
+                if let Some(amount) = self.amount {
+                    params.push(format!("value={}", amount));
+                }
+                format!("ethereum:{}@{}", self.address, chain_id)
+            }
+        };
+        if params.is_empty() {
+            uri
+        } else {
+            format!("{}?{}", uri, params.join("&"))
+        }
+    }
+}
+
+/// Format satoshis as a BTC decimal without trailing zeros
+fn format_btc(sats: u128) -> String {
+    let (whole, fraction) = (sats / SATS_PER_BTC, sats % SATS_PER_BTC);
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    let fraction = format!("{:08}", fraction);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+impl Wallet {
+    /// Issue the next receive address, label it, and return a BIP-21
+    /// request for it. Add an amount to the request if one is wanted.
+    pub fn payment_request(&mut self, label: &str) -> Result<PaymentRequest, Error> {
+        let address = self.next_address(Change::External)?;
+        self.set_label(&address, label);
+        Ok(PaymentRequest::bitcoin(&address).with_label(label))
+    }
+
+    /// Label the address at an index of the first account on an EVM chain
+    /// and return an EIP-681 request for it
+    pub fn evm_payment_request(
+        &mut self,
+        chain: &EvmChain,
+        index: u32,
+        label: &str,
+    ) -> Result<PaymentRequest, Error> {
+        if chain.address_format == EvmAddressFormat::Tron {
+            return Err(Error::Unsupported(
+                "EIP-681 URIs need 0x-prefixed addresses".to_string(),
+            ));
+        }
+        let address = self.evm_address(chain, index)?;
+        self.set_label(&address, label);
+        Ok(PaymentRequest::ethereum(&address, chain.chain_id).with_label(label))
+    }
+}
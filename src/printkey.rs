@@ -0,0 +1,118 @@
+use crate::address::{self, AddressType};
+use crate::bip32::Network;
+use crate::error::Error;
+use crate::utils;
+use bech32::primitives::decode::CheckedHrpstring;
+use bech32::{Bech32m, Hrp};
+use secp256k1::{PublicKey, SecretKey};
+
+/// Human-readable part of mainnet printable keys
+const MAINNET_HRP: Hrp = Hrp::parse_unchecked("bkey");
+/// Human-readable part of testnet printable keys
+const TESTNET_HRP: Hrp = Hrp::parse_unchecked("tkey");
+/// Version byte of the payload
+const PRINTABLE_KEY_VERSION: u8 = 0;
+
+/// A single private key in a bech32m export string meant for print and
+/// manual transcription: the checksum catches any four substituted
+/// characters, the alphabet leaves out the look-alike `1`, `b`, `i` and
+/// `o`, and decoding ignores case, spaces and dashes. The payload records the
+/// address type the key pays to, which WIF can't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrintableKey {
+    pub secret_key: SecretKey,
+    pub network: Network,
+    pub address_type: AddressType,
+}
+
+impl PrintableKey {
+    /// Encode as a lowercase bech32m string
+    pub fn encode(&self) -> String {
+        let mut payload = vec![PRINTABLE_KEY_VERSION, address_type_byte(self.address_type)];
+        payload.extend_from_slice(&self.secret_key.secret_bytes());
+        let hrp = match self.network {
+            Network::Bitcoin => MAINNET_HRP,
+            Network::Testnet => TESTNET_HRP,
+        };
+        bech32::encode::<Bech32m>(hrp, &payload).expect("34-byte payload is always valid")
+    }
+
+    /// Encode with the data part split into dash-separated groups, easier to
+    /// read aloud and check against a printout
+    pub fn encode_grouped(&self, group_len: usize) -> String {
+        let encoded = self.encode();
+        let (hrp, data) = encoded.split_at(encoded.rfind('1').expect("bech32 separator") + 1);
+        let groups: Vec<String> = data
+            .as_bytes()
+            .chunks(group_len.max(1))
+            .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+            .collect();
+        format!("{}{}", hrp, groups.join("-"))
+    }
+
+    /// Decode a printable key, as produced by [`PrintableKey::encode`] or
+    /// [`PrintableKey::encode_grouped`], or retyped in upper case
+    pub fn decode(s: &str) -> Result<Self, Error> {
+        let cleaned: String = s
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .collect::<String>()
+            .to_lowercase();
+        let checked = CheckedHrpstring::new::<Bech32m>(&cleaned)
+            .map_err(|e| Error::InvalidKey(format!("Invalid printable key: {}", e)))?;
+        let network = match checked.hrp() {
+            hrp if hrp == MAINNET_HRP => Network::Bitcoin,
+            hrp if hrp == TESTNET_HRP => Network::Testnet,
+            hrp => {
+                return Err(Error::InvalidKey(format!(
+                    "Unknown printable key prefix {}",
+                    hrp
+                )))
+            }
+        };
+
+        let payload: Vec<u8> = checked.byte_iter().collect();
+        let [version, address_type, key @ ..] = &payload[..] else {
+            return Err(Error::InvalidKey("Truncated printable key".to_string()));
+        };
+        if *version != PRINTABLE_KEY_VERSION {
+            return Err(Error::InvalidKey(format!(
+                "Unsupported printable key version {}",
+                version
+            )));
+        }
+        Ok(PrintableKey {
+            secret_key: SecretKey::from_slice(key).map_err(|e| Error::InvalidKey(e.to_string()))?,
+            network,
+            address_type: match address_type {
+                0 => AddressType::P2pkh,
+                1 => AddressType::P2shP2wpkh,
+                2 => AddressType::P2wpkh,
+                n => {
+                    return Err(Error::InvalidKey(format!(
+                        "Unknown address type {} in printable key",
+                        n
+                    )))
+                }
+            },
+        })
+    }
+
+    /// Get the public key
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from_secret_key(utils::secp(), &self.secret_key)
+    }
+
+    /// Get the address the key pays to, for checking a transcription
+    pub fn address(&self) -> String {
+        address::encode(&self.public_key(), self.address_type, self.network)
+    }
+}
+
+fn address_type_byte(address_type: AddressType) -> u8 {
+    match address_type {
+        AddressType::P2pkh => 0,
+        AddressType::P2shP2wpkh => 1,
+        AddressType::P2wpkh => 2,
+    }
+}
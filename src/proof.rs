@@ -0,0 +1,58 @@
+use crate::address::AddressType;
+use crate::bip32::{DerivationPath, ExtendedPubKey};
+use crate::error::Error;
+use secp256k1::PublicKey;
+
+/// Outcome of checking that a key belongs to a parent extended public key
+#[derive(Debug, Clone)]
+pub enum ChildProof {
+    /// The path derives the claimed key; holds the derived extended key
+    Verified(ExtendedPubKey),
+    /// The path derives a different key
+    Mismatch { derived: PublicKey },
+}
+
+impl ChildProof {
+    /// Whether the claimed key was derived from the parent
+    pub fn is_verified(&self) -> bool {
+        matches!(self, ChildProof::Verified(_))
+    }
+}
+
+/// Recompute the CKDpub chain from a parent along a path relative to it and
+/// check that it ends at the claimed child key. Hardened steps are an error
+/// since they can't be proven from public data.
+pub fn prove_child(
+    parent: &ExtendedPubKey,
+    child: &PublicKey,
+    path: &DerivationPath,
+) -> Result<ChildProof, Error> {
+    let derived = parent.derive_path(path)?;
+
+    if derived.public_key == *child {
+        Ok(ChildProof::Verified(derived))
+    } else {
+        Ok(ChildProof::Mismatch {
+            derived: derived.public_key,
+        })
+    }
+}
+
+/// Check that an address is derived from a parent along a path relative to it,
+/// e.g. when auditing an address list supplied for a known account xpub
+pub fn prove_address(
+    parent: &ExtendedPubKey,
+    address: &str,
+    path: &DerivationPath,
+    address_type: AddressType,
+) -> Result<ChildProof, Error> {
+    let derived = parent.derive_path(path)?;
+
+    if derived.address(address_type) == address {
+        Ok(ChildProof::Verified(derived))
+    } else {
+        Ok(ChildProof::Mismatch {
+            derived: derived.public_key,
+        })
+    }
+}
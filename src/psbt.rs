@@ -0,0 +1,564 @@
+use crate::address::{self, AddressType};
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPubKey};
+use crate::descriptor::{Descriptor, KeyOrigin};
+use crate::error::Error;
+use crate::utils;
+use base64::Engine;
+use secp256k1::PublicKey;
+use std::fmt;
+use std::str::FromStr;
+
+/// PSBT magic bytes: "psbt" followed by 0xff
+const MAGIC: &[u8; 5] = b"psbt\xff";
+/// Transaction version of created PSBTs
+pub(crate) const TX_VERSION: u32 = 2;
+/// Input sequence signalling replaceability (BIP-125)
+pub const SEQUENCE_RBF: u32 = 0xffff_fffd;
+
+const GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const IN_WITNESS_UTXO: u8 = 0x01;
+const IN_REDEEM_SCRIPT: u8 = 0x04;
+const IN_BIP32_DERIVATION: u8 = 0x06;
+const OUT_REDEEM_SCRIPT: u8 = 0x00;
+const OUT_BIP32_DERIVATION: u8 = 0x02;
+
+/// A reference to a transaction output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutPoint {
+    /// Transaction id, in the usual displayed byte order
+    pub txid: [u8; 32],
+    pub vout: u32,
+}
+
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", hex::encode(self.txid), self.vout)
+    }
+}
+
+impl FromStr for OutPoint {
+    type Err = Error;
+
+    /// Parse `txid:vout`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidExport(format!("Invalid outpoint '{}'", s));
+        let (txid, vout) = s.split_once(':').ok_or_else(invalid)?;
+        Ok(OutPoint {
+            txid: hex::decode(txid)
+                .ok()
+                .and_then(|txid| txid.try_into().ok())
+                .ok_or_else(invalid)?,
+            vout: vout.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// A transaction output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxOut {
+    /// Value in satoshis
+    pub amount: u64,
+    pub script_pubkey: Vec<u8>,
+}
+
+/// A wallet UTXO to spend: where it is, what it holds and the full path of
+/// the key controlling it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Utxo {
+    pub outpoint: OutPoint,
+    pub amount: u64,
+    pub script_pubkey: Vec<u8>,
+    pub path: DerivationPath,
+}
+
+/// Watch-only key material of the account funding a PSBT
+#[derive(Debug, Clone)]
+pub struct AccountKey {
+    pub origin: KeyOrigin,
+    pub xpub: ExtendedPubKey,
+    pub address_type: AddressType,
+}
+
+impl From<&Descriptor> for AccountKey {
+    fn from(descriptor: &Descriptor) -> Self {
+        AccountKey {
+            origin: descriptor.origin.clone(),
+            xpub: descriptor.xpub.clone(),
+            address_type: descriptor.address_type,
+        }
+    }
+}
+
+impl AccountKey {
+    /// Derive the public key at a full path under the account
+    pub fn key_source(&self, path: &DerivationPath) -> Result<KeySource, Error> {
+        let relative = path
+            .path
+            .strip_prefix(self.origin.path.path.as_slice())
+            .ok_or_else(|| {
+                Error::InvalidDerivationPath(format!(
+                    "{} is not under account {}",
+                    path, self.origin.path
+                ))
+            })?;
+        let public_key = self
+            .xpub
+            .derive_path(&DerivationPath {
+                path: relative.to_vec(),
+            })?
+            .public_key;
+
+        Ok(KeySource {
+            public_key,
+            origin: KeyOrigin {
+                fingerprint: self.origin.fingerprint,
+                path: path.clone(),
+            },
+        })
+    }
+}
+
+/// A public key and where it was derived, as in PSBT bip32_derivation fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySource {
+    pub public_key: PublicKey,
+    pub origin: KeyOrigin,
+}
+
+/// Per-input PSBT data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PsbtInput {
+    pub outpoint: OutPoint,
+    pub sequence: u32,
+    pub witness_utxo: Option<TxOut>,
+    pub redeem_script: Option<Vec<u8>>,
+    pub bip32_derivation: Vec<KeySource>,
+    /// Fields this crate does not interpret, kept as key-value pairs
+    pub unknown: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Per-output PSBT data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PsbtOutput {
+    pub txout: TxOut,
+    pub redeem_script: Option<Vec<u8>>,
+    pub bip32_derivation: Vec<KeySource>,
+    /// Fields this crate does not interpret, kept as key-value pairs
+    pub unknown: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// A partially signed Bitcoin transaction (BIP-174, version 0)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Psbt {
+    pub version: u32,
+    pub lock_time: u32,
+    pub inputs: Vec<PsbtInput>,
+    pub outputs: Vec<PsbtOutput>,
+}
+
+impl Psbt {
+    /// Build an unsigned PSBT spending wallet UTXOs of an account to the
+    /// given outputs, plus an optional change output to a path of the same
+    /// account. Inputs signal replaceability.
+    pub fn from_utxos(
+        account: &AccountKey,
+        utxos: &[Utxo],
+        outputs: &[TxOut],
+        change: Option<(&DerivationPath, u64)>,
+    ) -> Result<Self, Error> {
+        if utxos.is_empty() {
+            return Err(Error::InvalidConfig(
+                "A PSBT needs at least one input".to_string(),
+            ));
+        }
+
+        let inputs = utxos
+            .iter()
+            .map(|utxo| account.input(utxo))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut psbt_outputs: Vec<PsbtOutput> = outputs
+            .iter()
+            .map(|txout| PsbtOutput {
+                txout: txout.clone(),
+                redeem_script: None,
+                bip32_derivation: Vec::new(),
+                unknown: Vec::new(),
+            })
+            .collect();
+        if let Some((path, amount)) = change {
+            psbt_outputs.push(account.change_output(path, amount)?);
+        }
+
+        let psbt = Psbt {
+            version: TX_VERSION,
+            lock_time: 0,
+            inputs,
+            outputs: psbt_outputs,
+        };
+        psbt.fee()?;
+        Ok(psbt)
+    }
+
+    /// Inputs minus outputs, in satoshis. Fails if an input lacks its UTXO
+    /// or the outputs exceed the inputs.
+    pub fn fee(&self) -> Result<u64, Error> {
+        let mut total_in = 0u64;
+        for input in &self.inputs {
+            let utxo = input.witness_utxo.as_ref().ok_or_else(|| {
+                Error::InvalidExport(format!("Input {} has no witness UTXO", input.outpoint))
+            })?;
+            total_in = total_in.saturating_add(utxo.amount);
+        }
+        let total_out = self
+            .outputs
+            .iter()
+            .fold(0u64, |sum, output| sum.saturating_add(output.txout.amount));
+        total_in.checked_sub(total_out).ok_or_else(|| {
+            Error::InvalidConfig(format!(
+                "Outputs ({} sat) exceed inputs ({} sat)",
+                total_out, total_in
+            ))
+        })
+    }
+
+    /// The unsigned transaction, serialized without witnesses
+    pub fn unsigned_tx(&self) -> Vec<u8> {
+        let mut tx = self.version.to_le_bytes().to_vec();
+        utils::push_compact_size(&mut tx, self.inputs.len());
+        for input in &self.inputs {
+            let mut txid = input.outpoint.txid;
+            txid.reverse();
+            tx.extend_from_slice(&txid);
+            tx.extend_from_slice(&input.outpoint.vout.to_le_bytes());
+            tx.push(0x00); // empty scriptSig
+            tx.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+        utils::push_compact_size(&mut tx, self.outputs.len());
+        for output in &self.outputs {
+            push_txout(&mut tx, &output.txout);
+        }
+        tx.extend_from_slice(&self.lock_time.to_le_bytes());
+        tx
+    }
+
+    /// Id of the unsigned transaction, in displayed byte order
+    pub fn txid(&self) -> [u8; 32] {
+        let mut txid = utils::hash_twice(&self.unsigned_tx());
+        txid.reverse();
+        txid
+    }
+
+    /// Serialize in the binary PSBT format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        push_pair(&mut data, &[GLOBAL_UNSIGNED_TX], &self.unsigned_tx());
+        data.push(0x00);
+
+        for input in &self.inputs {
+            if let Some(utxo) = &input.witness_utxo {
+                let mut value = Vec::new();
+                push_txout(&mut value, utxo);
+                push_pair(&mut data, &[IN_WITNESS_UTXO], &value);
+            }
+            if let Some(script) = &input.redeem_script {
+                push_pair(&mut data, &[IN_REDEEM_SCRIPT], script);
+            }
+            push_derivations(&mut data, IN_BIP32_DERIVATION, &input.bip32_derivation);
+            for (key, value) in &input.unknown {
+                push_pair(&mut data, key, value);
+            }
+            data.push(0x00);
+        }
+
+        for output in &self.outputs {
+            if let Some(script) = &output.redeem_script {
+                push_pair(&mut data, &[OUT_REDEEM_SCRIPT], script);
+            }
+            push_derivations(&mut data, OUT_BIP32_DERIVATION, &output.bip32_derivation);
+            for (key, value) in &output.unknown {
+                push_pair(&mut data, key, value);
+            }
+            data.push(0x00);
+        }
+        data
+    }
+
+    /// Serialize as base64, the usual text form
+    pub fn to_base64(&self) -> String {
+        base64::engine::general_purpose::STANDARD.encode(self.to_bytes())
+    }
+
+    /// Parse a binary PSBT
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader(data);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(Error::InvalidExport("Missing PSBT magic".to_string()));
+        }
+
+        let mut unsigned_tx = None;
+        for (key, value) in reader.map()? {
+            if key == [GLOBAL_UNSIGNED_TX] {
+                unsigned_tx = Some(value);
+            }
+        }
+        let unsigned_tx = unsigned_tx
+            .ok_or_else(|| Error::InvalidExport("PSBT has no unsigned transaction".to_string()))?;
+        let mut psbt = parse_unsigned_tx(unsigned_tx)?;
+
+        for input in &mut psbt.inputs {
+            for (key, value) in reader.map()? {
+                match key[0] {
+                    IN_WITNESS_UTXO if key.len() == 1 => {
+                        let mut value = Reader(value);
+                        input.witness_utxo = Some(value.txout()?);
+                    }
+                    IN_REDEEM_SCRIPT if key.len() == 1 => {
+                        input.redeem_script = Some(value.to_vec())
+                    }
+                    IN_BIP32_DERIVATION => {
+                        input.bip32_derivation.push(parse_key_source(key, value)?)
+                    }
+                    _ => input.unknown.push((key.to_vec(), value.to_vec())),
+                }
+            }
+        }
+        for output in &mut psbt.outputs {
+            for (key, value) in reader.map()? {
+                match key[0] {
+                    OUT_REDEEM_SCRIPT if key.len() == 1 => {
+                        output.redeem_script = Some(value.to_vec())
+                    }
+                    OUT_BIP32_DERIVATION => {
+                        output.bip32_derivation.push(parse_key_source(key, value)?)
+                    }
+                    _ => output.unknown.push((key.to_vec(), value.to_vec())),
+                }
+            }
+        }
+
+        if !reader.0.is_empty() {
+            return Err(Error::InvalidExport("Trailing data in PSBT".to_string()));
+        }
+        Ok(psbt)
+    }
+
+    /// Parse a base64 PSBT
+    pub fn from_base64(s: &str) -> Result<Self, Error> {
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(s.trim())
+            .map_err(|e| Error::InvalidExport(e.to_string()))?;
+        Psbt::from_bytes(&data)
+    }
+}
+
+impl AccountKey {
+    /// A replaceable input spending a UTXO of this account, after checking
+    /// that its script pays to the key at its path
+    pub(crate) fn input(&self, utxo: &Utxo) -> Result<PsbtInput, Error> {
+        if self.address_type == AddressType::P2pkh {
+            return Err(Error::Unsupported(
+                "Legacy inputs need the full previous transaction".to_string(),
+            ));
+        }
+        let source = self.key_source(&utxo.path)?;
+        if address::script_pubkey(&source.public_key, self.address_type) != utxo.script_pubkey {
+            return Err(Error::InvalidAddress(format!(
+                "Script does not match the key at {}",
+                utxo.path
+            )));
+        }
+
+        Ok(PsbtInput {
+            outpoint: utxo.outpoint,
+            sequence: SEQUENCE_RBF,
+            witness_utxo: Some(TxOut {
+                amount: utxo.amount,
+                script_pubkey: utxo.script_pubkey.clone(),
+            }),
+            redeem_script: self.redeem_script(&source.public_key),
+            bip32_derivation: vec![source],
+            unknown: Vec::new(),
+        })
+    }
+
+    /// A change output paying to the key at a path of this account
+    pub(crate) fn change_output(
+        &self,
+        path: &DerivationPath,
+        amount: u64,
+    ) -> Result<PsbtOutput, Error> {
+        let source = self.key_source(path)?;
+        Ok(PsbtOutput {
+            txout: TxOut {
+                amount,
+                script_pubkey: address::script_pubkey(&source.public_key, self.address_type),
+            },
+            redeem_script: self.redeem_script(&source.public_key),
+            bip32_derivation: vec![source],
+            unknown: Vec::new(),
+        })
+    }
+
+    fn redeem_script(&self, public_key: &PublicKey) -> Option<Vec<u8>> {
+        (self.address_type == AddressType::P2shP2wpkh)
+            .then(|| address::p2wpkh_script(&utils::pubkey_hash(public_key)))
+    }
+}
+
+fn push_txout(data: &mut Vec<u8>, txout: &TxOut) {
+    data.extend_from_slice(&txout.amount.to_le_bytes());
+    utils::push_compact_size(data, txout.script_pubkey.len());
+    data.extend_from_slice(&txout.script_pubkey);
+}
+
+fn push_pair(data: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+    utils::push_compact_size(data, key.len());
+    data.extend_from_slice(key);
+    utils::push_compact_size(data, value.len());
+    data.extend_from_slice(value);
+}
+
+fn push_derivations(data: &mut Vec<u8>, key_type: u8, sources: &[KeySource]) {
+    for source in sources {
+        let mut key = vec![key_type];
+        key.extend_from_slice(&source.public_key.serialize());
+        let mut value = source.origin.fingerprint.to_vec();
+        for child in &source.origin.path.path {
+            value.extend_from_slice(&child.to_u32().to_le_bytes());
+        }
+        push_pair(data, &key, &value);
+    }
+}
+
+fn parse_key_source(key: &[u8], value: &[u8]) -> Result<KeySource, Error> {
+    if value.len() < 4 || !value.len().is_multiple_of(4) {
+        return Err(Error::InvalidExport(
+            "Invalid BIP-32 derivation".to_string(),
+        ));
+    }
+    Ok(KeySource {
+        public_key: PublicKey::from_slice(&key[1..])
+            .map_err(|e| Error::InvalidExport(e.to_string()))?,
+        origin: KeyOrigin {
+            fingerprint: value[..4].try_into().expect("4 bytes"),
+            path: DerivationPath {
+                path: value[4..]
+                    .chunks(4)
+                    .map(|c| ChildNumber::from(u32::from_le_bytes(c.try_into().expect("4 bytes"))))
+                    .collect(),
+            },
+        },
+    })
+}
+
+/// Read the inputs and outputs of an unsigned transaction
+fn parse_unsigned_tx(tx: &[u8]) -> Result<Psbt, Error> {
+    let mut reader = Reader(tx);
+    let version = reader.u32()?;
+
+    let count = reader.compact_size()?;
+    let mut inputs = Vec::new();
+    for _ in 0..count {
+        let mut txid: [u8; 32] = reader.take(32)?.try_into().expect("32 bytes");
+        txid.reverse();
+        let vout = reader.u32()?;
+        if reader.compact_size()? != 0 {
+            return Err(Error::InvalidExport(
+                "Unsigned transaction has a scriptSig".to_string(),
+            ));
+        }
+        inputs.push(PsbtInput {
+            outpoint: OutPoint { txid, vout },
+            sequence: reader.u32()?,
+            witness_utxo: None,
+            redeem_script: None,
+            bip32_derivation: Vec::new(),
+            unknown: Vec::new(),
+        });
+    }
+
+    let count = reader.compact_size()?;
+    let mut outputs = Vec::new();
+    for _ in 0..count {
+        outputs.push(PsbtOutput {
+            txout: reader.txout()?,
+            redeem_script: None,
+            bip32_derivation: Vec::new(),
+            unknown: Vec::new(),
+        });
+    }
+
+    let lock_time = reader.u32()?;
+    if !reader.0.is_empty() {
+        return Err(Error::InvalidExport(
+            "Trailing data in unsigned transaction".to_string(),
+        ));
+    }
+    Ok(Psbt {
+        version,
+        lock_time,
+        inputs,
+        outputs,
+    })
+}
+
+/// A raw key-value pair of a PSBT map
+type Pair<'a> = (&'a [u8], &'a [u8]);
+
+/// Bounds-checked cursor over PSBT data
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::InvalidExport("Truncated PSBT".to_string()));
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(
+            self.take(4)?.try_into().expect("4 bytes"),
+        ))
+    }
+
+    fn compact_size(&mut self) -> Result<usize, Error> {
+        let len = match self.take(1)?[0] {
+            0xfd => u16::from_le_bytes(self.take(2)?.try_into().expect("2 bytes")) as u64,
+            0xfe => self.u32()? as u64,
+            0xff => u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")),
+            n => n as u64,
+        };
+        // Anything longer than the remaining data is malformed
+        if len > self.0.len() as u64 {
+            return Err(Error::InvalidExport("Truncated PSBT".to_string()));
+        }
+        Ok(len as usize)
+    }
+
+    fn txout(&mut self) -> Result<TxOut, Error> {
+        let amount = u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes"));
+        let len = self.compact_size()?;
+        Ok(TxOut {
+            amount,
+            script_pubkey: self.take(len)?.to_vec(),
+        })
+    }
+
+    /// Read one key-value map up to its separator
+    fn map(&mut self) -> Result<Vec<Pair<'a>>, Error> {
+        let mut pairs = Vec::new();
+        loop {
+            let len = self.compact_size()?;
+            if len == 0 {
+                return Ok(pairs);
+            }
+            let key = self.take(len)?;
+            let len = self.compact_size()?;
+            pairs.push((key, self.take(len)?));
+        }
+    }
+}
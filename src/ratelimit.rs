@@ -0,0 +1,238 @@
+use crate::bip32::DerivationPath;
+use crate::clock::{Clock, SystemClock};
+use crate::error::Error;
+use crate::signer::{Signer, SigningOptions};
+use secp256k1::{ecdsa::Signature, PublicKey};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Most paths whose signatures are tracked at once. Paths with no signature
+/// left in the window are forgotten first; past that, signing a new path is
+/// refused until one expires.
+pub const MAX_TRACKED_PATHS: usize = 1024;
+
+/// At most `max_signatures` signatures in any sliding `window`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max_signatures: u32,
+    pub window: Duration,
+}
+
+/// Signing counters of a rate-limited signer
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SigningCounters {
+    /// Signatures produced
+    pub signed: u64,
+    /// Requests refused by a limit
+    pub rejected: u64,
+    /// Signatures produced per path, for up to [`MAX_TRACKED_PATHS`] paths
+    pub per_path: HashMap<DerivationPath, u64>,
+}
+
+#[derive(Default)]
+struct LimitState {
+    global: VecDeque<Duration>,
+    per_path: HashMap<DerivationPath, VecDeque<Duration>>,
+    counters: SigningCounters,
+}
+
+/// Signer decorator refusing signatures beyond global and per-path rate
+/// limits, as a defense in depth for hot wallets
+pub struct RateLimitedSigner<S: Signer, C: Clock = SystemClock> {
+    inner: S,
+    clock: C,
+    global: Option<RateLimit>,
+    per_path: Option<RateLimit>,
+    state: Mutex<LimitState>,
+}
+
+impl<S: Signer> RateLimitedSigner<S> {
+    /// Wrap a signer, with no limits set yet
+    pub fn new(inner: S) -> Self {
+        RateLimitedSigner {
+            inner,
+            clock: SystemClock,
+            global: None,
+            per_path: None,
+            state: Mutex::new(LimitState::default()),
+        }
+    }
+}
+
+impl<S: Signer, C: Clock> RateLimitedSigner<S, C> {
+    /// Limit signatures across all paths
+    pub fn with_global_limit(mut self, limit: RateLimit) -> Self {
+        self.global = Some(limit);
+        self
+    }
+
+    /// Limit signatures with each path separately
+    pub fn with_path_limit(mut self, limit: RateLimit) -> Self {
+        self.per_path = Some(limit);
+        self
+    }
+
+    /// Use another clock
+    pub fn with_clock<C2: Clock>(self, clock: C2) -> RateLimitedSigner<S, C2> {
+        RateLimitedSigner {
+            inner: self.inner,
+            clock,
+            global: self.global,
+            per_path: self.per_path,
+            state: self.state,
+        }
+    }
+
+    /// Get the wrapped signer
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Snapshot the signing counters
+    pub fn counters(&self) -> SigningCounters {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .counters
+            .clone()
+    }
+
+    /// Run a signing operation if the limits allow it. A slot is reserved
+    /// before signing and released if signing fails, so the lock is not held
+    /// while the inner signer works.
+    fn limited<T>(
+        &self,
+        path: &DerivationPath,
+        sign: impl FnOnce() -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let now = self.clock.now();
+        self.reserve(path, now)?;
+
+        let result = sign();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match result {
+            Ok(_) => {
+                state.counters.signed += 1;
+                let tracked = state.counters.per_path.len();
+                match state.counters.per_path.get_mut(path) {
+                    Some(count) => *count += 1,
+                    None if tracked < MAX_TRACKED_PATHS => {
+                        state.counters.per_path.insert(path.clone(), 1);
+                    }
+                    None => {}
+                }
+            }
+            Err(_) => {
+                release(&mut state.global, now);
+                if let Some(times) = state.per_path.get_mut(path) {
+                    release(times, now);
+                }
+            }
+        }
+        result
+    }
+
+    /// Check the limits and record a signature at `now`
+    fn reserve(&self, path: &DerivationPath, now: Duration) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let state = &mut *state;
+
+        if let Some(limit) = self.per_path {
+            if !state.per_path.contains_key(path) && state.per_path.len() >= MAX_TRACKED_PATHS {
+                // Forget paths with no signature left in the window
+                state.per_path.retain(|_, times| {
+                    expire(times, now, limit.window);
+                    !times.is_empty()
+                });
+                if state.per_path.len() >= MAX_TRACKED_PATHS {
+                    state.counters.rejected += 1;
+                    return Err(Error::RateLimited(format!(
+                        "Signatures on more than {} paths per {:?}",
+                        MAX_TRACKED_PATHS, limit.window
+                    )));
+                }
+            }
+        }
+
+        let path_times = match self.per_path {
+            Some(_) => Some(state.per_path.entry(path.clone()).or_default()),
+            None => None,
+        };
+        let exceeded = [
+            (self.global, Some(&mut state.global), "global"),
+            (self.per_path, path_times, "per-path"),
+        ]
+        .into_iter()
+        .find_map(|(limit, times, scope)| {
+            let (limit, times) = (limit?, times?);
+            expire(times, now, limit.window);
+            (times.len() >= limit.max_signatures as usize).then_some((scope, limit))
+        });
+        if let Some((scope, limit)) = exceeded {
+            state.counters.rejected += 1;
+            return Err(Error::RateLimited(format!(
+                "{} limit of {} signatures per {:?} reached for {}",
+                scope, limit.max_signatures, limit.window, path
+            )));
+        }
+
+        state.global.push_back(now);
+        if let Some(times) = state.per_path.get_mut(path) {
+            times.push_back(now);
+        }
+        Ok(())
+    }
+}
+
+/// Drop the times that fell out of a window
+fn expire(times: &mut VecDeque<Duration>, now: Duration, window: Duration) {
+    while times
+        .front()
+        .is_some_and(|&t| now.saturating_sub(t) >= window)
+    {
+        times.pop_front();
+    }
+}
+
+/// Drop a reservation made at `now`
+fn release(times: &mut VecDeque<Duration>, now: Duration) {
+    if let Some(position) = times.iter().rposition(|&t| t == now) {
+        times.remove(position);
+    }
+}
+
+impl<S: Signer, C: Clock> Signer for RateLimitedSigner<S, C> {
+    fn public_key(&self, path: &DerivationPath) -> Result<PublicKey, Error> {
+        self.inner.public_key(path)
+    }
+
+    fn sign_ecdsa(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        options: SigningOptions,
+    ) -> Result<Signature, Error> {
+        self.limited(path, || self.inner.sign_ecdsa(path, digest, options))
+    }
+
+    fn anti_exfil_commit(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        host_commitment: &[u8; 32],
+    ) -> Result<PublicKey, Error> {
+        self.inner.anti_exfil_commit(path, digest, host_commitment)
+    }
+
+    fn anti_exfil_sign(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        host_nonce: &[u8; 32],
+    ) -> Result<Signature, Error> {
+        self.limited(path, || {
+            self.inner.anti_exfil_sign(path, digest, host_nonce)
+        })
+    }
+}
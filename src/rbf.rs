@@ -0,0 +1,133 @@
+use crate::address::AddressType;
+use crate::error::Error;
+use crate::psbt::{AccountKey, Psbt, Utxo, SEQUENCE_RBF};
+
+/// Smallest change output kept when bumping; anything less is uneconomical
+/// to spend and rejected by relay policy
+pub const DUST_LIMIT: u64 = 546;
+
+/// Fee rate in sat/vB a replacement must pay for its own size on top of
+/// the original fee (BIP-125 rule 4), Bitcoin Core's long-standing default
+pub const INCREMENTAL_RELAY_FEE: u64 = 1;
+
+/// Input key types holding signatures, which a replacement invalidates:
+/// partial, final scriptSig, final witness, taproot key and script path
+const SIGNATURE_KEY_TYPES: [u8; 5] = [0x02, 0x07, 0x08, 0x13, 0x14];
+
+/// Weight units of the signature data spending one input of a type: a
+/// 72-byte DER signature and a compressed key, in the scriptSig or witness
+fn input_signature_weight(address_type: AddressType) -> u64 {
+    match address_type {
+        AddressType::P2pkh => 4 * 107,
+        AddressType::P2shP2wpkh => 4 * 23 + 108,
+        AddressType::P2wpkh => 108,
+    }
+}
+
+impl Psbt {
+    /// Whether any input signals replaceability (BIP-125)
+    pub fn signals_rbf(&self) -> bool {
+        self.inputs
+            .iter()
+            .any(|input| input.sequence <= SEQUENCE_RBF)
+    }
+
+    /// Virtual size of the transaction once every input, all of the
+    /// account's address type, is signed
+    pub fn estimated_vsize(&self, address_type: AddressType) -> u64 {
+        let mut weight = 4 * self.unsigned_tx().len() as u64
+            + self.inputs.len() as u64 * input_signature_weight(address_type);
+        if address_type != AddressType::P2pkh {
+            weight += 2; // segwit marker and flag
+        }
+        weight.div_ceil(4)
+    }
+
+    /// Index of the change output paying back to an account, recognized by
+    /// its BIP-32 derivation and script
+    pub fn change_index(&self, account: &AccountKey) -> Option<usize> {
+        self.outputs.iter().position(|output| {
+            output.bip32_derivation.iter().any(|source| {
+                source.origin.fingerprint == account.origin.fingerprint
+                    && account
+                        .change_output(&source.origin.path, output.txout.amount)
+                        .is_ok_and(|expected| expected.txout == output.txout)
+            })
+        })
+    }
+
+    /// Build a BIP-125 replacement paying `new_fee` satoshis. The extra fee
+    /// comes out of the account's change output; when that would leave it
+    /// below the dust limit, `extra_utxos` of the account are added as
+    /// inputs, in order, until it doesn't. All original inputs are kept so
+    /// the replacement conflicts with the original, derivation metadata is
+    /// carried over and signatures are dropped. The fee must rise by at
+    /// least [`INCREMENTAL_RELAY_FEE`] per vbyte of the replacement.
+    pub fn bump_fee(
+        &self,
+        account: &AccountKey,
+        new_fee: u64,
+        extra_utxos: &[Utxo],
+    ) -> Result<Psbt, Error> {
+        if !self.signals_rbf() {
+            return Err(Error::Unsupported(
+                "Transaction does not signal replaceability".to_string(),
+            ));
+        }
+        let old_fee = self.fee()?;
+        if new_fee <= old_fee {
+            return Err(Error::InvalidConfig(format!(
+                "Replacement fee {} must exceed the original fee {}",
+                new_fee, old_fee
+            )));
+        }
+        let change = self.change_index(account).ok_or_else(|| {
+            Error::Unsupported("No change output to take the extra fee from".to_string())
+        })?;
+
+        let mut replacement = self.clone();
+        for input in &mut replacement.inputs {
+            input
+                .unknown
+                .retain(|(key, _)| !key.first().is_some_and(|t| SIGNATURE_KEY_TYPES.contains(t)));
+        }
+
+        let mut extra_utxos = extra_utxos.iter();
+        let needed = new_fee - old_fee;
+        loop {
+            let output = &mut replacement.outputs[change].txout;
+            if output.amount >= needed.saturating_add(DUST_LIMIT) {
+                output.amount -= needed;
+                break;
+            }
+            let utxo = extra_utxos.next().ok_or_else(|| {
+                Error::InvalidConfig(format!(
+                    "Not enough funds to raise the fee to {} sat",
+                    new_fee
+                ))
+            })?;
+            if replacement
+                .inputs
+                .iter()
+                .any(|input| input.outpoint == utxo.outpoint)
+            {
+                continue;
+            }
+            replacement.inputs.push(account.input(utxo)?);
+            output.amount += utxo.amount;
+        }
+
+        let min_fee = old_fee.saturating_add(
+            INCREMENTAL_RELAY_FEE * replacement.estimated_vsize(account.address_type),
+        );
+        if new_fee < min_fee {
+            return Err(Error::InvalidConfig(format!(
+                "Replacement fee {} is below the minimum of {} sat for its size",
+                new_fee, min_fee
+            )));
+        }
+
+        debug_assert_eq!(replacement.fee()?, new_fee);
+        Ok(replacement)
+    }
+}
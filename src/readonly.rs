@@ -0,0 +1,120 @@
+use crate::address::AddressType;
+use crate::bip32::Network;
+use crate::bip44::Change;
+use crate::descriptor::Descriptor;
+use crate::error::Error;
+use crate::wallet::{AddressLocation, Wallet};
+use crate::watchonly::WatchOnlyWallet;
+
+/// The public side of a wallet: addresses, ownership checks and
+/// descriptors. Code that must never sign can take `&dyn ReadOnlyWallet`
+/// and accept both full and watch-only wallets.
+pub trait ReadOnlyWallet {
+    /// Get the wallet's network
+    fn network(&self) -> Network;
+
+    /// Get the wallet's address type
+    fn address_type(&self) -> AddressType;
+
+    /// Indices of the accounts the wallet watches
+    fn account_indices(&self) -> Vec<u32>;
+
+    /// Derive a batch of addresses on a chain of an account
+    fn addresses(
+        &self,
+        account: u32,
+        change: Change,
+        start: u32,
+        count: u32,
+    ) -> Result<Vec<String>, Error>;
+
+    /// Find where an address was derived, within the watched ranges
+    fn find_address(&self, address: &str) -> Result<Option<AddressLocation>, Error>;
+
+    /// Whether an address belongs to the wallet
+    fn is_mine(&self, address: &str) -> Result<bool, Error> {
+        Ok(self.find_address(address)?.is_some())
+    }
+
+    /// Get the receive and change descriptors of an account
+    fn descriptors(&self, account: u32) -> Result<[Descriptor; 2], Error>;
+}
+
+impl ReadOnlyWallet for Wallet {
+    fn network(&self) -> Network {
+        Wallet::network(self)
+    }
+
+    fn address_type(&self) -> AddressType {
+        Wallet::address_type(self)
+    }
+
+    fn account_indices(&self) -> Vec<u32> {
+        self.accounts()
+            .iter()
+            .map(|account| account.index)
+            .collect()
+    }
+
+    fn addresses(
+        &self,
+        account: u32,
+        change: Change,
+        start: u32,
+        count: u32,
+    ) -> Result<Vec<String>, Error> {
+        Wallet::addresses(self, account, change, start, count)
+    }
+
+    fn find_address(&self, address: &str) -> Result<Option<AddressLocation>, Error> {
+        Wallet::find_address(self, address)
+    }
+
+    fn descriptors(&self, account: u32) -> Result<[Descriptor; 2], Error> {
+        Wallet::descriptors(self, account)
+    }
+}
+
+impl ReadOnlyWallet for WatchOnlyWallet {
+    fn network(&self) -> Network {
+        WatchOnlyWallet::network(self)
+    }
+
+    fn address_type(&self) -> AddressType {
+        WatchOnlyWallet::address_type(self)
+    }
+
+    fn account_indices(&self) -> Vec<u32> {
+        vec![self.account().index]
+    }
+
+    fn addresses(
+        &self,
+        account: u32,
+        change: Change,
+        start: u32,
+        count: u32,
+    ) -> Result<Vec<String>, Error> {
+        check_account(self, account)?;
+        WatchOnlyWallet::addresses(self, change, start, count)
+    }
+
+    fn find_address(&self, address: &str) -> Result<Option<AddressLocation>, Error> {
+        WatchOnlyWallet::find_address(self, address)
+    }
+
+    fn descriptors(&self, account: u32) -> Result<[Descriptor; 2], Error> {
+        check_account(self, account)?;
+        WatchOnlyWallet::descriptors(self)
+    }
+}
+
+fn check_account(wallet: &WatchOnlyWallet, account: u32) -> Result<(), Error> {
+    if account != wallet.account().index {
+        return Err(Error::InvalidDerivationPath(format!(
+            "Unknown account {}",
+            account
+        )));
+    }
+    Ok(())
+}
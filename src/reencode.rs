@@ -0,0 +1,105 @@
+use crate::bip39::{Language, Mnemonic, MnemonicType};
+use crate::error::Error;
+use crate::wipe::wipe;
+
+/// What re-encoding did to a phrase's entropy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reencoding {
+    /// Same word count; the phrase is unchanged
+    Identical,
+    /// Only the leading entropy was kept, lowering security to the target size
+    Truncated { dropped_bits: usize },
+    /// Zero bytes were appended, which add no security; truncating back
+    /// restores the original phrase
+    ZeroPadded { added_bits: usize },
+}
+
+/// A phrase re-encoded at another word count, with what happened to its entropy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reencoded {
+    pub mnemonic: Mnemonic,
+    pub reencoding: Reencoding,
+}
+
+impl Reencoded {
+    /// Whether the new phrase derives a different seed, and so a different
+    /// wallet, than the original. Only identical re-encodings keep the seed.
+    pub fn changes_seed(&self) -> bool {
+        self.reencoding != Reencoding::Identical
+    }
+}
+
+impl Mnemonic {
+    /// Re-encode the phrase's entropy at another word count. Shrinking
+    /// discards entropy and requires `allow_truncate`.
+    pub fn reencode(&self, target: MnemonicType, allow_truncate: bool) -> Result<Reencoded, Error> {
+        let mut entropy = self.to_entropy();
+        let reencoded = reencode_entropy(&entropy, target, self.language(), allow_truncate);
+        wipe(&mut entropy);
+        reencoded
+    }
+}
+
+/// Re-encode hex entropy, as printed by backup tooling, as a phrase of the
+/// target word count
+pub fn reencode_hex(
+    entropy_hex: &str,
+    target: MnemonicType,
+    language: Language,
+    allow_truncate: bool,
+) -> Result<Reencoded, Error> {
+    let mut entropy = hex::decode(entropy_hex.trim())
+        .map_err(|e| Error::InvalidEntropy(format!("Invalid hex entropy: {}", e)))?;
+    if ![16, 20, 24, 28, 32].contains(&entropy.len()) {
+        let len = entropy.len();
+        wipe(&mut entropy);
+        return Err(Error::InvalidEntropy(format!(
+            "Entropy must be 16 to 32 bytes in steps of 4, got {}",
+            len
+        )));
+    }
+    let reencoded = reencode_entropy(&entropy, target, language, allow_truncate);
+    wipe(&mut entropy);
+    reencoded
+}
+
+fn reencode_entropy(
+    entropy: &[u8],
+    target: MnemonicType,
+    language: Language,
+    allow_truncate: bool,
+) -> Result<Reencoded, Error> {
+    let target_bytes = target.entropy_bytes();
+
+    let (mut entropy, reencoding) = if entropy.len() == target_bytes {
+        (entropy.to_vec(), Reencoding::Identical)
+    } else if entropy.len() > target_bytes {
+        let dropped_bits = (entropy.len() - target_bytes) * 8;
+        if !allow_truncate {
+            return Err(Error::InvalidEntropy(format!(
+                "Re-encoding as {} words discards {} bits of entropy and requires explicit opt-in",
+                target.word_count(),
+                dropped_bits
+            )));
+        }
+        (
+            entropy[..target_bytes].to_vec(),
+            Reencoding::Truncated { dropped_bits },
+        )
+    } else {
+        let mut padded = entropy.to_vec();
+        padded.resize(target_bytes, 0);
+        let added_bits = (target_bytes - entropy.len()) * 8;
+        (padded, Reencoding::ZeroPadded { added_bits })
+    };
+
+    let phrase = Mnemonic::entropy_to_words(&entropy, target, &language);
+    wipe(&mut entropy);
+    let mut phrase = phrase?;
+    let mnemonic = Mnemonic::from_phrase(&phrase, language);
+    wipe(&mut phrase);
+    Ok(Reencoded {
+        mnemonic: mnemonic?,
+        reencoding,
+    })
+}
@@ -0,0 +1,175 @@
+use crate::bip32::DerivationPath;
+use crate::error::Error;
+use crate::signer::{Signer, SigningOptions};
+use secp256k1::{ecdsa::Signature, PublicKey};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io::{ErrorKind, Read, Write};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// Largest frame either side accepts
+pub const MAX_FRAME_LEN: usize = 64 * 1024;
+
+/// A request from client to server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Request {
+    PublicKey {
+        path: String,
+    },
+    SignEcdsa {
+        path: String,
+        digest: Vec<u8>,
+        grind_low_r: bool,
+    },
+}
+
+/// The server's answer to a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Response {
+    /// Compressed SEC1 public key
+    PublicKey(Vec<u8>),
+    /// Compact 64-byte signature
+    Signature(Vec<u8>),
+    Error(String),
+}
+
+/// Write a message as a frame: its CBOR encoding prefixed by a big-endian
+/// u32 length
+pub fn write_frame<T: Serialize>(writer: &mut impl Write, message: &T) -> Result<(), Error> {
+    let mut body = Vec::new();
+    ciborium::into_writer(message, &mut body).map_err(|e| Error::Remote(e.to_string()))?;
+    if body.len() > MAX_FRAME_LEN {
+        return Err(Error::Remote("Frame too large".to_string()));
+    }
+    writer
+        .write_all(&(body.len() as u32).to_be_bytes())
+        .and_then(|_| writer.write_all(&body))
+        .and_then(|_| writer.flush())
+        .map_err(|e| Error::Remote(e.to_string()))
+}
+
+/// Read one frame written by [`write_frame`]. Returns `None` if the stream
+/// ends cleanly before a frame starts.
+pub fn read_frame<T: DeserializeOwned>(reader: &mut impl Read) -> Result<Option<T>, Error> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(Error::Remote(e.to_string())),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(Error::Remote("Frame too large".to_string()));
+    }
+
+    let mut body = vec![0u8; len];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| Error::Remote(e.to_string()))?;
+    ciborium::from_reader(body.as_slice())
+        .map(Some)
+        .map_err(|e| Error::Remote(e.to_string()))
+}
+
+/// Serves requests from remote clients with a local signer, such as an
+/// [`ExtendedPrivKey`](crate::ExtendedPrivKey) kept in a separate process
+/// or host
+pub struct SignerServer<S: Signer> {
+    signer: S,
+}
+
+impl<S: Signer> SignerServer<S> {
+    pub fn new(signer: S) -> Self {
+        SignerServer { signer }
+    }
+
+    /// Answer a single request
+    pub fn handle(&self, request: Request) -> Response {
+        let result = match request {
+            Request::PublicKey { path } => DerivationPath::from_str(&path)
+                .and_then(|path| self.signer.public_key(&path))
+                .map(|key| Response::PublicKey(key.serialize().to_vec())),
+            Request::SignEcdsa {
+                path,
+                digest,
+                grind_low_r,
+            } => <[u8; 32]>::try_from(digest.as_slice())
+                .map_err(|_| Error::Remote("Digest must be 32 bytes".to_string()))
+                .and_then(|digest| {
+                    let path = DerivationPath::from_str(&path)?;
+                    self.signer
+                        .sign_ecdsa(&path, &digest, SigningOptions { grind_low_r })
+                })
+                .map(|signature| Response::Signature(signature.serialize_compact().to_vec())),
+        };
+        result.unwrap_or_else(|e| Response::Error(e.to_string()))
+    }
+
+    /// Answer requests on a connection until the client closes it
+    pub fn serve<T: Read + Write>(&self, connection: &mut T) -> Result<(), Error> {
+        while let Some(request) = read_frame(connection)? {
+            write_frame(connection, &self.handle(request))?;
+        }
+        Ok(())
+    }
+}
+
+/// A [`Signer`] forwarding every request to a [`SignerServer`] over a
+/// connection, such as a TCP or Unix socket stream
+pub struct RemoteSigner<T: Read + Write> {
+    connection: Mutex<T>,
+}
+
+impl<T: Read + Write> RemoteSigner<T> {
+    pub fn new(connection: T) -> Self {
+        RemoteSigner {
+            connection: Mutex::new(connection),
+        }
+    }
+
+    /// Get the connection back
+    pub fn into_inner(self) -> T {
+        self.connection
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn call(&self, request: &Request) -> Result<Response, Error> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        write_frame(&mut *connection, request)?;
+        match read_frame(&mut *connection)? {
+            Some(Response::Error(message)) => Err(Error::Remote(message)),
+            Some(response) => Ok(response),
+            None => Err(Error::Remote("Connection closed".to_string())),
+        }
+    }
+}
+
+impl<T: Read + Write> Signer for RemoteSigner<T> {
+    fn public_key(&self, path: &DerivationPath) -> Result<PublicKey, Error> {
+        let request = Request::PublicKey {
+            path: path.to_string(),
+        };
+        match self.call(&request)? {
+            Response::PublicKey(key) => Ok(PublicKey::from_slice(&key)?),
+            _ => Err(Error::Remote("Unexpected response".to_string())),
+        }
+    }
+
+    fn sign_ecdsa(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        options: SigningOptions,
+    ) -> Result<Signature, Error> {
+        let request = Request::SignEcdsa {
+            path: path.to_string(),
+            digest: digest.to_vec(),
+            grind_low_r: options.grind_low_r,
+        };
+        match self.call(&request)? {
+            Response::Signature(signature) => Ok(Signature::from_compact(&signature)?),
+            _ => Err(Error::Remote("Unexpected response".to_string())),
+        }
+    }
+}
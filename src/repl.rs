@@ -0,0 +1,108 @@
+use crate::bip32::{DerivationPath, ExtendedPrivKey};
+use crate::config::Config;
+use crate::error::Error;
+
+/// Commands understood by [`Session::execute`]
+pub const HELP: &str = "\
+derive <path>            extended public key at a path
+address <path>           address of the key at a path
+sign <path> <message>    BIP-137 signature of a message
+inspect [path]           network, depth and fingerprints of a key
+help                     this list
+quit                     end the session";
+
+/// Outcome of one session command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// Text to print
+    Output(String),
+    /// The operator ended the session
+    Quit,
+}
+
+/// An interactive session over an unlocked master key, so a keystore is
+/// opened once per operator session instead of once per command. Private
+/// keys are used for signing but never printed.
+#[derive(Debug, Clone)]
+pub struct Session {
+    master: ExtendedPrivKey,
+    config: Config,
+}
+
+impl Session {
+    /// Start a session over a master key
+    pub fn new(master: ExtendedPrivKey, config: Config) -> Self {
+        Session { master, config }
+    }
+
+    /// Start a session over the master key of a seed
+    pub fn from_seed(seed: &[u8], config: Config) -> Result<Self, Error> {
+        Ok(Session::new(
+            ExtendedPrivKey::new_master(seed, config.network)?,
+            config,
+        ))
+    }
+
+    /// Run one command line
+    pub fn execute(&self, line: &str) -> Result<Reply, Error> {
+        let line = line.trim();
+        let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim();
+
+        let output = match command {
+            "" => String::new(),
+            "help" => HELP.to_string(),
+            "quit" | "exit" => return Ok(Reply::Quit),
+            "derive" => {
+                let key = self.derive(required(args, "derive <path>")?)?;
+                key.to_extended_public_key().to_string()
+            }
+            "address" => {
+                let key = self.derive(required(args, "address <path>")?)?;
+                key.to_extended_public_key()
+                    .address(self.config.address_type()?)
+            }
+            "sign" => {
+                let usage = "sign <path> <message>";
+                let (path, message) = required(args, usage)?
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| Error::Usage(format!("expected '{}'", usage)))?;
+                self.derive(path)?
+                    .sign_message(message.trim_start(), self.config.address_type()?)
+            }
+            "inspect" => {
+                let path = if args.is_empty() { "m" } else { args };
+                let key = self.derive(path)?;
+                format!(
+                    "path: {}\nnetwork: {:?}\ndepth: {}\nfingerprint: {}\nparent fingerprint: {}\nchild number: {}",
+                    self.config.format_path(&self.config.parse_path(path)?),
+                    key.network,
+                    key.depth,
+                    hex::encode(key.fingerprint()),
+                    hex::encode(key.parent_fingerprint),
+                    key.child_number
+                )
+            }
+            other => {
+                return Err(Error::Usage(format!(
+                    "unknown command '{}', try 'help'",
+                    other
+                )))
+            }
+        };
+        Ok(Reply::Output(output))
+    }
+
+    fn derive(&self, path: &str) -> Result<ExtendedPrivKey, Error> {
+        let path: DerivationPath = self.config.parse_path(path)?;
+        self.master.derive_path(&path)
+    }
+}
+
+/// The arguments of a command, which must not be empty
+fn required<'a>(args: &'a str, usage: &str) -> Result<&'a str, Error> {
+    if args.is_empty() {
+        return Err(Error::Usage(format!("expected '{}'", usage)));
+    }
+    Ok(args)
+}
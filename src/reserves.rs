@@ -0,0 +1,54 @@
+use crate::address::AddressType;
+use crate::bip32::{DerivationPath, ExtendedPrivKey, Network};
+use crate::error::Error;
+use crate::message;
+
+/// A signed statement proving control of a derived address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressAttestation {
+    /// Derivation path of the address key
+    pub path: DerivationPath,
+    /// The attested address
+    pub address: String,
+    /// Base64 BIP-137 signature over the challenge
+    pub signature: String,
+}
+
+/// Sign a challenge with the key at each path, proving control of the derived addresses.
+/// Signatures use the BIP-137 message format.
+pub fn attest_addresses(
+    master: &ExtendedPrivKey,
+    paths: &[DerivationPath],
+    address_type: AddressType,
+    challenge: &str,
+) -> Result<Vec<AddressAttestation>, Error> {
+    paths
+        .iter()
+        .map(|path| {
+            let key = master.derive_path(path)?;
+            Ok(AddressAttestation {
+                path: path.clone(),
+                address: key.to_extended_public_key().address(address_type),
+                signature: key.sign_message(challenge, address_type),
+            })
+        })
+        .collect()
+}
+
+/// Verify every attestation against the challenge
+pub fn verify_attestations(
+    attestations: &[AddressAttestation],
+    challenge: &str,
+    network: Network,
+) -> Result<(), Error> {
+    for attestation in attestations {
+        message::verify_message(
+            &attestation.address,
+            challenge,
+            &attestation.signature,
+            network,
+        )?;
+    }
+
+    Ok(())
+}
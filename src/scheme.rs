@@ -0,0 +1,207 @@
+use crate::address::{self, AddressType};
+use crate::avalanche::{self, AvalancheChain};
+use crate::bip32::{DerivationPath, Network};
+use crate::bip44::{AccountLevel, AddressIndex, Bip44Path, Change, CoinType};
+use crate::cosmos;
+use crate::error::Error;
+use crate::evm::EvmChain;
+use crate::filecoin;
+use crate::wallet::Wallet;
+use secp256k1::PublicKey;
+
+/// An address format for secp256k1 keys. The built-in chains implement it,
+/// and downstream crates can implement it to add their own.
+pub trait AddressScheme {
+    /// Human-readable name of the chain or address type
+    fn name(&self) -> &str;
+
+    /// Coin type addresses are derived at
+    fn coin_type(&self) -> CoinType;
+
+    /// Get the path of an address; `m/44'/coin'/account'/0/index` by default
+    fn path(&self, account: u32, index: u32) -> DerivationPath {
+        Bip44Path::standard(
+            self.coin_type(),
+            AccountLevel::new(account),
+            Change::External,
+            AddressIndex::new(index),
+        )
+        .to_derivation_path()
+    }
+
+    /// Encode the address of a public key
+    fn encode(&self, public_key: &PublicKey) -> Result<String, Error>;
+
+    /// Check that a string is a well-formed address of this scheme
+    fn validate(&self, address: &str) -> Result<(), Error>;
+}
+
+/// Bitcoin single-key addresses of a type on a network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitcoinScheme {
+    pub address_type: AddressType,
+    pub network: Network,
+}
+
+impl AddressScheme for BitcoinScheme {
+    fn name(&self) -> &str {
+        match self.address_type {
+            AddressType::P2pkh => "Bitcoin P2PKH",
+            AddressType::P2shP2wpkh => "Bitcoin P2SH-P2WPKH",
+            AddressType::P2wpkh => "Bitcoin P2WPKH",
+        }
+    }
+
+    fn coin_type(&self) -> CoinType {
+        CoinType::for_network(self.network)
+    }
+
+    /// Get the path of an address under the address type's BIP-43 purpose
+    fn path(&self, account: u32, index: u32) -> DerivationPath {
+        Bip44Path::new(
+            self.address_type.purpose(),
+            self.coin_type(),
+            AccountLevel::new(account),
+            Change::External,
+            AddressIndex::new(index),
+        )
+        .to_derivation_path()
+    }
+
+    fn encode(&self, public_key: &PublicKey) -> Result<String, Error> {
+        Ok(address::encode(public_key, self.address_type, self.network))
+    }
+
+    fn validate(&self, address: &str) -> Result<(), Error> {
+        address::validate(address, self.address_type, self.network)
+    }
+}
+
+impl AddressScheme for EvmChain {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn coin_type(&self) -> CoinType {
+        self.coin_type
+    }
+
+    fn path(&self, account: u32, index: u32) -> DerivationPath {
+        EvmChain::path(self, account, index)
+    }
+
+    fn encode(&self, public_key: &PublicKey) -> Result<String, Error> {
+        Ok(self.encode_address(public_key))
+    }
+
+    fn validate(&self, address: &str) -> Result<(), Error> {
+        self.decode_address(address).map(|_| ())
+    }
+}
+
+/// Cosmos SDK account addresses with a chain's bech32 prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CosmosScheme {
+    pub hrp: String,
+}
+
+impl CosmosScheme {
+    /// Cosmos Hub addresses (`cosmos1...`)
+    pub fn hub() -> Self {
+        CosmosScheme {
+            hrp: cosmos::COSMOS_HRP.to_string(),
+        }
+    }
+}
+
+impl AddressScheme for CosmosScheme {
+    fn name(&self) -> &str {
+        "Cosmos"
+    }
+
+    fn coin_type(&self) -> CoinType {
+        CoinType::COSMOS
+    }
+
+    fn encode(&self, public_key: &PublicKey) -> Result<String, Error> {
+        cosmos::address(public_key, &self.hrp)
+    }
+
+    fn validate(&self, address: &str) -> Result<(), Error> {
+        cosmos::decode_address(address, &self.hrp).map(|_| ())
+    }
+}
+
+/// Filecoin secp256k1 (f1/t1) addresses on a network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilecoinScheme {
+    pub network: Network,
+}
+
+impl AddressScheme for FilecoinScheme {
+    fn name(&self) -> &str {
+        "Filecoin"
+    }
+
+    fn coin_type(&self) -> CoinType {
+        CoinType::FILECOIN
+    }
+
+    fn encode(&self, public_key: &PublicKey) -> Result<String, Error> {
+        Ok(filecoin::encode_address(public_key, self.network))
+    }
+
+    fn validate(&self, address: &str) -> Result<(), Error> {
+        match filecoin::decode_address(address)? {
+            (network, _) if network == self.network => Ok(()),
+            _ => Err(Error::InvalidAddress(
+                "Filecoin address is for another network".to_string(),
+            )),
+        }
+    }
+}
+
+/// Avalanche X- or P-chain addresses on a network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AvalancheScheme {
+    pub chain: AvalancheChain,
+    pub network: Network,
+}
+
+impl AddressScheme for AvalancheScheme {
+    fn name(&self) -> &str {
+        match self.chain {
+            AvalancheChain::X => "Avalanche X-Chain",
+            AvalancheChain::P => "Avalanche P-Chain",
+        }
+    }
+
+    fn coin_type(&self) -> CoinType {
+        CoinType::AVALANCHE
+    }
+
+    fn encode(&self, public_key: &PublicKey) -> Result<String, Error> {
+        Ok(avalanche::encode_address(
+            public_key,
+            self.chain,
+            self.network,
+        ))
+    }
+
+    fn validate(&self, address: &str) -> Result<(), Error> {
+        match avalanche::decode_address(address)? {
+            (chain, network, _) if chain == self.chain && network == self.network => Ok(()),
+            _ => Err(Error::InvalidAddress(
+                "Avalanche address is for another chain or network".to_string(),
+            )),
+        }
+    }
+}
+
+impl Wallet {
+    /// Get the address at an index of the first account under any address scheme
+    pub fn scheme_address(&self, scheme: &dyn AddressScheme, index: u32) -> Result<String, Error> {
+        let key = self.master().derive_path(&scheme.path(0, index))?;
+        scheme.encode(&key.to_extended_public_key().public_key)
+    }
+}
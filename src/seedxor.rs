@@ -0,0 +1,96 @@
+use crate::bip39::Mnemonic;
+use crate::error::Error;
+use crate::wipe::wipe;
+use rand::rngs::OsRng;
+use rand_core::CryptoRngCore;
+
+/// Entropy lengths Seed XOR is defined for: 12 and 24 word phrases
+const SUPPORTED_ENTROPY_LEN: [usize; 2] = [16, 32];
+
+impl Mnemonic {
+    /// Split the phrase into `n` Seed XOR shares, as on Coldcard. Each
+    /// share is a valid phrase of the same length and language; all of
+    /// them are needed to recover this one.
+    pub fn xor_split(&self, n: usize) -> Result<Vec<Mnemonic>, Error> {
+        self.xor_split_with_rng(n, &mut OsRng)
+    }
+
+    /// Split into `n` Seed XOR shares with randomness from a caller-supplied RNG
+    pub fn xor_split_with_rng(
+        &self,
+        n: usize,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<Vec<Mnemonic>, Error> {
+        if n < 2 {
+            return Err(Error::InvalidConfig(
+                "Seed XOR needs at least 2 shares".to_string(),
+            ));
+        }
+        check_entropy_len(self)?;
+        let mut last = self.to_entropy();
+
+        let mut shares = Vec::with_capacity(n);
+        let mut share = vec![0u8; last.len()];
+        let split = (1..n)
+            .try_for_each(|_| {
+                rng.fill_bytes(&mut share);
+                xor_into(&mut last, &share);
+                shares.push(Mnemonic::from_entropy(&share, self.language())?);
+                Ok(())
+            })
+            .and_then(|()| {
+                shares.push(Mnemonic::from_entropy(&last, self.language())?);
+                Ok(())
+            });
+        wipe(&mut share);
+        wipe(&mut last);
+        split.map(|()| shares)
+    }
+
+    /// Recombine Seed XOR shares into the original phrase, in the language
+    /// of the first share. Shares can be given in any order.
+    pub fn xor_combine(shares: &[Mnemonic]) -> Result<Mnemonic, Error> {
+        if shares.len() < 2 {
+            return Err(Error::InvalidConfig(
+                "Seed XOR needs at least 2 shares".to_string(),
+            ));
+        }
+        let (first, rest) = (&shares[0], &shares[1..]);
+
+        check_entropy_len(first)?;
+        let mut entropy = first.to_entropy();
+        for share in rest {
+            let mut bytes = share.to_entropy();
+            if bytes.len() != entropy.len() {
+                wipe(&mut bytes);
+                wipe(&mut entropy);
+                return Err(Error::InvalidEntropy(
+                    "Seed XOR shares must have the same length".to_string(),
+                ));
+            }
+            xor_into(&mut entropy, &bytes);
+            wipe(&mut bytes);
+        }
+        let mnemonic = Mnemonic::from_entropy(&entropy, first.language());
+        wipe(&mut entropy);
+        mnemonic
+    }
+}
+
+/// Check the entropy length from the word count, before any entropy is
+/// extracted. Each word holds 11 bits, one in 33 of which is checksum.
+fn check_entropy_len(mnemonic: &Mnemonic) -> Result<(), Error> {
+    let len = mnemonic.phrase().split_whitespace().count() * 4 / 3;
+    if !SUPPORTED_ENTROPY_LEN.contains(&len) {
+        return Err(Error::InvalidEntropy(
+            "Seed XOR supports 12 and 24 word phrases".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn xor_into(target: &mut [u8], other: &[u8]) {
+    for (t, o) in target.iter_mut().zip(other) {
+        *t ^= o;
+    }
+}
@@ -0,0 +1,121 @@
+use crate::antiexfil;
+use crate::bip32::{DerivationPath, ExtendedPrivKey};
+use crate::error::Error;
+use crate::metrics::{self, SignatureKind};
+use crate::utils;
+use secp256k1::{ecdsa::Signature, Message, PublicKey};
+
+/// Options controlling ECDSA signature generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SigningOptions {
+    /// Grind the nonce until R has its high bit clear, like Bitcoin Core.
+    /// Low-R signatures always DER-encode to at most 70 bytes.
+    pub grind_low_r: bool,
+}
+
+impl SigningOptions {
+    /// Options matching Bitcoin Core's signing behavior
+    pub fn bitcoin_core() -> Self {
+        SigningOptions { grind_low_r: true }
+    }
+}
+
+/// A source of signatures for keys identified by derivation path
+pub trait Signer {
+    /// Get the public key at the given path
+    fn public_key(&self, path: &DerivationPath) -> Result<PublicKey, Error>;
+
+    /// Sign a 32-byte digest with the key at the given path
+    fn sign_ecdsa(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        options: SigningOptions,
+    ) -> Result<Signature, Error>;
+
+    /// First round of anti-exfil signing: commit to the nonce point for a digest
+    fn anti_exfil_commit(
+        &self,
+        _path: &DerivationPath,
+        _digest: &[u8; 32],
+        _host_commitment: &[u8; 32],
+    ) -> Result<PublicKey, Error> {
+        Err(Error::Unsupported(
+            "Anti-exfil signing not supported by this signer".to_string(),
+        ))
+    }
+
+    /// Second round of anti-exfil signing: sign using the revealed host nonce
+    fn anti_exfil_sign(
+        &self,
+        _path: &DerivationPath,
+        _digest: &[u8; 32],
+        _host_nonce: &[u8; 32],
+    ) -> Result<Signature, Error> {
+        Err(Error::Unsupported(
+            "Anti-exfil signing not supported by this signer".to_string(),
+        ))
+    }
+}
+
+impl ExtendedPrivKey {
+    /// Sign a 32-byte digest with this key
+    pub fn sign_digest(&self, digest: &[u8; 32], options: SigningOptions) -> Signature {
+        let secp = utils::secp();
+        let message = Message::from_digest(*digest);
+
+        metrics::signature(SignatureKind::Ecdsa);
+        if options.grind_low_r {
+            secp.sign_ecdsa_low_r(&message, &self.private_key)
+        } else {
+            secp.sign_ecdsa(&message, &self.private_key)
+        }
+    }
+}
+
+impl Signer for ExtendedPrivKey {
+    fn public_key(&self, path: &DerivationPath) -> Result<PublicKey, Error> {
+        Ok(self.derive_path(path)?.to_extended_public_key().public_key)
+    }
+
+    fn sign_ecdsa(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        options: SigningOptions,
+    ) -> Result<Signature, Error> {
+        Ok(self.derive_path(path)?.sign_digest(digest, options))
+    }
+
+    fn anti_exfil_commit(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        host_commitment: &[u8; 32],
+    ) -> Result<PublicKey, Error> {
+        let key = self.derive_path(path)?;
+        antiexfil::signer_commitment(&key.private_key, digest, host_commitment)
+    }
+
+    fn anti_exfil_sign(
+        &self,
+        path: &DerivationPath,
+        digest: &[u8; 32],
+        host_nonce: &[u8; 32],
+    ) -> Result<Signature, Error> {
+        let key = self.derive_path(path)?;
+        antiexfil::sign(&key.private_key, digest, host_nonce)
+    }
+}
+
+/// Verify an ECDSA signature over a 32-byte digest
+pub fn verify_ecdsa(
+    public_key: &PublicKey,
+    digest: &[u8; 32],
+    signature: &Signature,
+) -> Result<(), Error> {
+    let secp = utils::secp();
+    let message = Message::from_digest(*digest);
+    secp.verify_ecdsa(&message, signature, public_key)?;
+    Ok(())
+}
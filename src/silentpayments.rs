@@ -0,0 +1,331 @@
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, Network};
+use crate::error::Error;
+use crate::utils;
+use bech32::primitives::decode::CheckedHrpstring;
+use bech32::{Bech32m, ByteIterExt, Fe32, Fe32IterExt, Hrp};
+use secp256k1::{PublicKey, Scalar, SecretKey, XOnlyPublicKey};
+use std::fmt;
+use std::str::FromStr;
+
+/// BIP-352 purpose (352')
+pub const SILENT_PAYMENTS_PURPOSE: u32 = 352;
+
+/// A transaction outpoint, with the txid in serialization (little-endian) byte order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Outpoint {
+    pub txid: [u8; 32],
+    pub vout: u32,
+}
+
+impl Outpoint {
+    /// Serialize as txid || vout (little-endian)
+    pub fn serialize(&self) -> [u8; 36] {
+        let mut data = [0u8; 36];
+        data[..32].copy_from_slice(&self.txid);
+        data[32..].copy_from_slice(&self.vout.to_le_bytes());
+        data
+    }
+}
+
+/// A sender input key eligible for silent payments
+#[derive(Debug, Clone, Copy)]
+pub struct SenderInput {
+    pub private_key: SecretKey,
+    /// Whether the input is a taproot key-path spend (x-only key)
+    pub taproot: bool,
+}
+
+/// A silent payment address (scan and spend public keys)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SilentPaymentAddress {
+    pub scan_key: PublicKey,
+    pub spend_key: PublicKey,
+    pub network: Network,
+}
+
+fn address_hrp(network: Network) -> Hrp {
+    match network {
+        Network::Bitcoin => Hrp::parse_unchecked("sp"),
+        Network::Testnet => Hrp::parse_unchecked("tsp"),
+    }
+}
+
+impl fmt::Display for SilentPaymentAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut payload = Vec::with_capacity(66);
+        payload.extend_from_slice(&self.scan_key.serialize());
+        payload.extend_from_slice(&self.spend_key.serialize());
+
+        let hrp = address_hrp(self.network);
+        for c in payload
+            .into_iter()
+            .bytes_to_fes()
+            .with_checksum::<Bech32m>(&hrp)
+            .with_witness_version(Fe32::Q)
+            .chars()
+        {
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SilentPaymentAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut checked = CheckedHrpstring::new::<Bech32m>(s)
+            .map_err(|e| Error::InvalidAddress(e.to_string()))?;
+
+        let network = if checked.hrp() == address_hrp(Network::Bitcoin) {
+            Network::Bitcoin
+        } else if checked.hrp() == address_hrp(Network::Testnet) {
+            Network::Testnet
+        } else {
+            return Err(Error::InvalidAddress(
+                "Unknown silent payment prefix".to_string(),
+            ));
+        };
+
+        if checked.remove_witness_version() != Some(Fe32::Q) {
+            return Err(Error::InvalidAddress(
+                "Unsupported silent payment version".to_string(),
+            ));
+        }
+
+        let payload: Vec<u8> = checked.byte_iter().collect();
+        if payload.len() != 66 {
+            return Err(Error::InvalidAddress(
+                "Invalid silent payment payload length".to_string(),
+            ));
+        }
+
+        Ok(SilentPaymentAddress {
+            scan_key: PublicKey::from_slice(&payload[..33])?,
+            spend_key: PublicKey::from_slice(&payload[33..])?,
+            network,
+        })
+    }
+}
+
+/// An output found while scanning, with the tweak needed to spend it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoundOutput {
+    pub output: XOnlyPublicKey,
+    /// Tweak to add to the spend private key
+    pub tweak: Scalar,
+    /// Label the output was paid to, if any
+    pub label: Option<u32>,
+}
+
+/// Receiver keys for silent payments
+#[derive(Debug, Clone, Copy)]
+pub struct SilentPaymentKeys {
+    pub scan_key: SecretKey,
+    pub spend_key: SecretKey,
+    pub network: Network,
+}
+
+fn scalar(hash: [u8; 32]) -> Result<Scalar, Error> {
+    Scalar::from_be_bytes(hash).map_err(|_| Error::InvalidKey("Hash out of range".to_string()))
+}
+
+/// hash_BIP0352/Inputs(outpoint_L || A)
+fn input_hash(outpoints: &[Outpoint], input_sum: &PublicKey) -> Result<Scalar, Error> {
+    let smallest = outpoints
+        .iter()
+        .map(Outpoint::serialize)
+        .min()
+        .ok_or_else(|| Error::InvalidKey("No outpoints given".to_string()))?;
+
+    let mut data = Vec::with_capacity(69);
+    data.extend_from_slice(&smallest);
+    data.extend_from_slice(&input_sum.serialize());
+    scalar(utils::tagged_hash("BIP0352/Inputs", &data))
+}
+
+/// hash_BIP0352/SharedSecret(ser_P(shared_secret) || ser_32(k))
+fn shared_secret_tweak(shared_secret: &PublicKey, k: u32) -> Result<Scalar, Error> {
+    let mut data = Vec::with_capacity(37);
+    data.extend_from_slice(&shared_secret.serialize());
+    data.extend_from_slice(&k.to_be_bytes());
+    scalar(utils::tagged_hash("BIP0352/SharedSecret", &data))
+}
+
+/// hash_BIP0352/Label(ser_256(b_scan) || ser_32(m))
+fn label_tweak(scan_key: &SecretKey, label: u32) -> Result<Scalar, Error> {
+    let mut data = Vec::with_capacity(36);
+    data.extend_from_slice(&scan_key[..]);
+    data.extend_from_slice(&label.to_be_bytes());
+    scalar(utils::tagged_hash("BIP0352/Label", &data))
+}
+
+/// Compute the taproot output keys a sender creates for the given recipients,
+/// in recipient order
+pub fn sender_outputs(
+    inputs: &[SenderInput],
+    outpoints: &[Outpoint],
+    recipients: &[SilentPaymentAddress],
+) -> Result<Vec<XOnlyPublicKey>, Error> {
+    let secp = utils::secp();
+
+    // Sum the input keys, negating taproot keys with odd y
+    let mut input_keys = inputs.iter().map(|input| {
+        let (_, parity) = input.private_key.x_only_public_key(secp);
+        if input.taproot && parity == secp256k1::Parity::Odd {
+            input.private_key.negate()
+        } else {
+            input.private_key
+        }
+    });
+    let first = input_keys
+        .next()
+        .ok_or_else(|| Error::InvalidKey("No inputs given".to_string()))?;
+    let input_sum = input_keys.try_fold(first, |sum, key| sum.add_tweak(&Scalar::from(key)))?;
+
+    let input_hash = input_hash(outpoints, &PublicKey::from_secret_key(secp, &input_sum))?;
+    let tweaked_sum = input_sum.mul_tweak(&input_hash)?;
+
+    // Outputs to the same scan key are numbered k = 0, 1, ... in recipient order
+    let mut counters: Vec<(PublicKey, u32)> = Vec::new();
+    recipients
+        .iter()
+        .map(|recipient| {
+            let k = match counters
+                .iter_mut()
+                .find(|(scan, _)| *scan == recipient.scan_key)
+            {
+                Some((_, k)) => {
+                    *k += 1;
+                    *k
+                }
+                None => {
+                    counters.push((recipient.scan_key, 0));
+                    0
+                }
+            };
+
+            let shared_secret = recipient
+                .scan_key
+                .mul_tweak(secp, &Scalar::from(tweaked_sum))?;
+            let tweak = shared_secret_tweak(&shared_secret, k)?;
+            let output = recipient.spend_key.add_exp_tweak(secp, &tweak)?;
+            Ok(output.x_only_public_key().0)
+        })
+        .collect()
+}
+
+impl SilentPaymentKeys {
+    /// Derive the scan (m/352'/coin'/account'/1'/0) and spend
+    /// (m/352'/coin'/account'/0'/0) keys from a master key
+    pub fn from_master(master: &ExtendedPrivKey, account: u32) -> Result<Self, Error> {
+        let coin_type = match master.network {
+            Network::Bitcoin => 0,
+            Network::Testnet => 1,
+        };
+        let derive = |branch: u32| {
+            master.derive_path(&DerivationPath {
+                path: vec![
+                    ChildNumber::Hardened(SILENT_PAYMENTS_PURPOSE),
+                    ChildNumber::Hardened(coin_type),
+                    ChildNumber::Hardened(account),
+                    ChildNumber::Hardened(branch),
+                    ChildNumber::Normal(0),
+                ],
+            })
+        };
+
+        Ok(SilentPaymentKeys {
+            scan_key: derive(1)?.private_key,
+            spend_key: derive(0)?.private_key,
+            network: master.network,
+        })
+    }
+
+    /// Get the silent payment address
+    pub fn address(&self) -> SilentPaymentAddress {
+        let secp = utils::secp();
+        SilentPaymentAddress {
+            scan_key: PublicKey::from_secret_key(secp, &self.scan_key),
+            spend_key: PublicKey::from_secret_key(secp, &self.spend_key),
+            network: self.network,
+        }
+    }
+
+    /// Get the address for a label (label 0 is reserved for change)
+    pub fn labeled_address(&self, label: u32) -> Result<SilentPaymentAddress, Error> {
+        let secp = utils::secp();
+        let mut address = self.address();
+        address.spend_key = address
+            .spend_key
+            .add_exp_tweak(secp, &label_tweak(&self.scan_key, label)?)?;
+        Ok(address)
+    }
+
+    /// Scan a transaction's taproot outputs for payments to these keys.
+    /// Taproot input keys must be given with even y.
+    pub fn scan(
+        &self,
+        input_public_keys: &[PublicKey],
+        outpoints: &[Outpoint],
+        outputs: &[XOnlyPublicKey],
+        labels: &[u32],
+    ) -> Result<Vec<FoundOutput>, Error> {
+        let secp = utils::secp();
+        let input_refs: Vec<&PublicKey> = input_public_keys.iter().collect();
+        let input_sum = PublicKey::combine_keys(&input_refs)?;
+        let input_hash = input_hash(outpoints, &input_sum)?;
+        let shared_secret = input_sum
+            .mul_tweak(secp, &input_hash)?
+            .mul_tweak(secp, &Scalar::from(self.scan_key))?;
+
+        let spend_key = PublicKey::from_secret_key(secp, &self.spend_key);
+        let label_tweaks: Vec<(u32, Scalar)> = labels
+            .iter()
+            .map(|&label| Ok((label, label_tweak(&self.scan_key, label)?)))
+            .collect::<Result<_, Error>>()?;
+
+        let mut found = Vec::new();
+        for k in 0.. {
+            let tweak = shared_secret_tweak(&shared_secret, k)?;
+            let candidate = spend_key.add_exp_tweak(secp, &tweak)?;
+
+            let mut matched = None;
+            if let Some(&output) = outputs
+                .iter()
+                .find(|o| **o == candidate.x_only_public_key().0)
+            {
+                matched = Some((output, tweak, None));
+            } else {
+                for (label, extra) in &label_tweaks {
+                    let labeled = candidate.add_exp_tweak(secp, extra)?;
+                    if let Some(&output) = outputs
+                        .iter()
+                        .find(|o| **o == labeled.x_only_public_key().0)
+                    {
+                        let total =
+                            SecretKey::from_slice(&tweak.to_be_bytes())?.add_tweak(extra)?;
+                        matched = Some((output, Scalar::from(total), Some(*label)));
+                        break;
+                    }
+                }
+            }
+
+            match matched {
+                Some((output, tweak, label)) => found.push(FoundOutput {
+                    output,
+                    tweak,
+                    label,
+                }),
+                None => break,
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Get the private key spending a found output
+    pub fn spending_key(&self, found: &FoundOutput) -> Result<SecretKey, Error> {
+        Ok(self.spend_key.add_tweak(&found.tweak)?)
+    }
+}
@@ -0,0 +1,128 @@
+use crate::bip32::{ChildNumber, DerivationPath};
+use crate::error::Error;
+use crate::metrics::{self, SignatureKind};
+use crate::utils;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+
+/// HMAC key for SLIP-10 ed25519 master keys
+const ED25519_SEED_KEY: &[u8] = b"ed25519 seed";
+
+/// SLIP-10 extended ed25519 private key. Ed25519 only supports hardened
+/// derivation, so there is no public derivation counterpart.
+#[derive(Clone)]
+pub struct Ed25519ExtendedKey {
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+    pub chain_code: [u8; 32],
+    pub secret_key: [u8; 32],
+}
+
+impl std::fmt::Debug for Ed25519ExtendedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Ed25519ExtendedKey")
+            .field("depth", &self.depth)
+            .field("fingerprint", &hex::encode(self.fingerprint()))
+            .field("parent_fingerprint", &hex::encode(self.parent_fingerprint))
+            .field("child_number", &self.child_number)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Ed25519ExtendedKey {
+    /// Create a master key from a seed
+    pub fn new_master(seed: &[u8]) -> Result<Self, Error> {
+        if seed.len() < 16 || seed.len() > 64 {
+            return Err(Error::InvalidSeed(
+                "Seed must be between 16 and 64 bytes".to_string(),
+            ));
+        }
+
+        let hash = utils::hmac_sha512(ED25519_SEED_KEY, seed);
+        Ok(Ed25519ExtendedKey::from_hash(0, [0; 4], 0, &hash))
+    }
+
+    /// Derive a hardened child key
+    pub fn derive_child(&self, child_number: ChildNumber) -> Result<Self, Error> {
+        if !child_number.is_hardened() {
+            return Err(Error::InvalidDerivationPath(
+                "Ed25519 keys only support hardened derivation".to_string(),
+            ));
+        }
+
+        let mut data = Vec::with_capacity(37);
+        data.push(0);
+        data.extend_from_slice(&self.secret_key);
+        data.extend_from_slice(&child_number.to_u32().to_be_bytes());
+        let hash = utils::hmac_sha512(&self.chain_code, &data);
+
+        Ok(Ed25519ExtendedKey::from_hash(
+            self.depth.checked_add(1).ok_or_else(|| {
+                Error::InvalidDerivationPath("Maximum depth exceeded".to_string())
+            })?,
+            self.fingerprint(),
+            child_number.to_u32(),
+            &hash,
+        ))
+    }
+
+    /// Derive a key along a path of hardened elements
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, Error> {
+        path.path
+            .iter()
+            .try_fold(self.clone(), |key, &child| key.derive_child(child))
+    }
+
+    /// Get the ed25519 signing key
+    pub fn signing_key(&self) -> SigningKey {
+        SigningKey::from_bytes(&self.secret_key)
+    }
+
+    /// Get the ed25519 public key
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.signing_key().verifying_key()
+    }
+
+    /// Get the fingerprint: the first 4 bytes of the HASH160 of `0x00 || public key`
+    pub fn fingerprint(&self) -> [u8; 4] {
+        let mut data = [0u8; 33];
+        data[1..].copy_from_slice(self.verifying_key().as_bytes());
+        let hash = utils::hash160(&data);
+        [hash[0], hash[1], hash[2], hash[3]]
+    }
+
+    /// Sign a message
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        metrics::signature(SignatureKind::Ed25519);
+        self.signing_key().sign(message).to_bytes()
+    }
+
+    fn from_hash(
+        depth: u8,
+        parent_fingerprint: [u8; 4],
+        child_number: u32,
+        hash: &[u8; 64],
+    ) -> Self {
+        let mut secret_key = [0u8; 32];
+        let mut chain_code = [0u8; 32];
+        secret_key.copy_from_slice(&hash[..32]);
+        chain_code.copy_from_slice(&hash[32..]);
+
+        Ed25519ExtendedKey {
+            depth,
+            parent_fingerprint,
+            child_number,
+            chain_code,
+            secret_key,
+        }
+    }
+}
+
+/// Verify an ed25519 signature
+pub fn verify(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> Result<(), Error> {
+    let public_key = VerifyingKey::from_bytes(public_key)
+        .map_err(|_| Error::InvalidKey("Invalid ed25519 public key".to_string()))?;
+    public_key
+        .verify(message, &Signature::from_bytes(signature))
+        .map_err(|_| Error::InvalidSignature("Ed25519 signature does not match".to_string()))
+}
@@ -0,0 +1,189 @@
+use crate::address::AddressType;
+use crate::bip32::{ExtendedPubKey, Network};
+use crate::error::Error;
+use crate::utils;
+
+/// SLIP-132 extended public key version bytes, with the network and the
+/// single-key address type they imply (`None` when ambiguous or multisig)
+const PUBLIC_VERSIONS: [([u8; 4], Network, Option<AddressType>); 10] = [
+    // xpub
+    ([0x04, 0x88, 0xb2, 0x1e], Network::Bitcoin, None),
+    // ypub
+    (
+        [0x04, 0x9d, 0x7c, 0xb2],
+        Network::Bitcoin,
+        Some(AddressType::P2shP2wpkh),
+    ),
+    // zpub
+    (
+        [0x04, 0xb2, 0x47, 0x46],
+        Network::Bitcoin,
+        Some(AddressType::P2wpkh),
+    ),
+    // Ypub
+    ([0x02, 0x95, 0xb4, 0x3f], Network::Bitcoin, None),
+    // Zpub
+    ([0x02, 0xaa, 0x7e, 0xd3], Network::Bitcoin, None),
+    // tpub
+    ([0x04, 0x35, 0x87, 0xcf], Network::Testnet, None),
+    // upub
+    (
+        [0x04, 0x4a, 0x52, 0x62],
+        Network::Testnet,
+        Some(AddressType::P2shP2wpkh),
+    ),
+    // vpub
+    (
+        [0x04, 0x5f, 0x1c, 0xf6],
+        Network::Testnet,
+        Some(AddressType::P2wpkh),
+    ),
+    // Upub
+    ([0x02, 0x42, 0x89, 0xef], Network::Testnet, None),
+    // Vpub
+    ([0x02, 0x57, 0x54, 0x83], Network::Testnet, None),
+];
+
+/// Parse an extended public key with any SLIP-132 prefix (xpub, ypub, zpub, ...),
+/// returning the key and the address type its prefix implies
+pub fn parse_xpub(s: &str) -> Result<(ExtendedPubKey, Option<AddressType>), Error> {
+    let mut data = utils::base58check_decode(s)?;
+    if data.len() != 78 {
+        return Err(Error::InvalidExtendedKey(
+            "Invalid extended key length".to_string(),
+        ));
+    }
+
+    let (network, address_type) = PUBLIC_VERSIONS
+        .iter()
+        .find(|(version, _, _)| data[0..4] == version[..])
+        .map(|&(_, network, address_type)| (network, address_type))
+        .ok_or_else(|| Error::InvalidExtendedKey("Unknown SLIP-132 version bytes".to_string()))?;
+
+    data[0..4].copy_from_slice(&network.xpub_version());
+    let xpub = ExtendedPubKey::decode(&data)?;
+
+    Ok((xpub, address_type))
+}
+
+/// Encode an extended public key with the SLIP-132 prefix for an address type
+/// (xpub/tpub, ypub/upub or zpub/vpub)
+pub fn encode_xpub(xpub: &ExtendedPubKey, address_type: AddressType) -> String {
+    let implied = match address_type {
+        AddressType::P2pkh => None,
+        address_type => Some(address_type),
+    };
+    let version = PUBLIC_VERSIONS
+        .iter()
+        .find(|(_, network, address_type)| *network == xpub.network && *address_type == implied)
+        .map(|(version, _, _)| version)
+        .expect("every network has a version for each single-key address type");
+
+    let mut data = xpub.encode();
+    data[0..4].copy_from_slice(version);
+    utils::base58check_encode(&data)
+}
+
+/// A validated key from a batch, normalized to its canonical xpub/tpub form
+#[derive(Debug, Clone)]
+pub struct NormalizedXpub {
+    /// Position of the key in the input
+    pub index: usize,
+    pub xpub: ExtendedPubKey,
+    /// The key re-encoded with xpub/tpub version bytes
+    pub canonical: String,
+    /// Address type implied by the input's SLIP-132 prefix
+    pub address_type: Option<AddressType>,
+}
+
+/// A problem found in a batch of extended public keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchIssue {
+    /// The input does not parse as an extended public key
+    Invalid { index: usize, reason: String },
+    /// The input is the same key as an earlier one
+    Duplicate { index: usize, first: usize },
+    /// The input is the same key as an earlier one, but its prefix implies
+    /// a different address type
+    AddressTypeConflict { index: usize, first: usize },
+    /// The input is for a different network than the first valid key
+    NetworkMismatch { index: usize, first: usize },
+}
+
+/// Result of validating and deduplicating a batch of extended public keys
+#[derive(Debug, Clone, Default)]
+pub struct XpubBatch {
+    /// Unique valid keys, in input order
+    pub keys: Vec<NormalizedXpub>,
+    pub issues: Vec<BatchIssue>,
+}
+
+impl XpubBatch {
+    /// Whether every input was a valid, unique key on one network
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Canonical xpub/tpub strings of the unique keys
+    pub fn canonical(&self) -> Vec<&str> {
+        self.keys.iter().map(|key| key.canonical.as_str()).collect()
+    }
+}
+
+/// Validate a list of extended public keys with any SLIP-132 prefix, normalize
+/// them to xpub/tpub and report invalid, duplicate and conflicting entries,
+/// e.g. when collecting cosigner keys for a multisig wallet
+pub fn normalize_xpub_batch<S: AsRef<str>>(inputs: &[S]) -> XpubBatch {
+    let mut batch = XpubBatch::default();
+
+    for (index, input) in inputs.iter().enumerate() {
+        let (xpub, address_type) = match parse_xpub(input.as_ref().trim()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                batch.issues.push(BatchIssue::Invalid {
+                    index,
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        if let Some(first) = batch.keys.first() {
+            if first.xpub.network != xpub.network {
+                batch.issues.push(BatchIssue::NetworkMismatch {
+                    index,
+                    first: first.index,
+                });
+                continue;
+            }
+        }
+
+        // Keys are the same when they derive the same children
+        let seen = batch.keys.iter().find(|key| {
+            key.xpub.public_key == xpub.public_key && key.xpub.chain_code == xpub.chain_code
+        });
+        if let Some(seen) = seen {
+            batch.issues.push(if seen.address_type == address_type {
+                BatchIssue::Duplicate {
+                    index,
+                    first: seen.index,
+                }
+            } else {
+                BatchIssue::AddressTypeConflict {
+                    index,
+                    first: seen.index,
+                }
+            });
+            continue;
+        }
+
+        batch.keys.push(NormalizedXpub {
+            index,
+            canonical: xpub.to_string(),
+            xpub,
+            address_type,
+        });
+    }
+
+    batch
+}
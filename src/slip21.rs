@@ -0,0 +1,49 @@
+use crate::utils;
+
+/// A SLIP-21 symmetric key derivation node
+#[derive(Clone)]
+pub struct Slip21Node([u8; 64]);
+
+impl std::fmt::Debug for Slip21Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Slip21Node").finish_non_exhaustive()
+    }
+}
+
+/// Compares in constant time
+impl PartialEq for Slip21Node {
+    fn eq(&self, other: &Self) -> bool {
+        utils::ct_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Slip21Node {}
+
+impl Slip21Node {
+    /// Create the master node from a seed
+    pub fn from_seed(seed: &[u8]) -> Self {
+        Slip21Node(utils::hmac_sha512(b"Symmetric key seed", seed))
+    }
+
+    /// Derive the child node for a label
+    pub fn derive(&self, label: &[u8]) -> Self {
+        let mut data = Vec::with_capacity(label.len() + 1);
+        data.push(0);
+        data.extend_from_slice(label);
+        Slip21Node(utils::hmac_sha512(&self.0[..32], &data))
+    }
+
+    /// Derive a node through a sequence of labels
+    pub fn derive_path(&self, labels: &[&[u8]]) -> Self {
+        labels
+            .iter()
+            .fold(self.clone(), |node, label| node.derive(label))
+    }
+
+    /// Get the symmetric key of this node
+    pub fn key(&self) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&self.0[32..]);
+        key
+    }
+}
@@ -0,0 +1,304 @@
+use crate::ecies;
+use crate::error::Error;
+use crate::utils;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{AeadCore, ChaCha20Poly1305, KeyInit};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use secp256k1::{PublicKey, SecretKey};
+
+/// Version byte of encoded social backups
+const BACKUP_VERSION: u8 = 1;
+/// ChaCha20-Poly1305 nonce length
+const NONCE_LEN: usize = 12;
+/// Length of each recipient's encrypted key share
+const SEALED_SHARE_LEN: usize = ecies::OVERHEAD + 32;
+/// Most recipient groups a backup may hold
+const MAX_GROUPS: usize = 1024;
+
+/// A recipient's decrypted XOR share of a group's data key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptedShare {
+    /// Index of the recipient group the share belongs to
+    pub group: usize,
+    /// Index of the recipient in the backup's recipient list
+    pub recipient: u8,
+    pub share: [u8; 32],
+}
+
+/// A threshold group of recipients and their encrypted shares
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Group {
+    members: Vec<u8>,
+    sealed_shares: Vec<Vec<u8>>,
+}
+
+/// A seed encrypted so that any `threshold` of `n` friends can recover it,
+/// without Shamir secret sharing: the data key is XOR-split once per group
+/// of `threshold` recipients, and each share is ECIES-encrypted to its
+/// recipient's secp256k1 key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocialBackup {
+    pub threshold: u8,
+    pub recipients: Vec<PublicKey>,
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+    groups: Vec<Group>,
+}
+
+impl SocialBackup {
+    /// Encrypt a secret, such as a seed or mnemonic entropy, to a set of
+    /// recipients so that any `threshold` of them can recover it
+    pub fn create(secret: &[u8], recipients: &[PublicKey], threshold: u8) -> Result<Self, Error> {
+        let n = recipients.len();
+        if threshold == 0 || threshold as usize > n || n > u8::MAX as usize {
+            return Err(Error::InvalidConfig(format!(
+                "Invalid {}-of-{} social backup",
+                threshold, n
+            )));
+        }
+        let members = combinations(n, threshold as usize);
+        if members.len() > MAX_GROUPS {
+            return Err(Error::InvalidConfig(format!(
+                "A {}-of-{} backup needs {} recipient groups, more than {}",
+                threshold,
+                n,
+                members.len(),
+                MAX_GROUPS
+            )));
+        }
+
+        let mut data_key = [0u8; 32];
+        OsRng.fill_bytes(&mut data_key);
+
+        let mut groups = Vec::with_capacity(members.len());
+        for members in members {
+            let mut last = data_key;
+            let mut shares = Vec::with_capacity(members.len());
+            for _ in 1..members.len() {
+                let mut share = [0u8; 32];
+                OsRng.fill_bytes(&mut share);
+                xor_into(&mut last, &share);
+                shares.push(share);
+            }
+            shares.push(last);
+
+            let sealed_shares = members
+                .iter()
+                .zip(&shares)
+                .map(|(&member, share)| ecies::encrypt(&recipients[member as usize], share))
+                .collect::<Result<Vec<_>, Error>>()?;
+            groups.push(Group {
+                members,
+                sealed_shares,
+            });
+        }
+
+        let mut backup = SocialBackup {
+            threshold,
+            recipients: recipients.to_vec(),
+            nonce: [0u8; NONCE_LEN],
+            ciphertext: Vec::new(),
+            groups,
+        };
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        backup.nonce.copy_from_slice(&nonce);
+        backup.ciphertext = ChaCha20Poly1305::new(&data_key.into())
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: secret,
+                    aad: &backup.header(),
+                },
+            )
+            .map_err(|_| Error::Encryption("Social backup encryption failed".to_string()))?;
+
+        Ok(backup)
+    }
+
+    /// Decrypt every share held by a recipient, to be handed to whoever
+    /// collects shares for recovery. A share on its own reveals nothing.
+    pub fn decrypt_shares(&self, secret_key: &SecretKey) -> Result<Vec<DecryptedShare>, Error> {
+        let public_key = PublicKey::from_secret_key(utils::secp(), secret_key);
+        let recipient = self
+            .recipients
+            .iter()
+            .position(|pk| *pk == public_key)
+            .ok_or_else(|| Error::Encryption("Key is not a recipient of this backup".to_string()))?
+            as u8;
+
+        let mut shares = Vec::new();
+        for (group, members) in self.groups.iter().enumerate() {
+            if let Some(position) = members.members.iter().position(|&m| m == recipient) {
+                let plaintext = ecies::decrypt(secret_key, &members.sealed_shares[position])?;
+                shares.push(DecryptedShare {
+                    group,
+                    recipient,
+                    share: plaintext
+                        .try_into()
+                        .map_err(|_| Error::Encryption("Malformed key share".to_string()))?,
+                });
+            }
+        }
+        Ok(shares)
+    }
+
+    /// Recover the secret from shares decrypted by at least `threshold`
+    /// recipients
+    pub fn recover(&self, shares: &[DecryptedShare]) -> Result<Vec<u8>, Error> {
+        for (index, group) in self.groups.iter().enumerate() {
+            let group_shares: Vec<&DecryptedShare> = group
+                .members
+                .iter()
+                .filter_map(|&member| {
+                    shares
+                        .iter()
+                        .find(|share| share.group == index && share.recipient == member)
+                })
+                .collect();
+            if group_shares.len() != group.members.len() {
+                continue;
+            }
+
+            let mut data_key = [0u8; 32];
+            for share in group_shares {
+                xor_into(&mut data_key, &share.share);
+            }
+            if let Ok(secret) = ChaCha20Poly1305::new(&data_key.into()).decrypt(
+                (&self.nonce).into(),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &self.header(),
+                },
+            ) {
+                return Ok(secret);
+            }
+        }
+
+        Err(Error::Encryption(format!(
+            "Recovery needs valid shares from {} recipients",
+            self.threshold
+        )))
+    }
+
+    /// Authenticated header: version, threshold and recipient keys
+    fn header(&self) -> Vec<u8> {
+        let mut header = vec![BACKUP_VERSION, self.threshold, self.recipients.len() as u8];
+        for recipient in &self.recipients {
+            header.extend_from_slice(&recipient.serialize());
+        }
+        header
+    }
+
+    /// Encode for storage or distribution to the recipients
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = self.header();
+        data.extend_from_slice(&self.nonce);
+        data.extend_from_slice(&(self.ciphertext.len() as u32).to_be_bytes());
+        data.extend_from_slice(&self.ciphertext);
+        data.extend_from_slice(&(self.groups.len() as u32).to_be_bytes());
+        for group in &self.groups {
+            for (member, sealed) in group.members.iter().zip(&group.sealed_shares) {
+                data.push(*member);
+                data.extend_from_slice(sealed);
+            }
+        }
+        data
+    }
+
+    /// Decode a backup produced by [`SocialBackup::to_bytes`]
+    pub fn from_bytes(mut data: &[u8]) -> Result<Self, Error> {
+        let mut take = |len: usize| -> Result<&[u8], Error> {
+            if data.len() < len {
+                return Err(Error::Encryption("Truncated social backup".to_string()));
+            }
+            let (head, tail) = data.split_at(len);
+            data = tail;
+            Ok(head)
+        };
+
+        let header = take(3)?;
+        let (version, threshold, n) = (header[0], header[1], header[2] as usize);
+        if version != BACKUP_VERSION {
+            return Err(Error::Encryption(
+                "Unsupported social backup version".to_string(),
+            ));
+        }
+        if threshold == 0 || threshold as usize > n {
+            return Err(Error::Encryption("Invalid backup threshold".to_string()));
+        }
+        let recipients = (0..n)
+            .map(|_| {
+                PublicKey::from_slice(take(33)?)
+                    .map_err(|_| Error::Encryption("Invalid recipient key".to_string()))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(take(NONCE_LEN)?);
+        let len = u32::from_be_bytes(take(4)?.try_into().expect("4 bytes")) as usize;
+        let ciphertext = take(len)?.to_vec();
+
+        let count = u32::from_be_bytes(take(4)?.try_into().expect("4 bytes")) as usize;
+        if count > MAX_GROUPS {
+            return Err(Error::Encryption("Too many recipient groups".to_string()));
+        }
+        let mut groups = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut group = Group {
+                members: Vec::new(),
+                sealed_shares: Vec::new(),
+            };
+            for _ in 0..threshold {
+                let member = take(1)?[0];
+                if member as usize >= n {
+                    return Err(Error::Encryption("Invalid group member".to_string()));
+                }
+                group.members.push(member);
+                group.sealed_shares.push(take(SEALED_SHARE_LEN)?.to_vec());
+            }
+            groups.push(group);
+        }
+
+        if !data.is_empty() {
+            return Err(Error::Encryption(
+                "Trailing data in social backup".to_string(),
+            ));
+        }
+
+        Ok(SocialBackup {
+            threshold,
+            recipients,
+            nonce,
+            ciphertext,
+            groups,
+        })
+    }
+}
+
+/// All `k`-element subsets of `0..n`, in lexicographic order
+fn combinations(n: usize, k: usize) -> Vec<Vec<u8>> {
+    let mut result = Vec::new();
+    let mut current: Vec<usize> = (0..k).collect();
+    loop {
+        result.push(current.iter().map(|&i| i as u8).collect());
+        if result.len() > MAX_GROUPS {
+            return result;
+        }
+
+        // Advance the rightmost index that still has room
+        let Some(i) = (0..k).rev().find(|&i| current[i] < n - k + i) else {
+            return result;
+        };
+        current[i] += 1;
+        for j in i + 1..k {
+            current[j] = current[j - 1] + 1;
+        }
+    }
+}
+
+fn xor_into(target: &mut [u8; 32], other: &[u8; 32]) {
+    for (t, o) in target.iter_mut().zip(other) {
+        *t ^= o;
+    }
+}
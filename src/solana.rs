@@ -0,0 +1,42 @@
+use crate::bip32::{ChildNumber, DerivationPath};
+use crate::bip44::CoinType;
+use crate::error::Error;
+use crate::slip10::{self, Ed25519ExtendedKey};
+
+/// Get the path used by common Solana wallets: `m/44'/501'/account'/0'`
+pub fn path(account: u32) -> DerivationPath {
+    DerivationPath {
+        path: vec![
+            ChildNumber::Hardened(44),
+            CoinType::SOLANA.child_number(),
+            ChildNumber::Hardened(account),
+            ChildNumber::Hardened(0),
+        ],
+    }
+}
+
+/// Encode a public key as a Solana address (plain base58)
+pub fn address(public_key: &[u8; 32]) -> String {
+    bs58::encode(public_key).into_string()
+}
+
+/// Decode a Solana address into its public key
+pub fn decode_address(address: &str) -> Result<[u8; 32], Error> {
+    bs58::decode(address)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::InvalidAddress("Invalid Solana address".to_string()))
+}
+
+/// Verify a signature over a message (e.g. serialized transaction message bytes)
+pub fn verify(address: &str, message: &[u8], signature: &[u8; 64]) -> Result<(), Error> {
+    slip10::verify(&decode_address(address)?, message, signature)
+}
+
+impl Ed25519ExtendedKey {
+    /// Get the Solana address of this key
+    pub fn solana_address(&self) -> String {
+        address(self.verifying_key().as_bytes())
+    }
+}
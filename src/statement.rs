@@ -0,0 +1,121 @@
+use crate::bip32::ChildNumber;
+use crate::bip44::Change;
+use crate::error::Error;
+use crate::limits;
+use crate::wallet::Wallet;
+
+/// Column names of CSV statements
+const CSV_HEADER: &str = "account,change,index,path,address,script_type";
+
+/// Which addresses a statement lists for each account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatementRange {
+    /// First address index
+    pub start: u32,
+    /// Number of addresses per chain
+    pub count: u32,
+    /// Whether to list the change chain as well as the receive chain
+    pub include_change: bool,
+}
+
+impl Default for StatementRange {
+    fn default() -> Self {
+        StatementRange {
+            start: 0,
+            count: 20,
+            include_change: true,
+        }
+    }
+}
+
+/// One derived address of a statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatementRow {
+    pub account: u32,
+    /// Chain of the address: 0 for receive, 1 for change
+    pub change: u32,
+    pub index: u32,
+    pub path: String,
+    pub address: String,
+    pub script_type: String,
+}
+
+/// The paths and addresses of a wallet's accounts, as requested by
+/// accountants and auditors
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Statement {
+    pub rows: Vec<StatementRow>,
+}
+
+impl Statement {
+    /// Render as CSV with a header row
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+        for row in &self.rows {
+            let fields = [
+                row.account.to_string(),
+                row.change.to_string(),
+                row.index.to_string(),
+                csv_field(&row.path),
+                csv_field(&row.address),
+                csv_field(&row.script_type),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Serialize to JSON
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::InvalidExport(e.to_string()))
+    }
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl Wallet {
+    /// List a range of addresses of every provisioned account
+    pub fn statement(&self, range: StatementRange) -> Result<Statement, Error> {
+        let chains: &[Change] = if range.include_change {
+            &[Change::External, Change::Internal]
+        } else {
+            &[Change::External]
+        };
+        let chain_count = (self.accounts().len() * chains.len()) as u32;
+        limits::check_batch_size(range.count.saturating_mul(chain_count))?;
+
+        let mut rows = Vec::new();
+        for account in self.accounts() {
+            let account_path = self.account_path(account.index);
+            for &change in chains {
+                let addresses = self.addresses(account.index, change, range.start, range.count)?;
+                for (index, address) in (range.start..).zip(addresses) {
+                    let mut path = account_path.clone();
+                    path.path
+                        .extend([change.child_number(), ChildNumber::Normal(index)]);
+                    rows.push(StatementRow {
+                        account: account.index,
+                        change: change.child_number().to_u32(),
+                        index,
+                        path: self.format_path(&path),
+                        address,
+                        script_type: self.address_type().name().to_string(),
+                    });
+                }
+            }
+        }
+        Ok(Statement { rows })
+    }
+}
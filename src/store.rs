@@ -0,0 +1,593 @@
+use crate::error::Error;
+use crate::wallet::Wallet;
+use crate::wipe::wipe;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{AeadCore, ChaCha20Poly1305, KeyInit};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Version byte prefixed to encrypted store payloads
+const ENCRYPTED_VERSION: u8 = 1;
+/// Argon2id salt length
+const SALT_LEN: usize = 16;
+/// ChaCha20-Poly1305 nonce length
+const NONCE_LEN: usize = 12;
+/// Header length: version, Argon2 memory/time/parallelism costs, salt and nonce
+const HEADER_LEN: usize = 1 + 12 + SALT_LEN + NONCE_LEN;
+
+/// Persisted state of one wallet account
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountState {
+    pub index: u32,
+    pub active: bool,
+    pub next_external: u32,
+    pub next_internal: u32,
+}
+
+/// Persisted wallet state: accounts, address indices and labels.
+/// Keys are never stored; they are re-derived from the master key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WalletState {
+    pub accounts: Vec<AccountState>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl WalletState {
+    /// Whether the wallet has been put to use: an address was issued, an
+    /// account added or a label set
+    pub fn is_activated(&self) -> bool {
+        self.accounts.len() > 1
+            || !self.labels.is_empty()
+            || self
+                .accounts
+                .iter()
+                .any(|account| account.next_external > 0 || account.next_internal > 0)
+    }
+
+    /// Compact binary encoding, used where serde is not available
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(self.accounts.len() as u32).to_be_bytes());
+        for account in &self.accounts {
+            data.extend_from_slice(&account.index.to_be_bytes());
+            data.push(account.active as u8);
+            data.extend_from_slice(&account.next_external.to_be_bytes());
+            data.extend_from_slice(&account.next_internal.to_be_bytes());
+        }
+
+        data.extend_from_slice(&(self.labels.len() as u32).to_be_bytes());
+        for (item, label) in &self.labels {
+            for s in [item, label] {
+                data.extend_from_slice(&(s.len() as u32).to_be_bytes());
+                data.extend_from_slice(s.as_bytes());
+            }
+        }
+
+        data
+    }
+
+    /// Decode a state produced by [`WalletState::to_bytes`]
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader(data);
+
+        let count = reader.u32()?;
+        let mut accounts = Vec::new();
+        for _ in 0..count {
+            accounts.push(AccountState {
+                index: reader.u32()?,
+                active: match reader.take(1)?[0] {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(Error::Storage("Invalid account flag".to_string())),
+                },
+                next_external: reader.u32()?,
+                next_internal: reader.u32()?,
+            });
+        }
+
+        let count = reader.u32()?;
+        let mut labels = BTreeMap::new();
+        for _ in 0..count {
+            labels.insert(reader.string()?, reader.string()?);
+        }
+
+        if !reader.0.is_empty() {
+            return Err(Error::Storage("Trailing data in wallet state".to_string()));
+        }
+
+        Ok(WalletState { accounts, labels })
+    }
+}
+
+/// Bounds-checked cursor over an encoded state
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::Storage("Truncated wallet state".to_string()));
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| Error::Storage("Invalid UTF-8 in wallet state".to_string()))
+    }
+}
+
+/// Storage backend for wallet state
+pub trait WalletStore {
+    /// Load the saved state, if any
+    fn load(&self) -> Result<Option<WalletState>, Error>;
+
+    /// Replace the saved state
+    fn save(&self, state: &WalletState) -> Result<(), Error>;
+
+    /// Load an opaque payload, such as encrypted state, if any. Stores
+    /// holding plaintext state return an error.
+    fn load_payload(&self) -> Result<Option<Vec<u8>>, Error> {
+        Err(Error::Unsupported(
+            "Store does not support opaque payloads".to_string(),
+        ))
+    }
+
+    /// Replace the saved state with an opaque payload
+    fn save_payload(&self, _payload: &[u8]) -> Result<(), Error> {
+        Err(Error::Unsupported(
+            "Store does not support opaque payloads".to_string(),
+        ))
+    }
+}
+
+fn plaintext_error() -> Error {
+    Error::Storage("Store holds plaintext state".to_string())
+}
+
+/// What a store currently holds
+#[derive(Debug, Clone, Default)]
+enum Stored {
+    #[default]
+    Empty,
+    State(WalletState),
+    Payload(Vec<u8>),
+}
+
+/// Store keeping state in memory, for tests and ephemeral wallets
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    stored: Mutex<Stored>,
+}
+
+impl MemoryStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+}
+
+impl WalletStore for MemoryStore {
+    fn load(&self) -> Result<Option<WalletState>, Error> {
+        match &*self.stored.lock().unwrap_or_else(|e| e.into_inner()) {
+            Stored::Empty => Ok(None),
+            Stored::State(state) => Ok(Some(state.clone())),
+            Stored::Payload(_) => Err(Error::Storage("Store holds an opaque payload".to_string())),
+        }
+    }
+
+    fn save(&self, state: &WalletState) -> Result<(), Error> {
+        *self.stored.lock().unwrap_or_else(|e| e.into_inner()) = Stored::State(state.clone());
+        Ok(())
+    }
+
+    fn load_payload(&self) -> Result<Option<Vec<u8>>, Error> {
+        match &*self.stored.lock().unwrap_or_else(|e| e.into_inner()) {
+            Stored::Empty => Ok(None),
+            Stored::State(_) => Err(plaintext_error()),
+            Stored::Payload(payload) => Ok(Some(payload.clone())),
+        }
+    }
+
+    fn save_payload(&self, payload: &[u8]) -> Result<(), Error> {
+        *self.stored.lock().unwrap_or_else(|e| e.into_inner()) = Stored::Payload(payload.to_vec());
+        Ok(())
+    }
+}
+
+/// Store keeping state in a JSON file, replaced atomically on save
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone)]
+pub struct JsonFileStore {
+    path: std::path::PathBuf,
+}
+
+#[cfg(feature = "serde")]
+impl JsonFileStore {
+    /// Create a store backed by the file at `path`
+    pub fn new<P: Into<std::path::PathBuf>>(path: P) -> Self {
+        JsonFileStore { path: path.into() }
+    }
+
+    fn read(&self) -> Result<Option<Vec<u8>>, Error> {
+        match std::fs::read(&self.path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Error::Storage(e.to_string())),
+        }
+    }
+
+    fn write(&self, data: &[u8]) -> Result<(), Error> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, data).map_err(|e| Error::Storage(e.to_string()))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| Error::Storage(e.to_string()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl WalletStore for JsonFileStore {
+    fn load(&self) -> Result<Option<WalletState>, Error> {
+        self.read()?
+            .map(|json| serde_json::from_slice(&json).map_err(|e| Error::Storage(e.to_string())))
+            .transpose()
+    }
+
+    fn save(&self, state: &WalletState) -> Result<(), Error> {
+        let json =
+            serde_json::to_string_pretty(state).map_err(|e| Error::Storage(e.to_string()))?;
+        self.write(json.as_bytes())
+    }
+
+    /// Payloads are written to the file as-is, not as JSON
+    fn load_payload(&self) -> Result<Option<Vec<u8>>, Error> {
+        let data = self.read()?;
+        if let Some(data) = &data {
+            if serde_json::from_slice::<WalletState>(data).is_ok() {
+                return Err(plaintext_error());
+            }
+        }
+        Ok(data)
+    }
+
+    fn save_payload(&self, payload: &[u8]) -> Result<(), Error> {
+        self.write(payload)
+    }
+}
+
+/// Schema migrations, applied in order and tracked with `PRAGMA user_version`
+#[cfg(feature = "store-sqlite")]
+const SQLITE_MIGRATIONS: &[&str] = &[
+    // 1: accounts and labels
+    "CREATE TABLE accounts (
+        idx INTEGER PRIMARY KEY,
+        active INTEGER NOT NULL,
+        next_external INTEGER NOT NULL,
+        next_internal INTEGER NOT NULL
+    );
+    CREATE TABLE labels (
+        item TEXT PRIMARY KEY,
+        label TEXT NOT NULL
+    );",
+    // 2: opaque payloads, such as encrypted state
+    "CREATE TABLE payload (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        data BLOB NOT NULL
+    );",
+];
+
+/// Store keeping state in a sqlite database
+#[cfg(feature = "store-sqlite")]
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "store-sqlite")]
+impl SqliteStore {
+    /// Open or create the database at `path`, migrating it to the current schema
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        SqliteStore::with_connection(rusqlite::Connection::open(path).map_err(sqlite_error)?)
+    }
+
+    /// Open a database that lives only as long as the store
+    pub fn open_in_memory() -> Result<Self, Error> {
+        SqliteStore::with_connection(rusqlite::Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    /// Wrap an existing connection, migrating it to the current schema
+    pub fn with_connection(mut conn: rusqlite::Connection) -> Result<Self, Error> {
+        let version: usize = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(sqlite_error)?;
+        if version > SQLITE_MIGRATIONS.len() {
+            return Err(Error::Storage(format!(
+                "Database schema version {} is newer than supported version {}",
+                version,
+                SQLITE_MIGRATIONS.len()
+            )));
+        }
+
+        let tx = conn.transaction().map_err(sqlite_error)?;
+        for (i, migration) in SQLITE_MIGRATIONS.iter().enumerate().skip(version) {
+            tx.execute_batch(migration).map_err(sqlite_error)?;
+            tx.pragma_update(None, "user_version", i + 1)
+                .map_err(sqlite_error)?;
+        }
+        tx.commit().map_err(sqlite_error)?;
+
+        Ok(SqliteStore {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Current schema version of the database
+    pub fn schema_version(&self) -> Result<u32, Error> {
+        self.conn
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(sqlite_error)
+    }
+}
+
+#[cfg(feature = "store-sqlite")]
+fn sqlite_error(e: rusqlite::Error) -> Error {
+    Error::Storage(e.to_string())
+}
+
+#[cfg(feature = "store-sqlite")]
+impl WalletStore for SqliteStore {
+    fn load(&self) -> Result<Option<WalletState>, Error> {
+        let conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut stmt = conn
+            .prepare("SELECT idx, active, next_external, next_internal FROM accounts ORDER BY idx")
+            .map_err(sqlite_error)?;
+        let accounts = stmt
+            .query_map([], |row| {
+                Ok(AccountState {
+                    index: row.get(0)?,
+                    active: row.get(1)?,
+                    next_external: row.get(2)?,
+                    next_internal: row.get(3)?,
+                })
+            })
+            .map_err(sqlite_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(sqlite_error)?;
+        if accounts.is_empty() {
+            let payloads: u32 = conn
+                .query_row("SELECT COUNT(*) FROM payload", [], |row| row.get(0))
+                .map_err(sqlite_error)?;
+            if payloads > 0 {
+                return Err(Error::Storage("Store holds an opaque payload".to_string()));
+            }
+            return Ok(None);
+        }
+
+        let mut stmt = conn
+            .prepare("SELECT item, label FROM labels")
+            .map_err(sqlite_error)?;
+        let labels = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(sqlite_error)?
+            .collect::<Result<BTreeMap<_, _>, _>>()
+            .map_err(sqlite_error)?;
+
+        Ok(Some(WalletState { accounts, labels }))
+    }
+
+    fn save(&self, state: &WalletState) -> Result<(), Error> {
+        let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let tx = conn.transaction().map_err(sqlite_error)?;
+
+        tx.execute_batch("DELETE FROM accounts; DELETE FROM labels; DELETE FROM payload;")
+            .map_err(sqlite_error)?;
+        for account in &state.accounts {
+            tx.execute(
+                "INSERT INTO accounts (idx, active, next_external, next_internal) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![
+                    account.index,
+                    account.active,
+                    account.next_external,
+                    account.next_internal
+                ],
+            )
+            .map_err(sqlite_error)?;
+        }
+        for (item, label) in &state.labels {
+            tx.execute(
+                "INSERT INTO labels (item, label) VALUES (?1, ?2)",
+                rusqlite::params![item, label],
+            )
+            .map_err(sqlite_error)?;
+        }
+
+        tx.commit().map_err(sqlite_error)
+    }
+
+    fn load_payload(&self) -> Result<Option<Vec<u8>>, Error> {
+        use rusqlite::OptionalExtension;
+
+        let conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let payload = conn
+            .query_row("SELECT data FROM payload WHERE id = 0", [], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(sqlite_error)?;
+        if payload.is_none() {
+            let accounts: u32 = conn
+                .query_row("SELECT COUNT(*) FROM accounts", [], |row| row.get(0))
+                .map_err(sqlite_error)?;
+            if accounts > 0 {
+                return Err(plaintext_error());
+            }
+        }
+        Ok(payload)
+    }
+
+    fn save_payload(&self, payload: &[u8]) -> Result<(), Error> {
+        let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let tx = conn.transaction().map_err(sqlite_error)?;
+
+        tx.execute_batch("DELETE FROM accounts; DELETE FROM labels;")
+            .map_err(sqlite_error)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO payload (id, data) VALUES (0, ?1)",
+            [payload],
+        )
+        .map_err(sqlite_error)?;
+
+        tx.commit().map_err(sqlite_error)
+    }
+}
+
+/// Store decorator encrypting the state with ChaCha20-Poly1305 under a key
+/// stretched from a passphrase with Argon2id
+pub struct EncryptedStore<S: WalletStore> {
+    inner: S,
+    passphrase: String,
+    params: Params,
+}
+
+impl<S: WalletStore> EncryptedStore<S> {
+    /// Wrap a store, using the default Argon2id costs for new payloads
+    pub fn new(inner: S, passphrase: &str) -> Self {
+        EncryptedStore {
+            inner,
+            passphrase: passphrase.to_string(),
+            params: Params::default(),
+        }
+    }
+
+    /// Set the Argon2id memory (KiB), time and parallelism costs for new payloads.
+    /// Existing payloads are decrypted with the costs stored alongside them.
+    pub fn with_costs(
+        mut self,
+        memory_kib: u32,
+        iterations: u32,
+        lanes: u32,
+    ) -> Result<Self, Error> {
+        self.params = Params::new(memory_kib, iterations, lanes, None)
+            .map_err(|e| Error::Encryption(e.to_string()))?;
+        Ok(self)
+    }
+
+    /// Get the wrapped store
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn cipher(&self, params: Params, salt: &[u8]) -> Result<ChaCha20Poly1305, Error> {
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(self.passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| Error::Encryption(e.to_string()))?;
+        let cipher = ChaCha20Poly1305::new(&key.into());
+        wipe(&mut key);
+        Ok(cipher)
+    }
+}
+
+impl<S: WalletStore> Drop for EncryptedStore<S> {
+    fn drop(&mut self) {
+        wipe(&mut self.passphrase);
+    }
+}
+
+impl<S: WalletStore> WalletStore for EncryptedStore<S> {
+    fn load(&self) -> Result<Option<WalletState>, Error> {
+        let payload = match self.inner.load_payload()? {
+            Some(payload) => payload,
+            None => return Ok(None),
+        };
+        if payload.len() < HEADER_LEN || payload[0] != ENCRYPTED_VERSION {
+            return Err(Error::Encryption("Unsupported store payload".to_string()));
+        }
+
+        let (header, ciphertext) = payload.split_at(HEADER_LEN);
+        let cost =
+            |i: usize| u32::from_be_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+        let params = Params::new(cost(1), cost(5), cost(9), None)
+            .map_err(|e| Error::Encryption(e.to_string()))?;
+        let salt = &header[13..13 + SALT_LEN];
+        let nonce = &header[13 + SALT_LEN..];
+
+        let plaintext = self
+            .cipher(params, salt)?
+            .decrypt(
+                nonce.into(),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| {
+                Error::Encryption("Wrong passphrase or corrupted store payload".to_string())
+            })?;
+
+        WalletState::from_bytes(&plaintext).map(Some)
+    }
+
+    fn save(&self, state: &WalletState) -> Result<(), Error> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let mut payload = Vec::with_capacity(HEADER_LEN);
+        payload.push(ENCRYPTED_VERSION);
+        payload.extend_from_slice(&self.params.m_cost().to_be_bytes());
+        payload.extend_from_slice(&self.params.t_cost().to_be_bytes());
+        payload.extend_from_slice(&self.params.p_cost().to_be_bytes());
+        payload.extend_from_slice(&salt);
+        payload.extend_from_slice(&nonce);
+
+        let ciphertext = self
+            .cipher(self.params.clone(), &salt)?
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &state.to_bytes(),
+                    aad: &payload,
+                },
+            )
+            .map_err(|_| Error::Encryption("Store encryption failed".to_string()))?;
+        payload.extend_from_slice(&ciphertext);
+
+        self.inner.save_payload(&payload)
+    }
+}
+
+impl Wallet {
+    /// Save the wallet's accounts, indices and labels
+    pub fn save(&self, store: &dyn WalletStore) -> Result<(), Error> {
+        store.save(&self.state())
+    }
+
+    /// Restore previously saved state into the wallet, returning whether
+    /// the store had any
+    pub fn load(&mut self, store: &dyn WalletStore) -> Result<bool, Error> {
+        match store.load()? {
+            Some(state) => {
+                self.restore_state(&state)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::bip39::{Mnemonic, Seed};
+use crate::utils::HmacSha512;
+use hmac::Mac;
+
+/// PBKDF2 rounds used by BIP-39
+pub const BIP39_ROUNDS: u32 = 2048;
+
+/// Rounds run per chunk by the progress-reporting helpers
+pub const DEFAULT_CHUNK: u32 = 128;
+
+/// Resumable PBKDF2-HMAC-SHA512 producing a single 64-byte block, so slow
+/// targets can spread seed stretching over several steps
+#[derive(Clone)]
+pub struct SeedStretcher {
+    mac: HmacSha512,
+    block: [u8; 64],
+    result: [u8; 64],
+    completed: u32,
+    rounds: u32,
+}
+
+impl SeedStretcher {
+    /// Start stretching a password with a salt for the given number of rounds
+    pub fn new(password: &[u8], salt: &[u8], rounds: u32) -> Self {
+        let mac = HmacSha512::new_from_slice(password).expect("HMAC can take key of any size");
+
+        // U_1 = HMAC(P, S || INT(1))
+        let mut first = mac.clone();
+        first.update(salt);
+        first.update(&1u32.to_be_bytes());
+        let block: [u8; 64] = first.finalize().into_bytes().into();
+
+        SeedStretcher {
+            mac,
+            block,
+            result: block,
+            completed: 1,
+            rounds: rounds.max(1),
+        }
+    }
+
+    /// Start stretching a mnemonic and passphrase into its BIP-39 seed
+    pub fn for_mnemonic(mnemonic: &Mnemonic, passphrase: &str) -> Self {
+        let (password, salt) = mnemonic.seed_inputs(passphrase);
+        SeedStretcher::new(password.as_bytes(), salt.as_bytes(), BIP39_ROUNDS)
+    }
+
+    /// Run up to `max_rounds` more rounds, returning whether stretching is finished
+    pub fn step(&mut self, max_rounds: u32) -> bool {
+        let end = self.completed.saturating_add(max_rounds).min(self.rounds);
+
+        while self.completed < end {
+            let mut mac = self.mac.clone();
+            mac.update(&self.block);
+            self.block = mac.finalize().into_bytes().into();
+            for (r, b) in self.result.iter_mut().zip(&self.block) {
+                *r ^= b;
+            }
+            self.completed += 1;
+        }
+
+        self.is_finished()
+    }
+
+    /// Whether all rounds have run
+    pub fn is_finished(&self) -> bool {
+        self.completed == self.rounds
+    }
+
+    /// Get the number of completed rounds and the total
+    pub fn progress(&self) -> (u32, u32) {
+        (self.completed, self.rounds)
+    }
+
+    /// Get the seed once all rounds have run
+    pub fn seed(&self) -> Option<Seed> {
+        self.is_finished().then_some(Seed(self.result))
+    }
+
+    /// Run to completion in chunks, reporting `(completed, total)` after each
+    pub fn run_with_progress<F: FnMut(u32, u32)>(mut self, chunk: u32, mut progress: F) -> Seed {
+        while !self.step(chunk.max(1)) {
+            progress(self.completed, self.rounds);
+        }
+        progress(self.completed, self.rounds);
+        Seed(self.result)
+    }
+
+    /// Run to completion in chunks, yielding to the executor between chunks
+    #[cfg(feature = "async")]
+    pub async fn run_async<F: FnMut(u32, u32)>(mut self, chunk: u32, mut progress: F) -> Seed {
+        while !self.step(chunk.max(1)) {
+            progress(self.completed, self.rounds);
+            YieldNow(false).await;
+        }
+        progress(self.completed, self.rounds);
+        Seed(self.result)
+    }
+}
+
+/// Future that returns `Pending` once so other tasks can run
+#[cfg(feature = "async")]
+struct YieldNow(bool);
+
+#[cfg(feature = "async")]
+impl std::future::Future for YieldNow {
+    type Output = ();
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<()> {
+        if self.0 {
+            return std::task::Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        std::task::Poll::Pending
+    }
+}
+
+impl Mnemonic {
+    /// Generate the seed in chunks, reporting `(completed, total)` rounds
+    pub fn to_seed_with_progress<F: FnMut(u32, u32)>(&self, passphrase: &str, progress: F) -> Seed {
+        SeedStretcher::for_mnemonic(self, passphrase).run_with_progress(DEFAULT_CHUNK, progress)
+    }
+
+    /// Generate the seed without blocking the executor for the whole stretch
+    #[cfg(feature = "async")]
+    pub async fn to_seed_async<F: FnMut(u32, u32)>(&self, passphrase: &str, progress: F) -> Seed {
+        SeedStretcher::for_mnemonic(self, passphrase)
+            .run_async(DEFAULT_CHUNK, progress)
+            .await
+    }
+}
@@ -0,0 +1,334 @@
+use crate::bip39::Mnemonic;
+use crate::error::Error;
+use crate::metrics::{self, SignatureKind};
+use crate::wipe::wipe;
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Blake2b512, Digest};
+use ed25519_dalek::{Signer, SigningKey};
+use hmac::Hmac;
+use pbkdf2::pbkdf2;
+use sha2::Sha512;
+
+/// SS58 prefix of Polkadot addresses
+pub const POLKADOT: u16 = 0;
+/// SS58 prefix of Kusama addresses
+pub const KUSAMA: u16 = 2;
+/// SS58 prefix of generic Substrate addresses
+pub const GENERIC_SUBSTRATE: u16 = 42;
+
+/// Domain prefix of SS58 checksums
+const SS58_PREFIX: &[u8] = b"SS58PRE";
+/// Domain of ed25519 hard derivation
+const ED25519_HDKD: &str = "Ed25519HDKD";
+
+fn blake2_256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// Append a SCALE compact-encoded length
+fn push_compact_len(data: &mut Vec<u8>, len: usize) {
+    match len {
+        0..=0x3f => data.push((len as u8) << 2),
+        0x40..=0x3fff => data.extend_from_slice(&(((len as u16) << 2) | 1).to_le_bytes()),
+        _ => data.extend_from_slice(&(((len as u32) << 2) | 2).to_le_bytes()),
+    }
+}
+
+/// Append a SCALE-encoded string
+fn push_scale_str(data: &mut Vec<u8>, s: &str) {
+    push_compact_len(data, s.len());
+    data.extend_from_slice(s.as_bytes());
+}
+
+/// Derive the 32-byte mini secret of a mnemonic the way Substrate does:
+/// PBKDF2 over the mnemonic's entropy rather than its phrase
+pub fn mini_secret(mnemonic: &Mnemonic, password: &str) -> Result<[u8; 32], Error> {
+    let mut entropy = mnemonic.to_entropy();
+
+    let mut seed = [0u8; 64];
+    let derived = pbkdf2::<Hmac<Sha512>>(
+        &entropy,
+        format!("mnemonic{}", password).as_bytes(),
+        2048,
+        &mut seed,
+    );
+    wipe(&mut entropy);
+    derived.map_err(|_| Error::InvalidSeed("PBKDF2 failed".to_string()))?;
+
+    let mut mini_secret = [0u8; 32];
+    mini_secret.copy_from_slice(&seed[..32]);
+    wipe(&mut seed);
+    Ok(mini_secret)
+}
+
+/// A step of a Substrate derivation path (`//hard` or `/soft`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Junction {
+    Hard([u8; 32]),
+    Soft([u8; 32]),
+}
+
+impl Junction {
+    /// Chain code of a junction name: numbers as little-endian u64, other text
+    /// SCALE-encoded, hashed when longer than 32 bytes
+    fn chain_code(name: &str) -> [u8; 32] {
+        let mut encoded = Vec::new();
+        match name.parse::<u64>() {
+            Ok(n) => encoded.extend_from_slice(&n.to_le_bytes()),
+            Err(_) => push_scale_str(&mut encoded, name),
+        }
+
+        if encoded.len() > 32 {
+            return blake2_256(&encoded);
+        }
+        let mut chain_code = [0u8; 32];
+        chain_code[..encoded.len()].copy_from_slice(&encoded);
+        chain_code
+    }
+
+    /// Parse a derivation path like `//polkadot//0/1`
+    pub fn parse_path(path: &str) -> Result<Vec<Junction>, Error> {
+        let mut junctions = Vec::new();
+        let mut rest = path;
+
+        while !rest.is_empty() {
+            let (hard, tail) = match rest.strip_prefix("//") {
+                Some(tail) => (true, tail),
+                None => match rest.strip_prefix('/') {
+                    Some(tail) => (false, tail),
+                    None => {
+                        return Err(Error::InvalidDerivationPath(format!(
+                            "Junction must start with '/': {}",
+                            rest
+                        )))
+                    }
+                },
+            };
+
+            let end = tail.find('/').unwrap_or(tail.len());
+            let name = &tail[..end];
+            if name.is_empty() {
+                return Err(Error::InvalidDerivationPath("Empty junction".to_string()));
+            }
+
+            let chain_code = Junction::chain_code(name);
+            junctions.push(if hard {
+                Junction::Hard(chain_code)
+            } else {
+                Junction::Soft(chain_code)
+            });
+            rest = &tail[end..];
+        }
+
+        Ok(junctions)
+    }
+}
+
+/// Substrate ed25519 key pair
+#[derive(Clone)]
+pub struct Ed25519Pair {
+    seed: [u8; 32],
+}
+
+impl std::fmt::Debug for Ed25519Pair {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Ed25519Pair")
+            .field("public", &hex::encode(self.public()))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Ed25519Pair {
+    /// Create the root key pair of a mnemonic
+    pub fn from_mnemonic(mnemonic: &Mnemonic, password: &str) -> Result<Self, Error> {
+        Ok(Ed25519Pair {
+            seed: mini_secret(mnemonic, password)?,
+        })
+    }
+
+    /// Create a key pair from a 32-byte seed
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Ed25519Pair { seed }
+    }
+
+    /// Derive along junctions; ed25519 only supports hard junctions
+    pub fn derive(&self, junctions: &[Junction]) -> Result<Self, Error> {
+        junctions
+            .iter()
+            .try_fold(self.clone(), |pair, junction| match junction {
+                Junction::Hard(chain_code) => {
+                    let mut data = Vec::with_capacity(76);
+                    push_scale_str(&mut data, ED25519_HDKD);
+                    data.extend_from_slice(&pair.seed);
+                    data.extend_from_slice(chain_code);
+                    Ok(Ed25519Pair {
+                        seed: blake2_256(&data),
+                    })
+                }
+                Junction::Soft(_) => Err(Error::InvalidDerivationPath(
+                    "Ed25519 keys only support hard junctions".to_string(),
+                )),
+            })
+    }
+
+    /// Get the public key
+    pub fn public(&self) -> [u8; 32] {
+        SigningKey::from_bytes(&self.seed)
+            .verifying_key()
+            .to_bytes()
+    }
+
+    /// Sign a message
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        metrics::signature(SignatureKind::Ed25519);
+        SigningKey::from_bytes(&self.seed).sign(message).to_bytes()
+    }
+}
+
+/// Substrate sr25519 key pair
+#[cfg(feature = "sr25519")]
+#[derive(Clone)]
+pub struct Sr25519Pair {
+    keypair: schnorrkel::Keypair,
+}
+
+#[cfg(feature = "sr25519")]
+impl std::fmt::Debug for Sr25519Pair {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Sr25519Pair")
+            .field("public", &hex::encode(self.public()))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "sr25519")]
+impl Sr25519Pair {
+    /// Signing context of Substrate sr25519 signatures
+    const SIGNING_CONTEXT: &'static [u8] = b"substrate";
+
+    /// Create the root key pair of a mnemonic
+    pub fn from_mnemonic(mnemonic: &Mnemonic, password: &str) -> Result<Self, Error> {
+        Sr25519Pair::from_seed(mini_secret(mnemonic, password)?)
+    }
+
+    /// Create a key pair from a 32-byte mini secret
+    pub fn from_seed(seed: [u8; 32]) -> Result<Self, Error> {
+        let mini = schnorrkel::MiniSecretKey::from_bytes(&seed)
+            .map_err(|e| Error::InvalidKey(e.to_string()))?;
+        Ok(Sr25519Pair {
+            keypair: mini.expand_to_keypair(schnorrkel::ExpansionMode::Ed25519),
+        })
+    }
+
+    /// Derive along hard and soft junctions
+    pub fn derive(&self, junctions: &[Junction]) -> Self {
+        use schnorrkel::derive::{ChainCode, Derivation};
+
+        let keypair =
+            junctions
+                .iter()
+                .fold(self.keypair.clone(), |keypair, junction| match junction {
+                    Junction::Hard(chain_code) => keypair
+                        .secret
+                        .hard_derive_mini_secret_key(Some(ChainCode(*chain_code)), b"")
+                        .0
+                        .expand_to_keypair(schnorrkel::ExpansionMode::Ed25519),
+                    Junction::Soft(chain_code) => {
+                        keypair.derived_key_simple(ChainCode(*chain_code), []).0
+                    }
+                });
+        Sr25519Pair { keypair }
+    }
+
+    /// Get the public key
+    pub fn public(&self) -> [u8; 32] {
+        self.keypair.public.to_bytes()
+    }
+
+    /// Sign a message in the Substrate signing context
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        metrics::signature(SignatureKind::Sr25519);
+        self.keypair
+            .sign_simple(Sr25519Pair::SIGNING_CONTEXT, message)
+            .to_bytes()
+    }
+
+    /// Verify a signature made in the Substrate signing context
+    pub fn verify(
+        public_key: &[u8; 32],
+        message: &[u8],
+        signature: &[u8; 64],
+    ) -> Result<(), Error> {
+        let public_key = schnorrkel::PublicKey::from_bytes(public_key)
+            .map_err(|e| Error::InvalidKey(e.to_string()))?;
+        let signature = schnorrkel::Signature::from_bytes(signature)
+            .map_err(|e| Error::InvalidSignature(e.to_string()))?;
+        public_key
+            .verify_simple(Sr25519Pair::SIGNING_CONTEXT, message, &signature)
+            .map_err(|e| Error::InvalidSignature(e.to_string()))
+    }
+}
+
+fn ss58_checksum(data: &[u8]) -> [u8; 2] {
+    let mut hasher = Blake2b512::new();
+    hasher.update(SS58_PREFIX);
+    hasher.update(data);
+    let hash = hasher.finalize();
+    [hash[0], hash[1]]
+}
+
+/// Encode a public key as an SS58 address with a network prefix
+pub fn ss58_encode(public_key: &[u8; 32], prefix: u16) -> Result<String, Error> {
+    let mut data = Vec::with_capacity(36);
+    match prefix {
+        0..=63 => data.push(prefix as u8),
+        64..=16383 => {
+            data.push((((prefix & 0b1111_1100) >> 2) as u8) | 0b0100_0000);
+            data.push(((prefix >> 8) as u8) | (((prefix & 0b11) as u8) << 6));
+        }
+        _ => {
+            return Err(Error::InvalidAddress(format!(
+                "Invalid SS58 prefix {}",
+                prefix
+            )))
+        }
+    }
+    data.extend_from_slice(public_key);
+    let checksum = ss58_checksum(&data);
+    data.extend_from_slice(&checksum);
+    Ok(bs58::encode(data).into_string())
+}
+
+/// Decode an SS58 address into its network prefix and public key
+pub fn ss58_decode(address: &str) -> Result<(u16, [u8; 32]), Error> {
+    let data = bs58::decode(address)
+        .into_vec()
+        .map_err(|_| Error::InvalidAddress("Invalid base58 string".to_string()))?;
+
+    let (prefix, prefix_len) = match data.first() {
+        Some(&first) if first < 64 => (first as u16, 1),
+        Some(&first) if first < 128 && data.len() > 1 => {
+            let second = data[1];
+            let lower = (first << 2) | (second >> 6);
+            let upper = second & 0b0011_1111;
+            (lower as u16 | ((upper as u16) << 8), 2)
+        }
+        _ => return Err(Error::InvalidAddress("Invalid SS58 prefix".to_string())),
+    };
+
+    if data.len() != prefix_len + 34 {
+        return Err(Error::InvalidAddress(
+            "Invalid SS58 address length".to_string(),
+        ));
+    }
+    let (body, checksum) = data.split_at(prefix_len + 32);
+    if ss58_checksum(body) != checksum {
+        return Err(Error::InvalidChecksum);
+    }
+
+    let mut public_key = [0u8; 32];
+    public_key.copy_from_slice(&body[prefix_len..]);
+    Ok((prefix, public_key))
+}
@@ -0,0 +1,177 @@
+use crate::address::{self, AddressType};
+use crate::bip32::{ChildNumber, DerivationPath};
+use crate::bip44::Change;
+use crate::error::Error;
+use crate::keybag::KeyBag;
+use crate::psbt::{AccountKey, OutPoint, Psbt, PsbtInput, TxOut, SEQUENCE_RBF, TX_VERSION};
+use crate::rbf::DUST_LIMIT;
+use crate::wallet::{AddressLocation, Wallet};
+
+/// A loose key of a [`KeyBag`] paired with the HD address its funds move to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepEntry {
+    /// Public key hash of the loose key
+    pub pubkey_hash: [u8; 20],
+    /// Addresses of the loose key to sweep
+    pub sources: Vec<String>,
+    pub destination: String,
+    pub location: AddressLocation,
+}
+
+/// Where each funded loose key of a bag is swept to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepPlan {
+    pub entries: Vec<SweepEntry>,
+    /// Outputs of loose keys left out because they pay to legacy P2PKH
+    /// scripts, whose inputs need the full previous transaction
+    pub legacy: Vec<OutPoint>,
+}
+
+/// An output held by a loose key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepUtxo {
+    pub outpoint: OutPoint,
+    pub amount: u64,
+    pub script_pubkey: Vec<u8>,
+}
+
+impl Wallet {
+    /// Pair each loose key of a bag holding any of `utxos` with a fresh
+    /// receive address of the active account, for migrating a random-key
+    /// wallet to HD. Keys already derived from an HD origin are left alone,
+    /// and keys holding only legacy P2PKH outputs get no address.
+    pub fn plan_sweep(&mut self, bag: &KeyBag, utxos: &[SweepUtxo]) -> Result<SweepPlan, Error> {
+        if bag.network() != self.network() {
+            return Err(Error::NetworkMismatch(format!(
+                "Key bag is for {:?}, the wallet for {:?}",
+                bag.network(),
+                self.network()
+            )));
+        }
+
+        let mut funded = Vec::new();
+        let mut legacy = Vec::new();
+        for utxo in utxos {
+            let (key, address_type) = bag.find_script(&utxo.script_pubkey).ok_or_else(|| {
+                Error::InvalidAddress(format!("{} is not held by the key bag", utxo.outpoint))
+            })?;
+            if !key.is_loose() {
+                continue;
+            }
+            if address_type == AddressType::P2pkh {
+                legacy.push(utxo.outpoint);
+            } else {
+                funded.push(key.pubkey_hash());
+            }
+        }
+
+        let mut entries = Vec::new();
+        for key in bag
+            .loose()
+            .filter(|key| funded.contains(&key.pubkey_hash()))
+        {
+            let destination = self.next_address(Change::External)?;
+            let location = self
+                .find_address(&destination)?
+                .expect("a handed out address is within the watched range");
+            entries.push(SweepEntry {
+                pubkey_hash: key.pubkey_hash(),
+                sources: key
+                    .address_types()
+                    .iter()
+                    .filter_map(|&t| key.address(t, bag.network()))
+                    .collect(),
+                destination,
+                location,
+            });
+        }
+        Ok(SweepPlan { entries, legacy })
+    }
+}
+
+impl SweepPlan {
+    /// Get the entry of a loose key
+    pub fn entry(&self, pubkey_hash: &[u8; 20]) -> Option<&SweepEntry> {
+        self.entries
+            .iter()
+            .find(|entry| &entry.pubkey_hash == pubkey_hash)
+    }
+
+    /// Build one unsigned PSBT per loose key holding any of `utxos`, moving
+    /// them all to its destination less a flat `fee` each. The destination
+    /// output carries its BIP-32 derivation. Legacy P2PKH outputs are
+    /// skipped, as their inputs need the full previous transaction.
+    pub fn psbts(
+        &self,
+        wallet: &Wallet,
+        bag: &KeyBag,
+        utxos: &[SweepUtxo],
+        fee: u64,
+    ) -> Result<Vec<Psbt>, Error> {
+        let mut groups: Vec<(&SweepEntry, Vec<PsbtInput>, u64)> = Vec::new();
+        for utxo in utxos {
+            let (key, address_type) = bag.find_script(&utxo.script_pubkey).ok_or_else(|| {
+                Error::InvalidAddress(format!("{} is not held by the key bag", utxo.outpoint))
+            })?;
+            if address_type == AddressType::P2pkh {
+                continue;
+            }
+            let entry = self.entry(&key.pubkey_hash()).ok_or_else(|| {
+                Error::InvalidConfig(format!("{} is not held by a swept key", utxo.outpoint))
+            })?;
+
+            let input = PsbtInput {
+                outpoint: utxo.outpoint,
+                sequence: SEQUENCE_RBF,
+                witness_utxo: Some(TxOut {
+                    amount: utxo.amount,
+                    script_pubkey: utxo.script_pubkey.clone(),
+                }),
+                redeem_script: (address_type == AddressType::P2shP2wpkh)
+                    .then(|| address::p2wpkh_script(&key.pubkey_hash())),
+                bip32_derivation: Vec::new(),
+                unknown: Vec::new(),
+            };
+            match groups
+                .iter_mut()
+                .find(|(e, _, _)| e.pubkey_hash == entry.pubkey_hash)
+            {
+                Some((_, inputs, total)) => {
+                    inputs.push(input);
+                    *total += utxo.amount;
+                }
+                None => groups.push((entry, vec![input], utxo.amount)),
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(entry, inputs, total)| {
+                let amount = total.saturating_sub(fee);
+                if amount < DUST_LIMIT {
+                    return Err(Error::InvalidConfig(format!(
+                        "Sweeping {} sat to {} leaves dust after a {} sat fee",
+                        total, entry.destination, fee
+                    )));
+                }
+                let [receive, _] = wallet.descriptors(entry.location.account)?;
+                let account = AccountKey::from(&receive);
+                let mut path = account.origin.path.path.clone();
+                path.extend([
+                    entry.location.change.child_number(),
+                    ChildNumber::Normal(entry.location.index),
+                ]);
+                let output = account.change_output(&DerivationPath { path }, amount)?;
+
+                let psbt = Psbt {
+                    version: TX_VERSION,
+                    lock_time: 0,
+                    inputs,
+                    outputs: vec![output],
+                };
+                psbt.fee()?;
+                Ok(psbt)
+            })
+            .collect()
+    }
+}
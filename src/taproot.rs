@@ -0,0 +1,188 @@
+use crate::address;
+use crate::bip32::{ExtendedPrivKey, ExtendedPubKey, Network};
+use crate::error::Error;
+use crate::utils;
+use bech32::segwit;
+use secp256k1::{Keypair, Parity, Scalar, XOnlyPublicKey};
+
+/// Leaf version for BIP-342 tapscript
+pub const TAPSCRIPT_LEAF_VERSION: u8 = 0xc0;
+
+/// A script leaf of a taproot script tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapLeaf {
+    pub version: u8,
+    pub script: Vec<u8>,
+}
+
+impl TapLeaf {
+    /// Create a tapscript leaf
+    pub fn new(script: Vec<u8>) -> Self {
+        TapLeaf {
+            version: TAPSCRIPT_LEAF_VERSION,
+            script,
+        }
+    }
+
+    /// Compute the TapLeaf hash of this leaf
+    pub fn hash(&self) -> [u8; 32] {
+        let mut data = Vec::with_capacity(self.script.len() + 10);
+        data.push(self.version);
+        utils::push_compact_size(&mut data, self.script.len());
+        data.extend_from_slice(&self.script);
+        utils::tagged_hash(utils::TAG_TAP_LEAF, &data)
+    }
+}
+
+/// Compute the TapBranch hash of two child nodes (ordered lexicographically)
+pub fn tap_branch_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(left);
+    data[32..].copy_from_slice(right);
+    utils::tagged_hash(utils::TAG_TAP_BRANCH, &data)
+}
+
+/// A script tree's merkle root with the inclusion path of each leaf
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapTree {
+    pub merkle_root: [u8; 32],
+    /// Merkle path of each leaf, in the order the leaves were given
+    pub paths: Vec<Vec<[u8; 32]>>,
+}
+
+/// Build a script tree by combining leaves pairwise, level by level
+pub fn build_tree(leaves: &[TapLeaf]) -> Option<TapTree> {
+    if leaves.is_empty() {
+        return None;
+    }
+
+    let mut paths = vec![Vec::new(); leaves.len()];
+    // Each node tracks its hash and the leaves below it
+    let mut level: Vec<([u8; 32], Vec<usize>)> = leaves
+        .iter()
+        .enumerate()
+        .map(|(i, leaf)| (leaf.hash(), vec![i]))
+        .collect();
+
+    while level.len() > 1 {
+        let mut next = Vec::with_capacity(level.len().div_ceil(2));
+        let mut nodes = level.into_iter();
+
+        while let Some((left_hash, left_leaves)) = nodes.next() {
+            match nodes.next() {
+                Some((right_hash, right_leaves)) => {
+                    for &i in &left_leaves {
+                        paths[i].push(right_hash);
+                    }
+                    for &i in &right_leaves {
+                        paths[i].push(left_hash);
+                    }
+                    let mut below = left_leaves;
+                    below.extend(right_leaves);
+                    next.push((tap_branch_hash(&left_hash, &right_hash), below));
+                }
+                None => next.push((left_hash, left_leaves)),
+            }
+        }
+
+        level = next;
+    }
+
+    Some(TapTree {
+        merkle_root: level[0].0,
+        paths,
+    })
+}
+
+/// Compute the TapTweak hash committing to an internal key and optional merkle root
+pub fn tap_tweak_hash(internal_key: &XOnlyPublicKey, merkle_root: Option<&[u8; 32]>) -> [u8; 32] {
+    let mut data = Vec::with_capacity(64);
+    data.extend_from_slice(&internal_key.serialize());
+    if let Some(root) = merkle_root {
+        data.extend_from_slice(root);
+    }
+    utils::tagged_hash(utils::TAG_TAP_TWEAK, &data)
+}
+
+/// A taproot output key committing to an internal key and optional script tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaprootOutput {
+    /// Untweaked internal key
+    pub internal_key: XOnlyPublicKey,
+    /// Merkle root of the script tree, if any
+    pub merkle_root: Option<[u8; 32]>,
+    /// Tweaked output key
+    pub output_key: XOnlyPublicKey,
+    /// Parity of the tweaked output key
+    pub output_parity: Parity,
+}
+
+impl TaprootOutput {
+    /// Tweak an internal key with an optional script tree merkle root
+    pub fn new(internal_key: XOnlyPublicKey, merkle_root: Option<[u8; 32]>) -> Result<Self, Error> {
+        let secp = utils::secp();
+        let tweak = Scalar::from_be_bytes(tap_tweak_hash(&internal_key, merkle_root.as_ref()))
+            .map_err(|_| Error::InvalidKey("TapTweak out of range".to_string()))?;
+
+        let (output_key, output_parity) = internal_key.add_tweak(secp, &tweak)?;
+
+        Ok(TaprootOutput {
+            internal_key,
+            merkle_root,
+            output_key,
+            output_parity,
+        })
+    }
+
+    /// Get the scriptPubKey (OP_1 <output key>)
+    pub fn script_pubkey(&self) -> Vec<u8> {
+        let mut script = Vec::with_capacity(34);
+        script.extend_from_slice(&[0x51, 0x20]);
+        script.extend_from_slice(&self.output_key.serialize());
+        script
+    }
+
+    /// Get the bech32m address of the output
+    pub fn address(&self, network: Network) -> String {
+        segwit::encode_v1(address::segwit_hrp(network), &self.output_key.serialize())
+            .expect("32-byte witness program is always valid")
+    }
+
+    /// Build the control block for spending a leaf through the script path
+    pub fn control_block(&self, leaf_version: u8, merkle_path: &[[u8; 32]]) -> Vec<u8> {
+        let parity_bit = match self.output_parity {
+            Parity::Even => 0,
+            Parity::Odd => 1,
+        };
+
+        let mut control_block = Vec::with_capacity(33 + 32 * merkle_path.len());
+        control_block.push((leaf_version & 0xfe) | parity_bit);
+        control_block.extend_from_slice(&self.internal_key.serialize());
+        for node in merkle_path {
+            control_block.extend_from_slice(node);
+        }
+        control_block
+    }
+}
+
+impl ExtendedPubKey {
+    /// Get the taproot output for this key, committing to an optional script tree
+    pub fn taproot_output(&self, merkle_root: Option<[u8; 32]>) -> Result<TaprootOutput, Error> {
+        TaprootOutput::new(self.public_key.x_only_public_key().0, merkle_root)
+    }
+}
+
+impl ExtendedPrivKey {
+    /// Get the tweaked keypair for key-path spending of this key's taproot output
+    pub fn taproot_keypair(&self, merkle_root: Option<[u8; 32]>) -> Result<Keypair, Error> {
+        let secp = utils::secp();
+        let keypair = Keypair::from_secret_key(secp, &self.private_key);
+        let (internal_key, _) = keypair.x_only_public_key();
+
+        let tweak = Scalar::from_be_bytes(tap_tweak_hash(&internal_key, merkle_root.as_ref()))
+            .map_err(|_| Error::InvalidKey("TapTweak out of range".to_string()))?;
+
+        Ok(keypair.add_xonly_tweak(secp, &tweak)?)
+    }
+}
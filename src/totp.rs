@@ -0,0 +1,143 @@
+use crate::bip39::Seed;
+use crate::error::Error;
+use crate::filecoin::base32_encode;
+use crate::slip21::Slip21Node;
+use crate::utils::percent_encode;
+use crate::wipe::wipe;
+
+/// SLIP-21 labels under which TOTP secrets are derived
+const TOTP_PATH: [&[u8]; 2] = [b"bip32hdwallet", b"TOTP"];
+/// Secret length recommended by RFC 4226 for HMAC-SHA1
+const SECRET_LEN: usize = 20;
+
+/// A 2FA secret for one service account, derived from the wallet seed at
+/// `bip32hdwallet/TOTP/<issuer>/<account>/<index>` so it can be recovered
+/// from the mnemonic. Issuer and account are used byte for byte, so
+/// `GitHub` and `github` give different secrets.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TotpSecret {
+    issuer: String,
+    account: String,
+    index: u32,
+    secret: [u8; SECRET_LEN],
+    digits: u8,
+    period: u32,
+}
+
+impl std::fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TotpSecret")
+            .field("issuer", &self.issuer)
+            .field("account", &self.account)
+            .field("index", &self.index)
+            .field("digits", &self.digits)
+            .field("period", &self.period)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TotpSecret {
+    /// Derive the secret of an account at a service. Bump `index` to
+    /// provision a fresh secret after the service resets 2FA.
+    pub fn derive(seed: &Seed, issuer: &str, account: &str, index: u32) -> Result<Self, Error> {
+        if issuer.is_empty() || issuer.contains(':') {
+            return Err(Error::InvalidConfig(
+                "Issuer must be non-empty and without ':'".to_string(),
+            ));
+        }
+        if account.is_empty() {
+            return Err(Error::InvalidConfig(
+                "Account must be non-empty".to_string(),
+            ));
+        }
+
+        let index_label = index.to_string();
+        let mut key = Slip21Node::from_seed(seed.as_bytes())
+            .derive_path(&TOTP_PATH)
+            .derive_path(&[
+                issuer.as_bytes(),
+                account.as_bytes(),
+                index_label.as_bytes(),
+            ])
+            .key();
+        let mut secret = [0u8; SECRET_LEN];
+        secret.copy_from_slice(&key[..SECRET_LEN]);
+        wipe(&mut key);
+
+        Ok(TotpSecret {
+            issuer: issuer.to_string(),
+            account: account.to_string(),
+            index,
+            secret,
+            digits: 6,
+            period: 30,
+        })
+    }
+
+    /// Use codes of 6 to 8 digits instead of 6
+    pub fn with_digits(mut self, digits: u8) -> Result<Self, Error> {
+        if !(6..=8).contains(&digits) {
+            return Err(Error::InvalidConfig(
+                "TOTP codes have 6 to 8 digits".to_string(),
+            ));
+        }
+        self.digits = digits;
+        Ok(self)
+    }
+
+    /// Use a time step other than 30 seconds
+    pub fn with_period(mut self, period: u32) -> Result<Self, Error> {
+        if period == 0 {
+            return Err(Error::InvalidConfig(
+                "TOTP period must be at least 1 second".to_string(),
+            ));
+        }
+        self.period = period;
+        Ok(self)
+    }
+
+    /// Get the issuer
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Get the account name
+    pub fn account(&self) -> &str {
+        &self.account
+    }
+
+    /// Get the provisioning index
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Get the raw secret
+    pub fn secret(&self) -> &[u8; SECRET_LEN] {
+        &self.secret
+    }
+
+    /// Get the secret in unpadded uppercase base32, as typed into
+    /// authenticator apps
+    pub fn secret_base32(&self) -> String {
+        base32_encode(&self.secret).to_ascii_uppercase()
+    }
+
+    /// Export as an `otpauth://totp/` URI, as encoded in provisioning QR codes
+    pub fn to_uri(&self) -> String {
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            percent_encode(&self.issuer),
+            percent_encode(&self.account),
+            self.secret_base32(),
+            percent_encode(&self.issuer),
+            self.digits,
+            self.period
+        )
+    }
+}
+
+impl Drop for TotpSecret {
+    fn drop(&mut self) {
+        wipe(&mut self.secret);
+    }
+}
@@ -0,0 +1,22 @@
+//! Instrumentation that compiles to nothing without the `tracing` feature.
+//! Fields are limited to paths, fingerprints, counts and outcomes; secret
+//! bytes, phrases and passphrases are never recorded.
+
+/// Enter a span for the rest of the enclosing block
+macro_rules! span {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $($arg)+).entered();
+    };
+}
+
+/// Emit an event
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$level, $($arg)+);
+    };
+}
+
+pub(crate) use event;
+pub(crate) use span;
@@ -0,0 +1,114 @@
+use crate::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey, Network};
+use crate::error::Error;
+
+/// Public data of one node along a derivation
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TranscriptStep {
+    /// Path of the node from the start of the derivation
+    pub path: String,
+    pub depth: u8,
+    /// Raw child number, with the hardened bit
+    pub child_number: u32,
+    pub parent_fingerprint: String,
+    pub fingerprint: String,
+    pub chain_code: String,
+    /// Compressed public key, hex encoded
+    pub public_key: String,
+}
+
+impl TranscriptStep {
+    fn new(path: String, key: &ExtendedPubKey) -> Self {
+        TranscriptStep {
+            path,
+            depth: key.depth,
+            child_number: key.child_number,
+            parent_fingerprint: hex::encode(key.parent_fingerprint),
+            fingerprint: hex::encode(key.fingerprint()),
+            chain_code: hex::encode(key.chain_code),
+            public_key: hex::encode(key.public_key.serialize()),
+        }
+    }
+}
+
+/// Every intermediate node of a derivation, without private keys, so two
+/// implementations can be compared step by step
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DerivationTranscript {
+    pub network: Network,
+    /// The starting node followed by one step per path element
+    pub steps: Vec<TranscriptStep>,
+}
+
+impl DerivationTranscript {
+    /// Record a derivation from a private key, which may include hardened steps
+    pub fn from_private(start: &ExtendedPrivKey, path: &DerivationPath) -> Result<Self, Error> {
+        let mut key = start.clone();
+        let mut steps = vec![TranscriptStep::new(
+            "m".to_string(),
+            &key.to_extended_public_key(),
+        )];
+
+        for (i, &child) in path.path.iter().enumerate() {
+            key = key.derive_child(child)?;
+            steps.push(TranscriptStep::new(
+                step_path(path, i),
+                &key.to_extended_public_key(),
+            ));
+        }
+
+        Ok(DerivationTranscript {
+            network: start.network,
+            steps,
+        })
+    }
+
+    /// Record a public derivation from an extended public key
+    pub fn from_public(start: &ExtendedPubKey, path: &DerivationPath) -> Result<Self, Error> {
+        let mut key = start.clone();
+        let mut steps = vec![TranscriptStep::new("m".to_string(), &key)];
+
+        for (i, &child) in path.path.iter().enumerate() {
+            key = key.derive_child(child)?;
+            steps.push(TranscriptStep::new(step_path(path, i), &key));
+        }
+
+        Ok(DerivationTranscript {
+            network: start.network,
+            steps,
+        })
+    }
+
+    /// Find the first step where two transcripts differ
+    pub fn first_divergence(&self, other: &DerivationTranscript) -> Option<usize> {
+        self.steps
+            .iter()
+            .zip(&other.steps)
+            .position(|(a, b)| a != b)
+            .or_else(|| {
+                (self.steps.len() != other.steps.len())
+                    .then(|| self.steps.len().min(other.steps.len()))
+            })
+    }
+
+    /// Serialize to JSON
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::InvalidExport(e.to_string()))
+    }
+
+    /// Parse from JSON
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(|e| Error::InvalidExport(e.to_string()))
+    }
+}
+
+/// Path of the node after the first `i + 1` elements
+fn step_path(path: &DerivationPath, i: usize) -> String {
+    DerivationPath {
+        path: path.path[..=i].to_vec(),
+    }
+    .to_string()
+}
@@ -1,8 +1,17 @@
 use hmac::{Hmac, Mac};
+use ripemd::Ripemd160;
+use secp256k1::{All, PublicKey, Secp256k1};
 use sha2::{Digest, Sha256, Sha512};
+use std::sync::OnceLock;
 
 pub type HmacSha512 = Hmac<Sha512>;
 
+/// Get the process-wide secp256k1 context, shared by all threads
+pub fn secp() -> &'static Secp256k1<All> {
+    static SECP: OnceLock<Secp256k1<All>> = OnceLock::new();
+    SECP.get_or_init(Secp256k1::new)
+}
+
 /// Compute HMAC-SHA512
 pub fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
     let mut mac = HmacSha512::new_from_slice(key).expect("HMAC can take key of any size");
@@ -23,12 +32,127 @@ pub fn sha256(data: &[u8]) -> [u8; 32] {
     hash
 }
 
+/// Compute RIPEMD160 hash
+pub fn ripemd160(data: &[u8]) -> [u8; 20] {
+    let mut hasher = Ripemd160::new();
+    hasher.update(data);
+    let result = hasher.finalize();
+    let mut hash = [0u8; 20];
+    hash.copy_from_slice(&result);
+    hash
+}
+
+/// RIPEMD160(SHA256(data)), as used for public key and script hashes
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    ripemd160(&sha256(data))
+}
+
+/// HASH160 of a compressed public key, as used in P2PKH/P2WPKH and fingerprints
+pub fn pubkey_hash(public_key: &PublicKey) -> [u8; 20] {
+    hash160(&public_key.serialize())
+}
+
 /// Double SHA256 hash
 pub fn hash_twice(data: &[u8]) -> [u8; 32] {
     let first = sha256(data);
     sha256(&first)
 }
 
+/// BIP-341 tag for output key tweaks
+pub const TAG_TAP_TWEAK: &str = "TapTweak";
+/// BIP-341 tag for script tree leaves
+pub const TAG_TAP_LEAF: &str = "TapLeaf";
+/// BIP-341 tag for script tree branches
+pub const TAG_TAP_BRANCH: &str = "TapBranch";
+/// BIP-341 tag for signature hashes
+pub const TAG_TAP_SIGHASH: &str = "TapSighash";
+/// BIP-340 tag for the signature challenge
+pub const TAG_BIP340_CHALLENGE: &str = "BIP0340/challenge";
+/// BIP-340 tag for auxiliary nonce randomness
+pub const TAG_BIP340_AUX: &str = "BIP0340/aux";
+/// BIP-340 tag for nonce derivation
+pub const TAG_BIP340_NONCE: &str = "BIP0340/nonce";
+
+/// Tags whose midstates are computed once and cached
+const CACHED_TAGS: [&str; 7] = [
+    TAG_TAP_TWEAK,
+    TAG_TAP_LEAF,
+    TAG_TAP_BRANCH,
+    TAG_TAP_SIGHASH,
+    TAG_BIP340_CHALLENGE,
+    TAG_BIP340_AUX,
+    TAG_BIP340_NONCE,
+];
+
+/// Hash state after absorbing SHA256(tag) || SHA256(tag), which fills
+/// exactly one block
+fn tag_midstate(tag: &str) -> Sha256 {
+    let tag_hash = sha256(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    hasher
+}
+
+/// Get a SHA256 engine primed with a tag, using the cached midstate for common tags
+pub fn tagged_engine(tag: &str) -> Sha256 {
+    static MIDSTATES: OnceLock<Vec<Sha256>> = OnceLock::new();
+
+    match CACHED_TAGS.iter().position(|&cached| cached == tag) {
+        Some(index) => MIDSTATES
+            .get_or_init(|| CACHED_TAGS.iter().map(|tag| tag_midstate(tag)).collect())[index]
+            .clone(),
+        None => tag_midstate(tag),
+    }
+}
+
+/// BIP-340 tagged hash: SHA256(SHA256(tag) || SHA256(tag) || data)
+pub fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let mut hasher = tagged_engine(tag);
+    hasher.update(data);
+    let result = hasher.finalize();
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&result);
+    hash
+}
+
+/// Compare secrets in time that depends only on their lengths
+pub(crate) fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Append a Bitcoin compact-size length
+pub(crate) fn push_compact_size(data: &mut Vec<u8>, len: usize) {
+    match len {
+        0..=0xfc => data.push(len as u8),
+        0xfd..=0xffff => {
+            data.push(0xfd);
+            data.extend_from_slice(&(len as u16).to_le_bytes());
+        }
+        0x10000..=0xffff_ffff => {
+            data.push(0xfe);
+            data.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+        _ => {
+            data.push(0xff);
+            data.extend_from_slice(&(len as u64).to_le_bytes());
+        }
+    }
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters
+pub(crate) fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
 /// Calculate checksum (first 4 bytes of double-SHA256 hash)
 pub fn checksum(data: &[u8]) -> [u8; 4] {
     let hash = hash_twice(data);
@@ -0,0 +1,82 @@
+use crate::bip32::{ChildNumber, DerivationPath};
+use crate::bip44::Change;
+use crate::error::Error;
+use crate::utils;
+use crate::wallet::Wallet;
+use std::fmt;
+use std::str::FromStr;
+
+/// Domain separation tag of wallet entity identifiers
+const UUID_TAG: &str = "bip32hdwallet/uuid";
+
+/// An RFC 9562 version 8 UUID naming a wallet entity, derived from the
+/// master fingerprint and the entity's path so it is stable across devices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Uuid(pub [u8; 16]);
+
+impl Uuid {
+    /// Derive the identifier of the key at a path under a master fingerprint
+    pub fn for_path(master_fingerprint: [u8; 4], path: &DerivationPath) -> Self {
+        let mut data = master_fingerprint.to_vec();
+        data.extend_from_slice(path.canonical().to_string().as_bytes());
+        let hash = utils::tagged_hash(UUID_TAG, &data);
+
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&hash[..16]);
+        // Version 8, RFC 9562 variant
+        bytes[6] = (bytes[6] & 0x0f) | 0x80;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        Uuid(bytes)
+    }
+
+    /// Get the version nibble
+    pub fn version(&self) -> u8 {
+        self.0[6] >> 4
+    }
+}
+
+impl fmt::Display for Uuid {
+    /// Hyphenated lowercase form, e.g. `0b6f2e4a-...`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hex = hex::encode(self.0);
+        write!(
+            f,
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        )
+    }
+}
+
+impl FromStr for Uuid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let groups: Vec<&str> = s.split('-').collect();
+        if groups.iter().map(|g| g.len()).collect::<Vec<_>>() != [8, 4, 4, 4, 12] {
+            return Err(Error::InvalidConfig(format!("Invalid UUID {}", s)));
+        }
+        let mut bytes = [0u8; 16];
+        hex::decode_to_slice(groups.concat(), &mut bytes)
+            .map_err(|_| Error::InvalidConfig(format!("Invalid UUID {}", s)))?;
+        Ok(Uuid(bytes))
+    }
+}
+
+impl Wallet {
+    /// Get the stable identifier of an account
+    pub fn account_uuid(&self, index: u32) -> Uuid {
+        Uuid::for_path(self.master().fingerprint(), &self.account_path(index))
+    }
+
+    /// Get the stable identifier of an address
+    pub fn address_uuid(&self, account: u32, change: Change, index: u32) -> Uuid {
+        let mut path = self.account_path(account);
+        path.path.push(change.child_number());
+        path.path.push(ChildNumber::Normal(index));
+        Uuid::for_path(self.master().fingerprint(), &path)
+    }
+}
@@ -0,0 +1,781 @@
+use crate::address::AddressType;
+use crate::bip32::{
+    ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey, Network, PathParseMode,
+};
+use crate::bip39::Seed;
+use crate::bip44::{Change, CoinType};
+use crate::cache::DerivationCache;
+use crate::config::{self, HardenedNotation};
+use crate::descriptor::Descriptor;
+use crate::error::Error;
+use crate::limits;
+use crate::metrics;
+use crate::slip21::Slip21Node;
+use crate::store::{AccountState, WalletState};
+use crate::trace;
+use crate::utils;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{AeadCore, ChaCha20Poly1305, KeyInit};
+use rand::rngs::OsRng;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// SLIP-21 labels of the node keying wallet backups
+const BACKUP_KEY_PATH: [&[u8]; 2] = [b"bip32hdwallet", b"Backup encryption key"];
+/// Version byte prefixed to encrypted backups
+const BACKUP_VERSION: u8 = 1;
+/// ChaCha20-Poly1305 nonce length
+const NONCE_LEN: usize = 12;
+/// Domain separation tag of the keyed change index shuffle
+const CHANGE_SHUFFLE_TAG: &[u8] = b"bip32hdwallet change selection";
+
+/// Lifecycle state of a wallet account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountStatus {
+    /// Hands out new addresses
+    Active,
+    /// Kept for watching and spending existing funds only
+    Retired,
+}
+
+/// Order in which change addresses are handed out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ChangeSelection {
+    /// One index after the other
+    #[default]
+    Sequential,
+    /// A shuffled order within each window of `gap_limit` indices, keyed by
+    /// the master key so it is reproducible from the seed. Every window is
+    /// used up before the next, so discovery with the same gap limit still
+    /// finds every address.
+    Shuffled,
+}
+
+/// Address scanning parameters of an account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountConfig {
+    /// Consecutive unused addresses after which discovery stops
+    pub gap_limit: u32,
+    /// Addresses past the next unused one that are watched for payments
+    pub lookahead: u32,
+    /// First address index used on each chain
+    pub start_index: u32,
+    /// How change indices are picked; changing the gap limit of a shuffled
+    /// account reorders the indices not handed out yet
+    pub change_selection: ChangeSelection,
+}
+
+impl Default for AccountConfig {
+    fn default() -> Self {
+        AccountConfig {
+            gap_limit: 20,
+            lookahead: 20,
+            start_index: 0,
+            change_selection: ChangeSelection::Sequential,
+        }
+    }
+}
+
+impl AccountConfig {
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if self.gap_limit == 0 {
+            return Err(Error::InvalidConfig(
+                "Gap limit must be at least 1".to_string(),
+            ));
+        }
+        if self.start_index > ChildNumber::MAX_NORMAL_INDEX {
+            return Err(Error::InvalidConfig(
+                "Start index must be a normal index".to_string(),
+            ));
+        }
+        // Discovery derives a gap limit of addresses at a time, and watching
+        // covers the lookahead past the next unused address
+        limits::check_batch_size(self.gap_limit)?;
+        limits::check_wildcard_range(0, self.lookahead)?;
+        Ok(())
+    }
+}
+
+/// Where a wallet address was derived
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressLocation {
+    pub account: u32,
+    pub change: Change,
+    pub index: u32,
+}
+
+/// An account of a wallet and its address chain state
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub index: u32,
+    pub xpub: ExtendedPubKey,
+    pub status: AccountStatus,
+    config: AccountConfig,
+    next_external: u32,
+    next_internal: u32,
+    /// Order of the current shuffled change window, kept so each issuance
+    /// doesn't recompute a keyed hash per position
+    change_window: Option<ShuffledWindow>,
+}
+
+/// Shuffled order of a change window, as offsets from its first index
+#[derive(Debug, Clone)]
+struct ShuffledWindow {
+    window: u32,
+    gap_limit: u32,
+    order: Vec<u32>,
+}
+
+impl Account {
+    /// An active account with no addresses handed out yet
+    pub(crate) fn new(index: u32, xpub: ExtendedPubKey, config: AccountConfig) -> Self {
+        Account {
+            index,
+            xpub,
+            status: AccountStatus::Active,
+            config,
+            next_external: config.start_index,
+            next_internal: config.start_index,
+            change_window: None,
+        }
+    }
+
+    /// Whether the account still hands out new addresses
+    pub fn is_active(&self) -> bool {
+        self.status == AccountStatus::Active
+    }
+
+    /// Get the account's scanning parameters
+    pub fn config(&self) -> &AccountConfig {
+        &self.config
+    }
+
+    /// Indices of a chain watched for payments: from the start index through
+    /// the lookahead window past the next unused address
+    pub fn watch_range(&self, change: Change) -> Range<u32> {
+        let config = &self.config;
+        let mut end = self.next_index(change).saturating_add(config.lookahead);
+        if change == Change::Internal && config.change_selection == ChangeSelection::Shuffled {
+            // Shuffled indices reach up to the end of the current window
+            let issued = self.next_internal.saturating_sub(config.start_index);
+            let window_end = config.start_index as u64
+                + issued.div_ceil(config.gap_limit) as u64 * config.gap_limit as u64;
+            end = end.max(window_end.min(u32::MAX as u64) as u32);
+        }
+        config.start_index..end.min(ChildNumber::MAX_NORMAL_INDEX + 1)
+    }
+
+    /// Iterate over the addresses of a chain from the start index
+    pub fn address_iter(
+        &self,
+        address_type: AddressType,
+        change: Change,
+    ) -> Result<AddressIter, Error> {
+        Ok(AddressIter {
+            chain: self.xpub.derive_child(change.child_number())?,
+            address_type,
+            next: Some(self.config.start_index),
+        })
+    }
+
+    /// Derive the address at an index of one of the account's chains
+    pub fn address(
+        &self,
+        address_type: AddressType,
+        change: Change,
+        index: u32,
+    ) -> Result<String, Error> {
+        account_address(&self.xpub, address_type, change, index)
+    }
+
+    /// Get the first receive address, for pairing checks
+    pub fn first_address(&self, address_type: AddressType) -> Result<String, Error> {
+        self.xpub.first_address(address_type)
+    }
+
+    /// Index of the next unused address on a chain. With shuffled change
+    /// selection, the internal chain's value counts the indices handed out
+    /// since the start index instead.
+    pub fn next_index(&self, change: Change) -> u32 {
+        match change {
+            Change::External => self.next_external,
+            Change::Internal => self.next_internal,
+        }
+    }
+
+    /// Move the next unused index of a chain
+    pub(crate) fn set_next_index(&mut self, change: Change, next: u32) {
+        match change {
+            Change::External => self.next_external = next,
+            Change::Internal => self.next_internal = next,
+        }
+    }
+}
+
+/// Iterator over `(index, address)` pairs of an account chain. Indices whose
+/// key is invalid are skipped, as BIP-32 prescribes.
+#[derive(Debug, Clone)]
+pub struct AddressIter {
+    chain: ExtendedPubKey,
+    address_type: AddressType,
+    next: Option<u32>,
+}
+
+impl Iterator for AddressIter {
+    type Item = (u32, String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let index = self.next.filter(|&i| i <= ChildNumber::MAX_NORMAL_INDEX)?;
+            self.next = index.checked_add(1);
+            if let Ok(key) = self.chain.derive_child(ChildNumber::Normal(index)) {
+                return Some((index, key.address(self.address_type)));
+            }
+        }
+    }
+}
+
+/// A single-address-type wallet over a master key
+#[derive(Clone)]
+pub struct Wallet {
+    master: ExtendedPrivKey,
+    address_type: AddressType,
+    accounts: Vec<Account>,
+    account_config: AccountConfig,
+    labels: BTreeMap<String, String>,
+    backup_key: Option<[u8; 32]>,
+    cache: Option<Arc<DerivationCache>>,
+    notation: HardenedNotation,
+    path_mode: PathParseMode,
+}
+
+impl std::fmt::Debug for Wallet {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Wallet")
+            .field("master", &self.master)
+            .field("address_type", &self.address_type)
+            .field("accounts", &self.accounts)
+            .field("account_config", &self.account_config)
+            .field("labels", &self.labels)
+            .field("cache", &self.cache)
+            .field("notation", &self.notation)
+            .field("path_mode", &self.path_mode)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Wallet {
+    /// Create a wallet with account 0 provisioned
+    pub fn new(master: ExtendedPrivKey, address_type: AddressType) -> Result<Self, Error> {
+        let mut wallet = Wallet {
+            master,
+            address_type,
+            accounts: Vec::new(),
+            account_config: AccountConfig::default(),
+            labels: BTreeMap::new(),
+            backup_key: None,
+            cache: None,
+            notation: HardenedNotation::default(),
+            path_mode: PathParseMode::default(),
+        };
+        let account = wallet.derive_account(0)?;
+        wallet.accounts.push(account);
+        Ok(wallet)
+    }
+
+    /// Create a wallet from a BIP-39 seed
+    pub fn from_seed(
+        seed: &Seed,
+        network: Network,
+        address_type: AddressType,
+    ) -> Result<Self, Error> {
+        let mut wallet = Wallet::new(ExtendedPrivKey::new_master(&seed.0, network)?, address_type)?;
+        wallet.backup_key = Some(
+            Slip21Node::from_seed(&seed.0)
+                .derive_path(&BACKUP_KEY_PATH)
+                .key(),
+        );
+        Ok(wallet)
+    }
+
+    /// Create a wallet from a serialized master xprv, for setups that hold
+    /// extended keys instead of mnemonics. Backup encryption needs the seed
+    /// and is unavailable.
+    pub fn from_xprv(xprv: &str, address_type: AddressType) -> Result<Self, Error> {
+        let master = ExtendedPrivKey::from_string(xprv)?;
+        if master.depth != 0 {
+            return Err(Error::InvalidExtendedKey(format!(
+                "Expected a master key, got depth {}",
+                master.depth
+            )));
+        }
+        Wallet::new(master, address_type)
+    }
+
+    /// Cache up to `capacity` derived address keys across calls
+    pub fn with_derivation_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Arc::new(DerivationCache::new(capacity)));
+        self
+    }
+
+    /// Use scanning parameters for every account, including those already
+    /// provisioned
+    pub fn with_account_config(mut self, config: AccountConfig) -> Result<Self, Error> {
+        config.validate()?;
+        self.account_config = config;
+        for account in &mut self.accounts {
+            apply_config(account, config);
+        }
+        Ok(self)
+    }
+
+    /// Change the scanning parameters of one provisioned account
+    pub fn set_account_config(&mut self, index: u32, config: AccountConfig) -> Result<(), Error> {
+        config.validate()?;
+        let account = self
+            .accounts
+            .iter_mut()
+            .find(|account| account.index == index)
+            .ok_or_else(|| Error::InvalidDerivationPath(format!("Unknown account {}", index)))?;
+        apply_config(account, config);
+        Ok(())
+    }
+
+    /// Get the derivation cache, if enabled
+    pub fn derivation_cache(&self) -> Option<&DerivationCache> {
+        self.cache.as_deref()
+    }
+
+    /// Get the master key
+    pub fn master(&self) -> &ExtendedPrivKey {
+        &self.master
+    }
+
+    /// Get the wallet's address type
+    pub fn address_type(&self) -> AddressType {
+        self.address_type
+    }
+
+    /// Get the wallet's network
+    pub fn network(&self) -> Network {
+        self.master.network
+    }
+
+    /// Get all provisioned accounts, oldest first
+    pub fn accounts(&self) -> &[Account] {
+        &self.accounts
+    }
+
+    /// Get a provisioned account by index
+    pub fn account(&self, index: u32) -> Option<&Account> {
+        self.accounts.iter().find(|account| account.index == index)
+    }
+
+    /// Get the account currently handing out addresses
+    pub fn active_account(&self) -> &Account {
+        self.accounts
+            .iter()
+            .rev()
+            .find(|account| account.is_active())
+            .expect("a wallet always has an active account")
+    }
+
+    /// Parse a derivation path with the wallet's strictness
+    pub fn parse_path(&self, path: &str) -> Result<DerivationPath, Error> {
+        DerivationPath::parse(path, self.path_mode)
+    }
+
+    /// Print a derivation path with the wallet's hardened notation
+    pub fn format_path(&self, path: &DerivationPath) -> String {
+        config::format_path(path, self.notation)
+    }
+
+    /// Use the path notation and strictness of a [`config::Config`]
+    pub(crate) fn set_path_style(&mut self, notation: HardenedNotation, path_mode: PathParseMode) {
+        self.notation = notation;
+        self.path_mode = path_mode;
+    }
+
+    /// Derivation path of an account
+    pub fn account_path(&self, index: u32) -> DerivationPath {
+        DerivationPath {
+            path: vec![
+                self.address_type.purpose().child_number(),
+                CoinType::for_network(self.network()).child_number(),
+                ChildNumber::Hardened(index),
+            ],
+        }
+    }
+
+    /// Get the first receive address of a provisioned account
+    pub fn first_address(&self, index: u32) -> Result<String, Error> {
+        self.account(index)
+            .ok_or_else(|| Error::InvalidDerivationPath(format!("Unknown account {}", index)))?
+            .first_address(self.address_type)
+    }
+
+    /// Get the receive and change descriptors of an account
+    pub fn descriptors(&self, index: u32) -> Result<[Descriptor; 2], Error> {
+        Ok([
+            Descriptor::for_account(&self.master, self.address_type, index, Change::External)?,
+            Descriptor::for_account(&self.master, self.address_type, index, Change::Internal)?,
+        ])
+    }
+
+    /// Derive a batch of addresses on a chain of a provisioned account
+    pub fn addresses(
+        &self,
+        index: u32,
+        change: Change,
+        start: u32,
+        count: u32,
+    ) -> Result<Vec<String>, Error> {
+        let account = self
+            .account(index)
+            .ok_or_else(|| Error::InvalidDerivationPath(format!("Unknown account {}", index)))?;
+        limits::check_batch_size(count)?;
+
+        (start..start.saturating_add(count))
+            .map(|i| self.derive_address(&account.xpub, change, i))
+            .collect()
+    }
+
+    /// Hand out the next unused address of the active account
+    pub fn next_address(&mut self, change: Change) -> Result<String, Error> {
+        let position = self
+            .accounts
+            .iter()
+            .rposition(|account| account.is_active())
+            .expect("a wallet always has an active account");
+
+        let index = self.issue_index(position, change)?;
+        let account = &self.accounts[position];
+        let address = self.derive_address(&account.xpub, change, index)?;
+
+        let account = &mut self.accounts[position];
+        account.set_next_index(change, account.next_index(change) + 1);
+        trace::event!(
+            DEBUG,
+            account = account.index,
+            ?change,
+            index,
+            "issued address"
+        );
+        Ok(address)
+    }
+
+    /// Address index of the next address handed out on a chain. The chain's
+    /// counter maps to itself unless change indices are shuffled.
+    fn issue_index(&mut self, position: usize, change: Change) -> Result<u32, Error> {
+        let account = &mut self.accounts[position];
+        let counter = account.next_index(change);
+        let config = account.config;
+        if change == Change::External || config.change_selection == ChangeSelection::Sequential {
+            return Ok(counter);
+        }
+
+        let offset = counter.saturating_sub(config.start_index);
+        let window = offset / config.gap_limit;
+        let window_start = config.start_index as u64 + window as u64 * config.gap_limit as u64;
+
+        let cached = account
+            .change_window
+            .as_ref()
+            .is_some_and(|w| w.window == window && w.gap_limit == config.gap_limit);
+        if !cached {
+            account.change_window = Some(ShuffledWindow {
+                window,
+                gap_limit: config.gap_limit,
+                order: shuffle_window(&self.master, account.index, window, config.gap_limit),
+            });
+        }
+        let order = &account.change_window.as_ref().expect("cached above").order;
+
+        let index = window_start + order[(offset % config.gap_limit) as usize] as u64;
+        u32::try_from(index)
+            .ok()
+            .filter(|&index| index <= ChildNumber::MAX_NORMAL_INDEX)
+            .ok_or_else(|| Error::InvalidDerivationPath("Change index exhausted".to_string()))
+    }
+
+    /// Derive the key at an index of an account chain, through the cache
+    /// when enabled
+    pub(crate) fn derive_key(
+        &self,
+        xpub: &ExtendedPubKey,
+        change: Change,
+        index: u32,
+    ) -> Result<ExtendedPubKey, Error> {
+        let path = DerivationPath {
+            path: vec![change.child_number(), ChildNumber::Normal(index)],
+        };
+        match &self.cache {
+            Some(cache) => cache.derive(xpub, &path),
+            None => xpub.derive_path(&path),
+        }
+    }
+
+    /// Derive an address of an account, through the cache when enabled
+    fn derive_address(
+        &self,
+        xpub: &ExtendedPubKey,
+        change: Change,
+        index: u32,
+    ) -> Result<String, Error> {
+        Ok(self
+            .derive_key(xpub, change, index)?
+            .address(self.address_type))
+    }
+
+    /// Find where an address was derived, searching the watched range of
+    /// each account's chains
+    pub fn find_address(&self, address: &str) -> Result<Option<AddressLocation>, Error> {
+        for account in &self.accounts {
+            for change in [Change::External, Change::Internal] {
+                for index in account.watch_range(change) {
+                    if self.derive_address(&account.xpub, change, index)? == address {
+                        return Ok(Some(AddressLocation {
+                            account: account.index,
+                            change,
+                            index,
+                        }));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Scan each account's chains until `gap_limit` consecutive addresses are
+    /// unused, moving the next unused index past the last used address
+    pub fn discover(&mut self, mut is_used: impl FnMut(&str) -> bool) -> Result<(), Error> {
+        trace::span!(INFO, "discover", accounts = self.accounts.len());
+        for account in &mut self.accounts {
+            discover_account(account, self.address_type, &mut is_used)?;
+        }
+        Ok(())
+    }
+
+    /// Attach a label to an address or other wallet item
+    pub fn set_label(&mut self, item: &str, label: &str) {
+        self.labels.insert(item.to_string(), label.to_string());
+    }
+
+    /// Get the label of an address or other wallet item
+    pub fn label(&self, item: &str) -> Option<&str> {
+        self.labels.get(item).map(String::as_str)
+    }
+
+    /// Snapshot the wallet's persistable state
+    pub fn state(&self) -> WalletState {
+        WalletState {
+            accounts: self
+                .accounts
+                .iter()
+                .map(|account| AccountState {
+                    index: account.index,
+                    active: account.is_active(),
+                    next_external: account.next_external,
+                    next_internal: account.next_internal,
+                })
+                .collect(),
+            labels: self.labels.clone(),
+        }
+    }
+
+    /// Replace the wallet's accounts and labels with a saved state,
+    /// re-deriving each account from the master key
+    pub fn restore_state(&mut self, state: &WalletState) -> Result<(), Error> {
+        if !state.accounts.iter().any(|account| account.active) {
+            return Err(Error::Storage(
+                "Saved state has no active account".to_string(),
+            ));
+        }
+
+        let accounts = state
+            .accounts
+            .iter()
+            .map(|saved| {
+                let mut account = self.derive_account(saved.index)?;
+                if !saved.active {
+                    account.status = AccountStatus::Retired;
+                }
+                account.next_external = saved.next_external;
+                account.next_internal = saved.next_internal;
+                Ok(account)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        self.accounts = accounts;
+        self.labels = state.labels.clone();
+        Ok(())
+    }
+
+    /// Retire the active account and provision the next one with a fresh
+    /// address chain. Retired accounts remain available read-only.
+    pub fn rotate(&mut self) -> Result<&Account, Error> {
+        let next = self
+            .accounts
+            .iter()
+            .map(|account| account.index)
+            .max()
+            .unwrap_or(0)
+            .checked_add(1)
+            .filter(|&index| index <= ChildNumber::MAX_NORMAL_INDEX)
+            .ok_or_else(|| Error::InvalidDerivationPath("Account index exhausted".to_string()))?;
+
+        // Provision first so a failed derivation leaves the wallet unchanged
+        let account = self.derive_account(next)?;
+        for account in &mut self.accounts {
+            account.status = AccountStatus::Retired;
+        }
+        trace::event!(INFO, account = next, "rotated to a new account");
+        self.accounts.push(account);
+
+        Ok(self.accounts.last().expect("account was just pushed"))
+    }
+
+    /// Encrypt app metadata with a SLIP-21 key recoverable from the mnemonic
+    pub fn backup_encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let cipher = ChaCha20Poly1305::new(&self.backup_key()?.into());
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: &[BACKUP_VERSION],
+                },
+            )
+            .map_err(|_| Error::Encryption("Backup encryption failed".to_string()))?;
+
+        let mut backup = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        backup.push(BACKUP_VERSION);
+        backup.extend_from_slice(&nonce);
+        backup.extend_from_slice(&ciphertext);
+        Ok(backup)
+    }
+
+    /// Decrypt a backup produced by [`Wallet::backup_encrypt`]
+    pub fn backup_decrypt(&self, backup: &[u8]) -> Result<Vec<u8>, Error> {
+        let cipher = ChaCha20Poly1305::new(&self.backup_key()?.into());
+
+        let (version, rest) = backup
+            .split_first()
+            .ok_or_else(|| Error::Encryption("Empty backup".to_string()))?;
+        if *version != BACKUP_VERSION || rest.len() < NONCE_LEN {
+            return Err(Error::Encryption("Unsupported backup format".to_string()));
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        cipher
+            .decrypt(
+                nonce.into(),
+                Payload {
+                    msg: ciphertext,
+                    aad: &[BACKUP_VERSION],
+                },
+            )
+            .map_err(|_| Error::Encryption("Backup authentication failed".to_string()))
+    }
+
+    fn backup_key(&self) -> Result<[u8; 32], Error> {
+        self.backup_key.ok_or_else(|| {
+            Error::Unsupported(
+                "Backup encryption requires a wallet created from a seed".to_string(),
+            )
+        })
+    }
+
+    fn derive_account(&self, index: u32) -> Result<Account, Error> {
+        let xpub = self
+            .master
+            .derive_path(&self.account_path(index))?
+            .to_extended_public_key();
+
+        Ok(Account::new(index, xpub, self.account_config))
+    }
+}
+
+/// Scan both chains of an account until `gap_limit` consecutive addresses
+/// are unused, moving the next unused index past the last used address
+pub(crate) fn discover_account(
+    account: &mut Account,
+    address_type: AddressType,
+    is_used: &mut impl FnMut(&str) -> bool,
+) -> Result<(), Error> {
+    for change in [Change::External, Change::Internal] {
+        let mut next = account.next_index(change);
+        let mut gap = 0;
+        let (mut scanned, mut used) = (0, 0);
+        for (index, address) in account.address_iter(address_type, change)? {
+            scanned += 1;
+            if is_used(&address) {
+                next = next.max(index + 1);
+                gap = 0;
+                used += 1;
+            } else {
+                gap += 1;
+                if gap >= account.config.gap_limit {
+                    break;
+                }
+            }
+        }
+
+        account.set_next_index(change, next);
+        trace::event!(
+            DEBUG,
+            account = account.index,
+            ?change,
+            next,
+            "discovered chain"
+        );
+        let index = account.index;
+        metrics::observe(|o| o.scan_progress(index, change, scanned, used));
+    }
+    Ok(())
+}
+
+/// Order a change window's positions by a keyed hash of each
+fn shuffle_window(master: &ExtendedPrivKey, account: u32, window: u32, gap_limit: u32) -> Vec<u32> {
+    let secret = master.private_key.secret_bytes();
+    let mut positions: Vec<(u32, [u8; 64])> = (0..gap_limit)
+        .map(|position| {
+            let mut data = CHANGE_SHUFFLE_TAG.to_vec();
+            for n in [account, window, position] {
+                data.extend_from_slice(&n.to_be_bytes());
+            }
+            (position, utils::hmac_sha512(&secret, &data))
+        })
+        .collect();
+    positions.sort_by_key(|&(_, key)| key);
+    positions
+        .into_iter()
+        .map(|(position, _)| position)
+        .collect()
+}
+
+pub(crate) fn apply_config(account: &mut Account, config: AccountConfig) {
+    account.config = config;
+    account.next_external = account.next_external.max(config.start_index);
+    account.next_internal = account.next_internal.max(config.start_index);
+}
+
+fn account_address(
+    xpub: &ExtendedPubKey,
+    address_type: AddressType,
+    change: Change,
+    index: u32,
+) -> Result<String, Error> {
+    Ok(xpub
+        .derive_child(change.child_number())?
+        .derive_child(ChildNumber::Normal(index))?
+        .address(address_type))
+}
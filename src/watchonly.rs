@@ -0,0 +1,157 @@
+use crate::address::AddressType;
+use crate::bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Network};
+use crate::bip44::{Change, CoinType};
+use crate::descriptor::{Descriptor, KeyOrigin};
+use crate::error::Error;
+use crate::limits;
+use crate::wallet::{
+    apply_config, discover_account, Account, AccountConfig, AddressLocation, ChangeSelection,
+    Wallet,
+};
+
+/// A wallet over a single account xpub, for setups that never hold private
+/// keys. Addresses, ownership checks and discovery work as for [`Wallet`].
+#[derive(Debug, Clone)]
+pub struct WatchOnlyWallet {
+    account: Account,
+    address_type: AddressType,
+    master_fingerprint: Option<[u8; 4]>,
+}
+
+impl WatchOnlyWallet {
+    /// Create a wallet from a serialized account xpub, such as one exported
+    /// at `m/84'/0'/0'`. The account index is taken from the key.
+    pub fn from_account_xpub(xpub: &str, address_type: AddressType) -> Result<Self, Error> {
+        let xpub = ExtendedPubKey::from_string(xpub)?;
+        if xpub.depth != 3 {
+            return Err(Error::InvalidExtendedKey(format!(
+                "Expected an account key at depth 3, got depth {}",
+                xpub.depth
+            )));
+        }
+        let index = xpub.child_number & ChildNumber::MAX_NORMAL_INDEX;
+        Ok(WatchOnlyWallet {
+            account: Account::new(index, xpub, AccountConfig::default()),
+            address_type,
+            master_fingerprint: None,
+        })
+    }
+
+    /// Record the master key fingerprint, which descriptors need for their
+    /// key origin
+    pub fn with_master_fingerprint(mut self, fingerprint: [u8; 4]) -> Self {
+        self.master_fingerprint = Some(fingerprint);
+        self
+    }
+
+    /// Use scanning parameters for the account. Shuffled change selection
+    /// is keyed by the master private key and is not available.
+    pub fn with_account_config(mut self, config: AccountConfig) -> Result<Self, Error> {
+        config.validate()?;
+        if config.change_selection == ChangeSelection::Shuffled {
+            return Err(Error::Unsupported(
+                "Shuffled change selection requires the master private key".to_string(),
+            ));
+        }
+        apply_config(&mut self.account, config);
+        Ok(self)
+    }
+
+    /// Get the account
+    pub fn account(&self) -> &Account {
+        &self.account
+    }
+
+    /// Get the wallet's address type
+    pub fn address_type(&self) -> AddressType {
+        self.address_type
+    }
+
+    /// Get the wallet's network
+    pub fn network(&self) -> Network {
+        self.account.xpub.network
+    }
+
+    /// Derivation path of the account, assuming the standard layout for
+    /// the address type
+    pub fn account_path(&self) -> DerivationPath {
+        DerivationPath {
+            path: vec![
+                self.address_type.purpose().child_number(),
+                CoinType::for_network(self.network()).child_number(),
+                ChildNumber::Hardened(self.account.index),
+            ],
+        }
+    }
+
+    /// Get the first receive address
+    pub fn first_address(&self) -> Result<String, Error> {
+        self.account.first_address(self.address_type)
+    }
+
+    /// Get the receive and change descriptors of the account
+    pub fn descriptors(&self) -> Result<[Descriptor; 2], Error> {
+        let fingerprint = self.master_fingerprint.ok_or_else(|| {
+            Error::Unsupported("Descriptors need the master key fingerprint".to_string())
+        })?;
+        let descriptor = |change| Descriptor {
+            address_type: self.address_type,
+            origin: KeyOrigin {
+                fingerprint,
+                path: self.account_path(),
+            },
+            xpub: self.account.xpub.clone(),
+            change,
+        };
+        Ok([descriptor(Change::External), descriptor(Change::Internal)])
+    }
+
+    /// Derive a batch of addresses on a chain
+    pub fn addresses(&self, change: Change, start: u32, count: u32) -> Result<Vec<String>, Error> {
+        limits::check_batch_size(count)?;
+        (start..start.saturating_add(count))
+            .map(|i| self.account.address(self.address_type, change, i))
+            .collect()
+    }
+
+    /// Hand out the next unused address of a chain
+    pub fn next_address(&mut self, change: Change) -> Result<String, Error> {
+        let index = self.account.next_index(change);
+        let address = self.account.address(self.address_type, change, index)?;
+        self.account.set_next_index(change, index + 1);
+        Ok(address)
+    }
+
+    /// Find where an address was derived, searching the watched range of
+    /// both chains
+    pub fn find_address(&self, address: &str) -> Result<Option<AddressLocation>, Error> {
+        for change in [Change::External, Change::Internal] {
+            for index in self.account.watch_range(change) {
+                if self.account.address(self.address_type, change, index)? == address {
+                    return Ok(Some(AddressLocation {
+                        account: self.account.index,
+                        change,
+                        index,
+                    }));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Scan both chains until `gap_limit` consecutive addresses are unused
+    pub fn discover(&mut self, mut is_used: impl FnMut(&str) -> bool) -> Result<(), Error> {
+        discover_account(&mut self.account, self.address_type, &mut is_used)
+    }
+}
+
+impl Wallet {
+    /// Create a watch-only wallet from a serialized account xpub; see
+    /// [`WatchOnlyWallet::from_account_xpub`]
+    pub fn from_account_xpub(
+        xpub: &str,
+        address_type: AddressType,
+    ) -> Result<WatchOnlyWallet, Error> {
+        WatchOnlyWallet::from_account_xpub(xpub, address_type)
+    }
+}
@@ -0,0 +1,12 @@
+//! Wiping of secret buffers before they are freed, a no-op without the
+//! `zeroize` feature.
+
+/// Overwrite a secret with zeros in a way the compiler cannot elide
+#[cfg(feature = "zeroize")]
+pub(crate) fn wipe<T: zeroize::Zeroize + ?Sized>(secret: &mut T) {
+    secret.zeroize();
+}
+
+/// Leave the secret as it is; wiping needs the `zeroize` feature
+#[cfg(not(feature = "zeroize"))]
+pub(crate) fn wipe<T: ?Sized>(_secret: &mut T) {}
@@ -0,0 +1,180 @@
+use crate::bip39::{self, Language, Mnemonic, MnemonicType, Seed};
+use crate::error::Error;
+use crate::wipe::wipe;
+use rand::rngs::OsRng;
+use rand_core::CryptoRngCore;
+use std::collections::HashMap;
+use unicode_normalization::UnicodeNormalization;
+
+/// Number of words in a BIP-39 wordlist
+pub const WORDLIST_LEN: usize = 2048;
+
+/// A list of 2048 words mnemonics are written in. Implemented by the built-in
+/// [`Language`]s and by [`CustomWordlist`]; other implementations must map
+/// every index below [`WORDLIST_LEN`] to a distinct word.
+pub trait Wordlist {
+    /// Get the word at an index
+    fn word(&self, index: usize) -> &str;
+
+    /// Find the index of a word
+    fn index_of(&self, word: &str) -> Option<usize>;
+
+    /// Whitespace separating words of generated phrases
+    fn separator(&self) -> &str {
+        " "
+    }
+}
+
+impl Wordlist for Language {
+    fn word(&self, index: usize) -> &str {
+        self.wordlist()[index]
+    }
+
+    fn index_of(&self, word: &str) -> Option<usize> {
+        Language::index_of(self, word)
+    }
+
+    fn separator(&self) -> &str {
+        Language::separator(self)
+    }
+}
+
+impl<W: Wordlist + ?Sized> Wordlist for &W {
+    fn word(&self, index: usize) -> &str {
+        (**self).word(index)
+    }
+
+    fn index_of(&self, word: &str) -> Option<usize> {
+        (**self).index_of(word)
+    }
+
+    fn separator(&self) -> &str {
+        (**self).separator()
+    }
+}
+
+/// A non-standard wordlist, such as a private corporate recovery list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomWordlist {
+    words: Vec<String>,
+    indices: HashMap<String, usize>,
+}
+
+impl CustomWordlist {
+    /// Create a wordlist from 2048 distinct words without whitespace. Words
+    /// are stored NFKD-normalized, like the official lists.
+    pub fn new<S: AsRef<str>>(words: &[S]) -> Result<Self, Error> {
+        if words.len() != WORDLIST_LEN {
+            return Err(Error::InvalidConfig(format!(
+                "A wordlist needs {} words, got {}",
+                WORDLIST_LEN,
+                words.len()
+            )));
+        }
+
+        let words: Vec<String> = words.iter().map(|w| w.as_ref().nfkd().collect()).collect();
+        let mut indices = HashMap::with_capacity(WORDLIST_LEN);
+        for (index, word) in words.iter().enumerate() {
+            if word.is_empty() || word.chars().any(char::is_whitespace) {
+                return Err(Error::InvalidConfig(format!(
+                    "Invalid word {:?} at index {}",
+                    word, index
+                )));
+            }
+            if indices.insert(word.clone(), index).is_some() {
+                return Err(Error::InvalidConfig(format!("Duplicate word {:?}", word)));
+            }
+        }
+
+        Ok(CustomWordlist { words, indices })
+    }
+}
+
+impl Wordlist for CustomWordlist {
+    fn word(&self, index: usize) -> &str {
+        &self.words[index]
+    }
+
+    fn index_of(&self, word: &str) -> Option<usize> {
+        self.indices
+            .get(word)
+            .or_else(|| self.indices.get(&word.nfkd().collect::<String>()))
+            .copied()
+    }
+}
+
+/// A mnemonic in any [`Wordlist`], sharing the checksum, entropy and seed
+/// derivation of [`Mnemonic`]. Pass `&dyn Wordlist` for a wordlist chosen
+/// at runtime.
+#[derive(Debug, Clone)]
+pub struct CustomMnemonic<W: Wordlist> {
+    phrase: String,
+    wordlist: W,
+}
+
+impl<W: Wordlist> CustomMnemonic<W> {
+    /// Parse a phrase, validating its words and checksum
+    pub fn from_phrase(phrase: &str, wordlist: W) -> Result<Self, Error> {
+        let words: Vec<&str> = phrase.split_whitespace().collect();
+        wipe(&mut Mnemonic::words_to_entropy(&words, &wordlist)?);
+        Ok(CustomMnemonic {
+            phrase: phrase.to_string(),
+            wordlist,
+        })
+    }
+
+    /// Encode entropy of 16, 20, 24, 28 or 32 bytes
+    pub fn from_entropy(entropy: &[u8], wordlist: W) -> Result<Self, Error> {
+        let mnemonic_type = MnemonicType::from_entropy_bytes(entropy.len())?;
+        Ok(CustomMnemonic {
+            phrase: Mnemonic::entropy_to_words(entropy, mnemonic_type, &wordlist)?,
+            wordlist,
+        })
+    }
+
+    /// Generate a new random mnemonic
+    pub fn generate(mnemonic_type: MnemonicType, wordlist: W) -> Result<Self, Error> {
+        CustomMnemonic::generate_with_rng(mnemonic_type, wordlist, &mut OsRng)
+    }
+
+    /// Generate a new mnemonic with entropy from a caller-supplied RNG
+    pub fn generate_with_rng(
+        mnemonic_type: MnemonicType,
+        wordlist: W,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<Self, Error> {
+        let mut entropy = vec![0u8; mnemonic_type.entropy_bytes()];
+        rng.fill_bytes(&mut entropy);
+        let mnemonic = CustomMnemonic::from_entropy(&entropy, wordlist);
+        wipe(&mut entropy);
+        mnemonic
+    }
+
+    /// Recover the entropy the phrase encodes
+    pub fn to_entropy(&self) -> Vec<u8> {
+        let words: Vec<&str> = self.phrase.split_whitespace().collect();
+        Mnemonic::words_to_entropy(&words, &self.wordlist)
+            .expect("mnemonic was validated on construction")
+    }
+
+    /// Generate a seed from the phrase, exactly as for standard wordlists
+    pub fn to_seed(&self, passphrase: &str) -> Seed {
+        bip39::phrase_to_seed(&self.phrase, passphrase)
+    }
+
+    /// Get the phrase
+    pub fn phrase(&self) -> &str {
+        &self.phrase
+    }
+
+    /// Get the wordlist
+    pub fn wordlist(&self) -> &W {
+        &self.wordlist
+    }
+}
+
+impl<W: Wordlist> Drop for CustomMnemonic<W> {
+    fn drop(&mut self) {
+        wipe(&mut self.phrase);
+    }
+}